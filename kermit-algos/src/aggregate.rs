@@ -0,0 +1,298 @@
+//! Aggregates in rule heads, e.g. `deg(X, count(Y)) :- edge(X, Y).`
+//!
+//! A rule with aggregates is evaluated in two steps. Its body is first joined
+//! as an ordinary query whose head lists the grouping variables (the head's
+//! plain variables) followed by every other body variable, so the join yields
+//! each distinct binding of the body once. The bindings are then folded into
+//! one row per group.
+//!
+//! The trie joins enumerate bindings in lexicographic order of their variable
+//! order. When the grouping variables lead that order, each group's bindings
+//! arrive contiguously and are folded as they stream past, without a hash
+//! table. Otherwise the groups are gathered in a map first.
+
+use {
+    kermit_parser::{AggregateFn, JoinQuery, Predicate, Term},
+    std::{
+        collections::{BTreeMap, HashSet},
+        iter::Peekable,
+    },
+};
+
+/// Where an output column of an aggregate rule comes from.
+#[derive(Clone, Debug)]
+enum Column {
+    /// The grouping variable at this index of a binding.
+    Group(usize),
+    /// An aggregate over the variable at this index of a binding; its
+    /// accumulator is the aggregate's position among the rule's aggregates.
+    Aggregate(AggregateFn, usize),
+}
+
+/// The grouping and folding plan of a rule with aggregates in its head.
+#[derive(Clone, Debug)]
+pub struct Aggregation {
+    /// The grouping variables, which lead every binding.
+    groups: Vec<String>,
+    /// One entry per head term.
+    columns: Vec<Column>,
+}
+
+impl Aggregation {
+    /// Splits a rule with head aggregates into its aggregation plan and the
+    /// query enumerating its body bindings. Returns `None` if the head holds
+    /// no aggregate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the head holds an atom or placeholder next to an aggregate,
+    /// or aggregates a variable no body predicate binds.
+    pub fn plan(query: &JoinQuery) -> Option<(Self, JoinQuery)> {
+        if !query.has_aggregates() {
+            return None;
+        }
+        let mut vars: Vec<String> = Vec::new();
+        for term in &query.head.terms {
+            match term {
+                | Term::Var(name) if !vars.contains(name) => vars.push(name.clone()),
+                | Term::Var(_) | Term::Aggregate(..) => {},
+                | Term::Atom(_) | Term::Int(_) | Term::Str(_) | Term::Placeholder => {
+                    panic!("a head with aggregates may only hold variables and aggregates")
+                },
+            }
+        }
+        let groups = vars.clone();
+        let body_vars: HashSet<&str> = query
+            .body
+            .iter()
+            .flat_map(|pred| &pred.terms)
+            .filter_map(|t| match t {
+                | Term::Var(name) => Some(name.as_str()),
+                | _ => None,
+            })
+            .collect();
+        for term in query.body.iter().flat_map(|pred| &pred.terms) {
+            if let Term::Var(name) = term {
+                if !vars.contains(name) {
+                    vars.push(name.clone());
+                }
+            }
+        }
+
+        let columns = query
+            .head
+            .terms
+            .iter()
+            .map(|term| match term {
+                | Term::Var(name) => Column::Group(groups.iter().position(|g| g == name).unwrap()),
+                | Term::Aggregate(func, var) => {
+                    assert!(
+                        body_vars.contains(var.as_str()),
+                        "aggregated variable {var} occurs in no body atom"
+                    );
+                    Column::Aggregate(*func, vars.iter().position(|v| v == var).unwrap())
+                },
+                | _ => unreachable!(),
+            })
+            .collect();
+
+        let bindings = JoinQuery {
+            head: Predicate {
+                name: query.head.name.clone(),
+                terms: vars.into_iter().map(Term::Var).collect(),
+            },
+            ..query.clone()
+        };
+        Some((
+            Aggregation {
+                groups,
+                columns,
+            },
+            bindings,
+        ))
+    }
+
+    /// Returns `true` if `order` binds the grouping variables before any
+    /// other, so that a trie join emits each group contiguously.
+    pub fn groups_lead(&self, order: &[String]) -> bool {
+        order.len() >= self.groups.len()
+            && order[..self.groups.len()]
+                .iter()
+                .all(|v| self.groups.contains(v))
+    }
+
+    /// Folds `bindings`, as produced by joining the query returned by
+    /// [`plan`](Self::plan), into one row per group.
+    ///
+    /// If `contiguous`, each group's bindings must be adjacent and are folded
+    /// as they stream past; otherwise they are gathered in a map first and
+    /// the rows come out ordered by group. Groups without bindings yield no
+    /// row, so a body with no bindings yields nothing rather than a zero
+    /// count. Sums saturate at `usize::MAX`.
+    pub fn apply<I>(self, bindings: I, contiguous: bool) -> AggregateIter<I>
+    where
+        I: Iterator<Item = Vec<usize>>,
+    {
+        let source = if contiguous {
+            Source::Contiguous(bindings.peekable())
+        } else {
+            let mut groups: BTreeMap<Vec<usize>, Vec<usize>> = BTreeMap::new();
+            for binding in bindings {
+                let key = binding[..self.groups.len()].to_vec();
+                match groups.get_mut(&key) {
+                    | Some(acc) => self.fold(acc, &binding),
+                    | None => {
+                        groups.insert(key, self.init(&binding));
+                    },
+                }
+            }
+            Source::Gathered(groups.into_iter())
+        };
+        AggregateIter {
+            aggregation: self,
+            source,
+        }
+    }
+
+    fn aggregates(&self) -> impl Iterator<Item = (AggregateFn, usize)> + '_ {
+        self.columns.iter().filter_map(|c| match c {
+            | Column::Aggregate(func, index) => Some((*func, *index)),
+            | Column::Group(_) => None,
+        })
+    }
+
+    /// Returns the accumulators after folding a group's first binding.
+    fn init(&self, binding: &[usize]) -> Vec<usize> {
+        self.aggregates()
+            .map(|(func, index)| match func {
+                | AggregateFn::Count => 1,
+                | AggregateFn::Sum | AggregateFn::Min | AggregateFn::Max => binding[index],
+            })
+            .collect()
+    }
+
+    fn fold(&self, acc: &mut [usize], binding: &[usize]) {
+        for (slot, (func, index)) in acc.iter_mut().zip(self.aggregates()) {
+            let value = binding[index];
+            *slot = match func {
+                | AggregateFn::Count => *slot + 1,
+                | AggregateFn::Sum => slot.saturating_add(value),
+                | AggregateFn::Min => (*slot).min(value),
+                | AggregateFn::Max => (*slot).max(value),
+            };
+        }
+    }
+
+    fn row(&self, key: &[usize], acc: &[usize]) -> Vec<usize> {
+        let mut acc = acc.iter();
+        self.columns
+            .iter()
+            .map(|c| match c {
+                | Column::Group(i) => key[*i],
+                | Column::Aggregate(..) => *acc.next().unwrap(),
+            })
+            .collect()
+    }
+}
+
+enum Source<I>
+where
+    I: Iterator<Item = Vec<usize>>,
+{
+    Contiguous(Peekable<I>),
+    Gathered(std::collections::btree_map::IntoIter<Vec<usize>, Vec<usize>>),
+}
+
+/// Iterator over the rows of an aggregate rule; see [`Aggregation::apply`].
+pub struct AggregateIter<I>
+where
+    I: Iterator<Item = Vec<usize>>,
+{
+    aggregation: Aggregation,
+    source: Source<I>,
+}
+
+impl<I> Iterator for AggregateIter<I>
+where
+    I: Iterator<Item = Vec<usize>>,
+{
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let aggregation = &self.aggregation;
+        match &mut self.source {
+            | Source::Contiguous(bindings) => {
+                let first = bindings.next()?;
+                let key = &first[..aggregation.groups.len()];
+                let mut acc = aggregation.init(&first);
+                while let Some(binding) = bindings.next_if(|b| b.starts_with(key)) {
+                    aggregation.fold(&mut acc, &binding);
+                }
+                Some(aggregation.row(key, &acc))
+            },
+            | Source::Gathered(groups) => {
+                let (key, acc) = groups.next()?;
+                Some(aggregation.row(&key, &acc))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(query: &str) -> (Aggregation, JoinQuery) {
+        Aggregation::plan(&query.parse().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn query_without_aggregates_has_no_plan() {
+        assert!(Aggregation::plan(&"Q(X) :- e(X, Y).".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn bindings_query_leads_with_the_groups() {
+        let (aggregation, bindings) = plan("Q(count(Z), X) :- e(X, Y), e(Y, Z), Y < 4.");
+        assert_eq!(aggregation.groups, vec!["X"]);
+        assert_eq!(bindings.head.terms, vec![
+            Term::Var("X".into()),
+            Term::Var("Y".into()),
+            Term::Var("Z".into())
+        ]);
+        assert_eq!(bindings.comparisons.len(), 1);
+        assert!(aggregation.groups_lead(&["X".into(), "Z".into(), "Y".into()]));
+        assert!(!aggregation.groups_lead(&["Y".into(), "X".into(), "Z".into()]));
+    }
+
+    #[test]
+    fn folds_every_aggregate_per_group() {
+        let (aggregation, _) = plan("Q(X, count(Y), sum(Y), min(Y), max(Y)) :- e(X, Y).");
+        let bindings = vec![vec![1, 4], vec![1, 2], vec![1, 9], vec![3, 5]];
+        let rows: Vec<_> = aggregation.apply(bindings.into_iter(), true).collect();
+        assert_eq!(rows, vec![vec![1, 3, 15, 2, 9], vec![3, 1, 5, 5, 5]]);
+    }
+
+    #[test]
+    fn scattered_groups_are_gathered() {
+        let (aggregation, _) = plan("Q(count(Y), X) :- e(X, Y).");
+        let bindings = vec![vec![3, 1], vec![1, 1], vec![3, 2], vec![1, 2], vec![3, 3]];
+        let rows: Vec<_> = aggregation.apply(bindings.into_iter(), false).collect();
+        assert_eq!(rows, vec![vec![2, 1], vec![3, 3]]);
+    }
+
+    #[test]
+    fn no_groups_fold_everything_into_one_row() {
+        let (aggregation, _) = plan("Q(max(Y)) :- e(X, Y).");
+        let rows: Vec<_> = aggregation
+            .clone()
+            .apply(vec![vec![1, 4], vec![2, 7]].into_iter(), true)
+            .collect();
+        assert_eq!(rows, vec![vec![7]]);
+        assert_eq!(aggregation.apply(std::iter::empty(), true).count(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "occurs in no body atom")]
+    fn unbound_aggregate_variable_panics() { plan("Q(X, count(Z)) :- e(X, Y)."); }
+}
@@ -0,0 +1,229 @@
+//! Comparison built-ins (`X < Y`, `X != 3`, …) compiled for the trie-based
+//! joins.
+//!
+//! The trie joins bind one variable per depth, so each comparison is checked
+//! at the depth of its later variable, once everything it mentions is bound.
+//! There it narrows the keys the depth may take: `<`, `<=`, `>`, `>=` and `=`
+//! give lower and upper bounds, which the joins turn into a `seek` past every
+//! key below the lower bound and an early stop above the upper bound, while
+//! `!=` only skips single keys.
+
+use {
+    kermit_parser::{Comparison, ComparisonOp, Operand},
+    std::collections::HashMap,
+};
+
+/// The other side of a comparison, as seen from the depth it is checked at.
+#[derive(Clone, Copy)]
+enum Bound {
+    /// An integer constant.
+    Const(usize),
+    /// The key bound at an earlier depth.
+    Var(usize),
+    /// The key being checked itself (`X < X`).
+    Key,
+}
+
+/// What a depth should do with its current key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Verdict {
+    /// The key satisfies every comparison checked at this depth.
+    Admit,
+    /// The key is rejected, but a later key may be admitted.
+    Skip,
+    /// No key below this one is admitted; seek to it.
+    SeekTo(usize),
+    /// No key from here on is admitted; the depth is exhausted.
+    Exhausted,
+}
+
+/// A query's comparisons compiled against a variable order. The default
+/// restricts nothing.
+#[derive(Default)]
+pub(crate) struct Comparisons {
+    /// For each depth, the comparisons `key op bound` checked there.
+    checks: Vec<Vec<(ComparisonOp, Bound)>>,
+    /// Whether a comparison between two constants is false, in which case
+    /// nothing is admitted.
+    unsatisfiable: bool,
+}
+
+impl Comparisons {
+    /// Compiles `comparisons` for a join binding variables in `order`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a comparison mentions a variable missing from `order`.
+    pub(crate) fn new(comparisons: &[Comparison], order: &[String]) -> Self {
+        let depth_of: HashMap<&str, usize> = order
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.as_str(), i))
+            .collect();
+        let depth = |operand: &Operand| match operand {
+            | Operand::Var(name) => Some(*depth_of.get(name.as_str()).unwrap_or_else(|| {
+                panic!("comparison variable {name} missing from the variable order")
+            })),
+            | Operand::Int(_) => None,
+        };
+
+        let mut checks = vec![Vec::new(); order.len()];
+        let mut unsatisfiable = false;
+        for cmp in comparisons {
+            // Orient every comparison as `later op other`.
+            let (later, op, other) = match (depth(&cmp.left), depth(&cmp.right)) {
+                | (Some(l), Some(r)) if l < r => (r, cmp.op.flip(), Bound::Var(l)),
+                | (Some(l), Some(r)) if l > r => (l, cmp.op, Bound::Var(r)),
+                | (Some(l), Some(_)) => (l, cmp.op, Bound::Key),
+                | (Some(l), None) => (l, cmp.op, constant(&cmp.right)),
+                | (None, Some(r)) => (r, cmp.op.flip(), constant(&cmp.left)),
+                | (None, None) => {
+                    let (Bound::Const(l), Bound::Const(r)) =
+                        (constant(&cmp.left), constant(&cmp.right))
+                    else {
+                        unreachable!()
+                    };
+                    unsatisfiable |= !cmp.op.holds(l, r);
+                    continue;
+                },
+            };
+            checks[later].push((op, other));
+        }
+        Comparisons {
+            checks,
+            unsatisfiable,
+        }
+    }
+
+    /// Returns `true` if no comparison restricts the keys at `depth`.
+    pub(crate) fn is_free(&self, depth: usize) -> bool {
+        !self.unsatisfiable && self.checks.get(depth).is_none_or(Vec::is_empty)
+    }
+
+    /// Judges `key` at `depth`, given the keys bound at the depths above it.
+    pub(crate) fn check(&self, depth: usize, key: usize, bound: &[usize]) -> Verdict {
+        if self.unsatisfiable {
+            return Verdict::Exhausted;
+        }
+        let mut lower = 0;
+        let mut upper = usize::MAX;
+        let mut skip = false;
+        for &(op, other) in &self.checks[depth] {
+            let value = match other {
+                | Bound::Const(value) => value,
+                | Bound::Var(depth) => bound[depth],
+                | Bound::Key => {
+                    if op.holds(key, key) {
+                        continue;
+                    }
+                    return Verdict::Exhausted;
+                },
+            };
+            match op {
+                | ComparisonOp::Lt => match value.checked_sub(1) {
+                    | Some(max) => upper = upper.min(max),
+                    | None => return Verdict::Exhausted,
+                },
+                | ComparisonOp::Le => upper = upper.min(value),
+                | ComparisonOp::Gt => match value.checked_add(1) {
+                    | Some(min) => lower = lower.max(min),
+                    | None => return Verdict::Exhausted,
+                },
+                | ComparisonOp::Ge => lower = lower.max(value),
+                | ComparisonOp::Eq => {
+                    lower = lower.max(value);
+                    upper = upper.min(value);
+                },
+                | ComparisonOp::Ne => skip |= key == value,
+            }
+        }
+        if key > upper || lower > upper {
+            Verdict::Exhausted
+        } else if key < lower {
+            Verdict::SeekTo(lower)
+        } else if skip {
+            Verdict::Skip
+        } else {
+            Verdict::Admit
+        }
+    }
+}
+
+fn constant(operand: &Operand) -> Bound {
+    match operand {
+        | Operand::Int(value) => Bound::Const(*value),
+        | Operand::Var(_) => unreachable!("variables are resolved to depths"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, kermit_parser::JoinQuery};
+
+    fn compile(query: &str) -> Comparisons {
+        let query: JoinQuery = query.parse().unwrap();
+        let order = crate::variable_order(&query);
+        Comparisons::new(&query.comparisons, &order)
+    }
+
+    #[test]
+    fn checks_at_the_later_variable() {
+        let cmps = compile("Q(X, Y) :- e(X, Y), Y > X.");
+        assert!(cmps.is_free(0));
+        assert!(!cmps.is_free(1));
+        assert_eq!(cmps.check(1, 2, &[5]), Verdict::SeekTo(6));
+        assert_eq!(cmps.check(1, 6, &[5]), Verdict::Admit);
+    }
+
+    #[test]
+    fn flips_comparisons_written_earlier_first() {
+        let cmps = compile("Q(X, Y) :- e(X, Y), X <= Y, 7 > Y.");
+        assert_eq!(cmps.check(1, 3, &[5]), Verdict::SeekTo(5));
+        assert_eq!(cmps.check(1, 6, &[5]), Verdict::Admit);
+        assert_eq!(cmps.check(1, 7, &[5]), Verdict::Exhausted);
+    }
+
+    #[test]
+    fn equality_pins_the_key() {
+        let cmps = compile("Q(X, Y) :- e(X, Y), Y = X.");
+        assert_eq!(cmps.check(1, 0, &[4]), Verdict::SeekTo(4));
+        assert_eq!(cmps.check(1, 4, &[4]), Verdict::Admit);
+        assert_eq!(cmps.check(1, 5, &[4]), Verdict::Exhausted);
+    }
+
+    #[test]
+    fn inequality_skips_single_keys() {
+        let cmps = compile("Q(X, Y) :- e(X, Y), X != Y, Y != 9.");
+        assert_eq!(cmps.check(1, 4, &[4]), Verdict::Skip);
+        assert_eq!(cmps.check(1, 9, &[4]), Verdict::Skip);
+        assert_eq!(cmps.check(1, 5, &[4]), Verdict::Admit);
+    }
+
+    #[test]
+    fn empty_ranges_are_exhausted() {
+        assert_eq!(
+            compile("Q(X) :- e(X), X < 0.").check(0, 0, &[]),
+            Verdict::Exhausted
+        );
+        assert_eq!(
+            compile("Q(X) :- e(X), X > 5, X < 3.").check(0, 0, &[]),
+            Verdict::Exhausted
+        );
+        assert_eq!(
+            compile("Q(X) :- e(X), X < X.").check(0, 1, &[]),
+            Verdict::Exhausted
+        );
+        assert_eq!(
+            compile("Q(X) :- e(X), X <= X.").check(0, 1, &[]),
+            Verdict::Admit
+        );
+    }
+
+    #[test]
+    fn false_constant_comparison_admits_nothing() {
+        let cmps = compile("Q(X) :- e(X), 2 < 1.");
+        assert!(!cmps.is_free(0));
+        assert_eq!(cmps.check(0, 1, &[]), Verdict::Exhausted);
+        assert!(compile("Q(X) :- e(X), 1 < 2.").is_free(0));
+    }
+}
@@ -4,20 +4,27 @@
 //! filtered by synthetic unary `Const_c42` predicates, so the existing
 //! LFTJ engine can handle them without modification. Intended to run
 //! immediately before [`crate::JoinAlgo::join_iter`].
+//!
+//! Integer constants (`p(X, 42)`) are dictionary IDs already. Quoted-string
+//! and IRI constants are first looked up in a dictionary by
+//! [`resolve_constants`]; any left unresolved name no stored value.
 
 use {
-    kermit_parser::{JoinQuery, Predicate, Term},
+    kermit_parser::{JoinQuery, Predicate, Program, Term},
     std::fmt,
 };
 
-/// Error returned by [`rewrite_atoms`] when an atom does not match the
-/// expected `c<digits>` shape.
+/// Error returned by [`rewrite_atoms`] when a constant cannot be turned
+/// into a dictionary ID.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RewriteError {
-    /// An atom was not of the form `c<digits>`. kermit currently only
-    /// supports constants encoded as dictionary IDs using this
-    /// convention.
+    /// An atom was not of the form `c<digits>`. Lowercase atoms are only
+    /// read as constants encoded as dictionary IDs using this convention.
     BadAtom(String),
+    /// A quoted-string or IRI constant in a body predicate was not resolved
+    /// by [`resolve_constants`], so the predicate matches nothing. Callers
+    /// report an empty result rather than a failure.
+    UnknownConstant(String),
 }
 
 impl fmt::Display for RewriteError {
@@ -28,6 +35,9 @@ impl fmt::Display for RewriteError {
                 "atom {s:?} does not match the expected c<digits> shape — kermit currently only \
                  supports constants encoded as dictionary IDs",
             ),
+            | RewriteError::UnknownConstant(s) => {
+                write!(f, "constant {s} is not in the dictionary")
+            },
         }
     }
 }
@@ -38,9 +48,70 @@ impl std::error::Error for RewriteError {}
 /// dictionary ID. One entry is produced per rewritten atom occurrence.
 pub type ConstSpec = (String, usize);
 
-/// Rewrites `query.body`: each `Term::Atom("c<id>")` becomes a fresh
-/// variable `K<i>`, with a new unary predicate `Const_c<id>(K<i>)`
-/// appended to the body.
+/// Replaces every quoted-string or IRI constant in the body and negated
+/// predicates of `query` with the dictionary ID `lookup` returns for it.
+/// Constants `lookup` does not know are left in place for [`rewrite_atoms`]
+/// to report.
+pub fn resolve_constants(query: &mut JoinQuery, lookup: impl Fn(&str) -> Option<usize>) {
+    for term in query
+        .body
+        .iter_mut()
+        .chain(&mut query.negated)
+        .flat_map(|pred| &mut pred.terms)
+    {
+        if let Term::Str(s) = term {
+            if let Some(id) = lookup(s) {
+                *term = Term::Int(id);
+            }
+        }
+    }
+}
+
+/// Like [`resolve_constants`], for every rule and ground fact of `program`.
+pub fn resolve_program_constants(program: &mut Program, lookup: impl Fn(&str) -> Option<usize>) {
+    for rule in &mut program.rules {
+        resolve_constants(rule, &lookup);
+    }
+    for term in program.facts.iter_mut().flat_map(|fact| &mut fact.terms) {
+        if let Term::Str(s) = term {
+            if let Some(id) = lookup(s) {
+                *term = Term::Int(id);
+            }
+        }
+    }
+}
+
+/// Returns the tuple of dictionary IDs a ground fact such as `edge(1, c2).`
+/// stands for, or `None` if it holds a string constant
+/// [`resolve_program_constants`] left unresolved, since no stored value can
+/// match it.
+///
+/// # Errors
+///
+/// Returns [`RewriteError::BadAtom`] if an atom doesn't match `c\d+`.
+///
+/// # Panics
+///
+/// Panics if `fact` holds a variable, placeholder or aggregate; the parser
+/// only reads facts made of constants.
+pub fn ground_tuple(fact: &Predicate) -> Result<Option<Vec<usize>>, RewriteError> {
+    let mut tuple = Vec::with_capacity(fact.terms.len());
+    for term in &fact.terms {
+        match term {
+            | Term::Int(id) => tuple.push(*id),
+            | Term::Atom(atom) => tuple.push(parse_const_atom(atom)?),
+            | Term::Str(_) => return Ok(None),
+            | Term::Var(_) | Term::Placeholder | Term::Aggregate(..) => {
+                panic!("fact {} holds a non-constant term", fact.name)
+            },
+        }
+    }
+    Ok(Some(tuple))
+}
+
+/// Rewrites `query.body`: each `Term::Atom("c<id>")` or `Term::Int(id)`
+/// becomes a fresh variable `K<i>`, with a new unary predicate
+/// `Const_c<id>(K<i>)` appended to the body.
 ///
 /// Each atom occurrence gets its own fresh variable, even if the same
 /// dictionary ID appears multiple times. This avoids forcing equality
@@ -58,9 +129,16 @@ pub type ConstSpec = (String, usize);
 /// pipeline. Keep this asymmetry in mind if authoring queries by
 /// hand: a `Term::Atom` in the head position will not be filtered.
 ///
+/// Atoms inside negated predicates are not rewritten, since the negated
+/// relation is probed rather than joined, but they must still be well-formed.
+/// A negated predicate with an unresolved string constant can match nothing
+/// and is dropped.
+///
 /// # Errors
 ///
-/// Returns [`RewriteError::BadAtom`] if any atom doesn't match `c\d+`.
+/// Returns [`RewriteError::BadAtom`] if any atom doesn't match `c\d+`, and
+/// [`RewriteError::UnknownConstant`] if a body predicate holds a string
+/// constant [`resolve_constants`] did not resolve.
 pub fn rewrite_atoms(mut query: JoinQuery) -> Result<(JoinQuery, Vec<ConstSpec>), RewriteError> {
     let mut next_k = highest_k_index(&query).map_or(0, |n| n + 1);
     let mut specs: Vec<ConstSpec> = Vec::new();
@@ -68,15 +146,16 @@ pub fn rewrite_atoms(mut query: JoinQuery) -> Result<(JoinQuery, Vec<ConstSpec>)
 
     for pred in &mut query.body {
         for term in &mut pred.terms {
-            let atom = match term {
-                | Term::Atom(s) => s.clone(),
+            let id = match term {
+                | Term::Atom(s) => parse_const_atom(s)?,
+                | Term::Int(id) => *id,
+                | Term::Str(s) => return Err(RewriteError::UnknownConstant(s.clone())),
                 | _ => continue,
             };
-            let id = parse_const_atom(&atom)?;
             let fresh = format!("K{next_k}");
             next_k += 1;
             *term = Term::Var(fresh.clone());
-            let const_name = format!("Const_{atom}");
+            let const_name = format!("Const_c{id}");
             new_preds.push(Predicate {
                 name: const_name.clone(),
                 terms: vec![Term::Var(fresh)],
@@ -85,10 +164,21 @@ pub fn rewrite_atoms(mut query: JoinQuery) -> Result<(JoinQuery, Vec<ConstSpec>)
         }
     }
     query.body.extend(new_preds);
+    // Negated atoms stay as they are: their constants are matched directly
+    // when the atom is probed, but are validated here all the same.
+    for term in query.negated.iter().flat_map(|pred| &pred.terms) {
+        if let Term::Atom(atom) = term {
+            parse_const_atom(atom)?;
+        }
+    }
+    query
+        .negated
+        .retain(|pred| !pred.terms.iter().any(|t| matches!(t, Term::Str(_))));
     Ok((query, specs))
 }
 
-fn parse_const_atom(s: &str) -> Result<usize, RewriteError> {
+/// Returns the dictionary ID encoded by a `c<digits>` atom.
+pub(crate) fn parse_const_atom(s: &str) -> Result<usize, RewriteError> {
     let rest = s
         .strip_prefix('c')
         .ok_or_else(|| RewriteError::BadAtom(s.to_string()))?;
@@ -197,6 +287,8 @@ mod tests {
                     name: "p".into(),
                     terms: vec![Term::Var("X".into()), Term::Atom(bad.into())],
                 }],
+                comparisons: vec![],
+                negated: vec![],
             };
             assert!(
                 matches!(rewrite_atoms(q), Err(RewriteError::BadAtom(_))),
@@ -205,6 +297,57 @@ mod tests {
         }
     }
 
+    #[test]
+    fn negated_atoms_are_validated_but_not_rewritten() {
+        let q = parse("Q(X) :- p(X), not r(X, c7).");
+        let (out, specs) = rewrite_atoms(q.clone()).unwrap();
+        assert_eq!(out, q);
+        assert!(specs.is_empty());
+
+        let q = parse("Q(X) :- p(X), not r(X, bob).");
+        assert_eq!(rewrite_atoms(q), Err(RewriteError::BadAtom("bob".into())));
+    }
+
+    #[test]
+    fn integer_constants_share_the_atom_encoding() {
+        let q = parse("Q(X) :- p(X, 42), r(X, c42), not s(X, 7).");
+        let (out, specs) = rewrite_atoms(q).unwrap();
+        assert_eq!(out.body.len(), 4);
+        assert_eq!(specs, vec![
+            ("Const_c42".into(), 42),
+            ("Const_c42".into(), 42),
+        ]);
+        assert_eq!(out.negated[0].terms[1], Term::Int(7));
+    }
+
+    #[test]
+    fn string_constants_resolve_through_the_lookup() {
+        let mut q = parse(r#"Q(X) :- p(X, "alice"), r(X, <http://x/bob>), not s(X, "eve")."#);
+        let lookup = |s: &str| match s {
+            | "\"alice\"" => Some(3),
+            | "<http://x/bob>" => Some(8),
+            | _ => None,
+        };
+        resolve_constants(&mut q, lookup);
+        assert_eq!(q.body[0].terms[1], Term::Int(3));
+        assert_eq!(q.body[1].terms[1], Term::Int(8));
+        assert_eq!(q.negated[0].terms[1], Term::Str("\"eve\"".into()));
+
+        // The unknown negated constant can match nothing, so it drops out.
+        let (out, specs) = rewrite_atoms(q).unwrap();
+        assert!(out.negated.is_empty());
+        assert_eq!(specs, vec![("Const_c3".into(), 3), ("Const_c8".into(), 8)]);
+    }
+
+    #[test]
+    fn unresolved_body_constant_is_reported() {
+        let q = parse(r#"Q(X) :- p(X, "alice")."#);
+        assert_eq!(
+            rewrite_atoms(q),
+            Err(RewriteError::UnknownConstant("\"alice\"".into()))
+        );
+    }
+
     #[test]
     fn placeholders_left_alone() {
         let q = parse("Q(X) :- p(X, _), r(_, c7).");
@@ -228,10 +371,26 @@ mod tests {
                 name: "p".into(),
                 terms: vec![Term::Var("X".into()), Term::Atom("c7".into())],
             }],
+            comparisons: vec![],
+            negated: vec![],
         };
         let (out, specs) = rewrite_atoms(q).unwrap();
         assert!(matches!(out.head.terms[0], Term::Atom(ref s) if s == "c5"));
         assert!(matches!(out.head.terms[1], Term::Var(ref n) if n == "X"));
         assert_eq!(specs, vec![("Const_c7".into(), 7)]);
     }
+
+    #[test]
+    fn ground_tuple_reads_constants() {
+        let fact = |terms: Vec<Term>| Predicate {
+            name: "p".into(),
+            terms,
+        };
+        let ok = fact(vec![Term::Int(3), Term::Atom("c4".into())]);
+        assert_eq!(ground_tuple(&ok), Ok(Some(vec![3, 4])));
+        let unknown = fact(vec![Term::Int(3), Term::Str("\"x\"".into())]);
+        assert_eq!(ground_tuple(&unknown), Ok(None));
+        let bad = fact(vec![Term::Atom("bob".into())]);
+        assert_eq!(ground_tuple(&bad), Err(RewriteError::BadAtom("bob".into())));
+    }
 }
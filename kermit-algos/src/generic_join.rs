@@ -0,0 +1,472 @@
+//! Generic Join — the worst-case-optimal join of [Ngo, Porat, Ré and
+//! Rudra](https://arxiv.org/abs/1203.1952) over sorted tries.
+//!
+//! Like [Leapfrog Triejoin](crate::LeapfrogTriejoin), Generic Join binds one
+//! variable per trie depth. It differs in how each depth's candidate keys are
+//! intersected: instead of leapfrogging all participating iterators past one
+//! another, it picks the participant with the fewest children, iterates its
+//! keys, and probes every other participant with
+//! [`seek`](kermit_iters::LinearIterator::seek).
+
+use {
+    crate::{
+        comparison::{Comparisons, Verdict},
+        join_algo::JoinAlgo,
+        negation::Negations,
+        variable_order::{build_variable_index, head_positions, variable_order},
+    },
+    kermit_iters::{TrieIterable, TrieIterator},
+    kermit_parser::JoinQuery,
+    std::collections::HashMap,
+};
+
+/// An iterator that performs Generic Join over one trie iterator per body
+/// predicate, yielding the bindings of the first `head_arity` variables with
+/// set semantics.
+pub struct GenericJoinIter<IT>
+where
+    IT: TrieIterator,
+{
+    /// Number of variables in the join (determines maximum depth).
+    arity: usize,
+    /// Number of leading variables that make up the output tuple.
+    head_arity: usize,
+    /// One trie iterator per body predicate.
+    iters: Vec<IT>,
+    /// For each variable index, the iterators that participate at that depth.
+    variable_to_iter_map: Vec<Vec<usize>>,
+    /// For each bound depth, the participant whose keys drive the
+    /// intersection.
+    drivers: Vec<usize>,
+    /// The key bound at each depth in `drivers`.
+    bindings: Vec<usize>,
+    /// Comparison built-ins, each checked at the depth of its later variable.
+    comparisons: Comparisons,
+    /// Negated predicates, each probed at the depth of its latest variable.
+    negations: Negations<IT>,
+    started: bool,
+    exhausted: bool,
+}
+
+impl<IT> GenericJoinIter<IT>
+where
+    IT: TrieIterator,
+{
+    /// Construct a new `GenericJoinIter` with the given iterators.
+    ///
+    /// # Arguments
+    /// * `variables` - The variables and their ordering.
+    /// * `rel_variables` - The variables in their relations.
+    /// * `iters` - Trie iterators.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a variable occurs in none of the relations.
+    pub fn new(variables: Vec<usize>, rel_variables: Vec<Vec<usize>>, iters: Vec<IT>) -> Self {
+        let variable_to_iter_map: Vec<Vec<usize>> = variables
+            .iter()
+            .map(|v| {
+                let participants: Vec<usize> = rel_variables
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, r)| r.contains(v))
+                    .map(|(i, _)| i)
+                    .collect();
+                assert!(
+                    !participants.is_empty(),
+                    "variable {v} occurs in no relation"
+                );
+                participants
+            })
+            .collect();
+
+        GenericJoinIter {
+            arity: variables.len(),
+            head_arity: variables.len(),
+            iters,
+            variable_to_iter_map,
+            drivers: Vec::new(),
+            bindings: Vec::new(),
+            comparisons: Comparisons::default(),
+            negations: Negations::default(),
+            started: false,
+            exhausted: false,
+        }
+    }
+
+    /// Projects the output onto the first `head_arity` variables with set
+    /// semantics. The remaining variables are only searched until one
+    /// satisfying binding is found for each distinct head prefix.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `head_arity` exceeds the number of variables.
+    pub fn with_head_arity(mut self, head_arity: usize) -> Self {
+        assert!(
+            head_arity <= self.arity,
+            "head arity {head_arity} exceeds join arity {}",
+            self.arity
+        );
+        self.head_arity = head_arity;
+        self
+    }
+
+    /// Restricts the join to bindings satisfying `comparisons`.
+    pub(crate) fn with_comparisons(mut self, comparisons: Comparisons) -> Self {
+        self.comparisons = comparisons;
+        self
+    }
+
+    /// Excludes bindings matched by any of `negations`.
+    pub(crate) fn with_negations(mut self, negations: Negations<IT>) -> Self {
+        self.negations = negations;
+        self
+    }
+
+    /// Binds the next variable: opens every participant and positions them
+    /// on their first common key. On failure nothing is left open and
+    /// `false` is returned.
+    fn open(&mut self) -> bool {
+        let depth = self.drivers.len();
+        let participants = &self.variable_to_iter_map[depth];
+        for (opened, &i) in participants.iter().enumerate() {
+            if !self.iters[i].open() {
+                for &j in &participants[..opened] {
+                    self.iters[j].up();
+                }
+                return false;
+            }
+        }
+        let driver = self.smallest(depth);
+        self.drivers.push(driver);
+        self.bindings.push(0);
+        if self.align() {
+            true
+        } else {
+            self.up();
+            false
+        }
+    }
+
+    /// Unbinds the deepest variable, closing its participants.
+    fn up(&mut self) {
+        let depth = self.drivers.len() - 1;
+        for &i in &self.variable_to_iter_map[depth] {
+            self.iters[i].up();
+        }
+        self.drivers.pop();
+        self.bindings.pop();
+    }
+
+    /// Returns the participant at `depth` with the fewest children, leaving
+    /// every participant rewound to its first child.
+    ///
+    /// Participants are stepped in lockstep until one runs out, so this
+    /// costs time proportional to the smallest child set rather than their
+    /// sum.
+    fn smallest(&mut self, depth: usize) -> usize {
+        let participants = &self.variable_to_iter_map[depth];
+        if participants.len() == 1 {
+            return participants[0];
+        }
+        let smallest = 'lockstep: loop {
+            for &i in participants {
+                if self.iters[i].next().is_none() {
+                    break 'lockstep i;
+                }
+            }
+        };
+        for &i in participants {
+            self.iters[i].up();
+            self.iters[i].open();
+        }
+        smallest
+    }
+
+    /// Advances the deepest driver until its key satisfies the comparisons
+    /// checked at this depth, every other participant can seek to it, and no
+    /// negated predicate checked here matches. Returns `false` once the
+    /// driver, or any participant, runs out.
+    fn align(&mut self) -> bool {
+        let depth = self.drivers.len() - 1;
+        let driver = self.drivers[depth];
+        loop {
+            let Some(key) = self.iters[driver].key() else {
+                return false;
+            };
+            if !self.comparisons.is_free(depth) {
+                match self.comparisons.check(depth, key, &self.bindings[..depth]) {
+                    | Verdict::Admit => {},
+                    | Verdict::Skip => {
+                        self.iters[driver].next();
+                        continue;
+                    },
+                    | Verdict::SeekTo(lower) => {
+                        self.iters[driver].seek(lower);
+                        continue;
+                    },
+                    | Verdict::Exhausted => return false,
+                }
+            }
+            let mut mismatch = None;
+            for &i in &self.variable_to_iter_map[depth] {
+                if i == driver {
+                    continue;
+                }
+                // Trie iterators only seek forwards; a probe already past
+                // `key` is itself the mismatch.
+                if self.iters[i].key().is_some_and(|k| k < key) {
+                    self.iters[i].seek(key);
+                }
+                match self.iters[i].key() {
+                    | Some(k) if k == key => {},
+                    | Some(k) => {
+                        mismatch = Some(k);
+                        break;
+                    },
+                    | None => return false,
+                }
+            }
+            match mismatch {
+                | None => {
+                    self.bindings[depth] = key;
+                    if self.negations.is_free(depth) {
+                        return true;
+                    }
+                    match self.negations.check(depth, &self.bindings[..=depth]) {
+                        | Verdict::Admit => return true,
+                        | Verdict::Exhausted => return false,
+                        | _ => {
+                            self.iters[driver].next();
+                        },
+                    }
+                },
+                // No key below the probe's can match, and seeking (rather
+                // than stepping) keeps every later probe moving forward.
+                | Some(k) => {
+                    self.iters[driver].seek(k);
+                },
+            }
+        }
+    }
+
+    /// Moves the deepest variable to its next common key.
+    fn advance(&mut self) -> bool {
+        let driver = self.drivers[self.drivers.len() - 1];
+        self.iters[driver].next();
+        self.align()
+    }
+}
+
+impl<IT> Iterator for GenericJoinIter<IT>
+where
+    IT: TrieIterator,
+{
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.exhausted || self.arity == 0 {
+            return None;
+        }
+
+        let mut found = if self.started {
+            // The previous result's body-only suffix was only a witness;
+            // move on from its head prefix.
+            while self.drivers.len() > self.head_arity {
+                self.up();
+            }
+            !self.drivers.is_empty() && self.advance()
+        } else {
+            self.started = true;
+            self.open()
+        };
+
+        loop {
+            if found {
+                if self.drivers.len() == self.arity {
+                    return Some(self.bindings[..self.head_arity].to_vec());
+                }
+                found = self.open() || self.advance();
+            } else {
+                if self.drivers.len() <= 1 {
+                    self.exhausted = true;
+                    return None;
+                }
+                self.up();
+                found = self.advance();
+            }
+        }
+    }
+}
+
+/// Entry point for Generic Join, implementing [`JoinAlgo`] for any
+/// [`TrieIterable`] data structure.
+pub struct GenericJoin {}
+
+impl<DS> JoinAlgo<DS> for GenericJoin
+where
+    DS: TrieIterable,
+{
+    const ORDERED: bool = true;
+
+    fn join_iter(
+        query: JoinQuery, datastructures: HashMap<String, &DS>,
+    ) -> impl Iterator<Item = Vec<usize>> {
+        let order = variable_order(&query);
+        <Self as JoinAlgo<DS>>::join_iter_with_order(query, order, datastructures)
+    }
+
+    fn join_iter_with_order(
+        query: JoinQuery, order: Vec<String>, datastructures: HashMap<String, &DS>,
+    ) -> impl Iterator<Item = Vec<usize>> {
+        let (variables, rel_variables, head_arity) = build_variable_index(&query, &order);
+
+        let trie_iters: Vec<_> = query
+            .body
+            .iter()
+            .map(|pred| {
+                let ds = datastructures
+                    .get(&pred.name)
+                    .expect("Missing datastructure for predicate name");
+                ds.trie_iter()
+            })
+            .collect();
+
+        let negations = Negations::new(&query.negated, &order, |name| {
+            datastructures
+                .get(name)
+                .expect("Missing datastructure for negated predicate name")
+                .trie_iter()
+        });
+
+        let head_positions = head_positions(&query, &order);
+        let expand = !head_positions.iter().copied().eq(0..head_arity);
+
+        GenericJoinIter::new(variables, rel_variables, trie_iters)
+            .with_head_arity(head_arity)
+            .with_comparisons(Comparisons::new(&query.comparisons, &order))
+            .with_negations(negations)
+            .map(move |tuple| {
+                if expand {
+                    head_positions.iter().map(|&i| tuple[i]).collect()
+                } else {
+                    tuple
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::leapfrog_triejoin::LeapfrogTriejoin,
+        kermit_ds::{ColumnTrie, Relation, TreeTrie},
+        rand::{rngs::StdRng, Rng, SeedableRng},
+    };
+
+    fn join<R, JA>(query: &str, relations: Vec<(&str, &R)>) -> Vec<Vec<usize>>
+    where
+        R: TrieIterable,
+        JA: JoinAlgo<R>,
+    {
+        let query: JoinQuery = query.parse().unwrap();
+        let relations = relations
+            .into_iter()
+            .map(|(name, r)| (name.to_string(), r))
+            .collect();
+        let mut rows: Vec<_> = JA::join_iter(query, relations).collect();
+        rows.sort();
+        rows
+    }
+
+    #[test]
+    fn triangle() {
+        let edge = TreeTrie::from_tuples(2.into(), vec![vec![1, 2], vec![1, 3], vec![2, 3], vec![
+            3, 4,
+        ]]);
+        assert_eq!(
+            join::<_, GenericJoin>("Q(A, B, C) :- e(A, B), e(B, C), e(A, C).", vec![(
+                "e", &edge
+            )]),
+            vec![vec![1, 2, 3]]
+        );
+    }
+
+    #[test]
+    fn single_relation_scan() {
+        let r = ColumnTrie::from_tuples(2.into(), vec![vec![1, 2], vec![1, 3], vec![4, 5]]);
+        assert_eq!(
+            join::<_, GenericJoin>("Q(X, Y) :- r(X, Y).", vec![("r", &r)]),
+            vec![vec![1, 2], vec![1, 3], vec![4, 5]]
+        );
+    }
+
+    #[test]
+    fn empty_relation_yields_nothing() {
+        let r = TreeTrie::from_tuples(2.into(), vec![vec![1, 2]]);
+        let s = TreeTrie::from_tuples(1.into(), vec![]);
+        assert!(
+            join::<_, GenericJoin>("Q(X) :- r(X, Y), s(Y).", vec![("r", &r), ("s", &s)]).is_empty()
+        );
+    }
+
+    #[test]
+    fn dead_end_below_depth_two_keeps_later_results() {
+        // (1, 2) has no Z in s, so the join must back out of depth 3 and
+        // still find (3, 4, 5).
+        let r = TreeTrie::from_tuples(2.into(), vec![vec![1, 2], vec![3, 4]]);
+        let s = TreeTrie::from_tuples(2.into(), vec![vec![2, 9], vec![4, 5]]);
+        let t = TreeTrie::from_tuples(1.into(), vec![vec![5]]);
+        assert_eq!(
+            join::<_, GenericJoin>("Q(X, Y, Z) :- r(X, Y), s(Y, Z), t(Z).", vec![
+                ("r", &r),
+                ("s", &s),
+                ("t", &t)
+            ]),
+            vec![vec![3, 4, 5]]
+        );
+    }
+
+    #[test]
+    fn projection_deduplicates_head_bindings() {
+        let r = TreeTrie::from_tuples(2.into(), vec![vec![1, 10], vec![1, 20], vec![2, 30]]);
+        let s = TreeTrie::from_tuples(1.into(), vec![vec![10], vec![20]]);
+        assert_eq!(
+            join::<_, GenericJoin>("Q(X) :- r(X, Y), s(Y).", vec![("r", &r), ("s", &s)]),
+            vec![vec![1]]
+        );
+    }
+
+    #[test]
+    fn repeated_head_variable() {
+        let r = TreeTrie::from_tuples(1.into(), vec![vec![1], vec![4]]);
+        assert_eq!(
+            join::<_, GenericJoin>("Q(X, X) :- r(X).", vec![("r", &r)]),
+            vec![vec![1, 1], vec![4, 4]]
+        );
+    }
+
+    #[test]
+    fn matches_leapfrog_triejoin_on_random_graphs() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let queries = [
+            "Q(A, B, C) :- e(A, B), e(B, C), e(A, C).",
+            "Q(A, C) :- e(A, B), e(B, C).",
+            "Q(A, B, C, D) :- e(A, B), e(B, C), e(C, D), e(A, D).",
+        ];
+        for _ in 0..5 {
+            let tuples: Vec<Vec<usize>> = (0..60)
+                .map(|_| vec![rng.random_range(0..12), rng.random_range(0..12)])
+                .collect();
+            let edge = ColumnTrie::from_tuples(2.into(), tuples);
+            for query in queries {
+                assert_eq!(
+                    join::<_, GenericJoin>(query, vec![("e", &edge)]),
+                    join::<_, LeapfrogTriejoin>(query, vec![("e", &edge)]),
+                    "{query}"
+                );
+            }
+        }
+    }
+}
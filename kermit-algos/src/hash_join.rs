@@ -0,0 +1,540 @@
+//! A left-deep, pipelined binary hash join — the traditional pairwise plan,
+//! kept as a baseline for the worst-case-optimal algorithms.
+//!
+//! Each body atom after the first is scanned once into a hash table keyed on
+//! the variables it shares with the atoms before it. Tuples of the first atom
+//! then stream through the probes one stage at a time, so an intermediate
+//! result is never materialised; its size is still counted and exposed
+//! through [`HashJoinIter::intermediate_sizes`]. On cyclic queries such as the
+//! triangle these intermediates can be asymptotically larger than the output,
+//! which is exactly where worst-case-optimal joins win.
+
+use {
+    crate::{const_rewrite::parse_const_atom, join_algo::JoinAlgo},
+    kermit_iters::TrieIterable,
+    kermit_parser::{ComparisonOp, JoinQuery, Operand, Term},
+    std::{
+        collections::{HashMap, HashSet},
+        ops::Range,
+    },
+};
+
+/// One atom of the left-deep plan, hashed on the variables it shares with
+/// the atoms joined before it.
+struct Stage {
+    /// Binding slots whose values form the probe key.
+    key_slots: Vec<usize>,
+    /// Binding slots the atom's newly bound variables are written to.
+    new_slots: Vec<usize>,
+    /// The atom's distinct `(key, new values)` rows, grouped by key.
+    rows: Vec<(Vec<usize>, Vec<usize>)>,
+    /// The range of `rows` matching each key.
+    index: HashMap<Vec<usize>, Range<usize>>,
+}
+
+impl Stage {
+    /// Scans `tuples` of an atom with the given `terms`, binding new
+    /// variables to fresh slots in `slots`.
+    fn new(
+        terms: &[Term], tuples: impl IntoIterator<Item = Vec<usize>>,
+        slots: &mut HashMap<String, usize>,
+    ) -> Self {
+        // Columns naming a variable bound by an earlier atom form the probe
+        // key; the rest bind new variables.
+        let mut key_slots = Vec::new();
+        let mut key_columns = Vec::new();
+        let mut new_slots = Vec::new();
+        let mut new_columns = Vec::new();
+        // Columns that must equal an earlier column of the same atom.
+        let mut repeats = Vec::new();
+        let mut first_column: HashMap<&str, usize> = HashMap::new();
+        for (column, term) in terms.iter().enumerate() {
+            let Term::Var(name) = term else {
+                continue;
+            };
+            if let Some(&earlier) = first_column.get(name.as_str()) {
+                repeats.push((earlier, column));
+                continue;
+            }
+            first_column.insert(name, column);
+            if let Some(&slot) = slots.get(name) {
+                key_slots.push(slot);
+                key_columns.push(column);
+            } else {
+                let slot = slots.len();
+                slots.insert(name.clone(), slot);
+                new_slots.push(slot);
+                new_columns.push(column);
+            }
+        }
+
+        let mut rows: Vec<(Vec<usize>, Vec<usize>)> = tuples
+            .into_iter()
+            .filter(|tuple| repeats.iter().all(|&(a, b)| tuple[a] == tuple[b]))
+            .map(|tuple| {
+                (
+                    key_columns.iter().map(|&c| tuple[c]).collect(),
+                    new_columns.iter().map(|&c| tuple[c]).collect(),
+                )
+            })
+            .collect();
+        rows.sort_unstable();
+        rows.dedup();
+
+        let mut index: HashMap<Vec<usize>, Range<usize>> = HashMap::new();
+        let mut start = 0;
+        while start < rows.len() {
+            let end = start + rows[start..].partition_point(|(key, _)| *key == rows[start].0);
+            index.insert(rows[start].0.clone(), start..end);
+            start = end;
+        }
+
+        Stage {
+            key_slots,
+            new_slots,
+            rows,
+            index,
+        }
+    }
+}
+
+/// A comparison operand resolved against the binding slots.
+#[derive(Clone, Copy)]
+enum Value {
+    Slot(usize),
+    Const(usize),
+}
+
+impl Value {
+    fn get(self, bindings: &[usize]) -> usize {
+        match self {
+            | Value::Slot(slot) => bindings[slot],
+            | Value::Const(value) => value,
+        }
+    }
+}
+
+/// A check on the bindings, applied once the stage binding its last
+/// variable has bound it.
+enum Filter {
+    /// A comparison built-in.
+    Compare {
+        left: Value,
+        op: ComparisonOp,
+        right: Value,
+    },
+    /// A negated atom: the values of its non-placeholder columns must not
+    /// form a row of `rows`, the negated relation projected onto them.
+    Absent {
+        values: Vec<Value>,
+        rows: HashSet<Vec<usize>>,
+    },
+}
+
+impl Filter {
+    fn holds(&self, bindings: &[usize]) -> bool {
+        match self {
+            | Filter::Compare {
+                left,
+                op,
+                right,
+            } => op.holds(left.get(bindings), right.get(bindings)),
+            | Filter::Absent {
+                values,
+                rows,
+            } => {
+                let row: Vec<usize> = values.iter().map(|v| v.get(bindings)).collect();
+                !rows.contains(&row)
+            },
+        }
+    }
+
+    /// Returns the latest binding slot the filter reads, if any.
+    fn last_slot(&self) -> Option<usize> {
+        let values = match self {
+            | Filter::Compare {
+                left,
+                right,
+                ..
+            } => &[*left, *right][..],
+            | Filter::Absent {
+                values, ..
+            } => values,
+        };
+        values
+            .iter()
+            .filter_map(|v| match v {
+                | Value::Slot(slot) => Some(*slot),
+                | Value::Const(_) => None,
+            })
+            .max()
+    }
+}
+
+/// Orders body atoms for the left-deep plan: starting from the first atom,
+/// repeatedly picks the earliest remaining atom that shares a variable with
+/// those already joined, falling back to the earliest remaining atom (a cross
+/// product) when none does.
+fn plan_order(query: &JoinQuery) -> Vec<usize> {
+    let vars = |i: usize| {
+        query.body[i].terms.iter().filter_map(|t| match t {
+            | Term::Var(name) => Some(name.as_str()),
+            | _ => None,
+        })
+    };
+    let mut remaining: Vec<usize> = (0..query.body.len()).collect();
+    let mut bound: HashSet<&str> = HashSet::new();
+    let mut order = Vec::with_capacity(remaining.len());
+    while !remaining.is_empty() {
+        let pick = remaining
+            .iter()
+            .position(|&i| vars(i).any(|v| bound.contains(v)))
+            .unwrap_or(0);
+        let atom = remaining.remove(pick);
+        bound.extend(vars(atom));
+        order.push(atom);
+    }
+    order
+}
+
+/// An iterator that performs a left-deep pipelined hash join, yielding the
+/// query's head bindings with set semantics.
+pub struct HashJoinIter {
+    /// One stage per body atom, in plan order.
+    stages: Vec<Stage>,
+    /// For each stage, the comparisons whose variables are all bound once
+    /// it has bound its own.
+    filters: Vec<Vec<Filter>>,
+    /// The value bound to each variable slot.
+    bindings: Vec<usize>,
+    /// For each head term, the slot of the variable it names.
+    head_slots: Vec<usize>,
+    /// The remaining matching rows at each stage on the current path.
+    cursors: Vec<Range<usize>>,
+    /// Tuples produced so far by joining the first `i + 1` stages.
+    intermediate_sizes: Vec<usize>,
+    /// Head tuples already yielded.
+    seen: HashSet<Vec<usize>>,
+    started: bool,
+}
+
+impl HashJoinIter {
+    /// Constructs a new `HashJoinIter` for `query`, where `scans[i]` holds
+    /// the tuples of the relation named by body atom `i`, followed by one
+    /// scan per negated atom.
+    ///
+    /// Atoms are joined in the order picked by a simple greedy planner that
+    /// follows the body order but avoids cross products where it can.
+    /// Placeholders and ground atoms match any value. Each comparison and
+    /// negated atom is applied at the first stage where all of its variables
+    /// are bound.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scans` does not hold one entry per body and negated atom,
+    /// if a head, comparison or negated variable occurs in no body atom, or
+    /// if a negated atom holds a malformed constant.
+    pub fn new(query: &JoinQuery, mut scans: Vec<Vec<Vec<usize>>>) -> Self {
+        assert_eq!(
+            scans.len(),
+            query.body.len() + query.negated.len(),
+            "expected one scan per body and negated atom"
+        );
+        let negated_scans = scans.split_off(query.body.len());
+        let mut slots: HashMap<String, usize> = HashMap::new();
+        // Slots are handed out in stage order, so stage `i` binds exactly the
+        // slots below `bound_after[i]`.
+        let mut bound_after = Vec::with_capacity(query.body.len());
+        let stages: Vec<Stage> = plan_order(query)
+            .into_iter()
+            .map(|i| {
+                let stage = Stage::new(
+                    &query.body[i].terms,
+                    std::mem::take(&mut scans[i]),
+                    &mut slots,
+                );
+                bound_after.push(slots.len());
+                stage
+            })
+            .collect();
+
+        let slot = |name: &String| {
+            Value::Slot(
+                *slots
+                    .get(name)
+                    .unwrap_or_else(|| panic!("variable {name} occurs in no body atom")),
+            )
+        };
+        let comparisons = query.comparisons.iter().map(|cmp| {
+            let value = |operand: &Operand| match operand {
+                | Operand::Var(name) => slot(name),
+                | Operand::Int(value) => Value::Const(*value),
+            };
+            Filter::Compare {
+                left: value(&cmp.left),
+                op: cmp.op,
+                right: value(&cmp.right),
+            }
+        });
+        let negations = query.negated.iter().zip(negated_scans).map(|(pred, scan)| {
+            let (columns, values): (Vec<usize>, Vec<Value>) = pred
+                .terms
+                .iter()
+                .enumerate()
+                .filter_map(|(column, term)| match term {
+                    | Term::Var(name) => Some((column, slot(name))),
+                    | Term::Atom(atom) => Some((
+                        column,
+                        Value::Const(
+                            parse_const_atom(atom)
+                                .expect("malformed constant in negated predicate"),
+                        ),
+                    )),
+                    | Term::Int(id) => Some((column, Value::Const(*id))),
+                    | Term::Placeholder => None,
+                    | Term::Str(_) => {
+                        unreachable!("rewrite_atoms drops negations of unknown constants")
+                    },
+                    | Term::Aggregate(..) => {
+                        unreachable!("aggregates only appear in rule heads")
+                    },
+                })
+                .unzip();
+            let rows = scan
+                .into_iter()
+                .map(|tuple| columns.iter().map(|&c| tuple[c]).collect())
+                .collect();
+            Filter::Absent {
+                values,
+                rows,
+            }
+        });
+
+        let mut filters: Vec<Vec<Filter>> = stages.iter().map(|_| Vec::new()).collect();
+        for filter in comparisons.chain(negations) {
+            let stage = filter
+                .last_slot()
+                .map_or(0, |slot| bound_after.partition_point(|&n| n <= slot));
+            filters[stage].push(filter);
+        }
+        let head_slots = query
+            .head
+            .terms
+            .iter()
+            .filter_map(|t| match t {
+                | Term::Var(name) => Some(
+                    *slots
+                        .get(name)
+                        .unwrap_or_else(|| panic!("head variable {name} occurs in no body atom")),
+                ),
+                | _ => None,
+            })
+            .collect();
+
+        HashJoinIter {
+            bindings: vec![0; slots.len()],
+            head_slots,
+            cursors: Vec::with_capacity(stages.len()),
+            intermediate_sizes: vec![0; stages.len()],
+            seen: HashSet::new(),
+            started: false,
+            stages,
+            filters,
+        }
+    }
+
+    /// Returns, for each stage of the plan, the number of tuples produced
+    /// by joining the atoms up to and including it. The last entry is the
+    /// size of the join before projection onto the head.
+    ///
+    /// The counts grow as the iterator is consumed and are final once it is
+    /// exhausted.
+    pub fn intermediate_sizes(&self) -> &[usize] { &self.intermediate_sizes }
+
+    /// Pushes the rows of stage `depth` matching the current bindings.
+    fn probe(&mut self, depth: usize) {
+        let stage = &self.stages[depth];
+        let key: Vec<usize> = stage.key_slots.iter().map(|&s| self.bindings[s]).collect();
+        let range = stage.index.get(&key).cloned().unwrap_or(0..0);
+        self.cursors.push(range);
+    }
+}
+
+impl Iterator for HashJoinIter {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.started {
+            self.started = true;
+            if self.stages.is_empty() {
+                return None;
+            }
+            self.probe(0);
+        }
+        while let Some(cursor) = self.cursors.last_mut() {
+            let Some(row) = cursor.next() else {
+                self.cursors.pop();
+                continue;
+            };
+            let depth = self.cursors.len() - 1;
+            let stage = &self.stages[depth];
+            for (&slot, &value) in stage.new_slots.iter().zip(&stage.rows[row].1) {
+                self.bindings[slot] = value;
+            }
+            if !self.filters[depth].iter().all(|f| f.holds(&self.bindings)) {
+                continue;
+            }
+            self.intermediate_sizes[depth] += 1;
+            if depth + 1 < self.stages.len() {
+                self.probe(depth + 1);
+                continue;
+            }
+            let tuple: Vec<usize> = self.head_slots.iter().map(|&s| self.bindings[s]).collect();
+            if self.seen.insert(tuple.clone()) {
+                return Some(tuple);
+            }
+        }
+        None
+    }
+}
+
+/// The binary hash join algorithm; see [`HashJoinIter`].
+pub struct HashJoin {}
+
+impl HashJoin {
+    fn iter<DS>(query: JoinQuery, datastructures: HashMap<String, &DS>) -> HashJoinIter
+    where
+        DS: TrieIterable,
+    {
+        let scans = query
+            .body
+            .iter()
+            .chain(&query.negated)
+            .map(|pred| {
+                datastructures
+                    .get(&pred.name)
+                    .expect("Missing datastructure for predicate name")
+                    .trie_iter()
+                    .into_iter()
+                    .collect()
+            })
+            .collect();
+        HashJoinIter::new(&query, scans)
+    }
+}
+
+impl<DS> JoinAlgo<DS> for HashJoin
+where
+    DS: TrieIterable,
+{
+    fn join_iter(
+        query: JoinQuery, datastructures: HashMap<String, &DS>,
+    ) -> impl Iterator<Item = Vec<usize>> {
+        Self::iter(query, datastructures)
+    }
+
+    fn intermediate_sizes(
+        query: JoinQuery, datastructures: HashMap<String, &DS>,
+    ) -> Option<Vec<usize>> {
+        let mut iter = Self::iter(query, datastructures);
+        iter.by_ref().for_each(drop);
+        Some(iter.intermediate_sizes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::leapfrog_triejoin::LeapfrogTriejoin,
+        kermit_ds::{ColumnTrie, Relation, TreeTrie},
+        rand::{rngs::StdRng, Rng, SeedableRng},
+    };
+
+    fn join<R, JA>(query: &str, relations: Vec<(&str, &R)>) -> Vec<Vec<usize>>
+    where
+        R: TrieIterable,
+        JA: JoinAlgo<R>,
+    {
+        let query: JoinQuery = query.parse().unwrap();
+        let relations = relations
+            .into_iter()
+            .map(|(name, r)| (name.to_string(), r))
+            .collect();
+        let mut out: Vec<_> = JA::join_iter(query, relations).collect();
+        out.sort();
+        out
+    }
+
+    fn edges(tuples: Vec<Vec<usize>>) -> TreeTrie { TreeTrie::from_tuples(2.into(), tuples) }
+
+    #[test]
+    fn triangle() {
+        let e = edges(vec![vec![1, 2], vec![2, 3], vec![3, 1], vec![1, 3]]);
+        let out = join::<_, HashJoin>("Q(X, Y, Z) :- e(X, Y), e(Y, Z), e(Z, X).", vec![("e", &e)]);
+        assert_eq!(out, vec![vec![1, 2, 3], vec![2, 3, 1], vec![3, 1, 2]]);
+    }
+
+    #[test]
+    fn triangle_counts_path_intermediate() {
+        let e = edges(vec![vec![1, 2], vec![2, 3], vec![3, 1], vec![1, 3]]);
+        let query: JoinQuery = "Q(X, Y, Z) :- e(X, Y), e(Y, Z), e(Z, X).".parse().unwrap();
+        let sizes = <HashJoin as JoinAlgo<TreeTrie>>::intermediate_sizes(
+            query,
+            HashMap::from([("e".to_string(), &e)]),
+        );
+        // 4 edges, 5 two-hop paths, 3 triangles.
+        assert_eq!(sizes, Some(vec![4, 5, 3]));
+    }
+
+    #[test]
+    fn disconnected_atoms_form_a_cross_product() {
+        let r = TreeTrie::from_tuples(1.into(), vec![vec![1], vec![2]]);
+        let s = TreeTrie::from_tuples(1.into(), vec![vec![7], vec![8]]);
+        let out = join::<_, HashJoin>("Q(X, Y) :- r(X), s(Y).", vec![("r", &r), ("s", &s)]);
+        assert_eq!(out, vec![vec![1, 7], vec![1, 8], vec![2, 7], vec![2, 8]]);
+    }
+
+    #[test]
+    fn plan_avoids_cross_products() {
+        let query: JoinQuery = "Q(X) :- a(X, Y), b(Z, W), c(Y, Z).".parse().unwrap();
+        assert_eq!(plan_order(&query), vec![0, 2, 1]);
+    }
+
+    #[test]
+    fn repeated_variable_and_placeholder() {
+        let r = TreeTrie::from_tuples(3.into(), vec![vec![1, 1, 5], vec![1, 2, 5], vec![3, 3, 6]]);
+        let out = join::<_, HashJoin>("Q(X) :- r(X, X, _).", vec![("r", &r)]);
+        assert_eq!(out, vec![vec![1], vec![3]]);
+    }
+
+    #[test]
+    fn projection_deduplicates_head_bindings() {
+        let e = edges(vec![vec![1, 2], vec![1, 3], vec![2, 3]]);
+        let out = join::<_, HashJoin>("Q(X, X) :- e(X, Y).", vec![("e", &e)]);
+        assert_eq!(out, vec![vec![1, 1], vec![2, 2]]);
+    }
+
+    #[test]
+    fn matches_leapfrog_triejoin_on_random_graphs() {
+        let queries = [
+            "Q(A, B, C) :- e(A, B), e(B, C), e(A, C).",
+            "Q(A, B) :- e(A, B), e(B, C).",
+            "Q(A, B, C, D) :- e(A, B), e(B, C), e(C, D), e(A, D).",
+        ];
+        let mut rng = StdRng::seed_from_u64(11);
+        for _ in 0..20 {
+            let tuples: Vec<Vec<usize>> = (0..rng.random_range(0..40))
+                .map(|_| vec![rng.random_range(0..8), rng.random_range(0..8)])
+                .collect();
+            let e = ColumnTrie::from_tuples(2.into(), tuples);
+            for query in queries {
+                assert_eq!(
+                    join::<_, HashJoin>(query, vec![("e", &e)]),
+                    join::<_, LeapfrogTriejoin>(query, vec![("e", &e)]),
+                    "{query}"
+                );
+            }
+        }
+    }
+}
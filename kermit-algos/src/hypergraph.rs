@@ -0,0 +1,467 @@
+//! The hypergraph of a [`JoinQuery`] and the structural measures that bound
+//! how hard it is to join.
+//!
+//! Each variable is a vertex and each positive body predicate a hyperedge
+//! over the variables it mentions. From it, [`QueryHypergraph`] derives
+//! alpha-acyclicity (by GYO reduction), a generalized hypertree decomposition
+//! of minimal fractional width, and the AGM bound on the join's output size.
+//! Worst-case optimal joins run within the AGM bound, and acyclic queries or
+//! queries of low fractional hypertree width admit cheaper plans, so these
+//! are the natural axes to correlate join runtimes with.
+
+use {
+    crate::lp::max_packing,
+    kermit_parser::{JoinQuery, Term},
+    std::collections::HashMap,
+};
+
+/// Queries with at most this many variables get an exact minimum-width
+/// decomposition; larger ones fall back to a greedy min-fill ordering.
+const EXACT_WIDTH_LIMIT: usize = 12;
+
+/// The hypergraph of a query's positive body: its variables, and for each
+/// body predicate, the variables it mentions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryHypergraph {
+    variables: Vec<String>,
+    edges: Vec<Vec<usize>>,
+}
+
+/// A generalized hypertree decomposition of a [`QueryHypergraph`]: a tree of
+/// bags of variables such that every hyperedge lies within some bag and the
+/// bags holding any one variable form a connected subtree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HypertreeDecomposition {
+    /// The bags, each a sorted list of indices into
+    /// [`QueryHypergraph::variables`].
+    pub bags: Vec<Vec<usize>>,
+    /// The tree's edges, as pairs of indices into `bags`.
+    pub tree: Vec<(usize, usize)>,
+    /// The fractional width: the largest fractional edge cover number of
+    /// any bag.
+    pub width: f64,
+}
+
+impl QueryHypergraph {
+    /// Builds the hypergraph of `query`'s positive body predicates.
+    ///
+    /// Variables are numbered in order of first appearance in the body, and
+    /// edge `i` belongs to `query.body[i]`. Constants and placeholders add no
+    /// vertices; the head, comparisons and negated predicates are ignored,
+    /// since they can only shrink the join.
+    pub fn new(query: &JoinQuery) -> Self {
+        let mut variables: Vec<String> = Vec::new();
+        let edges = query
+            .body
+            .iter()
+            .map(|pred| {
+                let mut edge: Vec<usize> = Vec::new();
+                for term in &pred.terms {
+                    if let Term::Var(name) = term {
+                        let v = match variables.iter().position(|known| known == name) {
+                            | Some(v) => v,
+                            | None => {
+                                variables.push(name.clone());
+                                variables.len() - 1
+                            },
+                        };
+                        edge.push(v);
+                    }
+                }
+                edge.sort_unstable();
+                edge.dedup();
+                edge
+            })
+            .collect();
+        QueryHypergraph {
+            variables,
+            edges,
+        }
+    }
+
+    /// Returns the variables, indexed as in [`edges`](Self::edges).
+    pub fn variables(&self) -> &[String] { &self.variables }
+
+    /// Returns each body predicate's variables, as sorted indices into
+    /// [`variables`](Self::variables).
+    pub fn edges(&self) -> &[Vec<usize>] { &self.edges }
+
+    /// Returns whether the hypergraph is alpha-acyclic.
+    ///
+    /// Runs the GYO reduction: repeatedly drops variables that occur in a
+    /// single edge and edges contained in another, and reports whether at
+    /// most one edge survives.
+    pub fn is_alpha_acyclic(&self) -> bool {
+        let mut edges = self.edges.clone();
+        loop {
+            let mut changed = false;
+            for v in 0..self.variables.len() {
+                let mut holders = edges.iter_mut().filter(|edge| edge.contains(&v));
+                if let (Some(edge), None) = (holders.next(), holders.next()) {
+                    edge.retain(|&u| u != v);
+                    changed = true;
+                }
+            }
+            let mut i = 0;
+            while i < edges.len() {
+                let contained = (0..edges.len())
+                    .any(|j| j != i && edges[i].iter().all(|v| edges[j].contains(v)));
+                if contained {
+                    edges.swap_remove(i);
+                    changed = true;
+                } else {
+                    i += 1;
+                }
+            }
+            if !changed {
+                return edges.len() <= 1;
+            }
+        }
+    }
+
+    /// Returns the fractional edge cover number of `vars`: the least total
+    /// weight of edges such that every variable in `vars` is covered by edges
+    /// of weight at least 1.
+    pub fn fractional_edge_cover_number(&self, vars: &[usize]) -> f64 {
+        self.weighted_cover(vars, |_| 1.0)
+    }
+
+    /// Returns the AGM bound on the number of tuples the body's join
+    /// produces before projection, given the cardinality of each body
+    /// predicate's relation in body order.
+    ///
+    /// This is the product of the cardinalities raised to the weights of an
+    /// optimal fractional edge cover, found by minimising its logarithm. It
+    /// is 0 if any relation is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cardinalities` does not have one entry per edge.
+    pub fn agm_bound(&self, cardinalities: &[usize]) -> f64 {
+        assert_eq!(cardinalities.len(), self.edges.len());
+        if cardinalities.contains(&0) {
+            return 0.0;
+        }
+        let all: Vec<usize> = (0..self.variables.len()).collect();
+        self.weighted_cover(&all, |e| (cardinalities[e] as f64).ln())
+            .exp()
+    }
+
+    /// Returns a generalized hypertree decomposition of minimal fractional
+    /// width.
+    ///
+    /// The decomposition comes from the elimination ordering of the
+    /// variables whose bags have the smallest largest fractional edge cover
+    /// number, found exactly by dynamic programming over subsets for up to 12
+    /// variables, and by greedy min-fill beyond that, in which case the width
+    /// is only an upper bound. Alpha-acyclic queries have width 1, the
+    /// triangle 1.5.
+    pub fn decomposition(&self) -> HypertreeDecomposition {
+        let order = if self.variables.len() <= EXACT_WIDTH_LIMIT {
+            self.exact_order()
+        } else {
+            self.min_fill_order()
+        };
+        self.decompose_along(&order)
+    }
+
+    /// Minimises `Σ weight(e) · x_e` over fractional covers `x` of `vars`,
+    /// through the dual packing LP.
+    fn weighted_cover(&self, vars: &[usize], weight: impl Fn(usize) -> f64) -> f64 {
+        let rows: Vec<(Vec<usize>, f64)> = self
+            .edges
+            .iter()
+            .enumerate()
+            .filter_map(|(e, edge)| {
+                let support: Vec<usize> = (0..vars.len())
+                    .filter(|&i| edge.contains(&vars[i]))
+                    .collect();
+                (!support.is_empty()).then(|| (support, weight(e)))
+            })
+            .collect();
+        max_packing(vars.len(), &rows)
+    }
+
+    /// Returns the adjacency matrix of the primal graph, in which two
+    /// variables are adjacent if they share an edge.
+    fn adjacency(&self) -> Vec<Vec<bool>> {
+        let n = self.variables.len();
+        let mut adjacent = vec![vec![false; n]; n];
+        for edge in &self.edges {
+            for &u in edge {
+                for &v in edge {
+                    adjacent[u][v] |= u != v;
+                }
+            }
+        }
+        adjacent
+    }
+
+    /// Finds a minimum-width elimination ordering: `best[S]` is the least
+    /// width of eliminating the variables in `S` first, and the variable
+    /// eliminated after `S` gets the bag of itself plus every later
+    /// variable it reaches through `S`.
+    fn exact_order(&self) -> Vec<usize> {
+        let n = self.variables.len();
+        let adjacent = self.adjacency();
+        let full = (1usize << n) - 1;
+        let mut widths: HashMap<usize, f64> = HashMap::new();
+        let mut best = vec![f64::INFINITY; full + 1];
+        let mut last = vec![0; full + 1];
+        best[0] = 0.0;
+        for set in 1..=full {
+            for v in (0..n).filter(|&v| set & (1 << v) != 0) {
+                let before = set & !(1 << v);
+                let bag = eliminated_bag(&adjacent, before, v);
+                let width = *widths
+                    .entry(bag)
+                    .or_insert_with(|| self.fractional_edge_cover_number(&members(bag, n)));
+                let cost = best[before].max(width);
+                if cost < best[set] {
+                    best[set] = cost;
+                    last[set] = v;
+                }
+            }
+        }
+
+        let mut order = Vec::with_capacity(n);
+        let mut set = full;
+        while set != 0 {
+            order.push(last[set]);
+            set &= !(1 << last[set]);
+        }
+        order.reverse();
+        order
+    }
+
+    /// Orders the variables by repeatedly eliminating the one whose
+    /// remaining neighbours need the fewest fill edges to form a clique.
+    fn min_fill_order(&self) -> Vec<usize> {
+        let n = self.variables.len();
+        let mut adjacent = self.adjacency();
+        let mut remaining: Vec<usize> = (0..n).collect();
+        let mut order = Vec::with_capacity(n);
+        while !remaining.is_empty() {
+            let neighbours = |v: usize| -> Vec<usize> {
+                remaining
+                    .iter()
+                    .copied()
+                    .filter(|&u| adjacent[v][u])
+                    .collect()
+            };
+            let fill = |v: usize| {
+                let ns = neighbours(v);
+                ns.iter()
+                    .enumerate()
+                    .flat_map(|(i, &a)| ns[i + 1..].iter().map(move |&b| (a, b)))
+                    .filter(|&(a, b)| !adjacent[a][b])
+                    .count()
+            };
+            let (at, &v) = remaining
+                .iter()
+                .enumerate()
+                .min_by_key(|&(_, &v)| fill(v))
+                .expect("remaining is not empty");
+            let ns = neighbours(v);
+            for &a in &ns {
+                for &b in &ns {
+                    adjacent[a][b] |= a != b;
+                }
+            }
+            remaining.remove(at);
+            order.push(v);
+        }
+        order
+    }
+
+    /// Builds the decomposition of an elimination ordering: each variable's
+    /// bag holds it and its not yet eliminated neighbours, which are then
+    /// made adjacent, and hangs off the bag of the neighbour eliminated next.
+    fn decompose_along(&self, order: &[usize]) -> HypertreeDecomposition {
+        let n = self.variables.len();
+        let mut adjacent = self.adjacency();
+        let mut position = vec![0; n];
+        for (i, &v) in order.iter().enumerate() {
+            position[v] = i;
+        }
+
+        let mut bags = Vec::with_capacity(n);
+        let mut parents = Vec::with_capacity(n);
+        for &v in order {
+            let ns: Vec<usize> = (0..n)
+                .filter(|&u| adjacent[v][u] && position[u] > position[v])
+                .collect();
+            for &a in &ns {
+                for &b in &ns {
+                    adjacent[a][b] |= a != b;
+                }
+            }
+            parents.push(ns.iter().copied().min_by_key(|&u| position[u]));
+            let mut bag = ns;
+            bag.push(v);
+            bag.sort_unstable();
+            bags.push(bag);
+        }
+
+        // Disconnected components end in separate roots; chaining them keeps
+        // the decomposition a tree without sharing any variable.
+        let mut tree = Vec::with_capacity(n.saturating_sub(1));
+        let mut previous_root = None;
+        for (i, parent) in parents.into_iter().enumerate() {
+            match parent {
+                | Some(u) => tree.push((i, position[u])),
+                | None => {
+                    if let Some(root) = previous_root {
+                        tree.push((root, i));
+                    }
+                    previous_root = Some(i);
+                },
+            }
+        }
+
+        let width = bags
+            .iter()
+            .map(|bag| self.fractional_edge_cover_number(bag))
+            .fold(0.0, f64::max);
+        HypertreeDecomposition {
+            bags,
+            tree,
+            width,
+        }
+    }
+}
+
+/// Returns, as a bitset, `v` and every variable outside `eliminated`
+/// reachable from `v` through variables in `eliminated`.
+fn eliminated_bag(adjacent: &[Vec<bool>], eliminated: usize, v: usize) -> usize {
+    let mut bag = 1 << v;
+    let mut visited = bag;
+    let mut stack = vec![v];
+    while let Some(u) = stack.pop() {
+        for (w, _) in adjacent[u].iter().enumerate().filter(|(_, &a)| a) {
+            if visited & (1 << w) == 0 {
+                visited |= 1 << w;
+                if eliminated & (1 << w) != 0 {
+                    stack.push(w);
+                } else {
+                    bag |= 1 << w;
+                }
+            }
+        }
+    }
+    bag
+}
+
+/// Lists the members of a bitset over `n` variables.
+fn members(set: usize, n: usize) -> Vec<usize> { (0..n).filter(|&v| set & (1 << v) != 0).collect() }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hypergraph(query: &str) -> QueryHypergraph {
+        QueryHypergraph::new(&query.parse::<JoinQuery>().unwrap())
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6 * expected.max(1.0),
+            "{actual} != {expected}"
+        );
+    }
+
+    /// Checks that every edge lies in a bag and that each variable's bags
+    /// are connected in the tree.
+    fn assert_valid(graph: &QueryHypergraph, decomposition: &HypertreeDecomposition) {
+        let bags = &decomposition.bags;
+        for edge in graph.edges() {
+            assert!(
+                bags.iter().any(|bag| edge.iter().all(|v| bag.contains(v))),
+                "edge {edge:?} in no bag of {bags:?}"
+            );
+        }
+        assert_eq!(decomposition.tree.len(), bags.len().saturating_sub(1));
+        for v in 0..graph.variables().len() {
+            let holding: Vec<usize> = (0..bags.len()).filter(|&b| bags[b].contains(&v)).collect();
+            let mut reached = vec![holding[0]];
+            while let Some(&(a, b)) = decomposition.tree.iter().find(|&&(a, b)| {
+                holding.contains(&a)
+                    && holding.contains(&b)
+                    && reached.contains(&a) != reached.contains(&b)
+            }) {
+                reached.push(if reached.contains(&a) {
+                    b
+                } else {
+                    a
+                });
+            }
+            assert_eq!(reached.len(), holding.len(), "bags of {v} are disconnected");
+        }
+    }
+
+    #[test]
+    fn builds_edges_from_positive_body_variables() {
+        let graph = hypergraph("Q(X) :- p(X, Y, X), q(Y, c3, _), not r(X, _), X < 4, s(c1).");
+        assert_eq!(graph.variables(), ["X", "Y"]);
+        assert_eq!(graph.edges(), [vec![0, 1], vec![1], vec![]]);
+    }
+
+    #[test]
+    fn gyo_recognises_acyclic_queries() {
+        for query in [
+            "P(X, W) :- e(X, Y), e(Y, Z), e(Z, W).",
+            "S(X) :- e(X, Y), e(X, Z), e(X, W).",
+            "T(X, Y, Z) :- e(X, Y), e(Y, Z), e(X, Z), t(X, Y, Z).",
+            "Q(X, Y) :- a(X), b(Y).",
+        ] {
+            assert!(hypergraph(query).is_alpha_acyclic(), "{query}");
+        }
+        for query in [
+            "T(X, Y, Z) :- e(X, Y), e(Y, Z), e(X, Z).",
+            "C(X, Y, Z, W) :- e(X, Y), e(Y, Z), e(Z, W), e(W, X).",
+        ] {
+            assert!(!hypergraph(query).is_alpha_acyclic(), "{query}");
+        }
+    }
+
+    #[test]
+    fn decompositions_have_minimal_fractional_width() {
+        for (query, width) in [
+            ("P(X, W) :- e(X, Y), e(Y, Z), e(Z, W).", 1.0),
+            ("T(X, Y, Z) :- e(X, Y), e(Y, Z), e(X, Z), t(X, Y, Z).", 1.0),
+            ("T(X, Y, Z) :- e(X, Y), e(Y, Z), e(X, Z).", 1.5),
+            ("C(X, Y, Z, W) :- e(X, Y), e(Y, Z), e(Z, W), e(W, X).", 2.0),
+            (
+                "K(A, B, C, D) :- e(A, B), e(A, C), e(A, D), e(B, C), e(B, D), e(C, D).",
+                2.0,
+            ),
+            ("Q(X, Y) :- a(X), b(Y).", 1.0),
+        ] {
+            let graph = hypergraph(query);
+            let decomposition = graph.decomposition();
+            assert_close(decomposition.width, width);
+            assert_valid(&graph, &decomposition);
+        }
+    }
+
+    #[test]
+    fn min_fill_decompositions_are_valid() {
+        let graph = hypergraph("C(X, Y, Z, W) :- e(X, Y), e(Y, Z), e(Z, W), e(W, X), a(X).");
+        let decomposition = graph.decompose_along(&graph.min_fill_order());
+        assert_valid(&graph, &decomposition);
+        assert_close(decomposition.width, 2.0);
+    }
+
+    #[test]
+    fn agm_bound_uses_an_optimal_fractional_cover() {
+        let triangle = hypergraph("T(X, Y, Z) :- e(X, Y), e(Y, Z), e(X, Z).");
+        assert_close(triangle.agm_bound(&[100, 100, 100]), 1000.0);
+        assert_close(triangle.agm_bound(&[4, 100, 100]), 200.0);
+        assert_eq!(triangle.agm_bound(&[4, 0, 100]), 0.0);
+
+        let path = hypergraph("P(X, Z) :- e(X, Y), f(Y, Z).");
+        assert_close(path.agm_bound(&[30, 7]), 210.0);
+        let ground = hypergraph("G(X) :- p(X), q(c1).");
+        assert_close(ground.agm_bound(&[5, 3]), 5.0);
+    }
+}
@@ -8,9 +8,49 @@ pub trait JoinAlgo<DS>
 where
     DS: JoinIterable,
 {
+    /// Whether [`join_iter_with_order`](Self::join_iter_with_order) yields
+    /// the bindings of a query that projects onto every variable in
+    /// lexicographic order of the variable order. Aggregation folds the
+    /// groups of such algorithms as they stream past.
+    const ORDERED: bool = false;
+
     /// Joins the given iterables based on the specified join plan.
-    /// Returns an iterator over the resulting join.
+    /// Returns an iterator over the resulting join, projected onto the
+    /// query's head variables with set semantics: each distinct head binding
+    /// is yielded exactly once.
     fn join_iter(
         query: JoinQuery, datastructures: HashMap<String, &DS>,
     ) -> impl Iterator<Item = Vec<usize>>;
+
+    /// Like [`join_iter`](Self::join_iter), but binds variables in the given
+    /// global `order` (see [`VariableOrderStrategy`]). Algorithms that do not
+    /// depend on a variable order ignore it.
+    ///
+    /// [`VariableOrderStrategy`]: crate::VariableOrderStrategy
+    fn join_iter_with_order(
+        query: JoinQuery, order: Vec<String>, datastructures: HashMap<String, &DS>,
+    ) -> impl Iterator<Item = Vec<usize>> {
+        let _ = order;
+        Self::join_iter(query, datastructures)
+    }
+
+    /// Counts the tuples [`join_iter_with_order`](Self::join_iter_with_order)
+    /// would yield. The default drains that iterator; algorithms that can
+    /// count results without materialising each one override it.
+    fn count_with_order(
+        query: JoinQuery, order: Vec<String>, datastructures: HashMap<String, &DS>,
+    ) -> usize {
+        Self::join_iter_with_order(query, order, datastructures).count()
+    }
+
+    /// Runs the join to completion and returns the size of each
+    /// intermediate result its plan produces, in plan order. Returns `None`
+    /// for algorithms that produce no intermediate results, such as the
+    /// worst-case-optimal joins that bind one variable at a time.
+    fn intermediate_sizes(
+        query: JoinQuery, datastructures: HashMap<String, &DS>,
+    ) -> Option<Vec<usize>> {
+        let _ = (query, datastructures);
+        None
+    }
 }
@@ -77,7 +77,7 @@ impl<IT> LeapfrogJoinIterator for LeapfrogJoinIter<IT>
 where
     IT: LinearIterator,
 {
-    fn key(&self) -> Option<usize> { self.iterators[self.p].key() }
+    fn key(&self) -> Option<usize> { self.iterators[self.iterator_indexes[self.p]].key() }
 
     fn leapfrog_init(&mut self) -> bool {
         for iter in &mut self.iterators {
@@ -129,8 +129,11 @@ where
             None
         } else {
             self.p = (self.p + 1) % self.k();
-            self.leapfrog_search();
-            self.key()
+            if self.leapfrog_search() {
+                self.key()
+            } else {
+                None
+            }
         }
     }
 
@@ -171,6 +174,21 @@ mod tests {
         assert_eq!(join_iter.leapfrog_next(), None);
     }
 
+    #[test]
+    fn test_leapfrog_join_iter_next_stops_when_search_runs_out() {
+        // After the common key 1, searching for 2 runs `v1` off its end while
+        // `v2` still has a key; that key must not be reported as common.
+        let v1: Vec<usize> = vec![1, 2];
+        let v2: Vec<usize> = vec![1, 3];
+
+        let mut join_iter = LeapfrogJoinIter::new(vec![v1.linear_iter(), v2.linear_iter()]);
+
+        assert!(join_iter.leapfrog_init());
+        assert_eq!(join_iter.key(), Some(1));
+        assert_eq!(join_iter.leapfrog_next(), None);
+        assert!(join_iter.at_end());
+    }
+
     #[test]
     fn test_leapfrog_join_iter_empty() {
         let v1: Vec<usize> = vec![];
@@ -11,11 +11,14 @@
 
 use {
     crate::{
+        comparison::{Comparisons, Verdict},
         join_algo::JoinAlgo,
         leapfrog_join::{LeapfrogJoinIter, LeapfrogJoinIterator},
+        negation::Negations,
+        variable_order::{build_variable_index, head_positions, variable_order},
     },
     kermit_iters::{LinearIterator, TrieIterable, TrieIterator, TrieIteratorWrapper},
-    kermit_parser::{JoinQuery, Term},
+    kermit_parser::JoinQuery,
     std::collections::HashMap,
 };
 
@@ -43,6 +46,9 @@ where
 {
     /// Number of variables in the join (determines maximum depth).
     arity: usize,
+    /// Number of leading variables that make up the output tuple. Variables
+    /// past this depth are existential: one witness suffices per prefix.
+    head_arity: usize,
     /// Pool of trie iterators, indexed by body predicate position. `None` when
     /// an iterator is currently borrowed by the leapfrog.
     iterator_pool: Vec<Option<IT>>,
@@ -56,16 +62,36 @@ where
     depth: usize,
     /// The inner leapfrog join operating at the current depth.
     leapfrog: LeapfrogJoinIter<IT>,
+    /// Comparison built-ins, each checked at the depth of its later variable.
+    comparisons: Comparisons,
+    /// Negated predicates, each probed at the depth of its latest variable.
+    negations: Negations<IT>,
+    /// The key bound at each open depth, as last admitted by
+    /// [`settle`](Self::settle).
+    bindings: Vec<usize>,
+    /// Whether a comparison's upper bound has ended the current depth while
+    /// the leapfrog still has keys.
+    cut: bool,
 }
 
 impl<IT> LeapfrogJoinIterator for LeapfrogTriejoinIter<IT>
 where
     IT: TrieIterator,
 {
-    fn leapfrog_next(&mut self) -> Option<usize> { self.leapfrog.leapfrog_next() }
+    fn leapfrog_next(&mut self) -> Option<usize> {
+        if self.cut {
+            return None;
+        }
+        self.leapfrog.leapfrog_next()?;
+        if self.settle() {
+            self.leapfrog.key()
+        } else {
+            None
+        }
+    }
 
     fn key(&self) -> Option<usize> {
-        if self.depth == 0 {
+        if self.depth == 0 || self.cut {
             None
         } else {
             self.leapfrog.key()
@@ -77,13 +103,15 @@ where
     fn leapfrog_search(&mut self) -> bool { self.leapfrog.leapfrog_search() }
 
     fn at_end(&self) -> bool {
-        if self.depth == 0 {
+        if self.depth == 0 || self.cut {
             return true;
         }
         self.leapfrog.at_end()
     }
 
-    fn leapfrog_seek(&mut self, seek_key: usize) -> bool { self.leapfrog.leapfrog_seek(seek_key) }
+    fn leapfrog_seek(&mut self, seek_key: usize) -> bool {
+        !self.cut && self.leapfrog.leapfrog_seek(seek_key) && self.settle()
+    }
 }
 
 impl<IT> LeapfrogTriejoinIter<IT>
@@ -123,9 +151,126 @@ where
             active_iter_indices: Vec::new(),
             variable_to_iter_map,
             arity: variables.len(),
+            head_arity: variables.len(),
             depth: 0,
             leapfrog: LeapfrogJoinIter::new(vec![]),
+            comparisons: Comparisons::default(),
+            negations: Negations::default(),
+            bindings: Vec::new(),
+            cut: false,
+        }
+    }
+
+    /// Projects the output onto the first `head_arity` variables with set
+    /// semantics. The remaining variables are only searched until one
+    /// satisfying binding is found for each distinct head prefix.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `head_arity` exceeds the number of variables.
+    pub fn with_head_arity(mut self, head_arity: usize) -> Self {
+        assert!(
+            head_arity <= self.arity,
+            "head arity {head_arity} exceeds join arity {}",
+            self.arity
+        );
+        self.head_arity = head_arity;
+        self
+    }
+
+    /// Restricts the join to bindings satisfying `comparisons`.
+    pub(crate) fn with_comparisons(mut self, comparisons: Comparisons) -> Self {
+        self.comparisons = comparisons;
+        self
+    }
+
+    /// Excludes bindings matched by any of `negations`.
+    pub(crate) fn with_negations(mut self, negations: Negations<IT>) -> Self {
+        self.negations = negations;
+        self
+    }
+
+    /// Moves the current depth forward to its first key admitted by the
+    /// comparisons and negated predicates checked there. Keys below a lower
+    /// bound are passed with a single seek, and a key past an upper bound
+    /// ends the depth. Returns `false` if no admitted key remains.
+    fn settle(&mut self) -> bool {
+        let depth = self.depth - 1;
+        while !self.leapfrog.at_end() {
+            let key = self.leapfrog.key().unwrap();
+            let mut verdict = if self.comparisons.is_free(depth) {
+                Verdict::Admit
+            } else {
+                self.comparisons.check(depth, key, &self.bindings[..depth])
+            };
+            if verdict == Verdict::Admit && !self.negations.is_free(depth) {
+                self.bindings[depth] = key;
+                verdict = self.negations.check(depth, &self.bindings[..=depth]);
+            }
+            match verdict {
+                | Verdict::Admit => {
+                    self.bindings[depth] = key;
+                    return true;
+                },
+                | Verdict::Skip => {
+                    self.leapfrog.leapfrog_next();
+                },
+                | Verdict::SeekTo(lower) => {
+                    self.leapfrog.leapfrog_seek(lower);
+                },
+                | Verdict::Exhausted => break,
+            }
+        }
+        self.cut = true;
+        false
+    }
+
+    /// Counts the tuples the triejoin yields without materialising them.
+    ///
+    /// Walks the join depth-first like its [`IntoIterator`] does, but only
+    /// tallies each head prefix that extends to a full binding instead of
+    /// allocating a tuple for it.
+    pub fn count(mut self) -> usize {
+        if self.arity == 0 {
+            return 0;
+        }
+        self.count_below()
+    }
+
+    /// Counts the head prefixes reachable below the current node, leaving
+    /// the triejoin at its current depth.
+    fn count_below(&mut self) -> usize {
+        if self.depth == self.head_arity {
+            return usize::from(self.has_witness());
+        }
+        if !self.triejoin_open() {
+            return 0;
+        }
+        let mut count = 0;
+        while !LeapfrogJoinIterator::at_end(self) {
+            count += self.count_below();
+            self.leapfrog_next();
+        }
+        self.triejoin_up();
+        count
+    }
+
+    /// Returns `true` if the current node extends to a binding of every
+    /// variable, leaving the triejoin at its current depth.
+    fn has_witness(&mut self) -> bool {
+        if self.depth == self.arity {
+            return true;
+        }
+        if !self.triejoin_open() {
+            return false;
         }
+        let mut found = false;
+        while !found && !LeapfrogJoinIterator::at_end(self) {
+            found = self.has_witness();
+            self.leapfrog_next();
+        }
+        self.triejoin_up();
+        found
     }
 
     /// Swaps iterators between the pool (`self.iterator_pool`) and the active
@@ -162,18 +307,35 @@ impl<IT> LeapfrogTriejoinIterator for LeapfrogTriejoinIter<IT>
 where
     IT: TrieIterator,
 {
+    /// Descends one variable. If any participating iterator cannot open, or
+    /// the leapfrog at the new depth has no common key, the descent is rolled
+    /// back so the triejoin stays at the parent depth and `false` is
+    /// returned, as the [`TrieIterator::open`] contract requires.
     fn triejoin_open(&mut self) -> bool {
         if self.depth == self.arity {
             return false;
         }
         self.depth += 1;
+        self.bindings.push(0);
         self.update_iters();
+        let mut opened = 0;
         for iter in &mut self.leapfrog.iterators {
             if !iter.open() {
-                return false;
+                break;
             }
+            opened += 1;
+        }
+        if opened == self.leapfrog.k() && self.leapfrog_init() && self.settle() {
+            return true;
+        }
+        for iter in self.leapfrog.iterators.iter_mut().take(opened) {
+            iter.up();
         }
-        self.leapfrog_init()
+        self.depth -= 1;
+        self.cut = false;
+        self.bindings.pop();
+        self.update_iters();
+        false
     }
 
     /// Ascends one variable, returning all participating iterators to the
@@ -195,6 +357,8 @@ where
             );
         }
         self.depth -= 1;
+        self.cut = false;
+        self.bindings.pop();
         self.update_iters();
         true
     }
@@ -231,73 +395,13 @@ where
 
     fn into_iter(self) -> Self::IntoIter {
         let arity = self.arity;
-        TrieIteratorWrapper::with_arity(self, arity)
-    }
-}
-
-/// Indexes the variables in a [`JoinQuery`] for the triejoin algorithm.
-///
-/// Performs three passes over the query:
-/// 1. Register head variables — assigns each unique name a numeric index,
-///    starting from 0. Head variables come first so the output tuple order
-///    matches the head declaration.
-/// 2. Register body-only variables — any variable not already seen in the head
-///    gets the next available index.
-/// 3. Build per-relation variable index lists — for each body predicate,
-///    collect the indices of the variables it contains.
-///
-/// Placeholders (`_`) and atoms are skipped in all passes.
-///
-/// Returns `(variables, rel_variables)` where `variables` is `0..num_vars` and
-/// `rel_variables[i]` lists the variable indices for body predicate `i`.
-fn build_variable_index(query: &JoinQuery) -> (Vec<usize>, Vec<Vec<usize>>) {
-    let mut var_to_index: HashMap<String, usize> = HashMap::new();
-    let mut next_index: usize = 0;
-
-    // Helper: assigns a fresh index to a variable name on first sight,
-    // returns the existing index on subsequent encounters.
-    let register_var = |name: &str, map: &mut HashMap<String, usize>, next: &mut usize| {
-        *map.entry(name.to_string()).or_insert_with(|| {
-            let idx = *next;
-            *next += 1;
-            idx
-        })
-    };
-
-    // Pass 1: head variables — establishes output tuple ordering.
-    for t in &query.head.terms {
-        if let Term::Var(ref vname) = t {
-            let _ = register_var(vname, &mut var_to_index, &mut next_index);
-        }
-    }
-
-    // Pass 2: body-only variables — any variable not already seen in the head.
-    for pred in &query.body {
-        for t in &pred.terms {
-            if let Term::Var(ref vname) = t {
-                let _ = register_var(vname, &mut var_to_index, &mut next_index);
-            }
-        }
-    }
-
-    let variables: Vec<usize> = (0..var_to_index.len()).collect();
-
-    // Pass 3: build per-relation variable index lists. Placeholders and atoms
-    // are skipped — they occupy trie levels but don't bind a join variable.
-    let mut rel_variables: Vec<Vec<usize>> = Vec::with_capacity(query.body.len());
-    for pred in &query.body {
-        let mut rel_vars_for_pred: Vec<usize> = Vec::new();
-        for t in &pred.terms {
-            if let Term::Var(ref vname) = t {
-                if let Some(idx) = var_to_index.get(vname) {
-                    rel_vars_for_pred.push(*idx);
-                }
-            }
+        let head_arity = self.head_arity;
+        if head_arity == arity {
+            TrieIteratorWrapper::with_arity(self, arity)
+        } else {
+            TrieIteratorWrapper::with_projection(self, arity, head_arity)
         }
-        rel_variables.push(rel_vars_for_pred);
     }
-
-    (variables, rel_variables)
 }
 
 /// Entry point for the Leapfrog Triejoin algorithm, implementing
@@ -308,35 +412,88 @@ impl<DS> JoinAlgo<DS> for LeapfrogTriejoin
 where
     DS: TrieIterable,
 {
+    const ORDERED: bool = true;
+
     fn join_iter(
         query: JoinQuery, datastructures: HashMap<String, &DS>,
     ) -> impl Iterator<Item = Vec<usize>> {
-        let (variables, rel_variables) = build_variable_index(&query);
-
-        let trie_iters: Vec<_> = query
-            .body
-            .iter()
-            .map(|pred| {
-                let ds = datastructures
-                    .get(&pred.name)
-                    .expect("Missing datastructure for predicate name");
-                ds.trie_iter()
-            })
-            .collect();
+        let order = variable_order(&query);
+        <Self as JoinAlgo<DS>>::join_iter_with_order(query, order, datastructures)
+    }
 
-        LeapfrogTriejoinIter::new(variables, rel_variables, trie_iters).into_iter()
+    fn join_iter_with_order(
+        query: JoinQuery, order: Vec<String>, datastructures: HashMap<String, &DS>,
+    ) -> impl Iterator<Item = Vec<usize>> {
+        // The triejoin emits head variables in `order`; map them back onto
+        // the head's term positions, which also re-expands a head that
+        // repeats a variable (`Q(X, X)`).
+        let head_positions = head_positions(&query, &order);
+        let triejoin = triejoin(&query, &order, &datastructures);
+        let expand = !head_positions.iter().copied().eq(0..triejoin.head_arity);
+
+        triejoin.into_iter().map(move |tuple| {
+            if expand {
+                head_positions.iter().map(|&i| tuple[i]).collect()
+            } else {
+                tuple
+            }
+        })
+    }
+
+    fn count_with_order(
+        query: JoinQuery, order: Vec<String>, datastructures: HashMap<String, &DS>,
+    ) -> usize {
+        triejoin(&query, &order, &datastructures).count()
     }
 }
 
+/// Builds the triejoin for `query` over `datastructures`, binding variables
+/// in `order` and projecting onto the head variables.
+fn triejoin<'a, DS>(
+    query: &JoinQuery, order: &[String], datastructures: &HashMap<String, &'a DS>,
+) -> LeapfrogTriejoinIter<impl TrieIterator + 'a>
+where
+    DS: TrieIterable + 'a,
+{
+    let (variables, rel_variables, head_arity) = build_variable_index(query, order);
+
+    let trie_iters: Vec<_> = query
+        .body
+        .iter()
+        .map(|pred| {
+            let ds = datastructures
+                .get(&pred.name)
+                .expect("Missing datastructure for predicate name");
+            ds.trie_iter()
+        })
+        .collect();
+
+    let negations = Negations::new(&query.negated, order, |name| {
+        datastructures
+            .get(name)
+            .expect("Missing datastructure for negated predicate name")
+            .trie_iter()
+    });
+
+    LeapfrogTriejoinIter::new(variables, rel_variables, trie_iters)
+        .with_head_arity(head_arity)
+        .with_comparisons(Comparisons::new(&query.comparisons, order))
+        .with_negations(negations)
+}
+
 #[cfg(test)]
 mod tests {
     use {
         crate::{
+            join_algo::JoinAlgo,
             leapfrog_join::LeapfrogJoinIterator,
-            leapfrog_triejoin::{LeapfrogTriejoinIter, LeapfrogTriejoinIterator},
+            leapfrog_triejoin::{LeapfrogTriejoin, LeapfrogTriejoinIter, LeapfrogTriejoinIterator},
+            variable_order::variable_order,
         },
         kermit_ds::{Relation, TreeTrie},
         kermit_iters::TrieIterable,
+        kermit_parser::JoinQuery,
+        std::collections::HashMap,
     };
 
     /// Collect triejoin results end-to-end via `into_iter().collect()`.
@@ -642,6 +799,155 @@ mod tests {
         );
     }
 
+    #[test]
+    fn dead_end_below_depth_two_keeps_later_results() {
+        // Regression: a = 1, b = 2, c = 4 has no T(4, _), so the open at depth
+        // 4 fails. That failed open used to leave the triejoin one level deeper
+        // than the wrapper's stack, dropping the valid (1, 3, 5, 7) tuple.
+        let r = TreeTrie::from_tuples(2.into(), vec![vec![1, 2], vec![1, 3]]);
+        let s = TreeTrie::from_tuples(2.into(), vec![vec![2, 4], vec![3, 5]]);
+        let t = TreeTrie::from_tuples(2.into(), vec![vec![5, 7]]);
+        assert_eq!(
+            triejoin_collect(
+                vec![0, 1, 2, 3],
+                vec![vec![0, 1], vec![1, 2], vec![2, 3]],
+                vec![&r, &s, &t],
+            ),
+            vec![vec![1, 3, 5, 7]],
+        );
+    }
+
+    // -- Head projection tests --
+
+    fn join_query(query: &str, relations: Vec<(&str, &TreeTrie)>) -> Vec<Vec<usize>> {
+        let query: JoinQuery = query.parse().unwrap();
+        let relations = relations
+            .into_iter()
+            .map(|(name, r)| (name.to_string(), r))
+            .collect();
+        LeapfrogTriejoin::join_iter(query, relations).collect()
+    }
+
+    #[test]
+    fn projection_deduplicates_head_bindings() {
+        // (2,3) extends to both Z=5 and Z=6 but is emitted once; (3,5) has no
+        // continuation and is dropped.
+        let edge = TreeTrie::from_tuples(2.into(), vec![vec![1, 2], vec![2, 3], vec![3, 5], vec![
+            3, 6,
+        ]]);
+        assert_eq!(
+            join_query("Q(X, Y) :- edge(X, Y), edge(Y, Z).", vec![("edge", &edge)]),
+            vec![vec![1, 2], vec![2, 3]],
+        );
+    }
+
+    #[test]
+    fn projection_onto_single_variable() {
+        let r = TreeTrie::from_tuples(2.into(), vec![vec![1, 10], vec![1, 20], vec![2, 30]]);
+        let s = TreeTrie::from_tuples(1.into(), vec![vec![10], vec![20], vec![30]]);
+        assert_eq!(
+            join_query("Q(X) :- r(X, Y), s(Y).", vec![("r", &r), ("s", &s)]),
+            vec![vec![1], vec![2]],
+        );
+    }
+
+    #[test]
+    fn projection_skips_head_bindings_without_witness() {
+        let r = TreeTrie::from_tuples(2.into(), vec![vec![1, 10], vec![2, 20]]);
+        let s = TreeTrie::from_tuples(1.into(), vec![vec![20]]);
+        assert_eq!(
+            join_query("Q(X) :- r(X, Y), s(Y).", vec![("r", &r), ("s", &s)]),
+            vec![vec![2]],
+        );
+    }
+
+    #[test]
+    fn projection_repeats_duplicated_head_variable() {
+        let r = TreeTrie::from_tuples(2.into(), vec![vec![1, 2], vec![1, 3], vec![4, 5]]);
+        assert_eq!(join_query("Q(X, X) :- r(X, Y).", vec![("r", &r)]), vec![
+            vec![1, 1],
+            vec![4, 4]
+        ],);
+    }
+
+    // -- Counting tests --
+
+    fn count_query(query: &str, relations: Vec<(&str, &TreeTrie)>) -> usize {
+        let query: JoinQuery = query.parse().unwrap();
+        let order = variable_order(&query);
+        let relations = relations
+            .into_iter()
+            .map(|(name, r)| (name.to_string(), r))
+            .collect();
+        LeapfrogTriejoin::count_with_order(query, order, relations)
+    }
+
+    #[test]
+    fn count_matches_collected_results() {
+        let edge = TreeTrie::from_tuples(2.into(), vec![
+            vec![1, 2],
+            vec![2, 3],
+            vec![3, 5],
+            vec![3, 6],
+            vec![5, 1],
+        ]);
+        for query in [
+            "Q(X, Y) :- edge(X, Y).",
+            "Q(X, Y, Z) :- edge(X, Y), edge(Y, Z).",
+            "Q(X, Y) :- edge(X, Y), edge(Y, Z).",
+            "Q(X) :- edge(X, Y), edge(Y, Z), edge(Z, W).",
+            "Q(X, X) :- edge(X, Y).",
+            "Q(A, B, C) :- edge(A, B), edge(B, C), edge(C, A).",
+        ] {
+            assert_eq!(
+                count_query(query, vec![("edge", &edge)]),
+                join_query(query, vec![("edge", &edge)]).len(),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn count_of_empty_join_is_zero() {
+        let r = TreeTrie::from_tuples(1.into(), vec![vec![1], vec![2]]);
+        let s = TreeTrie::from_tuples(1.into(), vec![vec![3]]);
+        assert_eq!(
+            count_query("Q(X) :- r(X), s(X).", vec![("r", &r), ("s", &s)]),
+            0
+        );
+    }
+
+    #[test]
+    fn triangle_ignores_keys_past_an_exhausted_iterator() {
+        // At C for (A, B) = (1, 0), e(0, _) = {1, 3} and e(1, _) = {0, 1}
+        // share only 1; 3 must not be reported once e(1, _) runs out.
+        let e = TreeTrie::from_tuples(2.into(), vec![vec![0, 1], vec![0, 3], vec![1, 0], vec![
+            1, 1,
+        ]]);
+        let mut rows = join_query("Q(A, B, C) :- e(A, B), e(B, C), e(A, C).", vec![("e", &e)]);
+        rows.sort();
+        assert_eq!(rows, vec![
+            vec![0, 1, 1],
+            vec![1, 0, 1],
+            vec![1, 1, 0],
+            vec![1, 1, 1]
+        ]);
+    }
+
+    #[test]
+    fn custom_order_maps_output_back_to_head() {
+        // r is keyed (Y, X); binding Y first lets it join without reordering,
+        // and rows still come out as (X, Y).
+        let r = TreeTrie::from_tuples(2.into(), vec![vec![1, 20], vec![2, 10], vec![2, 30]]);
+        let query: JoinQuery = "Q(X, Y) :- r(Y, X).".parse().unwrap();
+        let order = vec!["Y".to_string(), "X".to_string()];
+        let relations = HashMap::from([("r".to_string(), &r)]);
+        let mut rows: Vec<_> =
+            LeapfrogTriejoin::join_iter_with_order(query, order, relations).collect();
+        rows.sort();
+        assert_eq!(rows, vec![vec![10, 2], vec![20, 1], vec![30, 2]]);
+    }
+
     // #[test_case(
     // vec!["tests/data/a.csv", "tests/data/b.csv", "tests/data/c.csv"],
     // vec![vec![8]];
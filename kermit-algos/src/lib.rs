@@ -1,27 +1,55 @@
 //! Join algorithms for Kermit's relational algebra engine.
 //!
-//! Implements the [Leapfrog Triejoin](https://arxiv.org/abs/1210.0481) algorithm,
-//! which performs worst-case optimal multi-way joins over trie-structured
-//! relations. The algorithm is generic over any data structure that implements
-//! [`TrieIterable`](kermit_iters::TrieIterable).
+//! Implements the [Leapfrog Triejoin](https://arxiv.org/abs/1210.0481) and
+//! [Generic Join](https://arxiv.org/abs/1203.1952) algorithms, which perform
+//! worst-case optimal multi-way joins over trie-structured relations. Both
+//! are generic over any data structure that implements
+//! [`TrieIterable`](kermit_iters::TrieIterable). A binary [`HashJoin`] is
+//! provided as a traditional pairwise baseline.
+//!
+//! Recursive Datalog [`Program`]s are evaluated to a fixpoint by
+//! [`evaluate_program`], which runs a join per rule body using semi-naive
+//! delta relations.
 #![deny(missing_docs)]
 
+mod aggregate;
+mod comparison;
 mod const_rewrite;
+mod generic_join;
+mod hash_join;
+mod hypergraph;
 mod join_algo;
 mod leapfrog_join;
 mod leapfrog_triejoin;
+mod lp;
+mod negation;
+mod seminaive;
 mod singleton;
 mod trie_iter_kind;
+mod validate;
+mod variable_order;
+mod view;
 
-use {clap::ValueEnum, std::str::FromStr};
 pub use {
-    const_rewrite::{rewrite_atoms, ConstSpec, RewriteError},
+    aggregate::{AggregateIter, Aggregation},
+    const_rewrite::{
+        ground_tuple, resolve_constants, resolve_program_constants, rewrite_atoms, ConstSpec,
+        RewriteError,
+    },
+    generic_join::{GenericJoin, GenericJoinIter},
+    hash_join::{HashJoin, HashJoinIter},
+    hypergraph::{HypertreeDecomposition, QueryHypergraph},
     join_algo::JoinAlgo,
-    kermit_parser::JoinQuery,
+    kermit_parser::{JoinQuery, Program},
     leapfrog_triejoin::LeapfrogTriejoin,
+    seminaive::{dependency_order, evaluate_program, ProgramError},
     singleton::SingletonTrieIter,
     trie_iter_kind::TrieIterKind,
+    validate::{validate_query, QueryError},
+    variable_order::{variable_order, VariableOrderStrategy},
+    view::ViewKey,
 };
+use {clap::ValueEnum, std::str::FromStr};
 
 /// The available join algorithm implementations.
 ///
@@ -31,6 +59,12 @@ pub enum JoinAlgorithm {
     /// The [Leapfrog Triejoin](https://arxiv.org/abs/1210.0481) algorithm;
     /// see [`LeapfrogTriejoin`].
     LeapfrogTriejoin,
+    /// The [Generic Join](https://arxiv.org/abs/1203.1952) algorithm; see
+    /// [`GenericJoin`].
+    GenericJoin,
+    /// A left-deep binary hash join, the traditional pairwise baseline; see
+    /// [`HashJoin`].
+    HashJoin,
 }
 
 impl FromStr for JoinAlgorithm {
@@ -39,6 +73,8 @@ impl FromStr for JoinAlgorithm {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             | "leapfrog_triejoin" => Ok(Self::LeapfrogTriejoin),
+            | "generic_join" => Ok(Self::GenericJoin),
+            | "hash_join" => Ok(Self::HashJoin),
             | _ => Err(format!("Invalid join algorithm: {}", s)),
         }
     }
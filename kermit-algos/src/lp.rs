@@ -0,0 +1,97 @@
+//! A dense simplex solver for the small packing LPs behind
+//! [`QueryHypergraph`](crate::QueryHypergraph)'s measures.
+//!
+//! Fractional edge covers are covering LPs; their duals are packing LPs,
+//! whose all-slack basis is feasible whenever the capacities are
+//! non-negative, so a single simplex phase suffices. Queries have a handful
+//! of variables and atoms, so a dense tableau is plenty.
+
+/// Tolerance below which a tableau entry counts as zero.
+const EPS: f64 = 1e-9;
+
+/// Maximises `y_0 + … + y_{vars-1}` over `y ≥ 0`, subject to one constraint
+/// per entry `(support, capacity)` of `rows`: the `y_j` for `j` in `support`
+/// sum to at most `capacity`.
+///
+/// Capacities must be non-negative. Returns [`f64::INFINITY`] if some
+/// variable occurs in no row.
+///
+/// By LP duality this equals the minimum of `Σ capacity_i · x_i` over
+/// `x ≥ 0` such that every variable is covered by rows of total weight at
+/// least 1.
+pub(crate) fn max_packing(vars: usize, rows: &[(Vec<usize>, f64)]) -> f64 {
+    let m = rows.len();
+    let rhs = vars + m;
+    // Row i is constraint i with slack column vars + i; the last row holds
+    // the negated objective coefficients and, in its rhs column, the value.
+    let mut tableau = vec![vec![0.0; rhs + 1]; m + 1];
+    for (i, (support, capacity)) in rows.iter().enumerate() {
+        for &j in support {
+            tableau[i][j] = 1.0;
+        }
+        tableau[i][vars + i] = 1.0;
+        tableau[i][rhs] = *capacity;
+    }
+    tableau[m][..vars].fill(-1.0);
+    let mut basis: Vec<usize> = (vars..rhs).collect();
+
+    // Bland's rule: the lowest-indexed improving column enters and ties in
+    // the ratio test go to the lowest basic column, so degenerate pivots
+    // (zero capacities) cannot cycle.
+    while let Some(entering) = (0..rhs).find(|&j| tableau[m][j] < -EPS) {
+        let leaving = (0..m)
+            .filter(|&i| tableau[i][entering] > EPS)
+            .min_by(|&a, &b| {
+                let ratio = |i: usize| tableau[i][rhs] / tableau[i][entering];
+                ratio(a).total_cmp(&ratio(b)).then(basis[a].cmp(&basis[b]))
+            });
+        let Some(leaving) = leaving else {
+            return f64::INFINITY;
+        };
+
+        let pivot = tableau[leaving][entering];
+        for value in &mut tableau[leaving] {
+            *value /= pivot;
+        }
+        let pivot_row = tableau[leaving].clone();
+        for (i, row) in tableau.iter_mut().enumerate() {
+            let factor = row[entering];
+            if i != leaving && factor != 0.0 {
+                for (value, p) in row.iter_mut().zip(&pivot_row) {
+                    *value -= factor * p;
+                }
+            }
+        }
+        basis[leaving] = entering;
+    }
+    tableau[m][rhs]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn triangle_packs_one_and_a_half() {
+        let rows = [(vec![0, 1], 1.0), (vec![1, 2], 1.0), (vec![0, 2], 1.0)];
+        assert_close(max_packing(3, &rows), 1.5);
+    }
+
+    #[test]
+    fn respects_capacities() {
+        let rows = [(vec![0, 1], 2.0), (vec![1], 0.5), (vec![0], 3.0)];
+        assert_close(max_packing(2, &rows), 2.0);
+        let rows = [(vec![0], 0.0), (vec![0, 1], 4.0)];
+        assert_close(max_packing(2, &rows), 4.0);
+    }
+
+    #[test]
+    fn uncovered_variables_are_unbounded() {
+        assert_eq!(max_packing(2, &[(vec![0], 1.0)]), f64::INFINITY);
+        assert_close(max_packing(0, &[]), 0.0);
+    }
+}
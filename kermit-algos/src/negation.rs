@@ -0,0 +1,258 @@
+//! Negated body predicates (`not p(X, Y)`) compiled for the trie-based
+//! joins.
+//!
+//! Safe rules bind every variable of a negated predicate positively, so the
+//! joins check it at the depth of its latest variable, once the predicate is
+//! fully determined up to placeholders. The check is a membership probe: the
+//! negated relation's trie is descended along the bound values, and the
+//! binding is rejected if the descent reaches a leaf. Placeholders match any
+//! value, so the probe branches over their level.
+
+use {
+    crate::{comparison::Verdict, const_rewrite::parse_const_atom},
+    kermit_iters::TrieIterator,
+    kermit_parser::{Predicate, Term},
+    std::collections::HashMap,
+};
+
+/// What a negated predicate's term matches, as seen from the join.
+#[derive(Clone, Copy)]
+enum Slot {
+    /// The key bound at a depth.
+    Depth(usize),
+    /// A dictionary-ID constant.
+    Const(usize),
+    /// A placeholder, matching any value.
+    Any,
+}
+
+/// A negated predicate bound to an iterator over its relation.
+struct Probe<IT> {
+    iter: IT,
+    slots: Vec<Slot>,
+}
+
+impl<IT> Probe<IT>
+where
+    IT: TrieIterator,
+{
+    /// Returns `true` if the relation holds a tuple matching `slots` from
+    /// `column` on, below the iterator's current position. Leaves the
+    /// iterator where it started.
+    fn matches(&mut self, column: usize, bound: &[usize]) -> bool {
+        if column == self.slots.len() {
+            return true;
+        }
+        if !self.iter.open() {
+            return false;
+        }
+        let found = match self.slots[column] {
+            | Slot::Any => {
+                let mut found = false;
+                while !found && !self.iter.at_end() {
+                    found = self.matches(column + 1, bound);
+                    self.iter.next();
+                }
+                found
+            },
+            | Slot::Depth(depth) => self.seek_and_match(bound[depth], column, bound),
+            | Slot::Const(value) => self.seek_and_match(value, column, bound),
+        };
+        self.iter.up();
+        found
+    }
+
+    fn seek_and_match(&mut self, value: usize, column: usize, bound: &[usize]) -> bool {
+        // Trie iterators only seek forwards.
+        if self.iter.key().is_some_and(|k| k < value) {
+            self.iter.seek(value);
+        }
+        self.iter.key() == Some(value) && self.matches(column + 1, bound)
+    }
+}
+
+/// A query's negated predicates compiled against a variable order. The
+/// default excludes nothing.
+pub(crate) struct Negations<IT> {
+    /// For each depth, the probes checked once its key is bound.
+    probes: Vec<Vec<Probe<IT>>>,
+    /// Whether a negated predicate without variables matches, in which case
+    /// nothing is admitted.
+    blocked: bool,
+}
+
+impl<IT> Default for Negations<IT> {
+    fn default() -> Self {
+        Negations {
+            probes: Vec::new(),
+            blocked: false,
+        }
+    }
+}
+
+impl<IT> Negations<IT>
+where
+    IT: TrieIterator,
+{
+    /// Compiles `negated` for a join binding variables in `order`, probing
+    /// each predicate's relation through the iterator `iter` returns for its
+    /// name.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a negated predicate mentions a variable missing from
+    /// `order` or a malformed constant; [`rewrite_atoms`] and the parser
+    /// rule both out.
+    ///
+    /// [`rewrite_atoms`]: crate::rewrite_atoms
+    pub(crate) fn new(
+        negated: &[Predicate], order: &[String], mut iter: impl FnMut(&str) -> IT,
+    ) -> Self {
+        let depth_of: HashMap<&str, usize> = order
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.as_str(), i))
+            .collect();
+
+        let mut probes: Vec<Vec<Probe<IT>>> = (0..order.len()).map(|_| Vec::new()).collect();
+        let mut blocked = false;
+        for pred in negated {
+            let slots: Vec<Slot> = pred
+                .terms
+                .iter()
+                .map(|term| match term {
+                    | Term::Var(name) => {
+                        Slot::Depth(*depth_of.get(name.as_str()).unwrap_or_else(|| {
+                            panic!("negated variable {name} missing from the variable order")
+                        }))
+                    },
+                    | Term::Atom(atom) => Slot::Const(
+                        parse_const_atom(atom).expect("malformed constant in negated predicate"),
+                    ),
+                    | Term::Int(id) => Slot::Const(*id),
+                    | Term::Placeholder => Slot::Any,
+                    | Term::Str(_) => {
+                        unreachable!("rewrite_atoms drops negations of unknown constants")
+                    },
+                    | Term::Aggregate(..) => {
+                        unreachable!("aggregates only appear in rule heads")
+                    },
+                })
+                .collect();
+            let latest = slots
+                .iter()
+                .filter_map(|slot| match slot {
+                    | Slot::Depth(depth) => Some(*depth),
+                    | _ => None,
+                })
+                .max();
+            let mut probe = Probe {
+                iter: iter(&pred.name),
+                slots,
+            };
+            match latest {
+                | Some(depth) => probes[depth].push(probe),
+                | None => blocked |= probe.matches(0, &[]),
+            }
+        }
+        Negations {
+            probes,
+            blocked,
+        }
+    }
+
+    /// Returns `true` if no negated predicate is checked at `depth`.
+    pub(crate) fn is_free(&self, depth: usize) -> bool {
+        !self.blocked && self.probes.get(depth).is_none_or(Vec::is_empty)
+    }
+
+    /// Judges the binding of `depth`, given the keys bound at every depth up
+    /// to and including it. Never returns [`Verdict::SeekTo`].
+    pub(crate) fn check(&mut self, depth: usize, bound: &[usize]) -> Verdict {
+        if self.blocked {
+            return Verdict::Exhausted;
+        }
+        if self.probes[depth]
+            .iter_mut()
+            .any(|probe| probe.matches(0, bound))
+        {
+            Verdict::Skip
+        } else {
+            Verdict::Admit
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        kermit_ds::{Relation, RelationHeader, TreeTrie},
+        kermit_iters::TrieIterable,
+    };
+
+    fn negated(query: &str) -> Vec<Predicate> {
+        query.parse::<kermit_parser::JoinQuery>().unwrap().negated
+    }
+
+    fn order(vars: &[&str]) -> Vec<String> { vars.iter().map(|v| v.to_string()).collect() }
+
+    fn trie(tuples: Vec<Vec<usize>>) -> TreeTrie {
+        let arity = tuples[0].len();
+        TreeTrie::from_tuples(RelationHeader::new_positional("s", arity), tuples)
+    }
+
+    #[test]
+    fn probes_at_the_latest_variable() {
+        let s = trie(vec![vec![1, 2], vec![2, 3]]);
+        let mut n = Negations::new(
+            &negated("Q(X, Y) :- r(X, Y), not s(X, Y)."),
+            &order(&["X", "Y"]),
+            |_| s.trie_iter(),
+        );
+        assert!(n.is_free(0));
+        assert!(!n.is_free(1));
+        assert_eq!(n.check(1, &[1, 2]), Verdict::Skip);
+        assert_eq!(n.check(1, &[1, 3]), Verdict::Admit);
+        assert_eq!(n.check(1, &[2, 3]), Verdict::Skip);
+        assert_eq!(n.check(1, &[3, 3]), Verdict::Admit);
+    }
+
+    #[test]
+    fn column_order_follows_the_predicate() {
+        let s = trie(vec![vec![2, 1]]);
+        let mut n = Negations::new(
+            &negated("Q(X, Y) :- r(X, Y), not s(Y, X)."),
+            &order(&["X", "Y"]),
+            |_| s.trie_iter(),
+        );
+        assert_eq!(n.check(1, &[1, 2]), Verdict::Skip);
+        assert_eq!(n.check(1, &[2, 1]), Verdict::Admit);
+    }
+
+    #[test]
+    fn placeholders_and_constants() {
+        let s = trie(vec![vec![1, 5, 7], vec![2, 6, 8]]);
+        let mut n = Negations::new(
+            &negated("Q(X) :- r(X), not s(_, X, c8)."),
+            &order(&["X"]),
+            |_| s.trie_iter(),
+        );
+        assert_eq!(n.check(0, &[5]), Verdict::Admit);
+        assert_eq!(n.check(0, &[6]), Verdict::Skip);
+        assert_eq!(n.check(0, &[7]), Verdict::Admit);
+    }
+
+    #[test]
+    fn ground_predicate_blocks_everything_when_present() {
+        let s = trie(vec![vec![4]]);
+        let present = Negations::new(&negated("Q(X) :- r(X), not s(c4)."), &order(&["X"]), |_| {
+            s.trie_iter()
+        });
+        assert!(!present.is_free(0));
+        let absent = Negations::new(&negated("Q(X) :- r(X), not s(c5)."), &order(&["X"]), |_| {
+            s.trie_iter()
+        });
+        assert!(absent.is_free(0));
+    }
+}
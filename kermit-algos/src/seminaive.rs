@@ -0,0 +1,861 @@
+//! Semi-naive bottom-up evaluation of recursive Datalog programs.
+//!
+//! Every rule body is evaluated with a [`JoinAlgo`] (Leapfrog Triejoin in
+//! practice). After an initial round over the non-recursive rules, each round
+//! re-fires a rule once per intensional body atom, binding that atom to the
+//! tuples derived in the previous round (its *delta*) and every other atom to
+//! the full relation. Only tuples not seen before form the next delta, and
+//! evaluation stops once every delta is empty.
+//!
+//! Programs with negation are split into strata so that every negated
+//! predicate is fully derived before any rule negating it fires. Each
+//! stratum is then evaluated semi-naively in turn, with the predicates of
+//! earlier strata treated like stored relations. Rules with head aggregates
+//! likewise sit above every predicate they read, so each group is folded
+//! over complete inputs.
+//!
+//! Ground facts seed the first round of their predicate's stratum, so rules
+//! build on them like on any other derived tuple. Non-recursive programs can
+//! instead be run rule by rule in [`dependency_order`].
+
+use {
+    crate::{
+        aggregate::Aggregation,
+        const_rewrite::{ground_tuple, rewrite_atoms, ConstSpec, RewriteError},
+        join_algo::JoinAlgo,
+        singleton::SingletonTrieIter,
+        trie_iter_kind::TrieIterKind,
+        validate::QueryError,
+        variable_order::variable_order,
+        view::ViewKey,
+    },
+    kermit_ds::{Relation, RelationHeader},
+    kermit_iters::TrieIterable,
+    kermit_parser::{JoinQuery, Program, Term},
+    std::{
+        collections::{HashMap, HashSet},
+        fmt,
+    },
+};
+
+/// Error returned by [`evaluate_program`] when a program cannot be
+/// evaluated against the given relations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgramError {
+    /// A body atom names a predicate that is neither a stored relation nor
+    /// defined by any rule head.
+    UnknownPredicate(String),
+    /// A rule head or fact redefines a stored relation.
+    StoredRelationInHead(String),
+    /// A predicate is used with a different number of terms than elsewhere.
+    ArityMismatch {
+        /// The predicate's name.
+        predicate: String,
+        /// The arity it was first seen (or stored) with.
+        expected: usize,
+        /// The conflicting arity.
+        found: usize,
+    },
+    /// A rule head contains a term other than a variable bound in its body
+    /// or an aggregate over one.
+    UnsafeHeadTerm {
+        /// The head predicate's name.
+        predicate: String,
+    },
+    /// A predicate depends negatively, or through an aggregate, on itself,
+    /// so the program cannot be stratified.
+    Unstratifiable(String),
+    /// A predicate depends on itself, but the program must be evaluated
+    /// rule by rule (see [`dependency_order`]).
+    Recursive(String),
+    /// The output predicate is defined by no rule or fact.
+    UnknownOutput(String),
+    /// A body constant does not match the `c<digits>` shape.
+    BadAtom(RewriteError),
+    /// A rule evaluated on its own does not fit the stored relations (see
+    /// [`validate_query`](crate::validate_query)).
+    Query(QueryError),
+}
+
+impl fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            | ProgramError::UnknownPredicate(name) => {
+                write!(
+                    f,
+                    "predicate {name:?} is neither a relation nor a rule head"
+                )
+            },
+            | ProgramError::StoredRelationInHead(name) => {
+                write!(f, "rule head {name:?} redefines a stored relation")
+            },
+            | ProgramError::ArityMismatch {
+                predicate,
+                expected,
+                found,
+            } => write!(
+                f,
+                "predicate {predicate:?} used with arity {found}, expected {expected}"
+            ),
+            | ProgramError::UnsafeHeadTerm {
+                predicate,
+            } => write!(
+                f,
+                "head of a rule for {predicate:?} must only contain variables bound in its body"
+            ),
+            | ProgramError::Unstratifiable(name) => write!(
+                f,
+                "predicate {name:?} depends negatively or through an aggregate on itself, so the \
+                 program cannot be stratified"
+            ),
+            | ProgramError::Recursive(name) => {
+                write!(f, "predicate {name:?} depends on itself")
+            },
+            | ProgramError::UnknownOutput(name) => {
+                write!(f, "output predicate {name:?} is defined by no rule or fact")
+            },
+            | ProgramError::BadAtom(e) => e.fmt(f),
+            | ProgramError::Query(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ProgramError {}
+
+impl From<RewriteError> for ProgramError {
+    fn from(e: RewriteError) -> Self { ProgramError::BadAtom(e) }
+}
+
+impl From<QueryError> for ProgramError {
+    fn from(e: QueryError) -> Self { ProgramError::Query(e) }
+}
+
+/// A rule after the constant rewrite, with the positions of its intensional
+/// body atoms.
+struct CompiledRule {
+    /// For a rule with head aggregates, the query over its body bindings.
+    query: JoinQuery,
+    aggregation: Option<Aggregation>,
+    consts: Vec<ConstSpec>,
+    /// Number of body atoms before the rewrite appended `Const_*` atoms.
+    atoms: usize,
+    /// Body positions whose predicate is defined in the rule's own stratum.
+    recursive: Vec<usize>,
+}
+
+/// The derived state of one intensional predicate.
+struct Derived<R> {
+    full: R,
+    delta: R,
+    seen: HashSet<Vec<usize>>,
+}
+
+/// Evaluates `program` to its least fixpoint over the stored relations in
+/// `edb`, returning one relation per intensional predicate (see
+/// [`Program::idb_predicates`]).
+///
+/// Rules with the same head predicate are unioned, and results follow set
+/// semantics. Body constants use the same `c<digits>` or integer encoding as
+/// [`rewrite_atoms`]; a rule matching a string constant that
+/// [`resolve_constants`](crate::resolve_constants) left unresolved derives
+/// nothing, as does a fact holding one (see [`ground_tuple`]). Negated
+/// predicates and head aggregates follow stratified semantics.
+///
+/// # Errors
+///
+/// Returns a [`ProgramError`] if a body predicate is unknown, a head or fact
+/// redefines a stored relation, arities disagree, a head term is not a
+/// variable bound in the body or an aggregate over one, a predicate depends
+/// negatively or through an aggregate on itself, or a constant is malformed.
+pub fn evaluate_program<R, JA>(
+    program: &Program, edb: &HashMap<String, R>,
+) -> Result<HashMap<String, R>, ProgramError>
+where
+    R: Relation + TrieIterable,
+    JA: for<'a> JoinAlgo<TrieIterKind<'a, R>>,
+{
+    let arities = check_program(program, edb)?;
+    let strata = stratify(program)?;
+    let rules = program
+        .rules
+        .iter()
+        .map(|rule| {
+            let atoms = rule.body.len();
+            let stratum = strata[rule.head.name.as_str()];
+            let recursive = (0..atoms)
+                .filter(|&i| strata.get(rule.body[i].name.as_str()) == Some(&stratum))
+                .collect();
+            let (aggregation, rule) = match Aggregation::plan(rule) {
+                | Some((aggregation, bindings)) => (Some(aggregation), bindings),
+                | None => (None, rule.clone()),
+            };
+            let (query, consts) = match rewrite_atoms(rule) {
+                | Ok(rewritten) => rewritten,
+                // A constant missing from the dictionary matches no stored
+                // value, so the rule derives nothing.
+                | Err(RewriteError::UnknownConstant(_)) => return Ok(None),
+                | Err(e) => return Err(e),
+            };
+            Ok(Some(CompiledRule {
+                query,
+                aggregation,
+                consts,
+                atoms,
+                recursive,
+            }))
+        })
+        .collect::<Result<Vec<_>, RewriteError>>()?;
+    let rules: Vec<CompiledRule> = rules.into_iter().flatten().collect();
+    let mut facts: HashMap<&str, Vec<Vec<usize>>> = HashMap::new();
+    for fact in &program.facts {
+        if let Some(tuple) = ground_tuple(fact)? {
+            facts.entry(fact.name.as_str()).or_default().push(tuple);
+        }
+    }
+
+    let mut idb: HashMap<String, Derived<R>> = program
+        .idb_predicates()
+        .into_iter()
+        .map(|name| {
+            let header = RelationHeader::new_positional(name, arities[name]);
+            let derived = Derived {
+                full: R::new(header.clone()),
+                delta: R::new(header),
+                seen: HashSet::new(),
+            };
+            (name.to_owned(), derived)
+        })
+        .collect();
+    // Views of stored relations never change during evaluation, so they are
+    // built once and shared by every round.
+    let mut edb_views: HashMap<ViewKey, R> = HashMap::new();
+
+    let top = strata.values().copied().max().unwrap_or(0);
+    for stratum in 0..=top {
+        let rules: Vec<&CompiledRule> = rules
+            .iter()
+            .filter(|rule| strata[rule.query.head.name.as_str()] == stratum)
+            .collect();
+        let facts: HashMap<&str, Vec<Vec<usize>>> = facts
+            .extract_if(|name, _| strata[name] == stratum)
+            .collect();
+        evaluate_stratum::<R, JA>(&rules, facts, edb, &mut idb, &mut edb_views);
+    }
+
+    Ok(idb
+        .into_iter()
+        .map(|(name, state)| (name, state.full))
+        .collect())
+}
+
+/// Runs `rules` and `facts`, which make up one stratum, to their fixpoint.
+/// Every intensional predicate they negate, or read from an earlier stratum,
+/// is already complete in `idb`.
+fn evaluate_stratum<'p, R, JA>(
+    rules: &[&'p CompiledRule], facts: HashMap<&'p str, Vec<Vec<usize>>>, edb: &HashMap<String, R>,
+    idb: &mut HashMap<String, Derived<R>>, edb_views: &mut HashMap<ViewKey, R>,
+) where
+    R: Relation + TrieIterable,
+    JA: for<'a> JoinAlgo<TrieIterKind<'a, R>>,
+{
+    let heads: HashSet<&str> = rules
+        .iter()
+        .map(|rule| rule.query.head.name.as_str())
+        .chain(facts.keys().copied())
+        .collect();
+    // The facts join the first round's derivations.
+    let mut seed = Some(facts);
+    let mut first_round = true;
+    loop {
+        let mut derived: HashMap<&str, Vec<Vec<usize>>> = seed.take().unwrap_or_default();
+        for rule in rules {
+            let variants: Vec<Option<usize>> = if first_round {
+                if rule.recursive.is_empty() {
+                    vec![None]
+                } else {
+                    vec![]
+                }
+            } else {
+                rule.recursive.iter().copied().map(Some).collect()
+            };
+            for delta_at in variants {
+                let tuples = fire::<R, JA>(rule, delta_at, edb, idb, edb_views);
+                derived
+                    .entry(rule.query.head.name.as_str())
+                    .or_default()
+                    .extend(tuples);
+            }
+        }
+        first_round = false;
+
+        let mut changed = false;
+        for (name, state) in idb.iter_mut() {
+            if !heads.contains(name.as_str()) {
+                continue;
+            }
+            let fresh: Vec<Vec<usize>> = derived
+                .remove(name.as_str())
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|t| state.seen.insert(t.clone()))
+                .collect();
+            changed |= !fresh.is_empty();
+            let header = state.full.header().clone();
+            state.full.insert_all(fresh.clone());
+            state.delta = R::from_tuples(header, fresh);
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+/// Checks predicate arities, head safety, and that every body predicate is
+/// known. Returns the arity of each intensional predicate.
+fn check_program<R: Relation>(
+    program: &Program, edb: &HashMap<String, R>,
+) -> Result<HashMap<String, usize>, ProgramError> {
+    let mut arities: HashMap<String, usize> = HashMap::new();
+    let mut record = |name: &str, arity: usize| match arities.get(name) {
+        | Some(&expected) if expected != arity => Err(ProgramError::ArityMismatch {
+            predicate: name.to_owned(),
+            expected,
+            found: arity,
+        }),
+        | _ => {
+            arities.insert(name.to_owned(), arity);
+            Ok(())
+        },
+    };
+
+    for decl in &program.declarations {
+        match edb.get(&decl.name) {
+            | Some(relation) if relation.header().arity() != decl.arity() => {
+                return Err(ProgramError::ArityMismatch {
+                    predicate: decl.name.clone(),
+                    expected: decl.arity(),
+                    found: relation.header().arity(),
+                })
+            },
+            | Some(_) => {},
+            | None => record(&decl.name, decl.arity())?,
+        }
+    }
+    for fact in &program.facts {
+        if edb.contains_key(&fact.name) {
+            return Err(ProgramError::StoredRelationInHead(fact.name.clone()));
+        }
+        record(&fact.name, fact.terms.len())?;
+    }
+    for rule in &program.rules {
+        let head = &rule.head;
+        if edb.contains_key(&head.name) {
+            return Err(ProgramError::StoredRelationInHead(head.name.clone()));
+        }
+        record(&head.name, head.terms.len())?;
+        let bound = |var: &String| {
+            rule.body.iter().any(|p| {
+                p.terms
+                    .iter()
+                    .any(|t| matches!(t, Term::Var(v) if v == var))
+            })
+        };
+        let safe = head.terms.iter().all(|t| match t {
+            | Term::Var(var) | Term::Aggregate(_, var) => bound(var),
+            | _ => false,
+        });
+        if !safe {
+            return Err(ProgramError::UnsafeHeadTerm {
+                predicate: head.name.clone(),
+            });
+        }
+    }
+
+    let idb: HashSet<&str> = program.idb_predicates().into_iter().collect();
+    for pred in program
+        .rules
+        .iter()
+        .flat_map(|r| r.body.iter().chain(&r.negated))
+    {
+        let arity = pred.terms.len();
+        match edb.get(&pred.name) {
+            | Some(relation) if relation.header().arity() != arity => {
+                return Err(ProgramError::ArityMismatch {
+                    predicate: pred.name.clone(),
+                    expected: relation.header().arity(),
+                    found: arity,
+                })
+            },
+            | Some(_) => {},
+            | None if idb.contains(pred.name.as_str()) => record(&pred.name, arity)?,
+            | None => return Err(ProgramError::UnknownPredicate(pred.name.clone())),
+        }
+    }
+    Ok(arities)
+}
+
+/// Returns the intensional predicates `output` depends on, itself included,
+/// ordered so that each follows every predicate its rules read or negate.
+/// Materialising them in this order evaluates a non-recursive program one
+/// rule at a time, without the fixpoint of [`evaluate_program`].
+///
+/// # Errors
+///
+/// Returns a [`ProgramError`] for the programs [`evaluate_program`] rejects,
+/// if `output` is not an intensional predicate, or if a predicate it depends
+/// on depends on itself.
+pub fn dependency_order<'p, R: Relation>(
+    program: &'p Program, edb: &HashMap<String, R>, output: &str,
+) -> Result<Vec<&'p str>, ProgramError> {
+    check_program(program, edb)?;
+    let idb = program.idb_predicates();
+    let Some(&output) = idb.iter().find(|&&name| name == output) else {
+        return Err(ProgramError::UnknownOutput(output.to_owned()));
+    };
+
+    /// Appends `name` after its dependencies; `visiting` holds the
+    /// predicates on the current path.
+    fn visit<'p>(
+        name: &'p str, program: &'p Program, idb: &[&str], visiting: &mut Vec<&'p str>,
+        order: &mut Vec<&'p str>,
+    ) -> Result<(), ProgramError> {
+        if order.contains(&name) {
+            return Ok(());
+        }
+        if visiting.contains(&name) {
+            return Err(ProgramError::Recursive(name.to_owned()));
+        }
+        visiting.push(name);
+        let deps = program
+            .rules
+            .iter()
+            .filter(|rule| rule.head.name == name)
+            .flat_map(|rule| rule.body.iter().chain(&rule.negated))
+            .map(|pred| pred.name.as_str())
+            .filter(|dep| idb.contains(dep));
+        for dep in deps {
+            visit(dep, program, idb, visiting, order)?;
+        }
+        visiting.pop();
+        order.push(name);
+        Ok(())
+    }
+
+    let mut order = Vec::new();
+    visit(output, program, &idb, &mut Vec::new(), &mut order)?;
+    Ok(order)
+}
+
+/// Assigns every intensional predicate a stratum: at least that of each
+/// predicate its rules read, and above that of each predicate they negate
+/// or, for rules with head aggregates, read. Stored relations sit below every
+/// stratum and are left out.
+///
+/// A cycle through a negation or aggregate would push its strata up forever, so
+/// once a stratum exceeds the number of intensional predicates the program is
+/// rejected.
+fn stratify(program: &Program) -> Result<HashMap<&str, usize>, ProgramError> {
+    let idb = program.idb_predicates();
+    let mut strata: HashMap<&str, usize> = idb.iter().map(|&name| (name, 0)).collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for rule in &program.rules {
+            let head = rule.head.name.as_str();
+            let step = usize::from(rule.has_aggregates());
+            let positive = rule.body.iter().map(|pred| (pred, step));
+            let negative = rule.negated.iter().map(|pred| (pred, 1));
+            for (pred, step) in positive.chain(negative) {
+                let Some(&below) = strata.get(pred.name.as_str()) else {
+                    continue;
+                };
+                if strata[head] < below + step {
+                    if below + step >= idb.len() {
+                        return Err(ProgramError::Unstratifiable(head.to_owned()));
+                    }
+                    strata.insert(head, below + step);
+                    changed = true;
+                }
+            }
+        }
+    }
+    Ok(strata)
+}
+
+/// Evaluates one rule, binding the body atom at `delta_at` (if any) to its
+/// predicate's delta and every other atom to the full relation, and folds
+/// the bindings of a rule with head aggregates.
+fn fire<R, JA>(
+    rule: &CompiledRule, delta_at: Option<usize>, edb: &HashMap<String, R>,
+    idb: &HashMap<String, Derived<R>>, edb_views: &mut HashMap<ViewKey, R>,
+) -> Vec<Vec<usize>>
+where
+    R: Relation + TrieIterable,
+    JA: for<'a> JoinAlgo<TrieIterKind<'a, R>>,
+{
+    let mut query = rule.query.clone();
+    let order = variable_order(&query);
+
+    // Each atom is bound under a name identifying its source, so that e.g.
+    // a delta and a full copy of the same predicate can meet in one join.
+    let mut direct: Vec<(String, &R)> = Vec::new();
+    let mut shared: Vec<(String, ViewKey)> = Vec::new();
+    let mut local: HashMap<String, R> = HashMap::new();
+    for (i, pred) in query.body.iter_mut().take(rule.atoms).enumerate() {
+        let (label, relation, stored) = match (edb.get(&pred.name), idb.get(&pred.name)) {
+            | (Some(relation), _) => (pred.name.clone(), relation, true),
+            | (None, Some(state)) if delta_at == Some(i) => {
+                (format!("delta[{}]", pred.name), &state.delta, false)
+            },
+            | (None, Some(state)) => (pred.name.clone(), &state.full, false),
+            | (None, None) => unreachable!("body predicates are checked up front"),
+        };
+        match ViewKey::plan(&label, &pred.terms, &order) {
+            | None => {
+                pred.name = label.clone();
+                direct.push((label, relation));
+            },
+            | Some((key, terms)) => {
+                let name = key.predicate_name();
+                pred.name = name.clone();
+                pred.terms = terms;
+                if stored {
+                    edb_views
+                        .entry(key.clone())
+                        .or_insert_with(|| key.build(relation));
+                    shared.push((name, key));
+                } else {
+                    local.entry(name).or_insert_with(|| key.build(relation));
+                }
+            },
+        }
+    }
+
+    let mut wrappers: HashMap<String, TrieIterKind<'_, R>> = HashMap::new();
+    for (name, relation) in direct {
+        wrappers.insert(name, TrieIterKind::Relation(relation));
+    }
+    for (name, key) in shared {
+        wrappers.insert(name, TrieIterKind::Relation(&edb_views[&key]));
+    }
+    for (name, relation) in &local {
+        wrappers.insert(name.clone(), TrieIterKind::Relation(relation));
+    }
+    // Negated predicates are probed in their own column order, against the
+    // stored relation or the completed derivation of an earlier stratum.
+    for pred in &query.negated {
+        let relation = match (edb.get(&pred.name), idb.get(&pred.name)) {
+            | (Some(relation), _) => relation,
+            | (None, Some(state)) => &state.full,
+            | (None, None) => unreachable!("negated predicates are checked up front"),
+        };
+        wrappers
+            .entry(pred.name.clone())
+            .or_insert(TrieIterKind::Relation(relation));
+    }
+    for (name, id) in &rule.consts {
+        wrappers
+            .entry(name.clone())
+            .or_insert_with(|| TrieIterKind::Singleton(SingletonTrieIter::new(*id)));
+    }
+
+    let ds_map: HashMap<String, &TrieIterKind<'_, R>> =
+        wrappers.iter().map(|(k, v)| (k.clone(), v)).collect();
+    match &rule.aggregation {
+        | Some(aggregation) => {
+            let contiguous =
+                <JA as JoinAlgo<TrieIterKind<'_, R>>>::ORDERED && aggregation.groups_lead(&order);
+            let bindings = JA::join_iter_with_order(query, order, ds_map);
+            aggregation.clone().apply(bindings, contiguous).collect()
+        },
+        | None => JA::join_iter_with_order(query, order, ds_map).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::leapfrog_triejoin::LeapfrogTriejoin,
+        kermit_ds::{ColumnTrie, TreeTrie},
+    };
+
+    fn relation<R: Relation>(name: &str, arity: usize, tuples: Vec<Vec<usize>>) -> (String, R) {
+        let header = RelationHeader::new_positional(name, arity);
+        (name.to_owned(), R::from_tuples(header, tuples))
+    }
+
+    fn evaluate<R>(
+        program: &str, edb: Vec<(String, R)>,
+    ) -> Result<HashMap<String, Vec<Vec<usize>>>, ProgramError>
+    where
+        R: Relation + TrieIterable,
+    {
+        let program: Program = program.parse().unwrap();
+        let edb: HashMap<String, R> = edb.into_iter().collect();
+        let idb = evaluate_program::<R, LeapfrogTriejoin>(&program, &edb)?;
+        Ok(idb
+            .into_iter()
+            .map(|(name, r)| (name, r.trie_iter().into_iter().collect()))
+            .collect())
+    }
+
+    const TRANSITIVE_CLOSURE: &str = "tc(X, Y) :- edge(X, Y).\ntc(X, Z) :- tc(X, Y), edge(Y, Z).";
+
+    #[test]
+    fn transitive_closure_of_a_chain() {
+        let edge = relation::<TreeTrie>("edge", 2, vec![vec![1, 2], vec![2, 3], vec![3, 4]]);
+        let idb = evaluate(TRANSITIVE_CLOSURE, vec![edge]).unwrap();
+        assert_eq!(idb["tc"], vec![
+            vec![1, 2],
+            vec![1, 3],
+            vec![1, 4],
+            vec![2, 3],
+            vec![2, 4],
+            vec![3, 4],
+        ]);
+    }
+
+    #[test]
+    fn transitive_closure_terminates_on_cycles() {
+        let edge = relation::<ColumnTrie>("edge", 2, vec![vec![1, 2], vec![2, 1]]);
+        let idb = evaluate(TRANSITIVE_CLOSURE, vec![edge]).unwrap();
+        assert_eq!(idb["tc"], vec![vec![1, 1], vec![1, 2], vec![2, 1], vec![
+            2, 2
+        ]]);
+    }
+
+    #[test]
+    fn doubly_recursive_rule() {
+        // Both body atoms are intensional, so each round fires two variants.
+        let program = "tc(X, Y) :- edge(X, Y).\ntc(X, Z) :- tc(X, Y), tc(Y, Z).";
+        let edge = relation::<TreeTrie>("edge", 2, vec![vec![1, 2], vec![2, 3], vec![3, 4], vec![
+            4, 5,
+        ]]);
+        let idb = evaluate(program, vec![edge]).unwrap();
+        assert_eq!(idb["tc"].len(), 10);
+    }
+
+    #[test]
+    fn mutual_recursion() {
+        let program =
+            "even(X) :- zero(X).\nodd(Y) :- even(X), succ(X, Y).\neven(Y) :- odd(X), succ(X, Y).";
+        let zero = relation::<TreeTrie>("zero", 1, vec![vec![0]]);
+        let succ = relation::<TreeTrie>("succ", 2, (0..5).map(|i| vec![i, i + 1]).collect());
+        let idb = evaluate(program, vec![zero, succ]).unwrap();
+        assert_eq!(idb["even"], vec![vec![0], vec![2], vec![4]]);
+        assert_eq!(idb["odd"], vec![vec![1], vec![3], vec![5]]);
+    }
+
+    #[test]
+    fn body_constants_filter_recursion() {
+        let program = "reach(Y) :- edge(c1, Y).\nreach(Z) :- reach(Y), edge(Y, Z).";
+        let edge = relation::<TreeTrie>("edge", 2, vec![vec![1, 2], vec![2, 3], vec![7, 8]]);
+        let idb = evaluate(program, vec![edge]).unwrap();
+        assert_eq!(idb["reach"], vec![vec![2], vec![3]]);
+    }
+
+    #[test]
+    fn empty_input_yields_empty_relations() {
+        let edge = relation::<TreeTrie>("edge", 2, vec![]);
+        let idb = evaluate(TRANSITIVE_CLOSURE, vec![edge]).unwrap();
+        assert!(idb["tc"].is_empty());
+    }
+
+    #[test]
+    fn rules_matching_unknown_constants_derive_nothing() {
+        let program = "p(X) :- edge(X, 2).\np(X) :- edge(X, \"two\").\nq(X) :- edge(X, Y), not \
+                       p(X), not edge(Y, <http://x/y>).";
+        let edge = relation::<TreeTrie>("edge", 2, vec![vec![1, 2], vec![2, 3]]);
+        let idb = evaluate(program, vec![edge]).unwrap();
+        assert_eq!(idb["p"], vec![vec![1]]);
+        assert_eq!(idb["q"], vec![vec![2]]);
+    }
+
+    #[test]
+    fn unknown_body_predicate_is_rejected() {
+        let edge = relation::<TreeTrie>("edge", 2, vec![]);
+        assert_eq!(
+            evaluate("p(X) :- edge(X, Y), missing(Y).", vec![edge]),
+            Err(ProgramError::UnknownPredicate("missing".to_owned()))
+        );
+    }
+
+    #[test]
+    fn arity_mismatch_is_rejected() {
+        let edge = relation::<TreeTrie>("edge", 2, vec![]);
+        assert_eq!(
+            evaluate("p(X) :- edge(X, Y).\nq(X) :- p(X, X).", vec![edge]),
+            Err(ProgramError::ArityMismatch {
+                predicate: "p".to_owned(),
+                expected: 1,
+                found: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn declared_arities_are_enforced() {
+        let edge = relation::<TreeTrie>("edge", 2, vec![]);
+        assert_eq!(
+            evaluate(".decl p(x: number, y: number)\np(X) :- edge(X, Y).", vec![
+                edge
+            ]),
+            Err(ProgramError::ArityMismatch {
+                predicate: "p".to_owned(),
+                expected: 2,
+                found: 1,
+            })
+        );
+        let edge = relation::<TreeTrie>("edge", 2, vec![]);
+        assert_eq!(
+            evaluate(".decl edge(x: number)\np(X) :- edge(X, Y).", vec![edge]),
+            Err(ProgramError::ArityMismatch {
+                predicate: "edge".to_owned(),
+                expected: 1,
+                found: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn unsafe_head_is_rejected() {
+        let edge = relation::<TreeTrie>("edge", 2, vec![]);
+        assert_eq!(
+            evaluate("p(X, Z) :- edge(X, Y).", vec![edge]),
+            Err(ProgramError::UnsafeHeadTerm {
+                predicate: "p".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn negation_of_a_stored_relation() {
+        let program = "oneway(X, Y) :- edge(X, Y), not edge(Y, X).";
+        let edge = relation::<TreeTrie>("edge", 2, vec![vec![1, 2], vec![2, 1], vec![2, 3]]);
+        let idb = evaluate(program, vec![edge]).unwrap();
+        assert_eq!(idb["oneway"], vec![vec![2, 3]]);
+    }
+
+    #[test]
+    fn negation_of_a_recursive_predicate_runs_in_a_later_stratum() {
+        let program = "reach(X) :- start(X).\nreach(Y) :- reach(X), edge(X, Y).\nunreached(X) :- \
+                       node(X), not reach(X).";
+        let start = relation::<ColumnTrie>("start", 1, vec![vec![1]]);
+        let edge = relation::<ColumnTrie>("edge", 2, vec![vec![1, 2], vec![2, 3], vec![4, 5]]);
+        let node = relation::<ColumnTrie>("node", 1, (1..=5).map(|i| vec![i]).collect());
+        let idb = evaluate(program, vec![start, edge, node]).unwrap();
+        assert_eq!(idb["reach"], vec![vec![1], vec![2], vec![3]]);
+        assert_eq!(idb["unreached"], vec![vec![4], vec![5]]);
+    }
+
+    #[test]
+    fn negation_through_recursion_is_rejected() {
+        let program = "p(X) :- node(X), not q(X).\nq(X) :- node(X), not p(X).";
+        let node = relation::<TreeTrie>("node", 1, vec![vec![1]]);
+        assert!(matches!(
+            evaluate(program, vec![node]),
+            Err(ProgramError::Unstratifiable(_))
+        ));
+    }
+
+    #[test]
+    fn aggregates_fold_complete_lower_strata() {
+        let program = "deg(X, count(Y)) :- edge(X, Y).\nmaxdeg(max(D)) :- deg(X, D).\nhub(X) :- \
+                       deg(X, D), maxdeg(D).";
+        let edge = relation::<ColumnTrie>("edge", 2, vec![
+            vec![1, 2],
+            vec![1, 3],
+            vec![1, 4],
+            vec![2, 3],
+            vec![3, 4],
+            vec![4, 1],
+        ]);
+        let idb = evaluate(program, vec![edge]).unwrap();
+        assert_eq!(idb["deg"], vec![vec![1, 3], vec![2, 1], vec![3, 1], vec![
+            4, 1
+        ]]);
+        assert_eq!(idb["maxdeg"], vec![vec![3]]);
+        assert_eq!(idb["hub"], vec![vec![1]]);
+    }
+
+    #[test]
+    fn aggregation_through_recursion_is_rejected() {
+        let program = "p(X, count(Y)) :- edge(X, Y), p(Y, Z).\np(X, Y) :- edge(X, Y).";
+        let edge = relation::<TreeTrie>("edge", 2, vec![vec![1, 2]]);
+        assert!(matches!(
+            evaluate(program, vec![edge]),
+            Err(ProgramError::Unstratifiable(_))
+        ));
+    }
+
+    #[test]
+    fn unknown_negated_predicate_is_rejected() {
+        let edge = relation::<TreeTrie>("edge", 2, vec![]);
+        assert_eq!(
+            evaluate("p(X) :- edge(X, Y), not missing(Y).", vec![edge]),
+            Err(ProgramError::UnknownPredicate("missing".to_owned()))
+        );
+    }
+
+    #[test]
+    fn redefining_a_stored_relation_is_rejected() {
+        let edge = relation::<TreeTrie>("edge", 2, vec![]);
+        assert_eq!(
+            evaluate("edge(X, Y) :- edge(Y, X).", vec![edge]),
+            Err(ProgramError::StoredRelationInHead("edge".to_owned()))
+        );
+    }
+
+    #[test]
+    fn facts_seed_their_predicates() {
+        let program = "edge(1, 2).\nedge(2, 3).\ntc(X, Y) :- edge(X, Y).\ntc(X, Z) :- tc(X, Y), \
+                       edge(Y, Z).\ntc(7, 1).";
+        let idb = evaluate::<TreeTrie>(program, vec![]).unwrap();
+        assert_eq!(idb["edge"], vec![vec![1, 2], vec![2, 3]]);
+        assert_eq!(idb["tc"], vec![
+            vec![1, 2],
+            vec![1, 3],
+            vec![2, 3],
+            vec![7, 1],
+            vec![7, 2],
+            vec![7, 3],
+        ]);
+    }
+
+    #[test]
+    fn facts_for_stored_relations_are_rejected() {
+        let edge = relation::<TreeTrie>("edge", 2, vec![]);
+        assert_eq!(
+            evaluate("edge(1, 2).\np(X) :- edge(X, Y).", vec![edge]),
+            Err(ProgramError::StoredRelationInHead("edge".to_owned()))
+        );
+    }
+
+    #[test]
+    fn dependency_order_lists_dependencies_first() {
+        let program: Program = "out(X) :- mid(X), not low(X).\nmid(X) :- low(X).\nmid(X) :- \
+                                edge(X, Y).\nlow(1).\nunused(X) :- edge(X, X)."
+            .parse()
+            .unwrap();
+        let edb: HashMap<String, TreeTrie> = [relation("edge", 2, vec![])].into_iter().collect();
+        assert_eq!(
+            dependency_order(&program, &edb, "out"),
+            Ok(vec!["low", "mid", "out"])
+        );
+        assert_eq!(
+            dependency_order(&program, &edb, "nope"),
+            Err(ProgramError::UnknownOutput("nope".to_owned()))
+        );
+    }
+
+    #[test]
+    fn dependency_order_rejects_recursion() {
+        let program: Program = TRANSITIVE_CLOSURE.parse().unwrap();
+        let edb: HashMap<String, TreeTrie> = [relation("edge", 2, vec![])].into_iter().collect();
+        assert_eq!(
+            dependency_order(&program, &edb, "tc"),
+            Err(ProgramError::Recursive("tc".to_owned()))
+        );
+    }
+}
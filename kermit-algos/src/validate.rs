@@ -0,0 +1,215 @@
+//! Checks a [`JoinQuery`] against the schema of the relations it reads.
+//!
+//! The parser only guarantees a query is well formed. The join algorithms
+//! further assume that every body predicate names a stored relation with as
+//! many columns as the predicate has terms, that every head variable is bound
+//! by the body, and that every constant is encoded as a dictionary ID. When
+//! one of these does not hold they panic or, worse, silently join the wrong
+//! columns, so [`validate_query`] rules them out up front.
+
+use {
+    crate::const_rewrite::{parse_const_atom, RewriteError},
+    kermit_parser::{JoinQuery, Term},
+    std::fmt,
+};
+
+/// Error returned by [`validate_query`] when a query does not fit the
+/// relations it reads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    /// A body or negated predicate names no stored relation.
+    UnknownPredicate(String),
+    /// A predicate is used with a different number of terms than its
+    /// relation has columns.
+    ArityMismatch {
+        /// The predicate's name.
+        predicate: String,
+        /// The arity of the stored relation.
+        expected: usize,
+        /// The number of terms the predicate was used with.
+        found: usize,
+    },
+    /// A head variable, or the variable of a head aggregate, occurs in no
+    /// positive body predicate.
+    UnsafeHeadVariable(String),
+    /// A head term is neither a variable nor an aggregate, such as a
+    /// constant or a placeholder.
+    NonVariableHeadTerm(String),
+    /// A body or negated constant does not match the `c<digits>` shape.
+    MalformedConstant(RewriteError),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            | QueryError::UnknownPredicate(name) => {
+                write!(f, "predicate {name:?} is not a loaded relation")
+            },
+            | QueryError::ArityMismatch {
+                predicate,
+                expected,
+                found,
+            } => write!(
+                f,
+                "predicate {predicate:?} used with arity {found}, but its relation has arity \
+                 {expected}"
+            ),
+            | QueryError::UnsafeHeadVariable(var) => write!(
+                f,
+                "head variable {var} is not bound by a positive body predicate"
+            ),
+            | QueryError::NonVariableHeadTerm(term) => write!(
+                f,
+                "head term {term} is not a variable or an aggregate over one"
+            ),
+            | QueryError::MalformedConstant(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+impl From<RewriteError> for QueryError {
+    fn from(e: RewriteError) -> Self { QueryError::MalformedConstant(e) }
+}
+
+/// Checks `query` against the stored relations, whose arities `arity`
+/// returns by name (`None` for a relation that is not stored).
+///
+/// Body predicates are checked before negated ones, each in order, then the
+/// head, then constants, so the first problem a reader would meet is the one
+/// reported. Quoted-string and IRI constants are not checked here: one the
+/// dictionary lacks makes the query empty, not invalid.
+///
+/// # Errors
+///
+/// Returns a [`QueryError`] if a body or negated predicate is unknown or has
+/// the wrong arity, a head term is not a variable or aggregate, a head
+/// variable is not bound by a positive body predicate, or an atom does not
+/// match `c<digits>`.
+pub fn validate_query(
+    query: &JoinQuery, arity: impl Fn(&str) -> Option<usize>,
+) -> Result<(), QueryError> {
+    for pred in query.body.iter().chain(&query.negated) {
+        let Some(expected) = arity(&pred.name) else {
+            return Err(QueryError::UnknownPredicate(pred.name.clone()));
+        };
+        if pred.terms.len() != expected {
+            return Err(QueryError::ArityMismatch {
+                predicate: pred.name.clone(),
+                expected,
+                found: pred.terms.len(),
+            });
+        }
+    }
+
+    let bound = |var: &String| {
+        query.body.iter().any(|pred| {
+            pred.terms
+                .iter()
+                .any(|t| matches!(t, Term::Var(v) if v == var))
+        })
+    };
+    for term in &query.head.terms {
+        match term {
+            | Term::Var(var) | Term::Aggregate(_, var) if !bound(var) => {
+                return Err(QueryError::UnsafeHeadVariable(var.clone()));
+            },
+            | Term::Var(_) | Term::Aggregate(..) => {},
+            | term => return Err(QueryError::NonVariableHeadTerm(term.to_string())),
+        }
+    }
+
+    for term in query
+        .body
+        .iter()
+        .chain(&query.negated)
+        .flat_map(|pred| &pred.terms)
+    {
+        if let Term::Atom(atom) = term {
+            parse_const_atom(atom)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(query: &str) -> Result<(), QueryError> {
+        let query: JoinQuery = query.parse().unwrap();
+        validate_query(&query, |name| match name {
+            | "edge" => Some(2),
+            | "node" => Some(1),
+            | _ => None,
+        })
+    }
+
+    #[test]
+    fn accepts_a_query_that_fits_the_schema() {
+        assert_eq!(
+            validate("Q(X, count(Z)) :- edge(X, Y), edge(Y, Z), node(c3), not edge(Z, X)."),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_predicates() {
+        assert_eq!(
+            validate("Q(X) :- edge(X, Y), not path(Y, X)."),
+            Err(QueryError::UnknownPredicate("path".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_arity_mismatches() {
+        assert_eq!(
+            validate("Q(X) :- node(X), edge(X, Y, Z)."),
+            Err(QueryError::ArityMismatch {
+                predicate: "edge".to_string(),
+                expected: 2,
+                found: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_unsafe_head_variables() {
+        assert_eq!(
+            validate("Q(X, W) :- edge(X, Y)."),
+            Err(QueryError::UnsafeHeadVariable("W".to_string()))
+        );
+        assert_eq!(
+            validate("Q(X, sum(W)) :- edge(X, Y)."),
+            Err(QueryError::UnsafeHeadVariable("W".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_non_variable_head_terms() {
+        assert_eq!(
+            validate("Q(c3, X) :- edge(X, Y)."),
+            Err(QueryError::NonVariableHeadTerm("c3".to_string()))
+        );
+        assert_eq!(
+            validate("Q(_) :- edge(X, Y)."),
+            Err(QueryError::NonVariableHeadTerm("_".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_constants() {
+        assert_eq!(
+            validate("Q(X) :- edge(X, alice)."),
+            Err(QueryError::MalformedConstant(RewriteError::BadAtom(
+                "alice".to_string()
+            )))
+        );
+    }
+
+    #[test]
+    fn leaves_string_constants_to_the_dictionary() {
+        assert_eq!(validate("Q(X) :- edge(X, \"alice\")."), Ok(()));
+    }
+}
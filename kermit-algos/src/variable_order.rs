@@ -0,0 +1,340 @@
+//! Global variable ordering shared by the triejoin and its callers.
+//!
+//! Leapfrog Triejoin binds one variable per trie depth, so every body atom's
+//! trie must be keyed in the same relative order as the global variable
+//! order. Exposing the order lets callers (e.g. the database engine) reorder
+//! relations up front so that this precondition holds for arbitrary queries.
+
+use {
+    clap::ValueEnum,
+    kermit_parser::{JoinQuery, Term},
+    std::collections::{HashMap, HashSet},
+};
+
+/// Strategy for choosing the global variable order of a join.
+///
+/// Every strategy keeps the head variables ahead of the body-only ones, so
+/// the head-prefix projection of the triejoin stays valid; strategies only
+/// differ in how they order variables within those two groups.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum VariableOrderStrategy {
+    /// Head variables in head order, then body variables in order of first
+    /// appearance; see [`variable_order`].
+    #[default]
+    HeadFirst,
+    /// Greedy cost-based order: repeatedly binds the variable with the
+    /// smallest estimated number of candidate keys, given the variables
+    /// already bound. Estimates come from per-level trie sizes.
+    Greedy,
+}
+
+impl VariableOrderStrategy {
+    /// Computes the variable order for `query`.
+    ///
+    /// `level_sizes` returns, for a body predicate name, the number of trie
+    /// nodes at each depth of its relation in stored column order (so the
+    /// last entry is the relation's cardinality), or `None` if unknown. It is
+    /// only consulted by cost-based strategies.
+    pub fn order<F>(self, query: &JoinQuery, level_sizes: F) -> Vec<String>
+    where
+        F: Fn(&str) -> Option<Vec<usize>>,
+    {
+        match self {
+            | VariableOrderStrategy::HeadFirst => variable_order(query),
+            | VariableOrderStrategy::Greedy => greedy_order(query, level_sizes),
+        }
+    }
+}
+
+/// Returns the query's variables in the order the triejoin binds them: head
+/// variables first (in head order), then body-only variables in order of
+/// first appearance. Each variable appears once; placeholders and atoms are
+/// skipped.
+///
+/// Head variables lead so that the output tuple order matches the head and
+/// equal head bindings are contiguous in the join output.
+pub fn variable_order(query: &JoinQuery) -> Vec<String> {
+    let mut order: Vec<String> = Vec::new();
+    let terms = query
+        .head
+        .terms
+        .iter()
+        .chain(query.body.iter().flat_map(|pred| pred.terms.iter()));
+    for t in terms {
+        if let Term::Var(name) = t {
+            if !order.contains(name) {
+                order.push(name.clone());
+            }
+        }
+    }
+    order
+}
+
+/// Indexes the variables in a [`JoinQuery`] for the trie-based join
+/// algorithms.
+///
+/// Variables are numbered by their position in `order`, which must list every
+/// query variable once with the head variables first (see
+/// [`VariableOrderStrategy`]). Each body
+/// predicate then lists the indices of the variables it contains.
+/// Placeholders (`_`) and atoms are skipped — they occupy trie levels but
+/// don't bind a join variable.
+///
+/// Returns `(variables, rel_variables, head_arity)` where `variables` is
+/// `0..num_vars`, `rel_variables[i]` lists the variable indices for body
+/// predicate `i`, and `head_arity` is the number of distinct head variables
+/// (which occupy indices `0..head_arity`).
+///
+/// # Panics
+///
+/// Panics if a query variable is missing from `order`, or if a body-only
+/// variable precedes a head variable.
+pub(crate) fn build_variable_index(
+    query: &JoinQuery, order: &[String],
+) -> (Vec<usize>, Vec<Vec<usize>>, usize) {
+    let var_to_index: HashMap<&str, usize> = order
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_str(), i))
+        .collect();
+    let index_of = |name: &str| {
+        *var_to_index
+            .get(name)
+            .unwrap_or_else(|| panic!("variable {name} missing from the variable order"))
+    };
+
+    let head_vars: HashSet<&str> = query
+        .head
+        .terms
+        .iter()
+        .filter_map(|t| match t {
+            | Term::Var(name) => Some(name.as_str()),
+            | _ => None,
+        })
+        .collect();
+    let head_arity = head_vars.len();
+    assert!(
+        head_vars.iter().all(|v| index_of(v) < head_arity),
+        "variable order {order:?} must list the head variables first"
+    );
+
+    let variables: Vec<usize> = (0..order.len()).collect();
+
+    let rel_variables: Vec<Vec<usize>> = query
+        .body
+        .iter()
+        .map(|pred| {
+            pred.terms
+                .iter()
+                .filter_map(|t| match t {
+                    | Term::Var(name) => Some(index_of(name)),
+                    | _ => None,
+                })
+                .collect()
+        })
+        .collect();
+
+    (variables, rel_variables, head_arity)
+}
+
+/// Returns, for each head term, the index in `order` of the variable it
+/// names. Joins emit head variables in `order`; these positions map them back
+/// onto the head's term order, re-expanding repeated head variables. Other
+/// head terms are skipped; [`validate_query`](crate::validate_query) rejects
+/// them.
+pub(crate) fn head_positions(query: &JoinQuery, order: &[String]) -> Vec<usize> {
+    query
+        .head
+        .terms
+        .iter()
+        .filter_map(|t| match t {
+            | Term::Var(name) => order.iter().position(|v| v == name),
+            | _ => None,
+        })
+        .collect()
+}
+
+/// A body atom's variables (with their first column) and its relation's
+/// per-level sizes, as consumed by [`greedy_order`].
+struct AtomStats<'q> {
+    vars: Vec<(&'q str, usize)>,
+    level_sizes: Option<Vec<usize>>,
+}
+
+impl AtomStats<'_> {
+    /// Estimated number of distinct keys `var` (at `col`) can take in this
+    /// atom once the `bound` variables are fixed. Without bindings this is
+    /// the distinct-key count at the variable's level; each bound variable
+    /// divides the relation's cardinality by its own level's count, assuming
+    /// independent columns.
+    fn estimate(&self, col: usize, bound: &HashSet<&str>) -> f64 {
+        let Some(levels) = self.level_sizes.as_deref().filter(|l| !l.is_empty()) else {
+            return f64::INFINITY;
+        };
+        let cardinality = levels[levels.len() - 1] as f64;
+        let distinct = |c: usize| levels.get(c).copied().unwrap_or(1).max(1) as f64;
+        let narrowed = self
+            .vars
+            .iter()
+            .filter(|(v, _)| bound.contains(v))
+            .fold(cardinality, |acc, &(_, c)| acc / distinct(c));
+        distinct(col).min(narrowed)
+    }
+}
+
+/// Greedy cost-based ordering; see [`VariableOrderStrategy::Greedy`].
+///
+/// Head variables are ordered first, then body-only variables. Within each
+/// group, the next variable is the one whose most selective atom yields the
+/// fewest estimated candidates. Ties fall back to [`variable_order`].
+fn greedy_order<F>(query: &JoinQuery, level_sizes: F) -> Vec<String>
+where
+    F: Fn(&str) -> Option<Vec<usize>>,
+{
+    let base = variable_order(query);
+    let head: HashSet<&str> = query
+        .head
+        .terms
+        .iter()
+        .filter_map(|t| match t {
+            | Term::Var(name) => Some(name.as_str()),
+            | _ => None,
+        })
+        .collect();
+
+    let atoms: Vec<AtomStats<'_>> = query
+        .body
+        .iter()
+        .map(|pred| {
+            let mut vars: Vec<(&str, usize)> = Vec::new();
+            for (col, t) in pred.terms.iter().enumerate() {
+                if let Term::Var(name) = t {
+                    if !vars.iter().any(|(v, _)| v == name) {
+                        vars.push((name.as_str(), col));
+                    }
+                }
+            }
+            AtomStats {
+                vars,
+                level_sizes: level_sizes(&pred.name),
+            }
+        })
+        .collect();
+
+    let cost = |var: &str, bound: &HashSet<&str>| {
+        atoms
+            .iter()
+            .filter_map(|a| {
+                a.vars
+                    .iter()
+                    .find(|(v, _)| *v == var)
+                    .map(|&(_, col)| a.estimate(col, bound))
+            })
+            .fold(f64::INFINITY, f64::min)
+    };
+
+    let mut bound: HashSet<&str> = HashSet::new();
+    let mut order: Vec<String> = Vec::with_capacity(base.len());
+    for in_head in [true, false] {
+        let mut remaining: Vec<&str> = base
+            .iter()
+            .map(String::as_str)
+            .filter(|v| head.contains(v) == in_head)
+            .collect();
+        while !remaining.is_empty() {
+            // `min_by` keeps the first of equal elements, i.e. base order.
+            let (i, _) = remaining
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (i, cost(v, &bound)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .expect("remaining is non-empty");
+            let var = remaining.remove(i);
+            bound.insert(var);
+            order.push(var.to_string());
+        }
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(q: &str) -> Vec<String> { variable_order(&q.parse().unwrap()) }
+
+    #[test]
+    fn head_variables_lead() {
+        assert_eq!(order("Q(Z, X) :- r(X, Y), s(Y, Z)."), vec!["Z", "X", "Y"]);
+    }
+
+    #[test]
+    fn body_variables_in_first_appearance_order() {
+        assert_eq!(order("Q(X) :- r(X, B), s(A, B)."), vec!["X", "B", "A"]);
+    }
+
+    #[test]
+    fn placeholders_and_atoms_are_skipped() {
+        assert_eq!(order("Q(X) :- r(X, _, c3), s(_, Y)."), vec!["X", "Y"]);
+    }
+
+    #[test]
+    fn repeated_variables_appear_once() {
+        assert_eq!(order("Q(X, X) :- r(X, X, Y)."), vec!["X", "Y"]);
+    }
+
+    fn greedy(q: &str, stats: &[(&str, Vec<usize>)]) -> Vec<String> {
+        VariableOrderStrategy::Greedy.order(&q.parse().unwrap(), |name| {
+            stats
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, l)| l.clone())
+        })
+    }
+
+    #[test]
+    fn head_first_ignores_statistics() {
+        let q: JoinQuery = "Q(X, Y) :- r(X, Y).".parse().unwrap();
+        let order = VariableOrderStrategy::HeadFirst.order(&q, |_| panic!("no stats needed"));
+        assert_eq!(order, vec!["X", "Y"]);
+    }
+
+    #[test]
+    fn greedy_starts_with_fewest_distinct_keys() {
+        // s has only 2 distinct B values; once B is bound, r pins A (1000
+        // tuples over 1000 prefixes) more tightly than s pins C (50 / 2).
+        let order = greedy("Q(A, B, C) :- r(A, B), s(B, C).", &[
+            ("r", vec![1000, 1000]),
+            ("s", vec![2, 50]),
+        ]);
+        assert_eq!(order, vec!["B", "A", "C"]);
+    }
+
+    #[test]
+    fn greedy_keeps_head_variables_first() {
+        // Y is the most selective variable but is not in the head.
+        let order = greedy("Q(X) :- r(X, Y), s(Y).", &[
+            ("r", vec![100, 200]),
+            ("s", vec![1]),
+        ]);
+        assert_eq!(order, vec!["X", "Y"]);
+    }
+
+    #[test]
+    fn greedy_prefers_variables_connected_to_bound_ones() {
+        // After binding A, B is narrowed by r (200 / 100 = 2 candidates) while
+        // the unrelated D still has 500.
+        let order = greedy("Q(A, D, B) :- r(A, B), t(D).", &[
+            ("r", vec![100, 200]),
+            ("t", vec![500]),
+        ]);
+        assert_eq!(order, vec!["A", "B", "D"]);
+    }
+
+    #[test]
+    fn greedy_without_statistics_falls_back_to_head_first() {
+        assert_eq!(greedy("Q(Z, X) :- r(X, Y), s(Y, Z).", &[]), vec![
+            "Z", "X", "Y"
+        ]);
+    }
+}
@@ -0,0 +1,127 @@
+//! Reordered views of stored relations for leapfrog-style joins.
+//!
+//! Leapfrog Triejoin requires every body atom's trie to be keyed in the
+//! global variable order (see [`variable_order`](crate::variable_order)).
+//! [`ViewKey`] plans and builds the reordered copy of a relation that an
+//! out-of-order atom needs, so callers can join against it instead.
+
+use {
+    kermit_ds::{Relation, RelationHeader},
+    kermit_iters::TrieIterable,
+    kermit_parser::Term,
+};
+
+/// Identifies a reordered view of a stored relation whose columns follow the
+/// global variable order of some query atom.
+///
+/// The view keeps the source columns listed in `columns`, in that order, of
+/// the tuples whose columns are pairwise equal for every entry of
+/// `equalities` (arising from a variable repeated within one atom).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ViewKey {
+    relation: String,
+    columns: Vec<usize>,
+    equalities: Vec<(usize, usize)>,
+}
+
+impl ViewKey {
+    /// Name of the stored relation the view is derived from.
+    pub fn relation(&self) -> &str { &self.relation }
+
+    /// Plans the view needed for an atom `relation(terms)` under the global
+    /// variable `order`. Returns the key together with the atom's rewritten
+    /// terms (one per distinct variable, in ascending global order), or
+    /// `None` if the stored column order can be used as-is.
+    ///
+    /// A view is needed when the atom's variables are out of global order,
+    /// when a variable repeats, or when a placeholder occupies a column.
+    pub fn plan(relation: &str, terms: &[Term], order: &[String]) -> Option<(Self, Vec<Term>)> {
+        // (rank in the global order, first source column) per distinct
+        // variable.
+        let mut vars: Vec<(usize, usize)> = Vec::new();
+        let mut equalities: Vec<(usize, usize)> = Vec::new();
+        let mut has_placeholder = false;
+        for (col, term) in terms.iter().enumerate() {
+            match term {
+                | Term::Var(name) => {
+                    let rank = order
+                        .iter()
+                        .position(|v| v == name)
+                        .expect("body variable missing from the global order");
+                    match vars.iter().find(|(r, _)| *r == rank) {
+                        | Some(&(_, first)) => equalities.push((first, col)),
+                        | None => vars.push((rank, col)),
+                    }
+                },
+                | Term::Atom(_) | Term::Int(_) | Term::Str(_) | Term::Placeholder => {
+                    has_placeholder = true
+                },
+                | Term::Aggregate(..) => unreachable!("aggregates only appear in rule heads"),
+            }
+        }
+
+        let in_order = vars.windows(2).all(|w| w[0].0 < w[1].0);
+        if vars.is_empty() || (in_order && equalities.is_empty() && !has_placeholder) {
+            return None;
+        }
+
+        vars.sort_unstable();
+        let key = ViewKey {
+            relation: relation.to_owned(),
+            columns: vars.iter().map(|&(_, col)| col).collect(),
+            equalities,
+        };
+        let terms = vars
+            .iter()
+            .map(|&(rank, _)| Term::Var(order[rank].clone()))
+            .collect();
+        Some((key, terms))
+    }
+
+    /// Name under which the view is bound for a single join, e.g.
+    /// `edge[1,0]` or `r[0,1|0=2]`. Brackets cannot occur in parsed
+    /// predicate names, so these never collide with stored relations.
+    pub fn predicate_name(&self) -> String {
+        let cols = self
+            .columns
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        if self.equalities.is_empty() {
+            format!("{}[{cols}]", self.relation)
+        } else {
+            let eqs = self
+                .equalities
+                .iter()
+                .map(|(a, b)| format!("{a}={b}"))
+                .collect::<Vec<_>>()
+                .join(",");
+            format!("{}[{cols}|{eqs}]", self.relation)
+        }
+    }
+
+    /// Materialises the view from `relation`. Pure permutations and
+    /// placeholder drops go through [`Projectable::project`]; repeated
+    /// variables additionally filter on the equalities first.
+    ///
+    /// [`Projectable::project`]: kermit_ds::Projectable::project
+    pub fn build<R>(&self, relation: &R) -> R
+    where
+        R: Relation + TrieIterable,
+    {
+        if self.equalities.is_empty() {
+            return relation.project(self.columns.clone());
+        }
+        let tuples = relation
+            .trie_iter()
+            .into_iter()
+            .filter(|t| self.equalities.iter().all(|&(a, b)| t[a] == t[b]))
+            .map(|t| self.columns.iter().map(|&c| t[c]).collect())
+            .collect();
+        R::from_tuples(
+            RelationHeader::new_nameless_positional(self.columns.len()),
+            tuples,
+        )
+    }
+}
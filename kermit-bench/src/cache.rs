@@ -3,10 +3,11 @@
 //! Relation files referenced by a [`BenchmarkDefinition`] are downloaded lazily
 //! into the platform cache directory under
 //! `<cache_dir>/kermit/benchmarks/<benchmark>/<relation>.parquet`. On Linux
-//! this resolves to `~/.cache/kermit/benchmarks/…`.
+//! this resolves to `~/.cache/kermit/benchmarks/…`. Prebuilt indexes of those
+//! files are kept next to them as `<relation>.ktrie`.
 //!
-//! [`ensure_cached`] is the entry point; [`clean_benchmark`] and [`clean_all`]
-//! remove cached files.
+//! [`ensure_cached`] is the entry point; [`ensure_indexed`] adds the prebuilt
+//! indexes, and [`clean_benchmark`] and [`clean_all`] remove cached files.
 
 use {
     crate::{definition::BenchmarkDefinition, error::BenchError},
@@ -44,6 +45,16 @@ pub fn relation_cache_path(
     Ok(cache_dir(benchmark_name)?.join(format!("{relation_name}.parquet")))
 }
 
+/// Returns the expected path for the prebuilt index of a cached relation.
+///
+/// # Errors
+///
+/// Returns [`BenchError::NoCacheDir`] if the platform cache directory cannot
+/// be determined.
+pub fn index_cache_path(benchmark_name: &str, relation_name: &str) -> Result<PathBuf, BenchError> {
+    Ok(cache_dir(benchmark_name)?.join(format!("{relation_name}.ktrie")))
+}
+
 /// Returns true if all relation files for the benchmark are cached.
 ///
 /// # Errors
@@ -89,6 +100,52 @@ pub fn ensure_cached(benchmark: &BenchmarkDefinition) -> Result<Vec<PathBuf>, Be
     Ok(paths)
 }
 
+/// Ensures every relation of a benchmark is cached and has a prebuilt index
+/// next to it.
+///
+/// An index is (re)built by calling `build(relation_file, index_file)` when
+/// it is missing or older than its relation file; the builder is expected to
+/// write `index_file`. Returns paths to the index files in the same order as
+/// the benchmark's relations list.
+///
+/// # Errors
+///
+/// Returns a [`BenchError`] as [`ensure_cached`] does, or
+/// [`BenchError::Index`] if `build` fails.
+pub fn ensure_indexed<F, E>(
+    benchmark: &BenchmarkDefinition, mut build: F,
+) -> Result<Vec<PathBuf>, BenchError>
+where
+    F: FnMut(&Path, &Path) -> Result<(), E>,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let sources = ensure_cached(benchmark)?;
+    let mut paths = Vec::with_capacity(sources.len());
+
+    for (rel, source) in benchmark.relations.iter().zip(&sources) {
+        let path = index_cache_path(&benchmark.name, &rel.name)?;
+        if !is_fresh(&path, source)? {
+            eprintln!("  indexing {}...", rel.name);
+            build(source, &path).map_err(|source| BenchError::Index {
+                path: path.clone(),
+                source: source.into(),
+            })?;
+        }
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+/// Returns true if `derived` exists and was modified no earlier than
+/// `source`.
+fn is_fresh(derived: &Path, source: &Path) -> Result<bool, BenchError> {
+    let Ok(derived) = fs::metadata(derived) else {
+        return Ok(false);
+    };
+    Ok(derived.modified()? >= fs::metadata(source)?.modified()?)
+}
+
 /// Downloads a file from a URL to the given destination path.
 fn download_file(url: &str, dest: &Path) -> Result<(), BenchError> {
     if let Some(parent) = dest.parent() {
@@ -167,6 +224,35 @@ mod tests {
         assert!(path.ends_with("kermit/benchmarks/triangle/edge.parquet"));
     }
 
+    #[test]
+    fn index_cache_path_sits_next_to_the_relation() {
+        let path = index_cache_path("triangle", "edge").unwrap();
+        assert_eq!(
+            path.with_extension("parquet"),
+            relation_cache_path("triangle", "edge").unwrap()
+        );
+    }
+
+    #[test]
+    fn is_fresh_compares_modification_times() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("edge.parquet");
+        let derived = dir.path().join("edge.ktrie");
+        fs::write(&source, b"rows").unwrap();
+        assert!(!is_fresh(&derived, &source).unwrap());
+
+        fs::write(&derived, b"index").unwrap();
+        let file = fs::File::options().write(true).open(&source).unwrap();
+        let later =
+            fs::metadata(&derived).unwrap().modified().unwrap() + std::time::Duration::from_secs(1);
+        file.set_modified(later).unwrap();
+        assert!(!is_fresh(&derived, &source).unwrap());
+
+        let file = fs::File::options().write(true).open(&derived).unwrap();
+        file.set_modified(later).unwrap();
+        assert!(is_fresh(&derived, &source).unwrap());
+    }
+
     #[test]
     fn is_cached_false_when_missing() {
         let def = BenchmarkDefinition {
@@ -180,6 +266,7 @@ mod tests {
                 name: "q".to_string(),
                 description: "test".to_string(),
                 query: "Q(X) :- r(X).".to_string(),
+                expected_count: None,
             }],
             generator: None,
         };
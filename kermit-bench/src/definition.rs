@@ -4,7 +4,11 @@
 //! at the top level. The schema is documented in the workspace
 //! `benchmarks/README.md`.
 
-use {crate::error::BenchError, std::collections::HashSet};
+use {
+    crate::error::BenchError,
+    kermit_parser::JoinQuery,
+    std::collections::{hash_map::Entry, HashMap, HashSet},
+};
 
 /// A benchmark definition loaded from a YAML file.
 ///
@@ -154,19 +158,24 @@ pub struct QueryDefinition {
     pub description: String,
     /// The Datalog query string (see `kermit-parser` for grammar).
     pub query: String,
+    /// Known result cardinality. When present, `bench run` counts the
+    /// query's results and reports whether they match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_count: Option<usize>,
 }
 
 impl BenchmarkDefinition {
     /// Validates structural invariants of the benchmark definition.
     ///
     /// Checks that `name`, `relations`, and `queries` are non-empty, that
-    /// every query has a non-empty `name` and `query`, and that relation
-    /// names and query names are unique within the benchmark.
+    /// every query has a non-empty `name` and a `query` that parses, and that
+    /// relation names and query names are unique within the benchmark.
     ///
     /// # Errors
     ///
     /// Returns [`BenchError::Invalid`] describing the first failing
-    /// constraint.
+    /// constraint, or [`BenchError::Query`] locating the first query that
+    /// does not parse.
     ///
     /// # Example
     ///
@@ -184,6 +193,7 @@ impl BenchmarkDefinition {
     ///         name: "triangle".into(),
     ///         description: "triangle".into(),
     ///         query: "T(X, Y, Z) :- edge(X, Y), edge(Y, Z), edge(X, Z).".into(),
+    ///         expected_count: None,
     ///     }],
     ///     generator: None,
     /// };
@@ -258,6 +268,13 @@ impl BenchmarkDefinition {
                     reason: format!("query '{}' has empty query string", q.name),
                 });
             }
+            if let Err(source) = q.query.parse::<JoinQuery>() {
+                return Err(BenchError::Query {
+                    name: self.name.clone(),
+                    query: q.name.clone(),
+                    source: Box::new(source),
+                });
+            }
         }
 
         let mut seen = HashSet::new();
@@ -282,6 +299,29 @@ impl BenchmarkDefinition {
 
         Ok(())
     }
+
+    /// Returns the queries that repeat an earlier one up to variable names,
+    /// body atom order and head name, as `(duplicate, original)` query-name
+    /// pairs in definition order.
+    ///
+    /// Queries are compared by [`JoinQuery::fingerprint`]. Queries that do
+    /// not parse are skipped; [`validate`](Self::validate) reports them.
+    pub fn duplicate_queries(&self) -> Vec<(&str, &str)> {
+        let mut first_seen: HashMap<u64, &str> = HashMap::new();
+        let mut duplicates = Vec::new();
+        for q in &self.queries {
+            let Ok(query) = q.query.parse::<JoinQuery>() else {
+                continue;
+            };
+            match first_seen.entry(query.fingerprint()) {
+                | Entry::Occupied(original) => duplicates.push((q.name.as_str(), *original.get())),
+                | Entry::Vacant(slot) => {
+                    slot.insert(&q.name);
+                },
+            }
+        }
+        duplicates
+    }
 }
 
 fn validate_generator(bench_name: &str, spec: &GeneratorSpec) -> Result<(), BenchError> {
@@ -378,6 +418,7 @@ mod tests {
             name: name.to_string(),
             description: format!("{name} query"),
             query: query.to_string(),
+            expected_count: None,
         }
     }
 
@@ -400,9 +441,31 @@ queries:
         assert_eq!(def.relations[0].name, "edge");
         assert_eq!(def.queries.len(), 1);
         assert_eq!(def.queries[0].name, "triangle");
+        assert_eq!(def.queries[0].expected_count, None);
         assert!(def.validate().is_ok());
     }
 
+    #[test]
+    fn deserialize_expected_count() {
+        let yaml = r#"
+name: triangle
+description: "Triangle query"
+relations:
+  - name: edge
+    url: "https://example.com/edge.parquet"
+queries:
+  - name: triangle
+    description: "Triangle query"
+    query: "T(X, Y, Z) :- edge(X, Y), edge(Y, Z), edge(X, Z)."
+    expected_count: 42
+"#;
+        let def: BenchmarkDefinition = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(def.queries[0].expected_count, Some(42));
+        let round_trip: BenchmarkDefinition =
+            serde_yaml::from_str(&serde_yaml::to_string(&def).unwrap()).unwrap();
+        assert_eq!(round_trip.queries[0].expected_count, Some(42));
+    }
+
     #[test]
     fn deserialize_multiple_relations() {
         let yaml = r#"
@@ -519,6 +582,31 @@ queries:
         assert!(def.validate().is_err());
     }
 
+    #[test]
+    fn validate_locates_query_parse_errors() {
+        let def = BenchmarkDefinition {
+            name: "test".to_string(),
+            description: "test".to_string(),
+            relations: vec![RelationSource {
+                name: "r".to_string(),
+                url: "http://x".to_string(),
+            }],
+            queries: vec![make_query("q", "Q(X) :- r(X)")],
+            generator: None,
+        };
+        let Err(BenchError::Query {
+            query,
+            source,
+            ..
+        }) = def.validate()
+        else {
+            panic!("expected a query parse error");
+        };
+        assert_eq!(query, "q");
+        assert_eq!((source.line, source.column), (1, 13));
+        assert_eq!(source.expected, vec!["`,`", "`.`"]);
+    }
+
     #[test]
     fn validate_duplicate_relation_names() {
         let def = BenchmarkDefinition {
@@ -558,6 +646,30 @@ queries:
         assert!(def.validate().is_err());
     }
 
+    #[test]
+    fn duplicate_queries_match_up_to_renaming_and_atom_order() {
+        let def = BenchmarkDefinition {
+            name: "test".to_string(),
+            description: "test".to_string(),
+            relations: vec![RelationSource {
+                name: "edge".to_string(),
+                url: "http://x".to_string(),
+            }],
+            queries: vec![
+                make_query("path", "P(X, Z) :- edge(X, Y), edge(Y, Z)."),
+                make_query("reversed", "P(Z, X) :- edge(X, Y), edge(Y, Z)."),
+                make_query("renamed", "Q(A, C) :- edge(B, C), edge(A, B)."),
+                make_query("broken", "Q(X) :- edge(X"),
+                make_query("again", "R(U, W) :- edge(U, V), edge(V, W)."),
+            ],
+            generator: None,
+        };
+        assert_eq!(def.duplicate_queries(), vec![
+            ("renamed", "path"),
+            ("again", "path")
+        ]);
+    }
+
     #[test]
     fn missing_required_fields_fails_validation() {
         let yaml = r#"
@@ -27,6 +27,15 @@ pub enum BenchError {
         source: reqwest::Error,
     },
 
+    /// Building a prebuilt index for a cached relation file failed.
+    #[error("failed to build index {path}: {source}")]
+    Index {
+        /// The index file being written.
+        path: PathBuf,
+        /// The error reported by the index builder.
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
     /// A benchmark with the requested name does not exist.
     #[error("benchmark not found: {0}")]
     NotFound(String),
@@ -41,6 +50,17 @@ pub enum BenchError {
         reason: String,
     },
 
+    /// A query string does not parse as a Datalog rule.
+    #[error("invalid query '{query}' in benchmark {name}: {source}")]
+    Query {
+        /// The benchmark name as declared in the YAML.
+        name: String,
+        /// The query's name.
+        query: String,
+        /// The parser's error, locating the problem in the query string.
+        source: Box<kermit_parser::ParseError>,
+    },
+
     /// The platform cache directory could not be determined.
     #[error("cache directory not available")]
     NoCacheDir,
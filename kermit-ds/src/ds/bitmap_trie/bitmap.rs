@@ -0,0 +1,114 @@
+//! Fixed-width bitmaps holding the sibling groups of a dense layer.
+
+/// One bitmap of `stride` words per sibling group, where bit `b` of a group
+/// stands for key `min + b`.
+///
+/// Every group spans the same key range, so a group's bits are found by
+/// multiplying its index by `stride` and need no offsets of their own.
+#[derive(Clone, Debug)]
+pub(crate) struct Bitmaps {
+    /// The key of bit 0.
+    min: usize,
+    /// Words per group.
+    stride: usize,
+    words: Vec<u64>,
+    /// Number of set bits across all groups.
+    len: usize,
+}
+
+impl Bitmaps {
+    /// Returns the words per group needed for keys in `min..=max`.
+    pub(crate) fn stride(min: usize, max: usize) -> usize { (max - min) / 64 + 1 }
+
+    /// Sets the bits of the keys of every group in `groups`, each of which
+    /// must lie in `min..min + 64 * stride`.
+    pub(crate) fn new<'k>(
+        min: usize, stride: usize, groups: impl ExactSizeIterator<Item = &'k [usize]>,
+    ) -> Self {
+        let mut words = vec![0; groups.len() * stride];
+        let mut len = 0;
+        for (g, keys) in groups.enumerate() {
+            for &key in keys {
+                let bit = g * stride * 64 + (key - min);
+                words[bit / 64] |= 1 << (bit % 64);
+            }
+            len += keys.len();
+        }
+        Bitmaps {
+            min,
+            stride,
+            words,
+            len,
+        }
+    }
+
+    /// Returns the number of keys.
+    pub(crate) fn len(&self) -> usize { self.len }
+
+    /// Returns the key of bit `bit`.
+    pub(crate) fn key(&self, bit: usize) -> usize { self.min + bit }
+
+    /// Returns the bit of `key`, or 0 if `key` lies below every bit.
+    pub(crate) fn bit(&self, key: usize) -> usize { key.saturating_sub(self.min) }
+
+    /// Returns the first set bit of `group` at or after `from`.
+    pub(crate) fn next_set_bit(&self, group: usize, from: usize) -> Option<usize> {
+        let words = self.group(group);
+        let mut w = from / 64;
+        let mut bits = words.get(w)? & (u64::MAX << (from % 64));
+        while bits == 0 {
+            w += 1;
+            bits = *words.get(w)?;
+        }
+        Some(w * 64 + bits.trailing_zeros() as usize)
+    }
+
+    /// Counts the set bits of `group` in `from..to`.
+    pub(crate) fn count_ones(&self, group: usize, from: usize, to: usize) -> usize {
+        if from >= to {
+            return 0;
+        }
+        let words = self.group(group);
+        let (first, last) = (from / 64, (to - 1) / 64);
+        let low = u64::MAX << (from % 64);
+        let high = u64::MAX >> (63 - (to - 1) % 64);
+        if first == last {
+            return (words[first] & low & high).count_ones() as usize;
+        }
+        let inner: u32 = words[first + 1..last].iter().map(|w| w.count_ones()).sum();
+        ((words[first] & low).count_ones() + inner + (words[last] & high).count_ones()) as usize
+    }
+
+    /// Returns the bytes allocated by the bitmaps.
+    pub(crate) fn heap_size_bytes(&self) -> usize {
+        self.words.capacity() * std::mem::size_of::<u64>()
+    }
+
+    fn group(&self, group: usize) -> &[u64] {
+        &self.words[group * self.stride..(group + 1) * self.stride]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scans_and_counts_across_words() {
+        let groups: [&[usize]; 2] = [&[10, 11, 75, 200], &[10, 138]];
+        let bitmaps = Bitmaps::new(10, Bitmaps::stride(10, 200), groups.into_iter());
+        assert_eq!(bitmaps.len(), 6);
+        assert_eq!(bitmaps.next_set_bit(0, 0), Some(0));
+        assert_eq!(bitmaps.next_set_bit(0, 2), Some(65));
+        assert_eq!(bitmaps.next_set_bit(0, 66), Some(190));
+        assert_eq!(bitmaps.next_set_bit(0, 191), None);
+        assert_eq!(bitmaps.next_set_bit(1, 1), Some(128));
+        assert_eq!(bitmaps.next_set_bit(1, 1000), None);
+        assert_eq!(bitmaps.count_ones(0, 0, 191), 4);
+        assert_eq!(bitmaps.count_ones(0, 1, 66), 2);
+        assert_eq!(bitmaps.count_ones(0, 64, 64), 0);
+        assert_eq!(bitmaps.count_ones(1, 1, 129), 1);
+        assert_eq!(bitmaps.key(190), 200);
+        assert_eq!(bitmaps.bit(3), 0);
+    }
+}
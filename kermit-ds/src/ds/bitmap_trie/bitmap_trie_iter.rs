@@ -0,0 +1,174 @@
+use {
+    super::implementation::{BitmapTrie, Keys},
+    crate::relation::Relation,
+    kermit_derive::IntoTrieIter,
+    kermit_iters::{LinearIterator, TrieIterable, TrieIterator, TrieIteratorWrapper},
+};
+
+/// The sibling group being scanned at one trie depth.
+#[derive(Clone, Copy)]
+struct Frame {
+    /// Index of the group in the layer's `interval`.
+    group: usize,
+    /// Index of the current key among the layer's keys.
+    pos: usize,
+    /// One past the group's last index.
+    end: usize,
+    /// The key at `pos`, or `None` once the group is exhausted.
+    key: Option<usize>,
+}
+
+/// Iterator over a [`BitmapTrie`].
+///
+/// A [`Frame`] per open depth records the sibling group being scanned, the
+/// current key and its index among the layer's keys, which names the child
+/// group to open. In a sorted layer the key is read from that index. In a
+/// bitmap layer `next` and `seek` find the next set bit of the group instead,
+/// and advance the index by the number of set bits they skipped.
+#[derive(IntoTrieIter)]
+pub struct BitmapTrieIter<'a> {
+    /// One frame per open depth; empty at the root.
+    frames: Vec<Frame>,
+    /// The trie being iterated.
+    trie: &'a BitmapTrie,
+}
+
+impl<'a> BitmapTrieIter<'a> {
+    /// Creates a new iterator positioned at the root. Call
+    /// [`open`](TrieIterator::open) to descend to the first layer.
+    pub fn new(trie: &'a BitmapTrie) -> Self {
+        BitmapTrieIter {
+            frames: Vec::with_capacity(trie.header().arity()),
+            trie,
+        }
+    }
+}
+
+impl LinearIterator for BitmapTrieIter<'_> {
+    fn key(&self) -> Option<usize> { self.frames.last()?.key }
+
+    fn next(&mut self) -> Option<usize> {
+        let layer = self.trie.layer(self.frames.len().checked_sub(1)?);
+        let frame = self.frames.last_mut()?;
+        let key = frame.key?;
+        frame.pos += 1;
+        frame.key = (frame.pos < frame.end).then(|| match &layer.keys {
+            | Keys::Sorted(data) => data[frame.pos],
+            | Keys::Bitmap(bitmaps) => {
+                let bit = bitmaps.next_set_bit(frame.group, bitmaps.bit(key) + 1);
+                bitmaps.key(bit.expect("the group has a key past `pos`"))
+            },
+        });
+        frame.key
+    }
+
+    fn seek(&mut self, seek_key: usize) -> bool {
+        if self.at_end() {
+            return false;
+        }
+        let layer = self.trie.layer(self.frames.len() - 1);
+        let frame = self.frames.last_mut().unwrap();
+        let key = frame.key.unwrap();
+        if seek_key <= key {
+            return true;
+        }
+        match &layer.keys {
+            | Keys::Sorted(data) => {
+                frame.pos += data[frame.pos..frame.end].partition_point(|&k| k < seek_key);
+                frame.key = (frame.pos < frame.end).then(|| data[frame.pos]);
+            },
+            | Keys::Bitmap(bitmaps) => {
+                let from = bitmaps.bit(key);
+                match bitmaps.next_set_bit(frame.group, bitmaps.bit(seek_key)) {
+                    | Some(bit) => {
+                        frame.pos += bitmaps.count_ones(frame.group, from + 1, bit + 1);
+                        frame.key = Some(bitmaps.key(bit));
+                    },
+                    | None => {
+                        frame.pos = frame.end;
+                        frame.key = None;
+                    },
+                }
+            },
+        }
+        frame.key.is_some()
+    }
+
+    fn at_end(&self) -> bool { self.frames.last().is_none_or(|frame| frame.key.is_none()) }
+}
+
+impl TrieIterator for BitmapTrieIter<'_> {
+    fn open(&mut self) -> bool {
+        let depth = self.frames.len();
+        if depth == self.trie.header().arity() {
+            return false;
+        }
+        let layer = self.trie.layer(depth);
+        let group = match self.frames.last() {
+            | None => 0,
+            | Some(frame) if frame.key.is_none() => return false,
+            | Some(frame) => frame.pos,
+        };
+        if group >= layer.interval.len() {
+            return false;
+        }
+        let range = layer.group(group);
+        if range.is_empty() {
+            return false;
+        }
+        let key = match &layer.keys {
+            | Keys::Sorted(data) => data[range.start],
+            | Keys::Bitmap(bitmaps) => {
+                let bit = bitmaps.next_set_bit(group, 0);
+                bitmaps.key(bit.expect("a non-empty group has a set bit"))
+            },
+        };
+        self.frames.push(Frame {
+            group,
+            pos: range.start,
+            end: range.end,
+            key: Some(key),
+        });
+        true
+    }
+
+    fn up(&mut self) -> bool { self.frames.pop().is_some() }
+}
+
+/// Implementation of the `TrieIterable` trait for `BitmapTrie`.
+impl TrieIterable for BitmapTrie {
+    fn trie_iter(&self) -> impl TrieIterator + IntoIterator<Item = Vec<usize>> {
+        BitmapTrieIter::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seek_skips_words_and_tracks_child_groups() {
+        // A dense first layer: 205 keys below 256, each with the child
+        // `key * 10`.
+        let tuples: Vec<Vec<usize>> = (0..256)
+            .filter(|k| k % 5 != 3)
+            .map(|k| vec![k, k * 10])
+            .collect();
+        let trie = BitmapTrie::from_tuples(2.into(), tuples);
+        assert!(matches!(trie.layer(0).keys, Keys::Bitmap(_)));
+
+        let mut iter = trie.trie_iter();
+        assert!(iter.open());
+        for (target, expected) in [(2, 2), (3, 4), (130, 130), (133, 134), (254, 254)] {
+            assert!(iter.seek(target));
+            assert_eq!(iter.key(), Some(expected));
+            assert!(iter.open());
+            assert_eq!(iter.key(), Some(expected * 10));
+            assert!(iter.up());
+        }
+        assert_eq!(iter.next(), Some(255));
+        assert_eq!(iter.next(), None);
+        assert!(iter.at_end());
+        assert!(!iter.seek(300));
+    }
+}
@@ -0,0 +1,331 @@
+use {
+    super::bitmap::Bitmaps,
+    crate::{
+        ds::ColumnTrie,
+        relation::{Relation, RelationHeader},
+    },
+    kermit_iters::{JoinIterable, TrieIterable},
+};
+
+/// How a [`BitmapLayer`] stores its keys.
+pub(crate) enum Keys {
+    /// Sorted keys, as in a [`ColumnTrie`] layer.
+    Sorted(Vec<usize>),
+    /// A bitmap per sibling group.
+    Bitmap(Bitmaps),
+}
+
+/// A single level of a [`BitmapTrie`].
+///
+/// `interval` maps each parent element to the index of its first child,
+/// counting this layer's keys in order, exactly as in a [`ColumnTrie`]
+/// layer. The keys themselves are stored sorted or as bitmaps, whichever is
+/// smaller.
+pub(crate) struct BitmapLayer {
+    /// Maps each parent element to the index of its first child.
+    pub(crate) interval: Vec<usize>,
+    /// The keys at this depth.
+    pub(crate) keys: Keys,
+}
+
+impl BitmapLayer {
+    /// Builds a layer from the `data` and `interval` arrays of a
+    /// [`ColumnTrie`] layer. The keys are stored as bitmaps if one bitmap
+    /// per sibling group, spanning the layer's smallest to largest key,
+    /// takes no more words than the keys themselves.
+    fn new(data: &[usize], interval: &[usize]) -> Self {
+        let dense = data
+            .iter()
+            .min()
+            .zip(data.iter().max())
+            .and_then(|(&min, &max)| {
+                let stride = Bitmaps::stride(min, max);
+                let words = stride.checked_mul(interval.len())?;
+                (words <= data.len()).then_some((min, stride))
+            });
+        let keys = match dense {
+            | Some((min, stride)) => {
+                let groups =
+                    (0..interval.len()).map(|g| &data[group_range(interval, g, data.len())]);
+                Keys::Bitmap(Bitmaps::new(min, stride, groups))
+            },
+            | None => Keys::Sorted(data.to_vec()),
+        };
+        BitmapLayer {
+            interval: interval.to_vec(),
+            keys,
+        }
+    }
+
+    /// Returns the number of keys at this depth.
+    pub(crate) fn len(&self) -> usize {
+        match &self.keys {
+            | Keys::Sorted(data) => data.len(),
+            | Keys::Bitmap(bitmaps) => bitmaps.len(),
+        }
+    }
+
+    /// Returns the index range `start..end` of the children of the element
+    /// at `interval_index`.
+    pub(crate) fn group(&self, interval_index: usize) -> std::ops::Range<usize> {
+        group_range(&self.interval, interval_index, self.len())
+    }
+}
+
+fn group_range(interval: &[usize], i: usize, len: usize) -> std::ops::Range<usize> {
+    interval[i]..interval.get(i + 1).copied().unwrap_or(len)
+}
+
+/// A [`ColumnTrie`] whose dense layers are stored as bitmaps.
+///
+/// Attributes with small, dense domains, such as class or category IDs,
+/// waste a word per key in a sorted layer. When giving every sibling group a
+/// bitmap over the layer's key range takes no more words than the keys, the
+/// layer is stored that way instead; the choice is made per layer when the
+/// trie is built. [`seek`](kermit_iters::LinearIterator::seek) in a bitmap
+/// layer finds the next set bit, skipping 64 keys per word, and counts the
+/// set bits it passes to keep track of the child group below. Sparse layers
+/// are kept sorted and searched as in [`ColumnTrie`].
+///
+/// # Invariants
+///
+/// The same as [`ColumnTrie`]'s, on the keys the layers stand for.
+///
+/// # When to prefer
+///
+/// Prefer `BitmapTrie` for static relations with low-cardinality
+/// attributes. Layers only become bitmaps when that saves space, so it is
+/// never larger than a [`ColumnTrie`] by more than a few bytes. The layers
+/// cannot be edited in place: every [`insert`](Relation::insert) or
+/// [`remove`](Relation::remove) unpacks the relation, applies the change and
+/// builds it again.
+///
+/// # Example
+///
+/// ```
+/// use kermit_ds::{BitmapTrie, Relation, TrieStats};
+///
+/// let trie = BitmapTrie::from_tuples(2.into(), vec![vec![1, 2], vec![1, 3], vec![2, 4]]);
+/// assert_eq!(trie.level_sizes(), vec![2, 3]);
+/// ```
+pub struct BitmapTrie {
+    header: RelationHeader,
+    layers: Vec<BitmapLayer>,
+}
+
+impl BitmapTrie {
+    /// Returns a reference to the layer at the given depth.
+    pub(crate) fn layer(&self, layer_i: usize) -> &BitmapLayer { &self.layers[layer_i] }
+
+    /// Unpacks the relation into a [`ColumnTrie`].
+    pub fn to_column_trie(&self) -> ColumnTrie {
+        ColumnTrie::from_sorted_tuples(self.header.clone(), self.trie_iter().into_iter().collect())
+    }
+
+    /// Unpacks the relation, applies `edit` and rebuilds the result if `edit`
+    /// reports a change.
+    fn edit(&mut self, edit: impl FnOnce(&mut ColumnTrie) -> bool) -> bool {
+        let mut trie = self.to_column_trie();
+        let changed = edit(&mut trie);
+        if changed {
+            *self = Self::from(&trie);
+        }
+        changed
+    }
+}
+
+impl From<&ColumnTrie> for BitmapTrie {
+    /// Copies the layers of `trie`, storing the dense ones as bitmaps.
+    fn from(trie: &ColumnTrie) -> Self {
+        BitmapTrie {
+            header: trie.header().clone(),
+            layers: trie
+                .layers
+                .iter()
+                .map(|layer| BitmapLayer::new(&layer.data, &layer.interval))
+                .collect(),
+        }
+    }
+}
+
+impl JoinIterable for BitmapTrie {}
+
+impl crate::relation::Projectable for BitmapTrie {
+    fn project(&self, columns: Vec<usize>) -> Self {
+        let current_header = self.header();
+        let projected_attrs: Vec<String> = columns
+            .iter()
+            .filter_map(|&col_idx| current_header.attrs().get(col_idx).cloned())
+            .collect();
+
+        let new_header = if projected_attrs.is_empty() {
+            crate::relation::RelationHeader::new_nameless_positional(columns.len())
+        } else {
+            crate::relation::RelationHeader::new_nameless(projected_attrs)
+        };
+
+        let projected_tuples: Vec<Vec<usize>> = self
+            .trie_iter()
+            .into_iter()
+            .map(|tuple| columns.iter().map(|&col_idx| tuple[col_idx]).collect())
+            .collect();
+
+        Self::from_tuples(new_header, projected_tuples)
+    }
+}
+
+impl Relation for BitmapTrie {
+    fn header(&self) -> &RelationHeader { &self.header }
+
+    fn new(header: RelationHeader) -> Self { Self::from(&ColumnTrie::new(header)) }
+
+    /// Builds a `BitmapTrie` by bulk-loading a [`ColumnTrie`] and converting
+    /// its layers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any tuple's length does not match `header.arity()`.
+    fn from_tuples(header: RelationHeader, tuples: Vec<Vec<usize>>) -> Self {
+        Self::from(&ColumnTrie::from_tuples(header, tuples))
+    }
+
+    /// Bulk-loads already sorted `tuples` into a [`ColumnTrie`] and converts
+    /// its layers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tuples` is not sorted lexicographically, or if any tuple's
+    /// length does not match `header.arity()`.
+    fn from_sorted_tuples(header: RelationHeader, tuples: Vec<Vec<usize>>) -> Self {
+        Self::from(&ColumnTrie::from_sorted_tuples(header, tuples))
+    }
+
+    /// Converts the layers of `trie` directly, without walking its tuples.
+    fn from_column_trie(trie: ColumnTrie) -> Self { Self::from(&trie) }
+
+    /// Inserts a single tuple by unpacking and rebuilding the whole relation.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if `tuple.len()` does not match the relation's
+    /// arity.
+    fn insert(&mut self, tuple: Vec<usize>) -> bool { self.edit(|trie| trie.insert(tuple)) }
+
+    /// Inserts every tuple in `tuples` with a single unpack and rebuild.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any tuple's length does not match the relation's arity.
+    fn insert_all(&mut self, tuples: Vec<Vec<usize>>) -> bool {
+        self.edit(|trie| trie.insert_all(tuples))
+    }
+
+    /// Removes a single tuple by unpacking and rebuilding the whole relation.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if `tuple.len()` does not match the relation's
+    /// arity.
+    fn remove(&mut self, tuple: &[usize]) -> bool { self.edit(|trie| trie.remove(tuple)) }
+
+    /// Removes every tuple in `tuples` with a single unpack and rebuild.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any tuple's length does not match the relation's arity.
+    fn remove_all(&mut self, tuples: Vec<Vec<usize>>) -> bool {
+        self.edit(|trie| trie.remove_all(tuples))
+    }
+}
+
+impl crate::heap_size::HeapSize for BitmapTrie {
+    fn heap_size_bytes(&self) -> usize {
+        let layers_vec_bytes = self.layers.capacity() * std::mem::size_of::<BitmapLayer>();
+        let layer_contents_bytes: usize = self
+            .layers
+            .iter()
+            .map(|layer| {
+                let keys_bytes = match &layer.keys {
+                    | Keys::Sorted(data) => data.capacity() * std::mem::size_of::<usize>(),
+                    | Keys::Bitmap(bitmaps) => bitmaps.heap_size_bytes(),
+                };
+                layer.interval.capacity() * std::mem::size_of::<usize>() + keys_bytes
+            })
+            .sum();
+        layers_vec_bytes + layer_contents_bytes
+    }
+}
+
+impl crate::trie_stats::TrieStats for BitmapTrie {
+    fn level_sizes(&self) -> Vec<usize> { self.layers.iter().map(BitmapLayer::len).collect() }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{HeapSize, TrieStats},
+    };
+
+    /// 5000 sparse subject IDs with one to three of 40 classes each, keyed
+    /// by class then by subject, or by subject then by class.
+    fn typed(class_first: bool) -> Vec<Vec<usize>> {
+        (0..5000_usize)
+            .flat_map(|s| (0..1 + s % 3).map(move |c| (s * 100, (s * 7 + c * 13) % 40)))
+            .map(|(s, c)| {
+                if class_first {
+                    vec![c, s]
+                } else {
+                    vec![s, c]
+                }
+            })
+            .collect()
+    }
+
+    fn dense_layers(trie: &BitmapTrie) -> Vec<bool> {
+        trie.layers
+            .iter()
+            .map(|layer| matches!(layer.keys, Keys::Bitmap(_)))
+            .collect()
+    }
+
+    #[test]
+    fn stores_layers_as_bitmaps_only_when_smaller() {
+        let class_first = BitmapTrie::from_tuples(2.into(), typed(true));
+        assert_eq!(dense_layers(&class_first), vec![true, false]);
+        let subject_first = BitmapTrie::from_tuples(2.into(), typed(false));
+        assert_eq!(dense_layers(&subject_first), vec![false, true]);
+        assert_eq!(
+            dense_layers(&BitmapTrie::from_tuples(1.into(), vec![vec![0], vec![
+                1000
+            ]])),
+            vec![false]
+        );
+    }
+
+    #[test]
+    fn matches_the_column_trie() {
+        for class_first in [true, false] {
+            let column = ColumnTrie::from_tuples(2.into(), typed(class_first));
+            let bitmap = BitmapTrie::from(&column);
+            assert_eq!(bitmap.level_sizes(), column.level_sizes());
+            assert_eq!(
+                bitmap.trie_iter().into_iter().collect::<Vec<_>>(),
+                column.trie_iter().into_iter().collect::<Vec<_>>()
+            );
+            assert!(bitmap.heap_size_bytes() <= column.heap_size_bytes());
+        }
+    }
+
+    #[test]
+    fn dense_class_layer_is_smaller() {
+        let column = ColumnTrie::from_tuples(2.into(), typed(false));
+        let bitmap = BitmapTrie::from(&column);
+        assert!(
+            bitmap.heap_size_bytes() * 4 < column.heap_size_bytes() * 3,
+            "{} vs {}",
+            bitmap.heap_size_bytes(),
+            column.heap_size_bytes()
+        );
+    }
+}
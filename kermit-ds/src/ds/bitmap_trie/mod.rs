@@ -0,0 +1,7 @@
+//! Column-oriented trie whose dense layers are stored as bitmaps.
+
+mod bitmap;
+mod bitmap_trie_iter;
+mod implementation;
+
+pub use implementation::BitmapTrie;
@@ -0,0 +1,131 @@
+//! Storage for the `data` and `interval` arrays of a [`ColumnTrieLayer`].
+//!
+//! [`ColumnTrieLayer`]: super::implementation::ColumnTrieLayer
+
+use {
+    memmap2::Mmap,
+    std::{
+        fmt,
+        ops::{Deref, DerefMut},
+        sync::Arc,
+    },
+};
+
+/// An array of keys or offsets, either owned or borrowed straight from a
+/// memory-mapped `.ktrie` file.
+///
+/// Both kinds dereference to `[usize]`, so reading a trie never cares where
+/// its layers live. Mutable access copies a mapped column into an owned one
+/// first, so inserts and removals work on loaded tries too.
+#[derive(Clone)]
+pub struct Column(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    Owned(Vec<usize>),
+    /// `len` words starting at byte `offset` of `map`.
+    Mapped {
+        map: Arc<Mmap>,
+        offset: usize,
+        len: usize,
+    },
+}
+
+impl Column {
+    /// Wraps `len` words of `map` starting at byte `offset`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range falls outside `map` or `offset` is not aligned
+    /// for `usize`.
+    pub(crate) fn mapped(map: Arc<Mmap>, offset: usize, len: usize) -> Self {
+        let end = len
+            .checked_mul(std::mem::size_of::<usize>())
+            .and_then(|bytes| bytes.checked_add(offset));
+        assert!(end.is_some_and(|end| end <= map.len()));
+        assert_eq!(
+            (map.as_ptr() as usize + offset) % std::mem::align_of::<usize>(),
+            0,
+            "mapped column is misaligned"
+        );
+        Column(Repr::Mapped {
+            map,
+            offset,
+            len,
+        })
+    }
+
+    /// Returns whether the column borrows from a memory-mapped file.
+    pub fn is_mapped(&self) -> bool { matches!(self.0, Repr::Mapped { .. }) }
+
+    /// Returns the owned vector, first copying a mapped column out of its
+    /// file.
+    pub fn to_mut(&mut self) -> &mut Vec<usize> {
+        if let Repr::Mapped {
+            ..
+        } = self.0
+        {
+            self.0 = Repr::Owned(self.to_vec());
+        }
+        match &mut self.0 {
+            | Repr::Owned(vec) => vec,
+            | Repr::Mapped {
+                ..
+            } => unreachable!(),
+        }
+    }
+
+    /// Returns the bytes the column occupies: the allocated capacity of an
+    /// owned column, or the mapped length of a mapped one.
+    pub(crate) fn size_bytes(&self) -> usize {
+        let words = match &self.0 {
+            | Repr::Owned(vec) => vec.capacity(),
+            | Repr::Mapped {
+                len, ..
+            } => *len,
+        };
+        words * std::mem::size_of::<usize>()
+    }
+}
+
+impl Default for Column {
+    fn default() -> Self { Column(Repr::Owned(Vec::new())) }
+}
+
+impl From<Vec<usize>> for Column {
+    fn from(vec: Vec<usize>) -> Self { Column(Repr::Owned(vec)) }
+}
+
+impl Deref for Column {
+    type Target = [usize];
+
+    fn deref(&self) -> &[usize] {
+        match &self.0 {
+            | Repr::Owned(vec) => vec,
+            // SAFETY: `mapped` checked that the range lies inside the map
+            // and is aligned, and the `Arc` keeps the map alive for as long
+            // as the slice is borrowed from `self`.
+            | Repr::Mapped {
+                map,
+                offset,
+                len,
+            } => unsafe {
+                std::slice::from_raw_parts(map.as_ptr().add(*offset).cast::<usize>(), *len)
+            },
+        }
+    }
+}
+
+impl DerefMut for Column {
+    fn deref_mut(&mut self) -> &mut [usize] { self.to_mut() }
+}
+
+impl PartialEq for Column {
+    fn eq(&self, other: &Self) -> bool { **self == **other }
+}
+
+impl Eq for Column {}
+
+impl fmt::Debug for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { fmt::Debug::fmt(&**self, f) }
+}
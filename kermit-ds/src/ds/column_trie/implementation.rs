@@ -1,4 +1,5 @@
 use {
+    super::column::Column,
     crate::relation::{Relation, RelationHeader},
     kermit_iters::{JoinIterable, TrieIterable},
     std::fmt,
@@ -11,11 +12,14 @@ use {
 /// offset of its children within this layer's `data`. The children of parent
 /// element `i` span `data[interval[i]..interval[i+1]]` (or to the end of
 /// `data` for the last parent).
+///
+/// Both arrays are [`Column`]s, which are either owned or borrowed from a
+/// memory-mapped `.ktrie` file (see [`ColumnTrie::open_ktrie`]).
 pub struct ColumnTrieLayer {
     /// Sorted keys at this trie depth.
-    pub data: Vec<usize>,
+    pub data: Column,
     /// Maps each parent element to the start index of its children in `data`.
-    pub interval: Vec<usize>,
+    pub interval: Column,
 }
 
 impl ColumnTrieLayer {
@@ -34,19 +38,29 @@ impl ColumnTrieLayer {
     /// Inserts `key` at position `pos` in the data array and increments all
     /// interval entries after `interval_index` to account for the shift.
     fn insert_key_and_shift_intervals(&mut self, pos: usize, key: usize, interval_index: usize) {
-        self.data.insert(pos, key);
+        self.data.to_mut().insert(pos, key);
         for j in (interval_index + 1)..self.interval.len() {
             self.interval[j] += 1;
         }
     }
 
+    /// Removes the key at position `pos` from the data array and decrements
+    /// all interval entries after `interval_index` to account for the shift.
+    fn remove_key_and_shift_intervals(&mut self, pos: usize, interval_index: usize) {
+        self.data.to_mut().remove(pos);
+        for j in (interval_index + 1)..self.interval.len() {
+            self.interval[j] -= 1;
+        }
+    }
+
     /// Adds an interval entry for a new child at position `i` in the next
     /// layer.
     fn add_interval(&mut self, i: usize) {
         if i == self.interval.len() {
-            self.interval.push(self.data.len());
+            self.interval.to_mut().push(self.data.len());
         } else {
-            self.interval.insert(i, self.interval[i]);
+            let start = self.interval[i];
+            self.interval.to_mut().insert(i, start);
         }
     }
 }
@@ -74,7 +88,10 @@ impl ColumnTrieLayer {
 /// # When to prefer
 ///
 /// Prefer `ColumnTrie` for large, mostly-static relations where iteration
-/// speed and compact layout matter. Prefer
+/// speed and compact layout matter. Batch construction
+/// ([`from_tuples`](Relation::from_tuples),
+/// [`insert_all`](Relation::insert_all)) sorts once and bulk-loads every
+/// layer in a single pass, avoiding the per-insert shifting. Prefer
 /// [`TreeTrie`](crate::ds::TreeTrie) for small inputs or when you are
 /// inserting one tuple at a time.
 ///
@@ -102,6 +119,64 @@ impl ColumnTrie {
     /// to the relation's arity).
     pub fn layer(&self, layer_i: usize) -> &ColumnTrieLayer { &self.layers[layer_i] }
 
+    /// Assembles a trie from layers that already satisfy its invariants.
+    pub(super) fn from_layers(header: RelationHeader, layers: Vec<ColumnTrieLayer>) -> Self {
+        debug_assert_eq!(layers.len(), header.arity());
+        ColumnTrie {
+            header,
+            layers,
+        }
+    }
+
+    /// Appends the tuples of a lexicographically sorted stream to an empty
+    /// trie in a single pass. Each tuple shares a prefix with its
+    /// predecessor; only the keys after that prefix are new, so each is
+    /// pushed onto the end of its layer, opening a new child group in the
+    /// layer below. Duplicate tuples are skipped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stream is not sorted or a tuple's length does not match
+    /// the arity.
+    fn bulk_load(&mut self, tuples: impl IntoIterator<Item = Vec<usize>>) {
+        let arity = self.header().arity();
+        debug_assert!(self.layers.iter().all(|layer| layer.data.is_empty()));
+        let mut prev: Option<Vec<usize>> = None;
+        for tuple in tuples {
+            assert_eq!(
+                tuple.len(),
+                arity,
+                "Tuple length must match the arity of the trie."
+            );
+            let shared = match &prev {
+                | Some(prev) => {
+                    assert!(*prev <= tuple, "bulk-loaded tuples must be sorted");
+                    let shared = prev.iter().zip(&tuple).take_while(|(a, b)| a == b).count();
+                    if shared == arity {
+                        continue;
+                    }
+                    shared
+                },
+                | None => {
+                    if arity > 0 {
+                        self.layers[0].interval.to_mut().push(0);
+                    }
+                    0
+                },
+            };
+            for (layer_i, &k) in tuple.iter().enumerate().skip(shared) {
+                if layer_i > shared {
+                    // The key above is new, so this key starts its child
+                    // group.
+                    let start = self.layers[layer_i].data.len();
+                    self.layers[layer_i].interval.to_mut().push(start);
+                }
+                self.layers[layer_i].data.to_mut().push(k);
+            }
+            prev = Some(tuple);
+        }
+    }
+
     /// Walks down the layer hierarchy inserting one key per level. The
     /// `interval_index` tracks our position in each layer's interval array,
     /// identifying which parent group the new key belongs to.
@@ -113,8 +188,8 @@ impl ColumnTrie {
             let is_last_layer = layer_i == arity - 1;
 
             if self.layers[layer_i].data.is_empty() {
-                self.layers[layer_i].data.push(k);
-                self.layers[layer_i].interval.push(0);
+                self.layers[layer_i].data.to_mut().push(k);
+                self.layers[layer_i].interval.to_mut().push(0);
                 interval_index = 0;
                 continue;
             }
@@ -124,6 +199,8 @@ impl ColumnTrie {
             // Search for the key within the current interval's data range
             for i in range.clone() {
                 if self.layers[layer_i].data[i] == k {
+                    // Descend into the existing key's child group.
+                    interval_index = i;
                     continue 'layer_loop;
                 }
                 if k < self.layers[layer_i].data[i] {
@@ -132,7 +209,8 @@ impl ColumnTrie {
                     if is_last_layer {
                         return true;
                     }
-                    // Inserting at layer_i creates a new child group in layer_i+1
+                    // Inserting at layer_i creates a new child group in
+                    // layer_i+1
                     self.layers[layer_i + 1].add_interval(i);
                     interval_index = i;
                     continue 'layer_loop;
@@ -142,7 +220,7 @@ impl ColumnTrie {
             // Key is larger than all existing keys in the interval — append
             let insert_pos = range.end;
             if insert_pos == self.layers[layer_i].data.len() {
-                self.layers[layer_i].data.push(k);
+                self.layers[layer_i].data.to_mut().push(k);
             } else {
                 self.layers[layer_i].insert_key_and_shift_intervals(insert_pos, k, interval_index);
             }
@@ -155,6 +233,46 @@ impl ColumnTrie {
         }
         true
     }
+
+    /// Finds the tuple's key in every layer, then removes them bottom-up. A
+    /// key goes only once its child group is empty, taking that group's
+    /// interval entry in the layer below with it.
+    fn internal_remove(&mut self, tuple: &[usize]) -> bool {
+        if tuple.is_empty() || self.layers[0].data.is_empty() {
+            return false;
+        }
+
+        // positions[i] indexes layers[i].data.
+        let mut positions = Vec::with_capacity(tuple.len());
+        let mut interval_index = 0;
+        for (layer, &k) in self.layers.iter().zip(tuple) {
+            let range = layer.data_range(interval_index);
+            match layer.data[range.clone()].binary_search(&k) {
+                | Ok(offset) => interval_index = range.start + offset,
+                | Err(_) => return false,
+            }
+            positions.push(interval_index);
+        }
+
+        for layer_i in (0..tuple.len()).rev() {
+            let pos = positions[layer_i];
+            let parent = if layer_i == 0 {
+                0
+            } else {
+                positions[layer_i - 1]
+            };
+            self.layers[layer_i].remove_key_and_shift_intervals(pos, parent);
+            if let Some(below) = self.layers.get_mut(layer_i + 1) {
+                below.interval.to_mut().remove(pos);
+            }
+            if !self.layers[layer_i].data_range(parent).is_empty() {
+                return true;
+            }
+        }
+        // The root layer emptied out, so its single group goes too.
+        self.layers[0].interval.to_mut().clear();
+        true
+    }
 }
 
 impl fmt::Display for ColumnTrie {
@@ -186,7 +304,8 @@ impl JoinIterable for ColumnTrie {}
 
 impl crate::relation::Projectable for ColumnTrie {
     fn project(&self, columns: Vec<usize>) -> Self {
-        // Create a new header based on the current header but with projected attributes
+        // Create a new header based on the current header but with projected
+        // attributes
         let current_header = self.header();
         let projected_attrs: Vec<String> = columns
             .iter()
@@ -222,37 +341,43 @@ impl Relation for ColumnTrie {
         ColumnTrie {
             layers: (0..header.arity())
                 .map(|_| ColumnTrieLayer {
-                    data: vec![],
-                    interval: vec![],
+                    data: Column::default(),
+                    interval: Column::default(),
                 })
                 .collect::<Vec<_>>(),
             header,
         }
     }
 
+    /// Builds a `ColumnTrie` by sorting `tuples` once and bulk-loading every
+    /// layer in a single linear pass (see
+    /// [`from_sorted_tuples`](Self::from_sorted_tuples)).
+    ///
+    /// # Panics
+    ///
+    /// Panics if any tuple's length does not match `header.arity()`.
     fn from_tuples(header: RelationHeader, mut tuples: Vec<Vec<usize>>) -> Self {
-        if tuples.is_empty() {
-            Self::new(header)
-        } else {
-            tuples.sort_unstable_by(|a, b| {
-                for i in 0..a.len() {
-                    match a[i].cmp(&b[i]) {
-                        | std::cmp::Ordering::Less => return std::cmp::Ordering::Less,
-                        | std::cmp::Ordering::Greater => return std::cmp::Ordering::Greater,
-                        | std::cmp::Ordering::Equal => continue,
-                    }
-                }
-                std::cmp::Ordering::Equal
-            });
+        tuples.sort_unstable();
+        Self::from_sorted_tuples(header, tuples)
+    }
 
-            let mut trie = Self::new(header);
-            for tuple in tuples {
-                trie.insert(tuple);
-            }
-            trie
-        }
+    /// Bulk-loads already sorted `tuples`, appending each key to the end of
+    /// its layer instead of shifting later interval entries as
+    /// [`insert`](Self::insert) does.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tuples` is not sorted lexicographically, or if any tuple's
+    /// length does not match `header.arity()`.
+    fn from_sorted_tuples(header: RelationHeader, tuples: Vec<Vec<usize>>) -> Self {
+        let mut trie = Self::new(header);
+        trie.bulk_load(tuples);
+        trie
     }
 
+    /// Returns `trie` itself, keeping its layers as they are.
+    fn from_column_trie(trie: ColumnTrie) -> Self { trie }
+
     /// Inserts a single tuple.
     ///
     /// # Panics
@@ -268,14 +393,81 @@ impl Relation for ColumnTrie {
         self.internal_insert(&tuple)
     }
 
-    fn insert_all(&mut self, tuples: Vec<Vec<usize>>) -> bool {
-        for tuple in tuples {
-            if !self.insert(tuple) {
-                return false;
-            }
+    /// Inserts every tuple in `tuples` by sorting the batch, merging it with
+    /// the stored tuples and bulk-loading the result, rather than inserting
+    /// one tuple at a time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any tuple's length does not match the relation's arity.
+    fn insert_all(&mut self, mut tuples: Vec<Vec<usize>>) -> bool {
+        tuples.sort_unstable();
+        let existing: Vec<Vec<usize>> = self.trie_iter().into_iter().collect();
+        let mut merged = Vec::with_capacity(existing.len() + tuples.len());
+        let (mut a, mut b) = (
+            existing.into_iter().peekable(),
+            tuples.into_iter().peekable(),
+        );
+        while let (Some(x), Some(y)) = (a.peek(), b.peek()) {
+            merged.push(
+                if x <= y {
+                    a.next()
+                } else {
+                    b.next()
+                }
+                .unwrap(),
+            );
         }
+        merged.extend(a.chain(b));
+
+        let mut trie = Self::new(self.header.clone());
+        trie.bulk_load(merged);
+        *self = trie;
         true
     }
+
+    /// Removes a single tuple, collapsing the data and interval entries of
+    /// every prefix it leaves without children.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if `tuple.len()` does not match the relation's
+    /// arity.
+    fn remove(&mut self, tuple: &[usize]) -> bool {
+        debug_assert!(
+            tuple.len() == self.header().arity(),
+            "Tuple length must match the arity of the trie."
+        );
+        self.internal_remove(tuple)
+    }
+
+    /// Removes every tuple in `tuples` by sorting the batch, filtering it out
+    /// of the stored tuples and bulk-loading the rest, rather than removing
+    /// one tuple at a time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any tuple's length does not match the relation's arity.
+    fn remove_all(&mut self, mut tuples: Vec<Vec<usize>>) -> bool {
+        tuples.sort_unstable();
+        let mut doomed = tuples.iter().peekable();
+        let mut removed = false;
+        let kept: Vec<Vec<usize>> = self
+            .trie_iter()
+            .into_iter()
+            .filter(|tuple| {
+                while doomed.next_if(|&d| d < tuple).is_some() {}
+                let hit = doomed.peek() == Some(&tuple);
+                removed |= hit;
+                !hit
+            })
+            .collect();
+
+        let mut trie = Self::new(self.header.clone());
+        trie.bulk_load(kept);
+        *self = trie;
+        removed
+    }
 }
 
 impl crate::heap_size::HeapSize for ColumnTrie {
@@ -284,15 +476,16 @@ impl crate::heap_size::HeapSize for ColumnTrie {
         let layer_contents_bytes: usize = self
             .layers
             .iter()
-            .map(|layer| {
-                layer.data.capacity() * std::mem::size_of::<usize>()
-                    + layer.interval.capacity() * std::mem::size_of::<usize>()
-            })
+            .map(|layer| layer.data.size_bytes() + layer.interval.size_bytes())
             .sum();
         layers_vec_bytes + layer_contents_bytes
     }
 }
 
+impl crate::trie_stats::TrieStats for ColumnTrie {
+    fn level_sizes(&self) -> Vec<usize> { self.layers.iter().map(|l| l.data.len()).collect() }
+}
+
 #[cfg(test)]
 mod tests {
     use {
@@ -313,6 +506,34 @@ mod tests {
         println!("potato")
     }
 
+    #[test]
+    fn bulk_load_matches_incremental_inserts() {
+        let tuples = vec![
+            vec![1, 3, 4],
+            vec![1, 3, 5],
+            vec![1, 4, 6],
+            vec![2, 1, 1],
+            vec![2, 1, 1],
+            vec![5, 0, 2],
+            vec![5, 2, 2],
+        ];
+        let bulk = ColumnTrie::from_sorted_tuples(3.into(), tuples.clone());
+        let mut incremental = ColumnTrie::new(3.into());
+        for tuple in tuples {
+            incremental.insert(tuple);
+        }
+        for (b, i) in bulk.layers.iter().zip(&incremental.layers) {
+            assert_eq!(b.data, i.data);
+            assert_eq!(b.interval, i.interval);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "bulk-loaded tuples must be sorted")]
+    fn bulk_load_rejects_unsorted_input() {
+        ColumnTrie::from_sorted_tuples(2.into(), vec![vec![2, 1], vec![1, 1]]);
+    }
+
     #[test]
     fn test_project() {
         let mut trie = ColumnTrie::new(3.into());
@@ -371,7 +592,8 @@ mod heap_size_tests {
     #[test]
     fn empty_column_trie_heap_size() {
         let trie = ColumnTrie::new(2.into());
-        // Layers Vec is allocated with arity capacity, but data/interval Vecs are empty
+        // Layers Vec is allocated with arity capacity, but data/interval Vecs
+        // are empty
         let expected = trie.layers.capacity() * std::mem::size_of::<ColumnTrieLayer>();
         assert_eq!(trie.heap_size_bytes(), expected);
     }
@@ -0,0 +1,458 @@
+//! The `.ktrie` file format: a [`ColumnTrie`]'s layers saved as-is, so it
+//! can be memory-mapped back instead of rebuilt.
+//!
+//! Every field is a little-endian 64-bit word, and strings are padded to a
+//! whole number of words, so every array in the file is aligned for `usize`:
+//!
+//! | Words | Contents |
+//! |---|---|
+//! | 1 | magic, the bytes `KERMTRIE` |
+//! | 1 | format version ([`VERSION`]) |
+//! | 1 | CRC-32 of every byte after this word |
+//! | 1 | arity |
+//! | 1 + ⌈n/8⌉ | relation name: byte length `n`, then UTF-8 bytes |
+//! | 1 + … | attribute count, then each attribute as a string |
+//! | 2 per layer | `data` length and `interval` length of each layer |
+//! | … | each layer's `data`, then its `interval` |
+//!
+//! On 64-bit little-endian targets the arrays are used in place; elsewhere
+//! they are decoded into owned vectors.
+
+use {
+    super::{column::Column, implementation::ColumnTrieLayer, ColumnTrie},
+    crate::relation::{Relation, RelationError, RelationHeader},
+    memmap2::Mmap,
+    std::{
+        fs::{self, File},
+        io::{self, BufWriter, Seek, SeekFrom, Write},
+        path::{Path, PathBuf},
+        sync::Arc,
+    },
+};
+
+const MAGIC: [u8; 8] = *b"KERMTRIE";
+
+/// The format version written by [`ColumnTrie::write_ktrie`]; files with any
+/// other version are rejected.
+const VERSION: u64 = 1;
+
+const WORD: usize = std::mem::size_of::<u64>();
+
+/// Byte offset of the checksum word.
+const CHECKSUM_OFFSET: usize = 2 * WORD;
+
+/// Byte offset of the first checksummed byte.
+const BODY_OFFSET: usize = 3 * WORD;
+
+/// Whether a `usize` array has the same bytes as its encoding, so mapped
+/// words can be borrowed and arrays written without conversion.
+const ZERO_COPY: bool = cfg!(all(target_endian = "little", target_pointer_width = "64"));
+
+impl ColumnTrie {
+    /// Writes the trie to `path` in the `.ktrie` format, to be loaded again
+    /// with [`open_ktrie`](Self::open_ktrie).
+    ///
+    /// The file is written next to `path` under a `.part` suffix and renamed
+    /// into place, so a reader never maps a half-written file and a file
+    /// already mapped by another trie is replaced rather than changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RelationError::Io`] if the file cannot be written.
+    pub fn write_ktrie<P: AsRef<Path>>(&self, path: P) -> Result<(), RelationError> {
+        let path = path.as_ref();
+        let mut part = path.as_os_str().to_owned();
+        part.push(".part");
+        let part = PathBuf::from(part);
+
+        let mut file = BufWriter::new(File::create(&part)?);
+        file.write_all(&MAGIC)?;
+        write_word(&mut file, VERSION)?;
+        // Patched below once the body has been hashed.
+        write_word(&mut file, 0)?;
+
+        let mut body = ChecksumWriter {
+            inner: file,
+            hasher: crc32fast::Hasher::new(),
+        };
+        let header = self.header();
+        write_word(&mut body, header.arity() as u64)?;
+        write_str(&mut body, header.name())?;
+        write_word(&mut body, header.attrs().len() as u64)?;
+        for attr in header.attrs() {
+            write_str(&mut body, attr)?;
+        }
+        for layer in &self.layers {
+            write_word(&mut body, layer.data.len() as u64)?;
+            write_word(&mut body, layer.interval.len() as u64)?;
+        }
+        for layer in &self.layers {
+            write_words(&mut body, &layer.data)?;
+            write_words(&mut body, &layer.interval)?;
+        }
+
+        let checksum = body.hasher.finalize();
+        let mut file = body
+            .inner
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)?;
+        file.seek(SeekFrom::Start(CHECKSUM_OFFSET as u64))?;
+        write_word(&mut file, u64::from(checksum))?;
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&part, path)?;
+        Ok(())
+    }
+
+    /// Loads a trie written by [`write_ktrie`](Self::write_ktrie) by
+    /// memory-mapping the file.
+    ///
+    /// The layers borrow the mapping instead of being copied, so loading
+    /// costs one checksum pass over the file rather than a rebuild; a layer
+    /// is copied into memory only when the trie is first modified. The
+    /// relation keeps the name and attributes it was written with.
+    ///
+    /// The file must not be modified while the trie (or any trie sharing its
+    /// layers) is alive. [`write_ktrie`](Self::write_ktrie) replaces files
+    /// rather than changing them, so rewriting an index that is in use is
+    /// safe.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`RelationError`] if any of the following occur:
+    /// - [`RelationError::Io`] — the file cannot be opened or mapped.
+    /// - [`RelationError::InvalidData`] — the file is not a `.ktrie` file, has
+    ///   an unsupported version, fails its checksum or describes an
+    ///   inconsistent trie, such as one whose sibling keys are not strictly
+    ///   increasing.
+    pub fn open_ktrie<P: AsRef<Path>>(path: P) -> Result<Self, RelationError> {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is only read, and callers are told not to
+        // modify the file while it is mapped.
+        let map = Arc::new(unsafe { Mmap::map(&file)? });
+
+        if map.len() < BODY_OFFSET || map[..WORD] != MAGIC {
+            return Err(invalid("not a .ktrie file"));
+        }
+        let mut reader = Reader {
+            map: &map,
+            pos: WORD,
+        };
+        let version = reader.word()?;
+        if version != VERSION {
+            return Err(invalid(format!(
+                "unsupported .ktrie version {version} (expected {VERSION})"
+            )));
+        }
+        let checksum = reader.word()?;
+        if u64::from(crc32fast::hash(&map[BODY_OFFSET..])) != checksum {
+            return Err(invalid(".ktrie checksum mismatch"));
+        }
+
+        let arity = reader.len()?;
+        let name = reader.string()?;
+        let attr_count = reader.len()?;
+        let attrs = (0..attr_count)
+            .map(|_| reader.string())
+            .collect::<Result<Vec<_>, _>>()?;
+        let header = match attrs.len() {
+            | 0 => RelationHeader::new_positional(name, arity),
+            | n if n == arity => RelationHeader::new(name, attrs),
+            | n => {
+                return Err(invalid(format!(
+                    "{n} attributes given for a relation of arity {arity}"
+                )))
+            },
+        };
+
+        let lengths = (0..arity)
+            .map(|_| Ok((reader.len()?, reader.len()?)))
+            .collect::<Result<Vec<_>, RelationError>>()?;
+        let mut layers: Vec<ColumnTrieLayer> = Vec::with_capacity(arity);
+        for (i, &(data_len, interval_len)) in lengths.iter().enumerate() {
+            let groups = match i {
+                | 0 => usize::from(data_len > 0),
+                | _ => layers[i - 1].data.len(),
+            };
+            if interval_len != groups {
+                return Err(invalid(format!(
+                    "layer {i} has {interval_len} intervals for {groups} parent keys"
+                )));
+            }
+            let layer = ColumnTrieLayer {
+                data: reader.column(&map, data_len)?,
+                interval: reader.column(&map, interval_len)?,
+            };
+            if layer.interval.first().is_some_and(|&start| start != 0) {
+                return Err(invalid(format!(
+                    "layer {i}'s first interval does not start at 0"
+                )));
+            }
+            if !layer.interval.is_sorted() {
+                return Err(invalid(format!("layer {i} has unsorted intervals")));
+            }
+            if layer.interval.iter().any(|&start| start > data_len) {
+                return Err(invalid(format!("layer {i} has an interval past its data")));
+            }
+            let mut starts = layer.interval.iter().skip(1).peekable();
+            for k in 1..data_len {
+                let group_start = starts.next_if_eq(&&k).is_some();
+                if !group_start && layer.data[k - 1] >= layer.data[k] {
+                    return Err(invalid(format!(
+                        "layer {i} has keys out of order in a sibling group"
+                    )));
+                }
+            }
+            layers.push(layer);
+        }
+        if reader.pos != map.len() {
+            return Err(invalid("trailing bytes after the last layer"));
+        }
+
+        Ok(ColumnTrie::from_layers(header, layers))
+    }
+}
+
+fn invalid(msg: impl Into<String>) -> RelationError { RelationError::InvalidData(msg.into()) }
+
+fn write_word(w: &mut impl Write, word: u64) -> io::Result<()> { w.write_all(&word.to_le_bytes()) }
+
+fn write_str(w: &mut impl Write, s: &str) -> io::Result<()> {
+    write_word(w, s.len() as u64)?;
+    w.write_all(s.as_bytes())?;
+    w.write_all(&[0; WORD][..padding(s.len())])
+}
+
+fn write_words(w: &mut impl Write, words: &[usize]) -> io::Result<()> {
+    if ZERO_COPY {
+        // SAFETY: any initialised `usize` slice can be viewed as bytes.
+        let bytes = unsafe {
+            std::slice::from_raw_parts(words.as_ptr().cast::<u8>(), std::mem::size_of_val(words))
+        };
+        return w.write_all(bytes);
+    }
+    words
+        .iter()
+        .try_for_each(|&word| write_word(w, word as u64))
+}
+
+/// Returns the number of zero bytes that pad `len` bytes to a whole word.
+fn padding(len: usize) -> usize { (WORD - len % WORD) % WORD }
+
+/// Forwards writes to `inner`, hashing every byte on the way.
+struct ChecksumWriter<W> {
+    inner: W,
+    hasher: crc32fast::Hasher,
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> { self.inner.flush() }
+}
+
+/// A cursor over the words of a mapped `.ktrie` file.
+struct Reader<'a> {
+    map: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, bytes: usize) -> Result<&[u8], RelationError> {
+        let end = self
+            .pos
+            .checked_add(bytes)
+            .filter(|&end| end <= self.map.len())
+            .ok_or_else(|| invalid("truncated .ktrie file"))?;
+        let taken = &self.map[self.pos..end];
+        self.pos = end;
+        Ok(taken)
+    }
+
+    fn word(&mut self) -> Result<u64, RelationError> {
+        let bytes = self.take(WORD)?;
+        Ok(u64::from_le_bytes(bytes.try_into().expect("took one word")))
+    }
+
+    fn len(&mut self) -> Result<usize, RelationError> {
+        let word = self.word()?;
+        usize::try_from(word).map_err(|_| invalid(format!("length {word} does not fit in usize")))
+    }
+
+    fn string(&mut self) -> Result<String, RelationError> {
+        let len = self.len()?;
+        let bytes = self.take(len)?.to_vec();
+        self.take(padding(len))?;
+        String::from_utf8(bytes).map_err(|e| invalid(e.to_string()))
+    }
+
+    /// Takes `len` words as a column, borrowing them from `map` when the
+    /// encoding allows it.
+    fn column(&mut self, map: &Arc<Mmap>, len: usize) -> Result<Column, RelationError> {
+        let bytes = len
+            .checked_mul(WORD)
+            .ok_or_else(|| invalid("truncated .ktrie file"))?;
+        let start = self.pos;
+        let words = self.take(bytes)?;
+        if ZERO_COPY {
+            return Ok(Column::mapped(Arc::clone(map), start, len));
+        }
+        words
+            .as_chunks::<WORD>()
+            .0
+            .iter()
+            .map(|&word| {
+                let word = u64::from_le_bytes(word);
+                usize::try_from(word)
+                    .map_err(|_| invalid(format!("key {word} does not fit in usize")))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Column::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::relation::RelationFileExt, kermit_iters::TrieIterable};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("kermit_ktrie_{}_{name}.ktrie", std::process::id()))
+    }
+
+    fn tuples(trie: &ColumnTrie) -> Vec<Vec<usize>> { trie.trie_iter().into_iter().collect() }
+
+    #[test]
+    fn round_trips_layers_and_header() {
+        let header = RelationHeader::new("edge", vec!["src".into(), "dst".into(), "w".into()]);
+        let trie = ColumnTrie::from_tuples(header, vec![
+            vec![1, 3, 4],
+            vec![1, 3, 5],
+            vec![2, 1, 1],
+            vec![5, 0, usize::MAX],
+        ]);
+        let path = temp_path("round_trip");
+        trie.write_ktrie(&path).unwrap();
+        let loaded = ColumnTrie::open_ktrie(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.header().name(), "edge");
+        assert_eq!(loaded.header().attrs(), trie.header().attrs());
+        for (a, b) in loaded.layers.iter().zip(&trie.layers) {
+            assert_eq!(a.data, b.data);
+            assert_eq!(a.interval, b.interval);
+            assert_eq!(a.data.is_mapped(), ZERO_COPY);
+        }
+        assert_eq!(tuples(&loaded), tuples(&trie));
+    }
+
+    #[test]
+    fn round_trips_empty_and_positional_tries() {
+        for trie in [
+            ColumnTrie::new(RelationHeader::new_positional("empty", 2)),
+            ColumnTrie::from_tuples(RelationHeader::new_positional("r", 1), vec![vec![7]]),
+            ColumnTrie::new(0.into()),
+        ] {
+            let path = temp_path(&format!("small_{}", trie.header().arity()));
+            trie.write_ktrie(&path).unwrap();
+            let loaded = ColumnTrie::open_ktrie(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(loaded.header().name(), trie.header().name());
+            assert_eq!(loaded.header().arity(), trie.header().arity());
+            assert!(loaded.header().attrs().is_empty());
+            assert_eq!(tuples(&loaded), tuples(&trie));
+        }
+    }
+
+    #[test]
+    fn loaded_tries_copy_on_write() {
+        let trie = ColumnTrie::from_tuples(2.into(), vec![vec![1, 2], vec![3, 4]]);
+        let path = temp_path("copy_on_write");
+        trie.write_ktrie(&path).unwrap();
+        let mut loaded = ColumnTrie::open_ktrie(&path).unwrap();
+        assert!(loaded.insert(vec![2, 9]));
+        assert!(loaded.remove(&[1, 2]));
+        assert!(!loaded.layers[0].data.is_mapped());
+        assert_eq!(tuples(&loaded), vec![vec![2, 9], vec![3, 4]]);
+
+        // The file itself is untouched.
+        let reloaded = ColumnTrie::open_ktrie(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(tuples(&reloaded), tuples(&trie));
+    }
+
+    #[test]
+    fn rejects_corrupt_files() {
+        let trie = ColumnTrie::from_tuples(2.into(), vec![vec![1, 2], vec![3, 4]]);
+        let path = temp_path("corrupt");
+        trie.write_ktrie(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+
+        let mut flipped = bytes.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        let mut versioned = bytes.clone();
+        versioned[WORD] = 2;
+        for (contents, expected) in [
+            (flipped, "checksum"),
+            (versioned, "version 2"),
+            (bytes[..bytes.len() - WORD].to_vec(), "checksum"),
+            (b"PAR1".to_vec(), "not a .ktrie file"),
+        ] {
+            std::fs::write(&path, contents).unwrap();
+            let err = ColumnTrie::open_ktrie(&path).err().unwrap();
+            assert!(err.to_string().contains(expected), "{err}");
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_misplaced_intervals() {
+        let path = temp_path("intervals");
+        let tuples = vec![vec![1, 2], vec![1, 3], vec![4, 5], vec![6, 7]];
+        for (interval, expected) in [
+            (vec![1, 2, 3], "does not start at 0"),
+            (vec![0, 2, 1], "unsorted intervals"),
+        ] {
+            let mut trie = ColumnTrie::from_tuples(2.into(), tuples.clone());
+            trie.layers[1].interval = interval.into();
+            trie.write_ktrie(&path).unwrap();
+            let err = ColumnTrie::open_ktrie(&path).err().unwrap();
+            assert!(err.to_string().contains(expected), "{err}");
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_unsorted_sibling_groups() {
+        let path = temp_path("unsorted");
+        let tuples = vec![vec![1, 2], vec![1, 3], vec![4, 5], vec![4, 6]];
+        for data in [vec![2, 3, 6, 5], vec![2, 2, 5, 6]] {
+            let mut trie = ColumnTrie::from_tuples(2.into(), tuples.clone());
+            trie.layers[1].data = data.into();
+            trie.write_ktrie(&path).unwrap();
+            let err = ColumnTrie::open_ktrie(&path).err().unwrap();
+            assert!(err.to_string().contains("out of order"), "{err}");
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn other_relations_load_ktrie_files_by_rebuilding() {
+        use crate::ds::TreeTrie;
+
+        let tuples = vec![vec![1, 2], vec![1, 3], vec![4, 5]];
+        let path = temp_path("tree_trie");
+        ColumnTrie::from_tuples(RelationHeader::new_positional("r", 2), tuples.clone())
+            .write_ktrie(&path)
+            .unwrap();
+        let tree = TreeTrie::from_ktrie(&path).unwrap();
+        let column = ColumnTrie::from_ktrie(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(tree.header().name(), "r");
+        assert_eq!(tree.trie_iter().into_iter().collect::<Vec<_>>(), tuples);
+        assert!(column.layers[0].data.is_mapped() || !ZERO_COPY);
+    }
+}
@@ -1,6 +1,8 @@
 //! Column-oriented (flattened) trie implementation.
 
+mod column;
 mod column_trie_iter;
 mod implementation;
+mod ktrie;
 
 pub use implementation::ColumnTrie;
@@ -0,0 +1,100 @@
+use {
+    super::implementation::CompressedColumnTrie,
+    crate::relation::Relation,
+    kermit_derive::IntoTrieIter,
+    kermit_iters::{LinearIterator, TrieIterable, TrieIterator, TrieIteratorWrapper},
+};
+
+/// The sibling group being scanned at one trie depth.
+#[derive(Clone, Copy)]
+struct Frame {
+    /// Index of the current key in the layer's `data`.
+    pos: usize,
+    /// One past the group's last index.
+    end: usize,
+    /// The key at `pos`, or `None` once the group is exhausted.
+    key: Option<usize>,
+}
+
+/// Iterator over a [`CompressedColumnTrie`].
+///
+/// A [`Frame`] per open depth records the sibling group being scanned and
+/// the key at the current position, so `key` is free and `next` decodes a
+/// single gap. `seek` runs a successor search within the group. `up` pops the
+/// frame, so unlike [`ColumnTrieIter`](crate::ds::ColumnTrie) it never
+/// rescans an interval array.
+#[derive(IntoTrieIter)]
+pub struct CompressedColumnTrieIter<'a> {
+    /// One frame per open depth; empty at the root.
+    frames: Vec<Frame>,
+    /// The trie being iterated.
+    trie: &'a CompressedColumnTrie,
+}
+
+impl<'a> CompressedColumnTrieIter<'a> {
+    /// Creates a new iterator positioned at the root. Call
+    /// [`open`](TrieIterator::open) to descend to the first layer.
+    pub fn new(trie: &'a CompressedColumnTrie) -> Self {
+        CompressedColumnTrieIter {
+            frames: Vec::with_capacity(trie.header().arity()),
+            trie,
+        }
+    }
+}
+
+impl LinearIterator for CompressedColumnTrieIter<'_> {
+    fn key(&self) -> Option<usize> { self.frames.last()?.key }
+
+    fn next(&mut self) -> Option<usize> {
+        let data = &self.trie.layer(self.frames.len().checked_sub(1)?).data;
+        let frame = self.frames.last_mut()?;
+        let key = frame.key?;
+        frame.pos += 1;
+        frame.key = (frame.pos < frame.end).then(|| data.next_after(frame.pos - 1, key));
+        frame.key
+    }
+
+    fn seek(&mut self, seek_key: usize) -> bool {
+        if self.at_end() {
+            return false;
+        }
+        let data = &self.trie.layer(self.frames.len() - 1).data;
+        let frame = self.frames.last_mut().unwrap();
+        let key = frame.key.unwrap();
+        (frame.pos, frame.key) = data.successor(frame.pos, key, frame.end, seek_key);
+        !self.at_end()
+    }
+
+    fn at_end(&self) -> bool { self.frames.last().is_none_or(|frame| frame.key.is_none()) }
+}
+
+impl TrieIterator for CompressedColumnTrieIter<'_> {
+    fn open(&mut self) -> bool {
+        let depth = self.frames.len();
+        if depth == self.trie.header().arity() {
+            return false;
+        }
+        let layer = self.trie.layer(depth);
+        let range = match self.frames.last() {
+            | None if layer.data.is_empty() => return false,
+            | None => 0..layer.data.len(),
+            | Some(frame) if frame.key.is_none() => return false,
+            | Some(frame) => layer.data_range(frame.pos),
+        };
+        self.frames.push(Frame {
+            pos: range.start,
+            end: range.end,
+            key: Some(layer.data.get(range.start, range.start)),
+        });
+        true
+    }
+
+    fn up(&mut self) -> bool { self.frames.pop().is_some() }
+}
+
+/// Implementation of the `TrieIterable` trait for `CompressedColumnTrie`.
+impl TrieIterable for CompressedColumnTrie {
+    fn trie_iter(&self) -> impl TrieIterator + IntoIterator<Item = Vec<usize>> {
+        CompressedColumnTrieIter::new(self)
+    }
+}
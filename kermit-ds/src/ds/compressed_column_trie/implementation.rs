@@ -0,0 +1,311 @@
+use {
+    super::packed::PackedSequence,
+    crate::{
+        ds::ColumnTrie,
+        relation::{Relation, RelationHeader},
+    },
+    kermit_iters::{JoinIterable, TrieIterable},
+};
+
+/// A single level of a [`CompressedColumnTrie`]: a [`ColumnTrie`] layer with
+/// both arrays packed.
+///
+/// `interval` is non-decreasing and is packed as a single run. `data` is
+/// sorted within each sibling group, so every group is packed as a run of
+/// its own, starting at the index `interval` gives for it.
+pub(crate) struct CompressedLayer {
+    /// Keys at this depth, one run per sibling group.
+    pub(crate) data: PackedSequence,
+    /// Maps each parent element to the start index of its children in `data`.
+    pub(crate) interval: PackedSequence,
+}
+
+impl CompressedLayer {
+    /// Returns the data index range `start..end` for the children of the
+    /// element at `interval_index`.
+    pub(crate) fn data_range(&self, interval_index: usize) -> std::ops::Range<usize> {
+        let start = self.interval.get(0, interval_index);
+        let end = if interval_index + 1 < self.interval.len() {
+            self.interval.next_after(interval_index, start)
+        } else {
+            self.data.len()
+        };
+        start..end
+    }
+}
+
+/// A [`ColumnTrie`] whose layers are delta-encoded and bit-packed.
+///
+/// Each layer keeps the `data` and `interval` arrays of the column trie, but
+/// stores them as blocks of 64 values packed at the width of the block's
+/// largest slot. The first key of the block and of every sibling group in it
+/// is stored as is, relative to the smallest such key; the rest as gaps
+/// within their group. Sibling keys are sorted, so those gaps are small; a
+/// dictionary-encoded layer whose IDs fit in 20 bits costs at most about 20
+/// bits per key instead of 64.
+///
+/// Reading a key decodes at most one block, and seeking binary-searches the
+/// first keys of the group's blocks before scanning one block, so
+/// [`seek`](kermit_iters::LinearIterator::seek) stays logarithmic. Walking
+/// forward with `next` decodes one gap per key.
+///
+/// # Invariants
+///
+/// The same as [`ColumnTrie`]'s, on the unpacked arrays.
+///
+/// # When to prefer
+///
+/// Prefer `CompressedColumnTrie` for large, static relations where memory is
+/// the constraint; every access pays for unpacking, so it is slower than
+/// [`ColumnTrie`]. It is built in one pass from a sorted batch, and the
+/// packed layers cannot be edited in place: every
+/// [`insert`](Relation::insert) or [`remove`](Relation::remove) unpacks the
+/// relation, applies the change and packs it again.
+///
+/// # Example
+///
+/// ```
+/// use kermit_ds::{CompressedColumnTrie, Relation, TrieStats};
+///
+/// let trie =
+///     CompressedColumnTrie::from_tuples(2.into(), vec![vec![1, 2], vec![1, 3], vec![2, 4]]);
+/// assert_eq!(trie.level_sizes(), vec![2, 3]);
+/// ```
+pub struct CompressedColumnTrie {
+    header: RelationHeader,
+    layers: Vec<CompressedLayer>,
+}
+
+impl CompressedColumnTrie {
+    /// Returns a reference to the layer at the given depth.
+    pub(crate) fn layer(&self, layer_i: usize) -> &CompressedLayer { &self.layers[layer_i] }
+
+    /// Unpacks the relation into a [`ColumnTrie`].
+    pub fn to_column_trie(&self) -> ColumnTrie {
+        ColumnTrie::from_sorted_tuples(self.header.clone(), self.trie_iter().into_iter().collect())
+    }
+
+    /// Unpacks the relation, applies `edit` and packs the result again if
+    /// `edit` reports a change.
+    fn edit(&mut self, edit: impl FnOnce(&mut ColumnTrie) -> bool) -> bool {
+        let mut trie = self.to_column_trie();
+        let changed = edit(&mut trie);
+        if changed {
+            *self = Self::from(&trie);
+        }
+        changed
+    }
+}
+
+impl From<&ColumnTrie> for CompressedColumnTrie {
+    /// Packs the layers of `trie`.
+    fn from(trie: &ColumnTrie) -> Self {
+        let layers = trie
+            .layers
+            .iter()
+            .map(|layer| CompressedLayer {
+                data: PackedSequence::new(&layer.data, &layer.interval),
+                interval: PackedSequence::new(&layer.interval, &[]),
+            })
+            .collect();
+        CompressedColumnTrie {
+            header: trie.header().clone(),
+            layers,
+        }
+    }
+}
+
+impl JoinIterable for CompressedColumnTrie {}
+
+impl crate::relation::Projectable for CompressedColumnTrie {
+    fn project(&self, columns: Vec<usize>) -> Self {
+        let current_header = self.header();
+        let projected_attrs: Vec<String> = columns
+            .iter()
+            .filter_map(|&col_idx| current_header.attrs().get(col_idx).cloned())
+            .collect();
+
+        let new_header = if projected_attrs.is_empty() {
+            crate::relation::RelationHeader::new_nameless_positional(columns.len())
+        } else {
+            crate::relation::RelationHeader::new_nameless(projected_attrs)
+        };
+
+        let projected_tuples: Vec<Vec<usize>> = self
+            .trie_iter()
+            .into_iter()
+            .map(|tuple| columns.iter().map(|&col_idx| tuple[col_idx]).collect())
+            .collect();
+
+        Self::from_tuples(new_header, projected_tuples)
+    }
+}
+
+impl Relation for CompressedColumnTrie {
+    fn header(&self) -> &RelationHeader { &self.header }
+
+    fn new(header: RelationHeader) -> Self {
+        CompressedColumnTrie {
+            layers: (0..header.arity())
+                .map(|_| CompressedLayer {
+                    data: PackedSequence::default(),
+                    interval: PackedSequence::default(),
+                })
+                .collect(),
+            header,
+        }
+    }
+
+    /// Builds a `CompressedColumnTrie` by bulk-loading a [`ColumnTrie`] and
+    /// packing its layers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any tuple's length does not match `header.arity()`.
+    fn from_tuples(header: RelationHeader, tuples: Vec<Vec<usize>>) -> Self {
+        Self::from(&ColumnTrie::from_tuples(header, tuples))
+    }
+
+    /// Bulk-loads already sorted `tuples` into a [`ColumnTrie`] and packs its
+    /// layers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tuples` is not sorted lexicographically, or if any tuple's
+    /// length does not match `header.arity()`.
+    fn from_sorted_tuples(header: RelationHeader, tuples: Vec<Vec<usize>>) -> Self {
+        Self::from(&ColumnTrie::from_sorted_tuples(header, tuples))
+    }
+
+    /// Packs the layers of `trie` directly, without walking its tuples.
+    fn from_column_trie(trie: ColumnTrie) -> Self { Self::from(&trie) }
+
+    /// Inserts a single tuple by unpacking and repacking the whole relation.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if `tuple.len()` does not match the relation's
+    /// arity.
+    fn insert(&mut self, tuple: Vec<usize>) -> bool { self.edit(|trie| trie.insert(tuple)) }
+
+    /// Inserts every tuple in `tuples` with a single unpack and repack.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any tuple's length does not match the relation's arity.
+    fn insert_all(&mut self, tuples: Vec<Vec<usize>>) -> bool {
+        self.edit(|trie| trie.insert_all(tuples))
+    }
+
+    /// Removes a single tuple by unpacking and repacking the whole relation.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if `tuple.len()` does not match the relation's
+    /// arity.
+    fn remove(&mut self, tuple: &[usize]) -> bool { self.edit(|trie| trie.remove(tuple)) }
+
+    /// Removes every tuple in `tuples` with a single unpack and repack.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any tuple's length does not match the relation's arity.
+    fn remove_all(&mut self, tuples: Vec<Vec<usize>>) -> bool {
+        self.edit(|trie| trie.remove_all(tuples))
+    }
+}
+
+impl crate::heap_size::HeapSize for CompressedColumnTrie {
+    fn heap_size_bytes(&self) -> usize {
+        let layers_vec_bytes = self.layers.capacity() * std::mem::size_of::<CompressedLayer>();
+        let layer_contents_bytes: usize = self
+            .layers
+            .iter()
+            .map(|layer| layer.data.heap_size_bytes() + layer.interval.heap_size_bytes())
+            .sum();
+        layers_vec_bytes + layer_contents_bytes
+    }
+}
+
+impl crate::trie_stats::TrieStats for CompressedColumnTrie {
+    fn level_sizes(&self) -> Vec<usize> { self.layers.iter().map(|l| l.data.len()).collect() }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{HeapSize, TrieStats},
+    };
+
+    /// Tuples whose layers have many sibling groups and keys below 2^20.
+    fn tuples() -> Vec<Vec<usize>> {
+        (0..5000_usize)
+            .map(|i| vec![i % 300, (i * 7919) % (1 << 20), i % 3])
+            .collect()
+    }
+
+    #[test]
+    fn packs_the_layers_of_a_column_trie() {
+        let column = ColumnTrie::from_tuples(3.into(), tuples());
+        let packed = CompressedColumnTrie::from(&column);
+        assert_eq!(packed.level_sizes(), column.level_sizes());
+        for (layer, unpacked) in packed.layers.iter().zip(&column.layers) {
+            assert_eq!(
+                layer.interval.iter().collect::<Vec<_>>(),
+                *unpacked.interval
+            );
+            for g in 0..layer.interval.len() {
+                let range = layer.data_range(g);
+                let keys: Vec<usize> = range
+                    .clone()
+                    .map(|i| layer.data.get(range.start, i))
+                    .collect();
+                assert_eq!(keys, unpacked.data[range]);
+            }
+        }
+        assert_eq!(
+            packed.trie_iter().into_iter().collect::<Vec<_>>(),
+            column.trie_iter().into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn packs_keys_up_to_usize_max() {
+        let tuples: Vec<Vec<usize>> = (0..20)
+            .flat_map(|i| [vec![i, usize::MAX - i], vec![i, 1 << 60], vec![i + 100, 1]])
+            .chain([vec![usize::MAX, usize::MAX]])
+            .collect();
+        let column = ColumnTrie::from_tuples(2.into(), tuples);
+        let packed = CompressedColumnTrie::from(&column);
+        assert_eq!(
+            packed.trie_iter().into_iter().collect::<Vec<_>>(),
+            column.trie_iter().into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn is_smaller_than_the_column_trie() {
+        let column = ColumnTrie::from_tuples(3.into(), tuples());
+        let packed = CompressedColumnTrie::from(&column);
+        assert!(
+            packed.heap_size_bytes() * 2 < column.heap_size_bytes(),
+            "{} vs {}",
+            packed.heap_size_bytes(),
+            column.heap_size_bytes()
+        );
+    }
+
+    #[test]
+    fn edits_repack_the_relation() {
+        let mut trie = CompressedColumnTrie::from_tuples(2.into(), vec![vec![1, 2], vec![3, 4]]);
+        assert!(trie.insert(vec![2, 5]));
+        assert!(trie.remove(&[1, 2]));
+        assert!(!trie.remove(&[1, 2]));
+        assert_eq!(trie.to_column_trie().level_sizes(), vec![2, 2]);
+        assert_eq!(trie.trie_iter().into_iter().collect::<Vec<_>>(), vec![
+            vec![2, 5],
+            vec![3, 4]
+        ]);
+    }
+}
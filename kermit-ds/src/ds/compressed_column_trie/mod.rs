@@ -0,0 +1,7 @@
+//! Column-oriented trie with delta-encoded, bit-packed layers.
+
+mod compressed_column_trie_iter;
+mod implementation;
+mod packed;
+
+pub use implementation::CompressedColumnTrie;
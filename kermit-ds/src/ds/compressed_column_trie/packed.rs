@@ -0,0 +1,309 @@
+//! Block-wise delta and bit-packing of integer sequences made of ascending
+//! runs.
+
+/// Values per block. Each block is a frame of reference for the values that
+/// start a run and packs the rest as gaps, so reaching any value decodes
+/// fewer than `BLOCK` slots.
+const BLOCK: usize = 64;
+
+/// A sequence of `usize`s made of ascending runs, stored as blocks of slots
+/// packed at the width of each block's largest slot.
+///
+/// The value starting a block or a run is stored as its difference from the
+/// smallest such value in the block; every other value as its gap from the
+/// one before. No value is ever offset by another run's, so any `usize` can
+/// be packed. The sequence does not record where runs start: callers pass
+/// the start of the run they read from, which a trie layer already knows
+/// from its `interval` array.
+///
+/// [`get`](Self::get) decodes at most one block, and
+/// [`successor`](Self::successor) binary-searches the first values of the
+/// blocks in a run before scanning a single block, so both are logarithmic
+/// in the length at worst.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PackedSequence {
+    len: usize,
+    /// The smallest value starting a block or a run in every block.
+    lows: Vec<usize>,
+    /// Bit offset into `words` at which each block's slots start.
+    offsets: Vec<usize>,
+    /// Bits per slot in each block.
+    widths: Vec<u8>,
+    words: Vec<u64>,
+}
+
+impl PackedSequence {
+    /// Packs `values`, where `runs` lists the ascending indices at which a
+    /// new run starts. Each run must be sorted ascending; the first value
+    /// always starts one.
+    pub(crate) fn new(values: &[usize], runs: &[usize]) -> Self {
+        let mut seq = PackedSequence {
+            len: values.len(),
+            ..Default::default()
+        };
+        let mut runs = runs.iter().copied().peekable();
+        let mut bits = 0;
+        for (b, block) in values.chunks(BLOCK).enumerate() {
+            let starts: Vec<bool> = (b * BLOCK..b * BLOCK + block.len())
+                .map(|i| {
+                    while runs.next_if(|&run| run < i).is_some() {}
+                    i % BLOCK == 0 || runs.next_if_eq(&i).is_some()
+                })
+                .collect();
+            let low = (0..block.len())
+                .filter(|&j| starts[j])
+                .map(|j| block[j])
+                .min()
+                .unwrap_or_default();
+            let slots: Vec<u64> = (0..block.len())
+                .map(|j| {
+                    if starts[j] {
+                        (block[j] - low) as u64
+                    } else {
+                        debug_assert!(block[j - 1] <= block[j], "runs must be sorted");
+                        (block[j] - block[j - 1]) as u64
+                    }
+                })
+                .collect();
+            let width = slots
+                .iter()
+                .max()
+                .map_or(0, |slot| u64::BITS - slot.leading_zeros());
+            seq.lows.push(low);
+            seq.offsets.push(bits);
+            seq.widths.push(width as u8);
+            for slot in slots {
+                seq.write_bits(bits, width, slot);
+                bits += width as usize;
+            }
+        }
+        seq.words.shrink_to_fit();
+        seq
+    }
+
+    /// Returns the number of values.
+    pub(crate) fn len(&self) -> usize { self.len }
+
+    /// Returns `true` if the sequence holds no values.
+    pub(crate) fn is_empty(&self) -> bool { self.len == 0 }
+
+    /// Returns the value at `i`, where `run` is the index starting the run
+    /// that holds `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i >= self.len()`.
+    pub(crate) fn get(&self, run: usize, i: usize) -> usize {
+        assert!(
+            i < self.len,
+            "index {i} out of bounds for length {}",
+            self.len
+        );
+        debug_assert!(run <= i, "run {run} starts after index {i}");
+        let block = i / BLOCK;
+        let from = run.max(block * BLOCK);
+        (from % BLOCK + 1..=i % BLOCK).fold(self.head(block, from % BLOCK), |value, j| {
+            value + self.slot(block, j)
+        })
+    }
+
+    /// Returns the value at `i + 1`, given that `value` is the value at `i`
+    /// and both lie in the same run. Costs a single slot decode.
+    pub(crate) fn next_after(&self, i: usize, value: usize) -> usize {
+        let next = i + 1;
+        match next % BLOCK {
+            | 0 => self.head(next / BLOCK, 0),
+            | j => value + self.slot(next / BLOCK, j),
+        }
+    }
+
+    /// Returns the first index in `i..hi` whose value is at least `target`,
+    /// together with that value, or `(hi, None)` if there is none. `value`
+    /// is the value at `i`, and `i..hi` must lie in one run.
+    pub(crate) fn successor(
+        &self, i: usize, value: usize, hi: usize, target: usize,
+    ) -> (usize, Option<usize>) {
+        if i >= hi {
+            return (hi, None);
+        }
+        // Values before the last block that starts below `target` are all
+        // below it, so the answer lies in that block or starts the next.
+        let (mut lo, mut up) = (i / BLOCK + 1, (hi - 1) / BLOCK + 1);
+        while lo < up {
+            let mid = lo + (up - lo) / 2;
+            if self.head(mid, 0) < target {
+                lo = mid + 1;
+            } else {
+                up = mid;
+            }
+        }
+        let (mut i, mut value) = match lo - 1 {
+            | block if block == i / BLOCK => (i, value),
+            | block => (block * BLOCK, self.head(block, 0)),
+        };
+        while value < target {
+            i += 1;
+            if i == hi {
+                return (hi, None);
+            }
+            value = self.next_after(i - 1, value);
+        }
+        (i, Some(value))
+    }
+
+    /// Returns the values of a sequence packed as a single run, in order.
+    #[cfg(test)]
+    pub(crate) fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        let mut value = None;
+        (0..self.len).map(move |i| {
+            let next = match value {
+                | None => self.get(0, 0),
+                | Some(prev) => self.next_after(i - 1, prev),
+            };
+            value = Some(next);
+            next
+        })
+    }
+
+    /// Returns the bytes allocated by the sequence.
+    pub(crate) fn heap_size_bytes(&self) -> usize {
+        self.lows.capacity() * std::mem::size_of::<usize>()
+            + self.offsets.capacity() * std::mem::size_of::<usize>()
+            + self.widths.capacity()
+            + self.words.capacity() * std::mem::size_of::<u64>()
+    }
+
+    /// Returns the value at slot `j` of `block`, which must start the block
+    /// or a run.
+    fn head(&self, block: usize, j: usize) -> usize { self.lows[block] + self.slot(block, j) }
+
+    /// Returns slot `j` of `block`.
+    fn slot(&self, block: usize, j: usize) -> usize {
+        let width = u32::from(self.widths[block]);
+        self.read_bits(self.offsets[block] + j * width as usize, width) as usize
+    }
+
+    fn read_bits(&self, at: usize, width: u32) -> u64 {
+        if width == 0 {
+            return 0;
+        }
+        let (word, shift) = (at / 64, (at % 64) as u32);
+        let mut bits = self.words[word] >> shift;
+        if shift + width > 64 {
+            bits |= self.words[word + 1] << (64 - shift);
+        }
+        match width {
+            | 64 => bits,
+            | _ => bits & ((1 << width) - 1),
+        }
+    }
+
+    fn write_bits(&mut self, at: usize, width: u32, bits: u64) {
+        if width == 0 {
+            return;
+        }
+        let end = (at + width as usize).div_ceil(64);
+        if self.words.len() < end {
+            self.words.resize(end, 0);
+        }
+        let (word, shift) = (at / 64, (at % 64) as u32);
+        self.words[word] |= bits << shift;
+        if shift + width > 64 {
+            self.words[word + 1] |= bits >> (64 - shift);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A sorted sequence mixing runs of equal values, small gaps and a few
+    /// gaps needing every bit.
+    fn values() -> Vec<usize> {
+        let mut values = Vec::new();
+        let mut x: usize = 0;
+        for i in 0..500_usize {
+            x += match i % 97 {
+                | 0 => 1 << 40,
+                | n if n % 5 == 0 => 0,
+                | n => n * 3,
+            };
+            values.push(x);
+        }
+        values.push(usize::MAX);
+        values
+    }
+
+    #[test]
+    fn get_and_iter_decode_every_value() {
+        let values = values();
+        let seq = PackedSequence::new(&values, &[]);
+        assert_eq!(seq.len(), values.len());
+        for (i, &value) in values.iter().enumerate() {
+            assert_eq!(seq.get(0, i), value, "index {i}");
+        }
+        assert_eq!(seq.iter().collect::<Vec<_>>(), values);
+        assert!(PackedSequence::new(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn successor_matches_partition_point() {
+        let values = values();
+        let seq = PackedSequence::new(&values, &[]);
+        let ranges = [
+            (0, values.len()),
+            (3, 70),
+            (64, 128),
+            (130, 131),
+            (200, 450),
+            (7, 7),
+        ];
+        for (lo, hi) in ranges {
+            for target in values[lo..hi]
+                .iter()
+                .flat_map(|&v| [v.saturating_sub(1), v, v.saturating_add(1)])
+            {
+                let i = lo + values[lo..hi].partition_point(|&v| v < target);
+                let expected = (i, values[..hi].get(i).copied());
+                assert_eq!(
+                    seq.successor(lo, values[lo], hi, target),
+                    expected,
+                    "{lo}..{hi} >= {target}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn runs_restart_at_any_value() {
+        // Runs of descending starts, each near `usize::MAX` or near zero.
+        let runs: Vec<usize> = (0..100).map(|r| r * 7).collect();
+        let values: Vec<usize> = (0..700)
+            .map(|i| match (i / 7) % 2 {
+                | 0 => usize::MAX - 100 + i % 7,
+                | _ => (i / 7) * 1000 + i % 7,
+            })
+            .collect();
+        let seq = PackedSequence::new(&values, &runs);
+        for &run in &runs {
+            for (i, &value) in values.iter().enumerate().skip(run).take(7) {
+                assert_eq!(seq.get(run, i), value, "index {i}");
+            }
+            let (i, value) = seq.successor(run, values[run], run + 7, values[run + 3]);
+            assert_eq!((i, value), (run + 3, Some(values[run + 3])));
+            assert_eq!(
+                seq.successor(run, values[run], run + 7, usize::MAX),
+                (run + 7, None)
+            );
+        }
+    }
+
+    #[test]
+    fn packs_small_gaps_into_few_bits() {
+        let values: Vec<usize> = (0..6400).map(|i| i * 3).collect();
+        let seq = PackedSequence::new(&values, &[]);
+        // Two bits per gap, plus the per-block headers.
+        assert!(seq.heap_size_bytes() < values.len());
+    }
+}
@@ -0,0 +1,240 @@
+use {
+    super::implementation::{HashNode, HashTrie},
+    kermit_derive::IntoTrieIter,
+    kermit_iters::{
+        LinearIterator, ProbeIterable, ProbeIterator, TrieIterable, TrieIterator,
+        TrieIteratorWrapper,
+    },
+};
+
+/// Where a [`HashTrieIter`] stands among the children of one node.
+#[derive(Clone, Copy)]
+enum Position {
+    /// At this index of the node's sorted keys, or at the end if it equals
+    /// the number of children.
+    Rank(usize),
+    /// At this key, reached by [`lookup`](ProbeIterator::lookup). Its rank
+    /// is only looked up if the iterator moves on in order.
+    Probed(usize),
+}
+
+/// The children of one node being scanned at one trie depth.
+struct Frame<'a> {
+    node: &'a HashNode,
+    pos: Position,
+}
+
+impl Frame<'_> {
+    fn key(&self) -> Option<usize> {
+        match self.pos {
+            | Position::Probed(key) => Some(key),
+            | Position::Rank(rank) if rank < self.node.len() => Some(self.node.sorted_keys()[rank]),
+            | Position::Rank(_) => None,
+        }
+    }
+
+    fn rank(&self) -> usize {
+        match self.pos {
+            | Position::Rank(rank) => rank,
+            | Position::Probed(key) => self.node.sorted_keys().partition_point(|&k| k < key),
+        }
+    }
+
+    fn at_end(&self) -> bool {
+        match self.pos {
+            | Position::Probed(_) => false,
+            | Position::Rank(rank) => rank >= self.node.len(),
+        }
+    }
+}
+
+/// Iterator over a [`HashTrie`].
+///
+/// A frame per open depth records the node whose children are being scanned
+/// and the position among them. Ordered moves (`next`, `seek`) work on the
+/// node's lazily sorted keys, so a node is only sorted once something walks
+/// it in order; [`lookup`](ProbeIterator::lookup) and
+/// [`contains`](ProbeIterator::contains) only hash.
+#[derive(IntoTrieIter)]
+pub struct HashTrieIter<'a> {
+    /// One frame per open depth; empty at the root.
+    frames: Vec<Frame<'a>>,
+    /// The trie being iterated.
+    trie: &'a HashTrie,
+}
+
+impl<'a> HashTrieIter<'a> {
+    /// Creates a new iterator positioned at the root. Call
+    /// [`open`](TrieIterator::open) to descend to the first layer.
+    pub fn new(trie: &'a HashTrie) -> Self {
+        HashTrieIter {
+            frames: Vec::new(),
+            trie,
+        }
+    }
+}
+
+impl LinearIterator for HashTrieIter<'_> {
+    fn key(&self) -> Option<usize> { self.frames.last()?.key() }
+
+    fn next(&mut self) -> Option<usize> {
+        let frame = self.frames.last_mut()?;
+        if frame.at_end() {
+            return None;
+        }
+        frame.pos = Position::Rank(frame.rank() + 1);
+        frame.key()
+    }
+
+    fn seek(&mut self, seek_key: usize) -> bool {
+        let Some(frame) = self.frames.last_mut() else {
+            return false;
+        };
+        if frame.at_end() {
+            return false;
+        }
+        let keys = frame.node.sorted_keys();
+        let rank = frame.rank();
+        let rank = rank + keys[rank..].partition_point(|&k| k < seek_key);
+        frame.pos = Position::Rank(rank);
+        rank < keys.len()
+    }
+
+    fn at_end(&self) -> bool { self.frames.last().is_none_or(Frame::at_end) }
+}
+
+impl TrieIterator for HashTrieIter<'_> {
+    fn open(&mut self) -> bool {
+        let node = match self.frames.last() {
+            | None => self.trie.root(),
+            | Some(frame) => match frame.key() {
+                | Some(key) => frame
+                    .node
+                    .child(key)
+                    .expect("the current key has a child node"),
+                | None => return false,
+            },
+        };
+        if node.is_leaf() {
+            return false;
+        }
+        self.frames.push(Frame {
+            node,
+            pos: Position::Rank(0),
+        });
+        true
+    }
+
+    fn up(&mut self) -> bool { self.frames.pop().is_some() }
+}
+
+impl ProbeIterator for HashTrieIter<'_> {
+    fn contains(&self, key: usize) -> bool {
+        self.frames
+            .last()
+            .is_some_and(|frame| frame.node.contains(key))
+    }
+
+    fn lookup(&mut self, key: usize) -> bool {
+        let Some(frame) = self.frames.last_mut() else {
+            return false;
+        };
+        let found = frame.node.contains(key);
+        frame.pos = if found {
+            Position::Probed(key)
+        } else {
+            Position::Rank(frame.node.len())
+        };
+        found
+    }
+}
+
+/// Implementation of the `TrieIterable` trait for `HashTrie`.
+impl TrieIterable for HashTrie {
+    fn trie_iter(&self) -> impl TrieIterator + IntoIterator<Item = Vec<usize>> {
+        HashTrieIter::new(self)
+    }
+}
+
+/// Implementation of the `ProbeIterable` trait for `HashTrie`.
+impl ProbeIterable for HashTrie {
+    fn probe_iter(&self) -> impl ProbeIterator + IntoIterator<Item = Vec<usize>> {
+        HashTrieIter::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{relation::Relation, HeapSize},
+    };
+
+    fn trie() -> HashTrie {
+        HashTrie::from_tuples(2.into(), vec![
+            vec![5, 1],
+            vec![1, 7],
+            vec![3, 2],
+            vec![3, 9],
+            vec![9, 4],
+        ])
+    }
+
+    #[test]
+    fn lookup_jumps_backwards_and_resumes_in_order() {
+        let trie = trie();
+        let mut iter = trie.probe_iter();
+        assert!(iter.open());
+        assert!(iter.lookup(9));
+        assert_eq!(iter.key(), Some(9));
+        assert!(iter.lookup(3));
+        assert_eq!(iter.next(), Some(5));
+        assert!(iter.lookup(1));
+        assert!(iter.seek(4));
+        assert_eq!(iter.key(), Some(5));
+    }
+
+    #[test]
+    fn lookup_opens_the_found_key() {
+        let trie = trie();
+        let mut iter = trie.probe_iter();
+        assert!(iter.open());
+        assert!(iter.lookup(3));
+        assert!(iter.open());
+        assert!(iter.contains(9));
+        assert!(!iter.contains(7));
+        assert!(iter.lookup(9));
+        assert!(iter.up());
+        assert_eq!(iter.key(), Some(3));
+    }
+
+    #[test]
+    fn failed_lookup_moves_to_the_end() {
+        let trie = trie();
+        let mut iter = trie.probe_iter();
+        assert!(!iter.contains(1));
+        assert!(!iter.lookup(1));
+        assert!(iter.open());
+        assert!(!iter.lookup(4));
+        assert!(iter.at_end());
+        assert_eq!(iter.key(), None);
+        assert!(!iter.open());
+        assert!(iter.lookup(1));
+        assert!(!iter.at_end());
+    }
+
+    #[test]
+    fn only_ordered_moves_sort_keys() {
+        let trie = trie();
+        let unsorted = trie.heap_size_bytes();
+        let mut iter = trie.probe_iter();
+        assert!(iter.open());
+        assert!(iter.lookup(5));
+        assert!(iter.open());
+        assert!(iter.lookup(1));
+        assert!(iter.up());
+        assert_eq!(trie.heap_size_bytes(), unsorted);
+        assert_eq!(iter.next(), Some(9));
+        assert!(trie.heap_size_bytes() > unsorted);
+    }
+}
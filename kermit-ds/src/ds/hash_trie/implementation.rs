@@ -0,0 +1,284 @@
+use {
+    crate::relation::{Relation, RelationHeader},
+    kermit_iters::{JoinIterable, TrieIterable},
+    std::{
+        collections::{hash_map::Entry, HashMap},
+        sync::OnceLock,
+    },
+};
+
+/// A node in a [`HashTrie`].
+///
+/// Children are kept in a hash map keyed by their key. Their keys in
+/// ascending order are only materialised when ordered iteration first asks
+/// for them, and dropped again whenever a child is added or removed. Leaf
+/// nodes have no children.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct HashNode {
+    children: HashMap<usize, HashNode>,
+    sorted: OnceLock<Vec<usize>>,
+}
+
+impl HashNode {
+    /// Returns the child with the given key, if any.
+    pub(crate) fn child(&self, key: usize) -> Option<&HashNode> { self.children.get(&key) }
+
+    /// Returns `true` iff a child has the given key.
+    pub(crate) fn contains(&self, key: usize) -> bool { self.children.contains_key(&key) }
+
+    /// Returns the number of children.
+    pub(crate) fn len(&self) -> usize { self.children.len() }
+
+    /// Returns `true` iff the node has no children.
+    pub(crate) fn is_leaf(&self) -> bool { self.children.is_empty() }
+
+    /// Returns the children's keys in ascending order, sorting them on the
+    /// first call since the last change.
+    pub(crate) fn sorted_keys(&self) -> &[usize] {
+        self.sorted.get_or_init(|| {
+            let mut keys: Vec<usize> = self.children.keys().copied().collect();
+            keys.sort_unstable();
+            keys
+        })
+    }
+
+    /// Adds the path `tuple` below this node.
+    fn insert(&mut self, tuple: &[usize]) {
+        let Some((&key, rest)) = tuple.split_first() else {
+            return;
+        };
+        let child = match self.children.entry(key) {
+            | Entry::Occupied(entry) => entry.into_mut(),
+            | Entry::Vacant(entry) => {
+                self.sorted.take();
+                entry.insert(HashNode::default())
+            },
+        };
+        child.insert(rest);
+    }
+
+    /// Removes the path `tuple` below this node, pruning every node whose
+    /// children it leaves empty.
+    fn remove(&mut self, tuple: &[usize]) -> bool {
+        let Some((&key, rest)) = tuple.split_first() else {
+            return false;
+        };
+        let Some(child) = self.children.get_mut(&key) else {
+            return false;
+        };
+        if !rest.is_empty() {
+            if !child.remove(rest) {
+                return false;
+            }
+            if !child.is_leaf() {
+                return true;
+            }
+        }
+        self.children.remove(&key);
+        self.sorted.take();
+        true
+    }
+}
+
+/// A trie whose levels are hash maps from key to child.
+///
+/// Each tuple `[k₀, k₁, …, kₙ₋₁]` is a root-to-leaf path, as in
+/// [`TreeTrie`](crate::ds::TreeTrie), but every node finds a child by
+/// hashing instead of binary search. Its iterator implements
+/// [`ProbeIterator`](kermit_iters::ProbeIterator), so a hash-based join can
+/// test and jump to keys in any order in constant expected time.
+///
+/// It still implements the ordered [`TrieIterator`](kermit_iters::TrieIterator)
+/// needed by Leapfrog Triejoin: the first ordered access to a node sorts its
+/// keys once and caches them until the node next changes.
+///
+/// # Invariants
+///
+/// - The depth of every root-to-leaf path equals
+///   [`RelationHeader::arity`](crate::RelationHeader::arity).
+/// - A node's cached sorted keys, once built, are exactly the keys of its
+///   children.
+///
+/// # When to prefer
+///
+/// Prefer `HashTrie` for joins that probe rather than intersect in order,
+/// and for relations that change often, since inserting and removing never
+/// shift sorted arrays. Ordered iteration pays for sorting each visited node
+/// once, and a hash map per node takes far more memory than
+/// [`ColumnTrie`](crate::ds::ColumnTrie)'s flat arrays.
+///
+/// # Example
+///
+/// ```
+/// use {
+///     kermit_ds::{HashTrie, Relation},
+///     kermit_iters::{ProbeIterable, ProbeIterator, TrieIterator},
+/// };
+///
+/// let trie = HashTrie::from_tuples(2.into(), vec![vec![1, 2], vec![1, 3], vec![2, 4]]);
+/// let mut iter = trie.probe_iter();
+/// assert!(iter.open());
+/// assert!(iter.lookup(2));
+/// assert!(iter.lookup(1));
+/// assert!(iter.open());
+/// assert!(iter.contains(3));
+/// assert!(!iter.contains(4));
+/// ```
+#[derive(Clone, Debug)]
+pub struct HashTrie {
+    header: RelationHeader,
+    root: HashNode,
+}
+
+impl HashTrie {
+    /// Returns the root node, whose children hold the first column.
+    pub(crate) fn root(&self) -> &HashNode { &self.root }
+}
+
+impl Relation for HashTrie {
+    fn header(&self) -> &RelationHeader { &self.header }
+
+    fn new(header: RelationHeader) -> Self {
+        Self {
+            header,
+            root: HashNode::default(),
+        }
+    }
+
+    /// Builds a `HashTrie` from a batch of tuples, in any order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any tuple's arity does not match `header.arity()`.
+    fn from_tuples(header: RelationHeader, tuples: Vec<Vec<usize>>) -> Self {
+        let mut trie = Self::new(header);
+        trie.insert_all(tuples);
+        trie
+    }
+
+    /// Inserts a single tuple.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tuple.len()` does not match the arity of the relation.
+    fn insert(&mut self, tuple: Vec<usize>) -> bool {
+        if tuple.len() != self.header().arity() {
+            panic!("Arity doesn't match.");
+        }
+        self.root.insert(&tuple);
+        true
+    }
+
+    /// Inserts every tuple in `tuples`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any tuple's arity does not match the relation's arity.
+    fn insert_all(&mut self, tuples: Vec<Vec<usize>>) -> bool {
+        for tuple in tuples {
+            self.insert(tuple);
+        }
+        true
+    }
+
+    /// Removes a single tuple, dropping every node left without children.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tuple.len()` does not match the arity of the relation.
+    fn remove(&mut self, tuple: &[usize]) -> bool {
+        if tuple.len() != self.header().arity() {
+            panic!("Arity doesn't match.");
+        }
+        self.root.remove(tuple)
+    }
+}
+
+impl JoinIterable for HashTrie {}
+
+impl crate::relation::Projectable for HashTrie {
+    fn project(&self, columns: Vec<usize>) -> Self {
+        let current_header = self.header();
+        let projected_attrs: Vec<String> = columns
+            .iter()
+            .filter_map(|&col_idx| current_header.attrs().get(col_idx).cloned())
+            .collect();
+
+        let new_header = if projected_attrs.is_empty() {
+            crate::relation::RelationHeader::new_nameless_positional(columns.len())
+        } else {
+            crate::relation::RelationHeader::new_nameless(projected_attrs)
+        };
+
+        let projected_tuples: Vec<Vec<usize>> = self
+            .trie_iter()
+            .into_iter()
+            .map(|tuple| columns.iter().map(|&col_idx| tuple[col_idx]).collect())
+            .collect();
+
+        Self::from_tuples(new_header, projected_tuples)
+    }
+}
+
+impl crate::heap_size::HeapSize for HashTrie {
+    /// Approximates each hash map by its capacity, one entry plus one
+    /// control byte per slot, and counts sorted key caches only once built.
+    fn heap_size_bytes(&self) -> usize {
+        fn node_heap_bytes(node: &HashNode) -> usize {
+            let map_bytes =
+                node.children.capacity() * (std::mem::size_of::<(usize, HashNode)>() + 1);
+            let sorted_bytes = node
+                .sorted
+                .get()
+                .map_or(0, |keys| keys.capacity() * std::mem::size_of::<usize>());
+            map_bytes + sorted_bytes + node.children.values().map(node_heap_bytes).sum::<usize>()
+        }
+
+        node_heap_bytes(&self.root)
+    }
+}
+
+impl crate::trie_stats::TrieStats for HashTrie {
+    fn level_sizes(&self) -> Vec<usize> {
+        let mut sizes = vec![0; self.header().arity()];
+        let mut level = vec![&self.root];
+        for size in &mut sizes {
+            *size = level.iter().map(|node| node.len()).sum();
+            level = level
+                .iter()
+                .flat_map(|node| node.children.values())
+                .collect();
+        }
+        sizes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{HeapSize, TrieStats},
+    };
+
+    #[test]
+    fn edits_invalidate_sorted_keys() {
+        let mut trie = HashTrie::from_tuples(2.into(), vec![vec![3, 1], vec![1, 2]]);
+        assert_eq!(trie.root().sorted_keys(), [1, 3]);
+        trie.insert(vec![2, 5]);
+        assert_eq!(trie.root().sorted_keys(), [1, 2, 3]);
+        assert!(trie.remove(&[1, 2]));
+        assert_eq!(trie.root().sorted_keys(), [2, 3]);
+        assert_eq!(trie.level_sizes(), vec![2, 2]);
+    }
+
+    #[test]
+    fn heap_size_counts_built_sorted_keys() {
+        let trie = HashTrie::from_tuples(1.into(), (0..100).map(|i| vec![i]).collect());
+        let unsorted = trie.heap_size_bytes();
+        trie.root().sorted_keys();
+        assert_eq!(
+            trie.heap_size_bytes(),
+            unsorted + 100 * std::mem::size_of::<usize>()
+        );
+    }
+}
@@ -0,0 +1,7 @@
+//! This module provides a trie whose levels are hash maps, for joins that
+//! probe relations by key.
+
+mod hash_trie_iter;
+mod implementation;
+
+pub use implementation::HashTrie;
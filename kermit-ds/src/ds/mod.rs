@@ -1,10 +1,17 @@
+mod bitmap_trie;
 mod column_trie;
+mod compressed_column_trie;
+mod hash_trie;
+mod sorted_tuple_array;
 mod tree_trie;
 
+pub use {
+    bitmap_trie::BitmapTrie, column_trie::ColumnTrie, compressed_column_trie::CompressedColumnTrie,
+    hash_trie::HashTrie, sorted_tuple_array::SortedTupleArray, tree_trie::TreeTrie,
+};
 use {clap::ValueEnum, std::str::FromStr};
-pub use {column_trie::ColumnTrie, tree_trie::TreeTrie};
 
-/// The available trie-based index structures for storing relations.
+/// The available index structures for storing relations.
 ///
 /// Used as a CLI argument to select which data structure backs the join.
 #[derive(Copy, Clone, PartialEq, Eq, Debug, ValueEnum)]
@@ -13,6 +20,16 @@ pub enum IndexStructure {
     ColumnTrie,
     /// Pointer-based trie; see [`TreeTrie`].
     TreeTrie,
+    /// Flat lexicographically sorted tuple array; see [`SortedTupleArray`].
+    SortedTupleArray,
+    /// Column-oriented trie with bit-packed layers; see
+    /// [`CompressedColumnTrie`].
+    CompressedColumnTrie,
+    /// Trie with hash-map levels and point probes; see [`HashTrie`].
+    HashTrie,
+    /// Column-oriented trie with bitmap layers where dense; see
+    /// [`BitmapTrie`].
+    BitmapTrie,
 }
 
 impl FromStr for IndexStructure {
@@ -22,6 +39,10 @@ impl FromStr for IndexStructure {
         match s {
             | "column_trie" => Ok(Self::ColumnTrie),
             | "tree_trie" => Ok(Self::TreeTrie),
+            | "sorted_tuple_array" => Ok(Self::SortedTupleArray),
+            | "compressed_column_trie" => Ok(Self::CompressedColumnTrie),
+            | "hash_trie" => Ok(Self::HashTrie),
+            | "bitmap_trie" => Ok(Self::BitmapTrie),
             | _ => Err(format!("Invalid index structure: {}", s)),
         }
     }
@@ -0,0 +1,278 @@
+use {
+    crate::relation::{Relation, RelationHeader},
+    kermit_iters::{JoinIterable, TrieIterable},
+    std::cmp::Ordering,
+};
+
+/// A relation stored as one contiguous, lexicographically sorted array of
+/// tuples.
+///
+/// Tuples are laid out row-major in a single `Vec<usize>` with a stride of
+/// the relation's arity, so row `i` occupies `data[i * arity..(i + 1) *
+/// arity]`. There are no per-level index arrays: the trie view is recovered
+/// on the fly by its trie iterator, which narrows a range of rows per depth
+/// with galloping and binary search. This is the sorted-array baseline used
+/// throughout the Leapfrog Triejoin literature.
+///
+/// # Invariants
+///
+/// - `data.len()` is a multiple of `header.arity()`.
+/// - Rows are sorted ascending lexicographically with no duplicates.
+///
+/// # When to prefer
+///
+/// `SortedTupleArray` has the smallest footprint of the index structures —
+/// exactly one word per stored value — and bulk construction is a single
+/// sort. Single-tuple inserts shift the tail of the array, so prefer
+/// [`TreeTrie`](crate::ds::TreeTrie) when inserting one tuple at a time.
+///
+/// # Example
+///
+/// ```
+/// use kermit_ds::{Relation, SortedTupleArray};
+///
+/// let array = SortedTupleArray::from_tuples(2.into(), vec![vec![2, 4], vec![1, 3], vec![1, 2]]);
+/// assert_eq!(array.data(), &[1, 2, 1, 3, 2, 4]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SortedTupleArray {
+    header: RelationHeader,
+    data: Vec<usize>,
+}
+
+impl SortedTupleArray {
+    /// Returns the sorted tuples as one row-major array.
+    pub fn data(&self) -> &[usize] { &self.data }
+
+    /// Returns the number of stored tuples.
+    pub fn len(&self) -> usize {
+        match self.header.arity() {
+            | 0 => 0,
+            | arity => self.data.len() / arity,
+        }
+    }
+
+    /// Returns `true` if the relation holds no tuples.
+    pub fn is_empty(&self) -> bool { self.data.is_empty() }
+
+    /// Returns the value in column `column` of row `row`.
+    pub(crate) fn value(&self, row: usize, column: usize) -> usize {
+        self.data[row * self.header.arity() + column]
+    }
+
+    /// Returns row `row` as a slice.
+    fn row(&self, row: usize) -> &[usize] {
+        let arity = self.header.arity();
+        &self.data[row * arity..(row + 1) * arity]
+    }
+
+    /// Returns the row holding `tuple`, or the row it would be inserted at.
+    fn search(&self, tuple: &[usize]) -> Result<usize, usize> {
+        let (mut lo, mut hi) = (0, self.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.row(mid).cmp(tuple) {
+                | Ordering::Less => lo = mid + 1,
+                | Ordering::Greater => hi = mid,
+                | Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(lo)
+    }
+
+    /// Replaces the contents with `tuples`, sorted and deduplicated.
+    fn fill(&mut self, mut tuples: Vec<Vec<usize>>) {
+        tuples.sort_unstable();
+        tuples.dedup();
+        self.data = tuples.into_iter().flatten().collect();
+    }
+}
+
+impl Relation for SortedTupleArray {
+    fn header(&self) -> &RelationHeader { &self.header }
+
+    fn new(header: RelationHeader) -> Self {
+        SortedTupleArray {
+            header,
+            data: vec![],
+        }
+    }
+
+    /// Builds a `SortedTupleArray` with a single sort of `tuples`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any tuple's arity does not match `header.arity()`.
+    fn from_tuples(header: RelationHeader, tuples: Vec<Vec<usize>>) -> Self {
+        assert!(
+            tuples.iter().all(|tuple| tuple.len() == header.arity()),
+            "Arity doesn't match."
+        );
+        let mut array = Self::new(header);
+        array.fill(tuples);
+        array
+    }
+
+    /// Inserts a single tuple at its sorted position; a tuple already present
+    /// is left in place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tuple.len()` does not match the arity of the relation.
+    fn insert(&mut self, tuple: Vec<usize>) -> bool {
+        let arity = self.header.arity();
+        if tuple.len() != arity {
+            panic!("Arity doesn't match.");
+        }
+        if arity == 0 {
+            return true;
+        }
+        if let Err(row) = self.search(&tuple) {
+            self.data.splice(row * arity..row * arity, tuple);
+        }
+        true
+    }
+
+    /// Inserts every tuple in `tuples` by re-sorting the whole array once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any tuple's arity does not match the relation's arity.
+    fn insert_all(&mut self, mut tuples: Vec<Vec<usize>>) -> bool {
+        let arity = self.header.arity();
+        assert!(
+            tuples.iter().all(|tuple| tuple.len() == arity),
+            "Arity doesn't match."
+        );
+        if arity == 0 {
+            return true;
+        }
+        tuples.extend(self.data.chunks_exact(arity).map(<[usize]>::to_vec));
+        self.fill(tuples);
+        true
+    }
+
+    /// Removes a single tuple, shifting the tail of the array down.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tuple.len()` does not match the arity of the relation.
+    fn remove(&mut self, tuple: &[usize]) -> bool {
+        let arity = self.header.arity();
+        if tuple.len() != arity {
+            panic!("Arity doesn't match.");
+        }
+        if arity == 0 {
+            return false;
+        }
+        match self.search(tuple) {
+            | Ok(row) => {
+                self.data.drain(row * arity..(row + 1) * arity);
+                true
+            },
+            | Err(_) => false,
+        }
+    }
+
+    /// Removes every tuple in `tuples` in a single pass over the array.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any tuple's arity does not match the relation's arity.
+    fn remove_all(&mut self, mut tuples: Vec<Vec<usize>>) -> bool {
+        let arity = self.header.arity();
+        assert!(
+            tuples.iter().all(|tuple| tuple.len() == arity),
+            "Arity doesn't match."
+        );
+        if arity == 0 {
+            return false;
+        }
+        tuples.sort_unstable();
+        let mut doomed = tuples.iter().map(Vec::as_slice).peekable();
+        let mut kept = Vec::with_capacity(self.data.len());
+        for row in self.data.chunks_exact(arity) {
+            while doomed.next_if(|&d| d < row).is_some() {}
+            if doomed.peek() != Some(&row) {
+                kept.extend_from_slice(row);
+            }
+        }
+        let removed = kept.len() < self.data.len();
+        self.data = kept;
+        removed
+    }
+}
+
+impl JoinIterable for SortedTupleArray {}
+
+impl crate::relation::Projectable for SortedTupleArray {
+    fn project(&self, columns: Vec<usize>) -> Self {
+        let current_header = self.header();
+        let projected_attrs: Vec<String> = columns
+            .iter()
+            .filter_map(|&col_idx| current_header.attrs().get(col_idx).cloned())
+            .collect();
+
+        let new_header = if projected_attrs.is_empty() {
+            crate::relation::RelationHeader::new_nameless_positional(columns.len())
+        } else {
+            crate::relation::RelationHeader::new_nameless(projected_attrs)
+        };
+
+        let projected_tuples: Vec<Vec<usize>> = self
+            .trie_iter()
+            .into_iter()
+            .map(|tuple| columns.iter().map(|&col_idx| tuple[col_idx]).collect())
+            .collect();
+
+        Self::from_tuples(new_header, projected_tuples)
+    }
+}
+
+impl crate::heap_size::HeapSize for SortedTupleArray {
+    fn heap_size_bytes(&self) -> usize { self.data.capacity() * std::mem::size_of::<usize>() }
+}
+
+impl crate::trie_stats::TrieStats for SortedTupleArray {
+    fn level_sizes(&self) -> Vec<usize> {
+        // A row opens a new prefix at every depth from the first column in
+        // which it differs from the previous row.
+        let mut sizes = vec![0; self.header.arity()];
+        for row in 0..self.len() {
+            let first_new = if row == 0 {
+                0
+            } else {
+                let (prev, curr) = (self.row(row - 1), self.row(row));
+                prev.iter().zip(curr).take_while(|(a, b)| a == b).count()
+            };
+            for size in &mut sizes[first_new..] {
+                *size += 1;
+            }
+        }
+        sizes
+    }
+}
+
+#[cfg(test)]
+mod heap_size_tests {
+    use {
+        super::*,
+        crate::{HeapSize, Relation},
+    };
+
+    #[test]
+    fn empty_sorted_tuple_array_heap_size() {
+        let array = SortedTupleArray::new(2.into());
+        assert_eq!(array.heap_size_bytes(), 0);
+    }
+
+    #[test]
+    fn heap_size_is_one_word_per_value() {
+        let array = SortedTupleArray::from_tuples(2.into(), (0..100).map(|i| vec![i, i]).collect());
+        assert_eq!(
+            array.heap_size_bytes(),
+            array.data.capacity() * std::mem::size_of::<usize>()
+        );
+        assert!(array.heap_size_bytes() >= 200 * std::mem::size_of::<usize>());
+    }
+}
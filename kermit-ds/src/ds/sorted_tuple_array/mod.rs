@@ -0,0 +1,104 @@
+//! Flat sorted-array implementation of a relation: the classic baseline from
+//! the Leapfrog Triejoin literature.
+
+mod implementation;
+mod sorted_tuple_array_iter;
+
+pub use implementation::SortedTupleArray;
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::implementation::*,
+        crate::relation::{Projectable, Relation},
+        kermit_iters::{LinearIterator, TrieIterable, TrieIterator},
+    };
+
+    #[test]
+    fn insert_keeps_rows_sorted_and_distinct() {
+        let mut array = SortedTupleArray::new(2.into());
+        array.insert(vec![2, 3]);
+        array.insert(vec![1, 5]);
+        array.insert(vec![2, 1]);
+        array.insert(vec![1, 5]);
+        assert_eq!(array.data(), &[1, 5, 2, 1, 2, 3]);
+        assert_eq!(array.len(), 3);
+    }
+
+    #[test]
+    fn insert_all_merges_with_existing_rows() {
+        let mut array = SortedTupleArray::from_tuples(2.into(), vec![vec![3, 1], vec![1, 1]]);
+        array.insert_all(vec![vec![2, 2], vec![1, 1], vec![0, 9]]);
+        assert_eq!(array.data(), &[0, 9, 1, 1, 2, 2, 3, 1]);
+    }
+
+    #[test]
+    fn trie_iterator() {
+        let array = SortedTupleArray::from_tuples(3.into(), vec![
+            vec![1, 3, 4],
+            vec![1, 3, 5],
+            vec![1, 4, 6],
+            vec![1, 4, 8],
+            vec![1, 4, 9],
+            vec![1, 5, 2],
+            vec![3, 5, 2],
+        ]);
+        let mut iter = array.trie_iter();
+
+        assert!(iter.open());
+        assert_eq!(iter.key(), Some(1));
+        assert!(iter.open());
+        assert_eq!(iter.key(), Some(3));
+        assert!(iter.open());
+        assert_eq!(iter.key(), Some(4));
+        assert_eq!(iter.next(), Some(5));
+        assert_eq!(iter.next(), None);
+        assert!(iter.up());
+        assert_eq!(iter.key(), Some(3));
+        assert_eq!(iter.next(), Some(4));
+        assert!(iter.open());
+        assert!(iter.seek(7));
+        assert_eq!(iter.key(), Some(8));
+        assert!(!iter.seek(10));
+        assert!(iter.at_end());
+        assert!(iter.up());
+        assert!(iter.seek(5));
+        assert_eq!(iter.key(), Some(5));
+        assert!(iter.up());
+        assert_eq!(iter.next(), Some(3));
+        assert!(iter.open());
+        assert_eq!(iter.key(), Some(5));
+        assert!(iter.open());
+        assert_eq!(iter.key(), Some(2));
+        assert!(!iter.open());
+    }
+
+    #[test]
+    fn seek_gallops_over_long_runs() {
+        let array =
+            SortedTupleArray::from_tuples(2.into(), (0..1000).map(|i| vec![i / 100, i]).collect());
+        let mut iter = array.trie_iter();
+        assert!(iter.open());
+        assert!(iter.seek(7));
+        assert_eq!(iter.key(), Some(7));
+        assert!(iter.open());
+        assert_eq!(iter.key(), Some(700));
+        assert!(iter.seek(777));
+        assert_eq!(iter.key(), Some(777));
+        assert!(!iter.seek(800));
+        assert!(iter.up());
+        assert_eq!(iter.next(), Some(8));
+    }
+
+    #[test]
+    fn test_project() {
+        let array =
+            SortedTupleArray::from_tuples(3.into(), vec![vec![1, 2, 3], vec![4, 5, 6], vec![
+                7, 8, 9,
+            ]]);
+        let projected = array.project(vec![2, 0]);
+        assert_eq!(projected.header().arity(), 2);
+        let tuples: Vec<Vec<usize>> = projected.trie_iter().into_iter().collect();
+        assert_eq!(tuples, vec![vec![3, 1], vec![6, 4], vec![9, 7]]);
+    }
+}
@@ -0,0 +1,146 @@
+use {
+    super::implementation::SortedTupleArray,
+    crate::relation::Relation,
+    kermit_derive::IntoTrieIter,
+    kermit_iters::{LinearIterator, TrieIterable, TrieIterator, TrieIteratorWrapper},
+};
+
+/// The rows sharing the current prefix at one trie depth.
+#[derive(Clone, Copy)]
+struct Frame {
+    /// First row of the current key's run.
+    pos: usize,
+    /// One past the last row sharing the parent prefix.
+    end: usize,
+}
+
+/// Iterator over a [`SortedTupleArray`] that recovers the trie view from the
+/// sorted rows.
+///
+/// At depth `d` (1-based) the rows sharing the bound prefix form a contiguous
+/// range; within it, column `d - 1` is sorted ascending and each distinct key
+/// occupies a contiguous run. A [`Frame`] per open depth records the first
+/// row of the current key's run and the end of the enclosing range. `next`
+/// and `seek` gallop forward from the current run — doubling the step until
+/// they overshoot, then binary-searching the last step — so a short move
+/// costs a few comparisons and a long one stays logarithmic.
+#[derive(IntoTrieIter)]
+pub struct SortedTupleArrayIter<'a> {
+    /// One frame per open depth; empty at the root.
+    frames: Vec<Frame>,
+    /// The array being iterated.
+    array: &'a SortedTupleArray,
+}
+
+impl<'a> SortedTupleArrayIter<'a> {
+    /// Creates a new iterator positioned at the root. Call
+    /// [`open`](TrieIterator::open) to descend to the first column.
+    pub fn new(array: &'a SortedTupleArray) -> Self {
+        SortedTupleArrayIter {
+            frames: Vec::with_capacity(array.header().arity()),
+            array,
+        }
+    }
+
+    /// Returns the first row in `lo..end` whose value in `column` does not
+    /// satisfy `pred`, assuming `pred` holds for a prefix of that range.
+    fn gallop(
+        &self, mut lo: usize, end: usize, column: usize, pred: impl Fn(usize) -> bool,
+    ) -> usize {
+        let mut step = 1;
+        let mut hi = lo;
+        while hi < end && pred(self.array.value(hi, column)) {
+            lo = hi + 1;
+            hi = lo + step;
+            step *= 2;
+        }
+        let mut hi = hi.min(end);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if pred(self.array.value(mid, column)) {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+
+    /// Returns one past the last row of the current key's run.
+    fn run_end(&self) -> usize {
+        let frame = *self.frames.last().expect("iterator is at the root");
+        let column = self.frames.len() - 1;
+        let key = self.array.value(frame.pos, column);
+        self.gallop(frame.pos, frame.end, column, |k| k == key)
+    }
+}
+
+impl LinearIterator for SortedTupleArrayIter<'_> {
+    fn key(&self) -> Option<usize> {
+        let frame = self.frames.last()?;
+        (frame.pos < frame.end).then(|| self.array.value(frame.pos, self.frames.len() - 1))
+    }
+
+    fn next(&mut self) -> Option<usize> {
+        if self.at_end() {
+            return None;
+        }
+        let pos = self.run_end();
+        self.frames.last_mut().unwrap().pos = pos;
+        self.key()
+    }
+
+    fn seek(&mut self, seek_key: usize) -> bool {
+        if self.at_end() {
+            return false;
+        }
+        let frame = *self.frames.last().unwrap();
+        let column = self.frames.len() - 1;
+        let pos = self.gallop(frame.pos, frame.end, column, |k| k < seek_key);
+        self.frames.last_mut().unwrap().pos = pos;
+        !self.at_end()
+    }
+
+    fn at_end(&self) -> bool {
+        match self.frames.last() {
+            | Some(frame) => frame.pos >= frame.end,
+            | None => true,
+        }
+    }
+}
+
+impl TrieIterator for SortedTupleArrayIter<'_> {
+    fn open(&mut self) -> bool {
+        if self.frames.len() == self.array.header().arity() {
+            return false;
+        }
+        let frame = if self.frames.is_empty() {
+            if self.array.is_empty() {
+                return false;
+            }
+            Frame {
+                pos: 0,
+                end: self.array.len(),
+            }
+        } else {
+            if self.at_end() {
+                return false;
+            }
+            Frame {
+                pos: self.frames.last().unwrap().pos,
+                end: self.run_end(),
+            }
+        };
+        self.frames.push(frame);
+        true
+    }
+
+    fn up(&mut self) -> bool { self.frames.pop().is_some() }
+}
+
+/// Implementation of the `TrieIterable` trait for `SortedTupleArray`.
+impl TrieIterable for SortedTupleArray {
+    fn trie_iter(&self) -> impl TrieIterator + IntoIterator<Item = Vec<usize>> {
+        SortedTupleArrayIter::new(self)
+    }
+}
@@ -34,6 +34,27 @@ fn insert_into_children(children: &mut Vec<TrieNode>, tuple: Vec<usize>) -> bool
     }
 }
 
+/// Remove a tuple from a sorted list of children nodes, pruning every node
+/// whose children it leaves empty.
+fn remove_from_children(children: &mut Vec<TrieNode>, tuple: &[usize]) -> bool {
+    let Some((&key, rest)) = tuple.split_first() else {
+        return false;
+    };
+    let Ok(pos) = children.binary_search_by(|node| node.key().cmp(&key)) else {
+        return false;
+    };
+    if !rest.is_empty() {
+        if !remove_from_children(children[pos].children_mut(), rest) {
+            return false;
+        }
+        if !children[pos].children().is_empty() {
+            return true;
+        }
+    }
+    children.remove(pos);
+    true
+}
+
 /// A node in the pointer-based trie.
 ///
 /// Each node stores a single `usize` key and owns a sorted list of child nodes.
@@ -188,6 +209,18 @@ impl Relation for TreeTrie {
         }
         true
     }
+
+    /// Removes a single tuple, dropping every node left without children.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tuple.len()` does not match the arity of the relation.
+    fn remove(&mut self, tuple: &[usize]) -> bool {
+        if tuple.len() != self.header().arity() {
+            panic!("Arity doesn't match.");
+        }
+        remove_from_children(&mut self.children, tuple)
+    }
 }
 
 impl JoinIterable for TreeTrie {}
@@ -235,6 +268,20 @@ impl crate::heap_size::HeapSize for TreeTrie {
     }
 }
 
+impl crate::trie_stats::TrieStats for TreeTrie {
+    fn level_sizes(&self) -> Vec<usize> {
+        let mut sizes = vec![0; self.header().arity()];
+        let mut level: Vec<&TrieNode> = self.children().iter().collect();
+        let mut depth = 0;
+        while !level.is_empty() && depth < sizes.len() {
+            sizes[depth] = level.len();
+            level = level.iter().flat_map(|n| n.children().iter()).collect();
+            depth += 1;
+        }
+        sizes
+    }
+}
+
 #[cfg(test)]
 mod heap_size_tests {
     use {
@@ -1,13 +1,22 @@
 //! Data structures for Kermit's relational algebra engine.
 //!
-//! Provides two trie-based relation implementations:
+//! Provides six relation implementations:
 //!
 //! - [`TreeTrie`]: A pointer-based trie where each node owns its children.
 //!   Simple and cache-friendly for small relations.
 //! - [`ColumnTrie`]: A column-oriented (flattened) trie that stores each level
 //!   in parallel `data`/`interval` arrays. More compact for large relations.
+//! - [`SortedTupleArray`]: One lexicographically sorted, row-major array of
+//!   tuples, navigated as a trie by galloping search. The classic baseline.
+//! - [`CompressedColumnTrie`]: A [`ColumnTrie`] whose layers are delta-encoded
+//!   and bit-packed, trading access speed for space.
+//! - [`HashTrie`]: A trie whose levels are hash maps, probed by key through
+//!   [`ProbeIterable`](kermit_iters::ProbeIterable) and sorted lazily for
+//!   ordered iteration.
+//! - [`BitmapTrie`]: A [`ColumnTrie`] that stores each layer dense enough to
+//!   save space as one bitmap per sibling group, seeking by next set bit.
 //!
-//! Both implement the [`Relation`] and
+//! All implement the [`Relation`] and
 //! [`TrieIterable`](kermit_iters::TrieIterable) traits, making them
 //! interchangeable in join algorithms.
 #![deny(missing_docs)]
@@ -16,10 +25,15 @@ mod ds;
 mod heap_size;
 mod relation;
 mod shared;
+mod trie_stats;
 
 // Re-export IndexStructure for external crates (CLI) to reference directly
 pub use {
-    ds::{ColumnTrie, IndexStructure, TreeTrie},
+    ds::{
+        BitmapTrie, ColumnTrie, CompressedColumnTrie, HashTrie, IndexStructure, SortedTupleArray,
+        TreeTrie,
+    },
     heap_size::HeapSize,
     relation::{ModelType, Projectable, Relation, RelationError, RelationFileExt, RelationHeader},
+    trie_stats::TrieStats,
 };
@@ -1,12 +1,13 @@
 //! This module defines the `Relation` trait and file reading extensions.
 use {
+    crate::ds::ColumnTrie,
     arrow::array::AsArray,
-    kermit_iters::JoinIterable,
+    kermit_iters::{JoinIterable, TrieIterable},
     parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder,
     std::{fmt, fs::File, path::Path},
 };
 
-/// Error type for relation file operations (CSV and Parquet).
+/// Error type for relation file operations (CSV, Parquet and `.ktrie`).
 #[derive(Debug)]
 pub enum RelationError {
     /// A CSV library error.
@@ -188,6 +189,36 @@ pub trait Relation: JoinIterable + Projectable {
     /// Creates a new relation with the specified arity and given tuples.
     fn from_tuples(header: RelationHeader, tuples: Vec<Vec<usize>>) -> Self;
 
+    /// Creates a new relation from tuples already sorted ascending
+    /// lexicographically; duplicates are allowed.
+    ///
+    /// Structures that can bulk-load sorted input override this to skip the
+    /// sort in [`from_tuples`](Self::from_tuples). The default simply
+    /// delegates to it.
+    ///
+    /// # Panics
+    ///
+    /// Implementations may panic if `tuples` is not sorted.
+    fn from_sorted_tuples(header: RelationHeader, tuples: Vec<Vec<usize>>) -> Self
+    where
+        Self: Sized,
+    {
+        Self::from_tuples(header, tuples)
+    }
+
+    /// Creates a new relation holding the tuples of `trie`, with its header.
+    ///
+    /// Used to load `.ktrie` indexes. The default bulk-loads the trie's
+    /// tuples, which come out sorted; structures that can reuse the trie's
+    /// layers override it.
+    fn from_column_trie(trie: ColumnTrie) -> Self
+    where
+        Self: Sized,
+    {
+        let tuples = trie.trie_iter().into_iter().collect();
+        Self::from_sorted_tuples(trie.header().clone(), tuples)
+    }
+
     /// Inserts a tuple into the relation, returning `true` if successful and
     /// `false` if otherwise.
     fn insert(&mut self, tuple: Vec<usize>) -> bool;
@@ -195,13 +226,35 @@ pub trait Relation: JoinIterable + Projectable {
     /// Inserts multiple tuples into the relation, returning `true` if
     /// successful and `false` if otherwise.
     fn insert_all(&mut self, tuples: Vec<Vec<usize>>) -> bool;
+
+    /// Removes a tuple from the relation, returning `true` if it was present
+    /// and `false` if otherwise.
+    ///
+    /// Prefixes left without any tuple are removed with it, so the trie
+    /// view never exposes a key with no children below the last level.
+    /// Updating a tuple is a `remove` followed by an
+    /// [`insert`](Self::insert).
+    fn remove(&mut self, tuple: &[usize]) -> bool;
+
+    /// Removes multiple tuples from the relation, returning `true` if any of
+    /// them was present.
+    ///
+    /// The default removes one tuple at a time. Structures that can rebuild
+    /// in a single pass override it.
+    fn remove_all(&mut self, tuples: Vec<Vec<usize>>) -> bool {
+        let mut removed = false;
+        for tuple in tuples {
+            removed |= self.remove(&tuple);
+        }
+        removed
+    }
 }
 
 /// Extension trait for `Relation` to add file reading capabilities.
 ///
 /// Blanket-implemented for every `Relation`, so any type implementing
-/// [`Relation`] automatically gains [`from_csv`](Self::from_csv) and
-/// [`from_parquet`](Self::from_parquet).
+/// [`Relation`] automatically gains [`from_csv`](Self::from_csv),
+/// [`from_parquet`](Self::from_parquet) and [`from_ktrie`](Self::from_ktrie).
 pub trait RelationFileExt: Relation {
     /// Creates a new relation from a Parquet file.
     ///
@@ -239,6 +292,21 @@ pub trait RelationFileExt: Relation {
     fn from_csv<P: AsRef<Path>>(filepath: P) -> Result<Self, RelationError>
     where
         Self: Sized;
+
+    /// Loads a relation from a `.ktrie` index written by
+    /// [`ColumnTrie::write_ktrie`].
+    ///
+    /// A [`ColumnTrie`] maps the file and uses its layers in place (see
+    /// [`ColumnTrie::open_ktrie`]); any other relation is built from the
+    /// mapped trie by [`Relation::from_column_trie`]. The relation keeps the
+    /// name and attributes stored in the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`RelationError`] as [`ColumnTrie::open_ktrie`] does.
+    fn from_ktrie<P: AsRef<Path>>(filepath: P) -> Result<Self, RelationError>
+    where
+        Self: Sized;
 }
 
 /// Blanket implementation of `RelationFileExt` for any type that
@@ -349,8 +417,16 @@ where
             }
         }
 
-        // Use from_tuples for efficient construction (sorts before insertion)
-        Ok(R::from_tuples(header, tuples))
+        // Files written in sorted order (e.g. by kermit-rdf) skip the sort.
+        if tuples.is_sorted() {
+            Ok(R::from_sorted_tuples(header, tuples))
+        } else {
+            Ok(R::from_tuples(header, tuples))
+        }
+    }
+
+    fn from_ktrie<P: AsRef<Path>>(filepath: P) -> Result<Self, RelationError> {
+        Ok(R::from_column_trie(ColumnTrie::open_ktrie(filepath)?))
     }
 }
 
@@ -0,0 +1,11 @@
+/// Trait for reading cheap structural statistics off a trie-shaped relation.
+///
+/// Used by cost-based join planning (e.g. greedy variable ordering) to
+/// estimate how many distinct keys each attribute contributes.
+pub trait TrieStats {
+    /// Returns the number of trie nodes at each depth, in stored column
+    /// order. Entry `i` counts the distinct prefixes of length `i + 1`, so
+    /// the first entry is the number of distinct keys in the first column and
+    /// the last entry is the relation's cardinality.
+    fn level_sizes(&self) -> Vec<usize>;
+}
@@ -33,6 +33,53 @@ macro_rules! relation_construction_tests {
             $crate::relation_construction_test!(ternary, $relation_type, [vec![1, 2, 3], vec![
                 4, 5, 6
             ]]);
+
+            $crate::relation_construction_test!(shared_later_prefix, $relation_type, [
+                vec![1, 2],
+                vec![2, 3],
+                vec![2, 4],
+                vec![3, 1],
+                vec![3, 5]
+            ]);
+
+            #[test]
+            fn unsorted_input_with_duplicates() {
+                use {kermit_ds::Relation, kermit_iters::TrieIterable};
+                let relation = $relation_type::from_tuples(2.into(), vec![
+                    vec![3, 1],
+                    vec![1, 2],
+                    vec![3, 1],
+                    vec![1, 1],
+                ]);
+                let res = relation.trie_iter().into_iter().collect::<Vec<_>>();
+                assert_eq!(res, vec![vec![1, 1], vec![1, 2], vec![3, 1]]);
+            }
+
+            #[test]
+            fn from_sorted_tuples_matches_from_tuples() {
+                use {kermit_ds::Relation, kermit_iters::TrieIterable};
+                let tuples = vec![vec![1, 2, 3], vec![1, 2, 3], vec![1, 4, 0], vec![
+                    2, 0, 0,
+                ]];
+                let sorted = $relation_type::from_sorted_tuples(3.into(), tuples.clone());
+                let unsorted = $relation_type::from_tuples(3.into(), tuples);
+                assert_eq!(
+                    sorted.trie_iter().into_iter().collect::<Vec<_>>(),
+                    unsorted.trie_iter().into_iter().collect::<Vec<_>>()
+                );
+            }
+
+            #[test]
+            fn insert_all_merges_with_existing_tuples() {
+                use {kermit_ds::Relation, kermit_iters::TrieIterable};
+                let mut relation =
+                    $relation_type::from_tuples(2.into(), vec![vec![1, 5], vec![4, 4]]);
+                assert!(relation.insert_all(vec![vec![4, 1], vec![1, 5], vec![0, 9]]));
+                let res = relation.trie_iter().into_iter().collect::<Vec<_>>();
+                assert_eq!(res, vec![vec![0, 9], vec![1, 5], vec![4, 1], vec![
+                    4, 4
+                ]]);
+            }
         }
     };
 }
@@ -484,6 +531,165 @@ macro_rules! trie_seek_tests {
     };
 }
 
+#[macro_export]
+macro_rules! trie_stats_tests {
+    ($relation_type:ident) => {
+        mod trie_stats {
+
+            use {
+                super::*,
+                kermit_ds::{Relation, TrieStats},
+            };
+
+            #[test]
+            fn empty() {
+                let relation = $relation_type::from_tuples(2.into(), vec![]);
+                assert_eq!(relation.level_sizes(), vec![0, 0]);
+            }
+
+            #[test]
+            fn counts_distinct_prefixes_per_level() {
+                let relation = $relation_type::from_tuples(3.into(), vec![
+                    vec![1, 2, 3],
+                    vec![1, 2, 4],
+                    vec![1, 5, 6],
+                    vec![2, 2, 3],
+                ]);
+                assert_eq!(relation.level_sizes(), vec![2, 3, 4]);
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! relation_mutation_tests {
+    ($relation_type:ident) => {
+        mod mutation {
+
+            use {
+                super::*,
+                kermit_ds::{Relation, TrieStats},
+                kermit_iters::{LinearIterator, TrieIterable, TrieIterator},
+                std::collections::BTreeSet,
+            };
+
+            fn tuples(relation: &$relation_type) -> Vec<Vec<usize>> {
+                relation.trie_iter().into_iter().collect()
+            }
+
+            #[test]
+            fn remove_reports_whether_the_tuple_was_present() {
+                let mut relation =
+                    $relation_type::from_tuples(2.into(), vec![vec![1, 2], vec![1, 3], vec![2, 4]]);
+                assert!(relation.remove(&[1, 3]));
+                assert!(!relation.remove(&[1, 3]));
+                assert!(!relation.remove(&[1, 4]));
+                assert!(!relation.remove(&[7, 2]));
+                assert_eq!(tuples(&relation), vec![vec![1, 2], vec![2, 4]]);
+            }
+
+            #[test]
+            fn remove_prunes_emptied_prefixes() {
+                let mut relation = $relation_type::from_tuples(3.into(), vec![
+                    vec![1, 2, 3],
+                    vec![1, 4, 5],
+                    vec![2, 0, 0],
+                    vec![2, 0, 1],
+                ]);
+                assert!(relation.remove(&[1, 2, 3]));
+                assert_eq!(relation.level_sizes(), vec![2, 2, 3]);
+                assert!(relation.remove(&[1, 4, 5]));
+                assert_eq!(relation.level_sizes(), vec![1, 1, 2]);
+
+                {
+                    let mut iter = relation.trie_iter();
+                    assert!(iter.open());
+                    assert_eq!(iter.key(), Some(2));
+                    assert!(iter.next().is_none());
+                    assert!(iter.up());
+                }
+
+                assert!(relation.remove(&[2, 0, 1]));
+                assert!(relation.remove(&[2, 0, 0]));
+                assert_eq!(relation.level_sizes(), vec![0, 0, 0]);
+                assert!(!relation.trie_iter().open());
+            }
+
+            #[test]
+            fn removed_tuples_can_be_reinserted() {
+                let mut relation = $relation_type::from_tuples(2.into(), vec![vec![3, 1]]);
+                assert!(relation.remove(&[3, 1]));
+                assert!(tuples(&relation).is_empty());
+                relation.insert(vec![5, 2]);
+                relation.insert(vec![3, 1]);
+                assert_eq!(tuples(&relation), vec![vec![3, 1], vec![5, 2]]);
+            }
+
+            #[test]
+            fn remove_all_reports_whether_anything_was_removed() {
+                let mut relation = $relation_type::from_tuples(2.into(), vec![
+                    vec![0, 9],
+                    vec![1, 5],
+                    vec![4, 1],
+                    vec![4, 4],
+                ]);
+                assert!(!relation.remove_all(vec![vec![2, 2], vec![4, 2]]));
+                assert!(relation.remove_all(vec![vec![4, 4], vec![9, 9], vec![0, 9], vec![4, 4]]));
+                assert_eq!(tuples(&relation), vec![vec![1, 5], vec![4, 1]]);
+                assert!(!relation.remove_all(vec![]));
+            }
+
+            #[test]
+            fn interleaved_inserts_and_removes_match_a_set() {
+                let mut relation = $relation_type::new(3.into());
+                let mut model = BTreeSet::new();
+                // A fixed linear congruential generator keeps the
+                // interleaving reproducible across runs.
+                let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+                let mut next = |bound: u64| {
+                    state = state
+                        .wrapping_mul(6_364_136_223_846_793_005)
+                        .wrapping_add(1_442_695_040_888_963_407);
+                    ((state >> 33) % bound) as usize
+                };
+                for step in 0..600 {
+                    let tuple = vec![next(4), next(3), next(5)];
+                    match next(5) {
+                        | 0 | 1 => {
+                            relation.insert(tuple.clone());
+                            model.insert(tuple);
+                        },
+                        | 2 | 3 => {
+                            assert_eq!(
+                                relation.remove(&tuple),
+                                model.remove(&tuple),
+                                "step {step}"
+                            );
+                        },
+                        | _ => {
+                            let batch = vec![tuple.clone(), vec![tuple[0], next(3), next(5)]];
+                            let present = batch.iter().any(|t| model.contains(t));
+                            assert_eq!(
+                                relation.remove_all(batch.clone()),
+                                present,
+                                "step {step}"
+                            );
+                            for t in &batch {
+                                model.remove(t);
+                            }
+                        },
+                    }
+                    assert_eq!(
+                        tuples(&relation),
+                        model.iter().cloned().collect::<Vec<_>>(),
+                        "step {step}"
+                    );
+                }
+            }
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! relation_trie_test_suite {
     (
@@ -506,6 +712,10 @@ macro_rules! relation_trie_test_suite {
 
                     $crate::trie_seek_tests!($relation_type);
 
+                    $crate::trie_stats_tests!($relation_type);
+
+                    $crate::relation_mutation_tests!($relation_type);
+
                 }
             }
         )+
@@ -3,3 +3,11 @@ mod common;
 parquet_test_suite!(TreeTrie);
 
 parquet_test_suite!(ColumnTrie);
+
+parquet_test_suite!(SortedTupleArray);
+
+parquet_test_suite!(CompressedColumnTrie);
+
+parquet_test_suite!(HashTrie);
+
+parquet_test_suite!(BitmapTrie);
@@ -1,6 +1,16 @@
-use kermit_ds::{ColumnTrie, TreeTrie};
+use kermit_ds::{
+    BitmapTrie, ColumnTrie, CompressedColumnTrie, HashTrie, SortedTupleArray, TreeTrie,
+};
 mod common;
 
 relation_trie_test_suite!(TreeTrie);
 
 relation_trie_test_suite!(ColumnTrie);
+
+relation_trie_test_suite!(SortedTupleArray);
+
+relation_trie_test_suite!(CompressedColumnTrie);
+
+relation_trie_test_suite!(HashTrie);
+
+relation_trie_test_suite!(BitmapTrie);
@@ -4,7 +4,8 @@
 //! algorithms build upon: [`LinearIterator`] for flat sorted sequences and
 //! [`TrieIterator`] for hierarchical trie traversal. [`TrieIteratorWrapper`]
 //! adapts any `TrieIterator` into a standard `Iterator<Item = Vec<usize>>` that
-//! yields complete tuples via depth-first traversal.
+//! yields complete tuples via depth-first traversal. [`ProbeIterator`] extends
+//! `TrieIterator` with point lookups for structures whose levels are hashed.
 //!
 //! All keys are `usize` (dictionary-encoded); see [`Key`]. The marker trait
 //! [`JoinIterable`] unifies data structures that may participate in joins.
@@ -37,11 +38,13 @@
 mod joinable;
 mod key_type;
 mod linear;
+mod probe;
 mod trie;
 
 pub use {
     joinable::JoinIterable,
     key_type::Key,
     linear::{LinearIterable, LinearIterator},
+    probe::{ProbeIterable, ProbeIterator},
     trie::{TrieIterable, TrieIterator, TrieIteratorWrapper},
 };
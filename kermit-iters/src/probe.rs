@@ -0,0 +1,40 @@
+//! This module defines the `ProbeIterator` trait, point lookups for trie
+//! iterators over hashed levels.
+
+use crate::trie::{TrieIterable, TrieIterator};
+
+/// The `ProbeIterator` trait, designed for trie iterators whose levels answer
+/// membership queries without scanning, e.g. levels stored as hash maps.
+///
+/// A hash-based Generic Join or Free Join binds a variable by iterating the
+/// keys of one participant and probing every other participant for each of
+/// them. [`seek`](crate::LinearIterator::seek) only moves forwards and
+/// expects sorted keys; [`lookup`](Self::lookup) may jump anywhere among the
+/// siblings, so the driving keys can come in any order.
+pub trait ProbeIterator: TrieIterator {
+    /// Returns `true` iff `key` is among the keys at the iterator's current
+    /// depth. Does not move the iterator.
+    ///
+    /// Returns `false` before the first [`open`](TrieIterator::open).
+    fn contains(&self, key: usize) -> bool;
+
+    /// Positions the iterator at `key` among the keys at its current depth
+    /// and returns `true`, or moves it to the end and returns `false` if no
+    /// such key exists.
+    ///
+    /// Unlike [`seek`](crate::LinearIterator::seek), the key may be below
+    /// the current one. Once found, the iterator behaves as if it had
+    /// stepped there: [`open`](TrieIterator::open) descends into its
+    /// children and [`next`](crate::LinearIterator::next) moves on to the
+    /// next larger key.
+    fn lookup(&mut self, key: usize) -> bool;
+}
+
+/// The `ProbeIterable` trait is used to specify types that can be iterated
+/// through the `ProbeIterator` interface, and as such used in algorithms that
+/// probe relations rather than intersect them in order.
+pub trait ProbeIterable: TrieIterable {
+    /// Returns a probe iterator over this structure that also implements
+    /// `IntoIterator`, like [`trie_iter`](TrieIterable::trie_iter).
+    fn probe_iter(&self) -> impl ProbeIterator + IntoIterator<Item = Vec<usize>>;
+}
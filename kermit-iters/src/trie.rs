@@ -41,7 +41,9 @@ pub trait TrieIterable: JoinIterable {
 /// path as a `Vec<usize>`. An internal `stack` tracks the keys along the
 /// current path. When `expected_arity` is set, tuples shorter than that
 /// length are skipped (useful for joins that produce partial paths at
-/// intermediate depths).
+/// intermediate depths). When `projection` is set, only the first
+/// `projection` keys of each path are yielded, and each distinct prefix is
+/// yielded once.
 pub struct TrieIteratorWrapper<IT>
 where
     IT: TrieIterator,
@@ -49,6 +51,8 @@ where
     iter: IT,
     stack: Vec<usize>,
     expected_arity: Option<usize>,
+    projection: Option<usize>,
+    exhausted: bool,
 }
 
 impl<IT> TrieIteratorWrapper<IT>
@@ -62,6 +66,8 @@ where
             iter,
             stack: vec![],
             expected_arity: None,
+            projection: None,
+            exhausted: false,
         }
     }
 
@@ -72,6 +78,34 @@ where
             iter,
             stack: vec![],
             expected_arity: Some(arity),
+            projection: None,
+            exhausted: false,
+        }
+    }
+
+    /// Creates a new wrapper that yields the distinct `prefix`-key prefixes of
+    /// paths with exactly `arity` keys.
+    ///
+    /// Once a full-length path is found below a prefix, the remainder of that
+    /// subtree is skipped: the wrapper ascends back to depth `prefix` and
+    /// advances there. Since the traversal is depth-first over sorted keys,
+    /// equal prefixes are contiguous and every prefix is yielded exactly once,
+    /// in sorted order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix > arity`.
+    pub fn with_projection(iter: IT, arity: usize, prefix: usize) -> Self {
+        assert!(
+            prefix <= arity,
+            "projection prefix {prefix} exceeds arity {arity}"
+        );
+        TrieIteratorWrapper {
+            iter,
+            stack: vec![],
+            expected_arity: Some(arity),
+            projection: Some(prefix),
+            exhausted: false,
         }
     }
 
@@ -115,6 +149,9 @@ where
     /// reached, then descends again via `down` until the next leaf. Returns
     /// `None` when the entire trie has been exhausted.
     fn next(&mut self) -> Option<Vec<usize>> {
+        if self.exhausted {
+            return None;
+        }
         loop {
             // Phase 1: Backtrack — advance to the next sibling, moving up
             // through ancestors until one has a remaining sibling.
@@ -141,6 +178,18 @@ where
                 }
             }
 
+            // Phase 4: Project — emit the prefix and abandon the rest of its
+            // subtree, so the next call advances at the prefix's last level.
+            if let Some(prefix) = self.projection {
+                let tuple = self.stack[..prefix].to_vec();
+                while self.stack.len() > prefix {
+                    self.up();
+                }
+                // An empty prefix has a single witness; nothing to advance.
+                self.exhausted = self.stack.is_empty();
+                return Some(tuple);
+            }
+
             return Some(self.stack.clone());
         }
     }
@@ -412,6 +461,52 @@ mod tests {
         assert_eq!(result, vec![vec![1, 3], vec![1, 4], vec![2]]);
     }
 
+    #[test]
+    fn with_projection_deduplicates_prefixes() {
+        // Every root-to-leaf path has length 3; projecting onto the first two
+        // keys collapses (1,2,5)/(1,2,6) into a single (1,2).
+        let trie = MockTrie {
+            roots: vec![
+                node(1, vec![
+                    node(2, vec![leaf(5), leaf(6)]),
+                    node(3, vec![leaf(7)]),
+                ]),
+                node(4, vec![node(8, vec![leaf(9), leaf(10)])]),
+            ],
+        };
+        let iter = MockTrieIter::new(&trie);
+        let wrapper = TrieIteratorWrapper::with_projection(iter, 3, 2);
+        let result: Vec<Vec<usize>> = wrapper.collect();
+        assert_eq!(result, vec![vec![1, 2], vec![1, 3], vec![4, 8]]);
+    }
+
+    #[test]
+    fn with_projection_skips_prefixes_without_witness() {
+        // Root 2 only reaches depth 2, so it has no full-length witness and
+        // must not appear in the projection.
+        let trie = MockTrie {
+            roots: vec![
+                node(1, vec![node(3, vec![leaf(4), leaf(5)])]),
+                node(2, vec![leaf(6)]),
+            ],
+        };
+        let iter = MockTrieIter::new(&trie);
+        let wrapper = TrieIteratorWrapper::with_projection(iter, 3, 1);
+        let result: Vec<Vec<usize>> = wrapper.collect();
+        assert_eq!(result, vec![vec![1]]);
+    }
+
+    #[test]
+    fn with_projection_empty_prefix_yields_once() {
+        let trie = MockTrie {
+            roots: vec![node(1, vec![leaf(2)]), node(3, vec![leaf(4)])],
+        };
+        let iter = MockTrieIter::new(&trie);
+        let wrapper = TrieIteratorWrapper::with_projection(iter, 2, 0);
+        let result: Vec<Vec<usize>> = wrapper.collect();
+        assert_eq!(result, vec![Vec::<usize>::new()]);
+    }
+
     #[test]
     fn exhaustion_returns_none() {
         let trie = MockTrie {
@@ -0,0 +1,243 @@
+//! Canonical forms and fingerprints of [`JoinQuery`]s.
+//!
+//! Generated workloads such as WatDiv's stress templates or LUBM repeat the
+//! same query under different variable names or with its atoms shuffled.
+//! [`JoinQuery::canonical`] rewrites a query into a normal form that erases
+//! those differences, and [`JoinQuery::fingerprint`] hashes that form.
+
+use {
+    crate::{ComparisonOp, JoinQuery, Operand, Predicate, Term},
+    std::collections::HashMap,
+};
+
+/// FNV-1a offset basis and prime; see <http://www.isthe.com/chongo/tech/comp/fnv/>.
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+impl JoinQuery {
+    /// Returns the query in canonical form.
+    ///
+    /// Variables are renamed `V0`, `V1`, … in order of first occurrence,
+    /// reading the head, then the body, then the negated predicates, then
+    /// the comparisons. Body predicates, negated predicates and comparisons
+    /// are sorted by their written form and deduplicated, `>` and `>=` are
+    /// turned around into `<` and `<=`, and the operands of `=` and `!=` are
+    /// ordered. The head keeps its predicate name and term order, since both
+    /// are visible in the result.
+    ///
+    /// Renaming depends on atom order and sorting on names, so the two
+    /// alternate until neither changes the query. Queries equal up to
+    /// variable names, atom order and comparison orientation almost always
+    /// reach the same form; a query whose atoms can only be told apart by
+    /// variables outside the head may occasionally settle on a different,
+    /// equally valid one. Two queries with the same canonical form are always
+    /// equivalent.
+    pub fn canonical(&self) -> JoinQuery {
+        let mut query = self.clone();
+        for cmp in &mut query.comparisons {
+            if matches!(cmp.op, ComparisonOp::Gt | ComparisonOp::Ge) {
+                std::mem::swap(&mut cmp.left, &mut cmp.right);
+                cmp.op = cmp.op.flip();
+            }
+        }
+
+        // Start from an order that renaming cannot change.
+        let head_vars = first_occurrences(std::slice::from_ref(&query.head));
+        query
+            .body
+            .sort_by_cached_key(|pred| shape(pred, &head_vars));
+        query
+            .negated
+            .sort_by_cached_key(|pred| shape(pred, &head_vars));
+
+        for _ in 0..=query.body.len() + query.negated.len() {
+            let before = query.clone();
+            rename_variables(&mut query);
+            for cmp in &mut query.comparisons {
+                if matches!(cmp.op, ComparisonOp::Eq | ComparisonOp::Ne)
+                    && cmp.left.to_string() > cmp.right.to_string()
+                {
+                    std::mem::swap(&mut cmp.left, &mut cmp.right);
+                }
+            }
+            query.body.sort_by_cached_key(ToString::to_string);
+            query.body.dedup();
+            query.negated.sort_by_cached_key(ToString::to_string);
+            query.negated.dedup();
+            query.comparisons.sort_by_cached_key(ToString::to_string);
+            query.comparisons.dedup();
+            if query == before {
+                break;
+            }
+        }
+        query
+    }
+
+    /// Returns a 64-bit fingerprint of the query's [canonical
+    /// form](JoinQuery::canonical), ignoring the head predicate's name, so
+    /// that queries differing only in name, variable names or atom order
+    /// share it.
+    ///
+    /// The fingerprint is the FNV-1a hash of the canonical form's written
+    /// form, so it is stable across runs and platforms and can be stored.
+    pub fn fingerprint(&self) -> u64 {
+        let mut canonical = self.canonical();
+        canonical.head.name.clear();
+        canonical
+            .to_string()
+            .bytes()
+            .fold(FNV_OFFSET, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+            })
+    }
+}
+
+/// Returns the variables of `preds`, head aggregates included, in order of
+/// first occurrence.
+fn first_occurrences(preds: &[Predicate]) -> Vec<&str> {
+    let mut vars: Vec<&str> = Vec::new();
+    for term in preds.iter().flat_map(|pred| &pred.terms) {
+        if let Term::Var(var) | Term::Aggregate(_, var) = term {
+            if !vars.contains(&var.as_str()) {
+                vars.push(var);
+            }
+        }
+    }
+    vars
+}
+
+/// Writes `pred` with each head variable replaced by its canonical name and
+/// every other variable by `?`, leaving only what renaming cannot change.
+fn shape(pred: &Predicate, head_vars: &[&str]) -> String {
+    let terms = pred
+        .terms
+        .iter()
+        .map(|term| match term {
+            | Term::Var(var) => match head_vars.iter().position(|v| v == var) {
+                | Some(i) => Term::Var(format!("V{i}")),
+                | None => Term::Var("?".to_string()),
+            },
+            | term => term.clone(),
+        })
+        .collect();
+    Predicate {
+        name: pred.name.clone(),
+        terms,
+    }
+    .to_string()
+}
+
+/// Renames the variables of `query` to `V0`, `V1`, … in order of first
+/// occurrence across the head, body, negated predicates and comparisons.
+fn rename_variables(query: &mut JoinQuery) {
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut rename = |var: &mut String| {
+        let next = names.len();
+        *var = names
+            .entry(std::mem::take(var))
+            .or_insert_with(|| format!("V{next}"))
+            .clone();
+    };
+    let preds = std::iter::once(&mut query.head)
+        .chain(&mut query.body)
+        .chain(&mut query.negated);
+    for term in preds.flat_map(|pred| &mut pred.terms) {
+        if let Term::Var(var) | Term::Aggregate(_, var) = term {
+            rename(var);
+        }
+    }
+    for cmp in &mut query.comparisons {
+        for operand in [&mut cmp.left, &mut cmp.right] {
+            if let Operand::Var(var) = operand {
+                rename(var);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(query: &str) -> String {
+        query.parse::<JoinQuery>().unwrap().canonical().to_string()
+    }
+
+    fn fingerprint(query: &str) -> u64 { query.parse::<JoinQuery>().unwrap().fingerprint() }
+
+    #[test]
+    fn renames_variables_and_sorts_atoms() {
+        assert_eq!(
+            canonical("Q(A, C) :- r(B, C), p(A, B)."),
+            "Q(V0, V1) :- p(V0, V2), r(V2, V1)."
+        );
+        assert_eq!(
+            canonical("Q(X, Z) :- p(X, Y), r(Y, Z)."),
+            canonical("Q(A, C) :- r(B, C), p(A, B).")
+        );
+    }
+
+    #[test]
+    fn isomorphic_triangles_agree() {
+        let triangle = canonical("T(X, Y, Z) :- edge(X, Y), edge(Y, Z), edge(X, Z).");
+        assert_eq!(
+            canonical("T(A, B, C) :- edge(A, C), edge(B, C), edge(A, B)."),
+            triangle
+        );
+        assert_eq!(
+            triangle,
+            "T(V0, V1, V2) :- edge(V0, V1), edge(V0, V2), edge(V1, V2)."
+        );
+    }
+
+    #[test]
+    fn body_only_variables_are_named_by_sorted_position() {
+        assert_eq!(
+            canonical("Q(X) :- knows(X, B), likes(X, A), likes(B, c3)."),
+            canonical("Q(P) :- likes(R, c3), likes(P, S), knows(P, R).")
+        );
+    }
+
+    #[test]
+    fn normalises_literals() {
+        assert_eq!(
+            canonical("Q(X, Y) :- e(X, Y), e(X, Y), Y > X, 3 != X, not f(Y, _)."),
+            "Q(V0, V1) :- e(V0, V1), 3 != V0, V0 < V1, not f(V1, _)."
+        );
+        assert_eq!(
+            canonical("Q(A, B) :- e(A, B), not f(B, _), A != 3, A < B."),
+            canonical("Q(X, Y) :- e(X, Y), Y > X, 3 != X, not f(Y, _).")
+        );
+    }
+
+    #[test]
+    fn canonical_form_is_a_fixpoint_and_parses() {
+        for query in [
+            "deg(X, count(Y)) :- edge(X, Y).",
+            "Q(X) :- p(X, \"a b\"), q(<http://x/y>, X), X <= 4.",
+            "T(X, Y, Z) :- edge(X, Y), edge(Y, Z), edge(X, Z).",
+        ] {
+            let canonical: JoinQuery = query.parse::<JoinQuery>().unwrap().canonical();
+            assert_eq!(canonical.canonical(), canonical, "{query}");
+            assert_eq!(
+                canonical.to_string().parse::<JoinQuery>().unwrap(),
+                canonical
+            );
+        }
+    }
+
+    #[test]
+    fn head_term_order_is_significant() {
+        assert_ne!(
+            fingerprint("Q(X, Y) :- edge(X, Y)."),
+            fingerprint("Q(Y, X) :- edge(X, Y).")
+        );
+    }
+
+    #[test]
+    fn fingerprint_ignores_head_name_and_is_stable() {
+        let fp = fingerprint("Q1(X, Z) :- edge(X, Y), edge(Y, Z).");
+        assert_eq!(fp, fingerprint("path(A, C) :- edge(B, C), edge(A, B)."));
+        assert_eq!(fp, 0x48bd_4bab_c244_c8b1);
+    }
+}
@@ -0,0 +1,121 @@
+//! Syntax errors located in the query or program source.
+
+use {
+    crate::UnsafeNegation,
+    std::{error::Error, fmt},
+    winnow::error::{ContextError, StrContext},
+};
+
+/// Error returned when a query or program fails to parse, pointing at the
+/// offending position of the source text.
+///
+/// Its [`Display`](fmt::Display) names what the parser expected there, or
+/// why it rejected otherwise well-formed input, followed by the source line
+/// and a caret under the error:
+///
+/// ```text
+/// expected `,` or `.` at line 1, column 13
+///     P(X) :- Q(X)
+///                 ^
+/// ```
+#[derive(Debug)]
+pub struct ParseError {
+    /// Byte offset of the error in the source.
+    pub offset: usize,
+    /// Line of the error, starting at 1.
+    pub line: usize,
+    /// Column of the error in characters, starting at 1.
+    pub column: usize,
+    /// The source line holding the error, without its line terminator.
+    pub snippet: String,
+    /// The tokens the parser would have accepted at the error, such as
+    /// `` `.` `` or `variable`, in the order it tried them.
+    pub expected: Vec<String>,
+    /// Why well-formed input was rejected, such as an [`UnsafeNegation`];
+    /// also reachable through [`Error::source`].
+    cause: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl ParseError {
+    /// Locates the error `inner`, raised at byte `offset` of `source`.
+    pub(crate) fn new(source: &str, offset: usize, inner: &ContextError) -> Self {
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let snippet = source[line_start..]
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let mut expected: Vec<String> = Vec::new();
+        for context in inner.context() {
+            if let StrContext::Expected(value) = context {
+                let value = value.to_string();
+                if !expected.contains(&value) {
+                    expected.push(value);
+                }
+            }
+        }
+        // Causes are cloned where their type is known, so callers can still
+        // downcast them, and kept as messages otherwise.
+        let cause = inner
+            .cause()
+            .map(|cause| match cause.downcast_ref::<UnsafeNegation>() {
+                | Some(unsafe_negation) => Box::new(unsafe_negation.clone()) as Box<_>,
+                | None => cause.to_string().into(),
+            });
+        ParseError {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            snippet,
+            expected,
+            cause,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.cause, self.expected.as_slice()) {
+            | (Some(cause), _) => write!(f, "{cause}")?,
+            | (None, []) => write!(f, "invalid syntax")?,
+            | (None, [only]) => write!(f, "expected {only}")?,
+            | (None, [init @ .., last]) => write!(f, "expected {} or {last}", init.join(", "))?,
+        }
+        writeln!(f, " at line {}, column {}", self.line, self.column)?;
+        // Tabs are kept in the caret's indent so it lines up under them.
+        let indent: String = self
+            .snippet
+            .chars()
+            .take(self.column - 1)
+            .map(|c| {
+                if c == '\t' {
+                    '\t'
+                } else {
+                    ' '
+                }
+            })
+            .collect();
+        writeln!(f, "    {}", self.snippet)?;
+        write!(f, "    {indent}^")
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.cause
+            .as_deref()
+            .map(|cause| cause as &(dyn Error + 'static))
+    }
+}
+
+/// Well-formed input the parser rejects, such as a second `.output`
+/// directive; reported as a [`ParseError`]'s cause.
+#[derive(Debug)]
+pub(crate) struct Rejected(pub(crate) &'static str);
+
+impl fmt::Display for Rejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(self.0) }
+}
+
+impl Error for Rejected {}
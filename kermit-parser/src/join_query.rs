@@ -2,7 +2,10 @@
 ///
 /// Variables start with an uppercase letter (e.g. `X`, `Name`), atoms start
 /// with a lowercase letter (e.g. `alice`, `edge`), and `_` is the anonymous
-/// placeholder that matches anything without binding.
+/// placeholder that matches anything without binding. Constants may also be
+/// written as integers (`42`), quoted strings (`"alice"`) or IRIs
+/// (`<http://example.org/alice>`). Rule heads may also hold aggregates such
+/// as `count(Y)`.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Term {
     /// A named variable (e.g. `X`).
@@ -11,6 +14,53 @@ pub enum Term {
     Atom(String),
     /// The anonymous wildcard `_`.
     Placeholder,
+    /// An integer constant (e.g. `42`), matched as a dictionary ID.
+    Int(usize),
+    /// A quoted string (e.g. `"alice"` or `"chat"@fr`) or an IRI (e.g.
+    /// `<http://example.org/alice>`), kept with its quotes or angle brackets
+    /// so that it matches the canonical form of a dictionary entry.
+    Str(String),
+    /// An aggregate over a body variable (e.g. `count(Y)`); only valid in a
+    /// rule head.
+    Aggregate(AggregateFn, String),
+}
+
+impl std::fmt::Display for Term {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            | Term::Var(name) | Term::Atom(name) | Term::Str(name) => f.write_str(name),
+            | Term::Placeholder => f.write_str("_"),
+            | Term::Int(value) => write!(f, "{value}"),
+            | Term::Aggregate(func, var) => write!(f, "{func}({var})"),
+        }
+    }
+}
+
+/// An aggregate function usable in a rule head.
+///
+/// Each aggregate folds the distinct bindings of the rule's body variables
+/// within a group, where groups are formed by the head's other variables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFn {
+    /// `count(V)`: the number of bindings.
+    Count,
+    /// `sum(V)`: the sum of `V` over the bindings.
+    Sum,
+    /// `min(V)`: the least value of `V`.
+    Min,
+    /// `max(V)`: the greatest value of `V`.
+    Max,
+}
+
+impl std::fmt::Display for AggregateFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            | AggregateFn::Count => "count",
+            | AggregateFn::Sum => "sum",
+            | AggregateFn::Min => "min",
+            | AggregateFn::Max => "max",
+        })
+    }
 }
 
 /// A Datalog predicate application, e.g. `edge(X, Y)`.
@@ -22,15 +72,314 @@ pub struct Predicate {
     pub terms: Vec<Term>,
 }
 
+impl std::fmt::Display for Predicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}(", self.name)?;
+        for (i, term) in self.terms.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{term}")?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// A comparison operator usable as a built-in in rule bodies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComparisonOp {
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+    /// `=`
+    Eq,
+    /// `!=`
+    Ne,
+}
+
+impl ComparisonOp {
+    /// Returns whether `left op right` holds.
+    pub fn holds(self, left: usize, right: usize) -> bool {
+        match self {
+            | ComparisonOp::Lt => left < right,
+            | ComparisonOp::Le => left <= right,
+            | ComparisonOp::Gt => left > right,
+            | ComparisonOp::Ge => left >= right,
+            | ComparisonOp::Eq => left == right,
+            | ComparisonOp::Ne => left != right,
+        }
+    }
+
+    /// Returns the operator with its operands swapped, so that `a op b`
+    /// holds exactly when `b op.flip() a` does.
+    pub fn flip(self) -> Self {
+        match self {
+            | ComparisonOp::Lt => ComparisonOp::Gt,
+            | ComparisonOp::Le => ComparisonOp::Ge,
+            | ComparisonOp::Gt => ComparisonOp::Lt,
+            | ComparisonOp::Ge => ComparisonOp::Le,
+            | op @ (ComparisonOp::Eq | ComparisonOp::Ne) => op,
+        }
+    }
+}
+
+impl std::fmt::Display for ComparisonOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            | ComparisonOp::Lt => "<",
+            | ComparisonOp::Le => "<=",
+            | ComparisonOp::Gt => ">",
+            | ComparisonOp::Ge => ">=",
+            | ComparisonOp::Eq => "=",
+            | ComparisonOp::Ne => "!=",
+        })
+    }
+}
+
+/// One side of a [`Comparison`]: a variable or an integer constant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    /// A named variable (e.g. `X`).
+    Var(String),
+    /// An integer constant, compared against the stored key directly.
+    Int(usize),
+}
+
+impl std::fmt::Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            | Operand::Var(name) => f.write_str(name),
+            | Operand::Int(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// A comparison built-in in a rule body, e.g. `X < Y` or `X != 3`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comparison {
+    /// Left-hand operand.
+    pub left: Operand,
+    /// The comparison operator.
+    pub op: ComparisonOp,
+    /// Right-hand operand.
+    pub right: Operand,
+}
+
+impl Comparison {
+    /// Returns the variables the comparison mentions, left first.
+    pub fn vars(&self) -> impl Iterator<Item = &str> {
+        [&self.left, &self.right]
+            .into_iter()
+            .filter_map(|operand| match operand {
+                | Operand::Var(name) => Some(name.as_str()),
+                | Operand::Int(_) => None,
+            })
+    }
+}
+
+impl std::fmt::Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.left, self.op, self.right)
+    }
+}
+
 /// A parsed Datalog join query of the form `Head :- Body1, Body2, ... .`
 ///
 /// For example: `path(X, Z) :- edge(X, Y), edge(Y, Z).`
 ///
-/// Implements [`FromStr`](std::str::FromStr) for parsing from a string.
+/// The body may also contain comparison built-ins between variables and
+/// integer constants, e.g. `Q(X, Y) :- edge(X, Y), X < Y.`, and negated
+/// predicates, e.g. `Q(X) :- author(X), not coauthor(X, _).` Every variable
+/// either mentions must be bound by a positive body predicate.
+///
+/// The head may aggregate body variables, grouping on its remaining
+/// variables: `deg(X, count(Y)) :- edge(X, Y).`
+///
+/// Implements [`FromStr`](std::str::FromStr) for parsing from a string, and
+/// [`Display`](std::fmt::Display) for writing it back: body predicates,
+/// then comparisons, then negated predicates, each group in order, so the
+/// written rule parses to an equal query.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct JoinQuery {
     /// The head predicate defining the output schema.
     pub head: Predicate,
     /// The body predicates to be joined.
     pub body: Vec<Predicate>,
+    /// The comparison built-ins restricting the join, in source order.
+    pub comparisons: Vec<Comparison>,
+    /// The negated body predicates (`not p(…)`), in source order. A binding
+    /// survives only if no tuple of the predicate matches it.
+    pub negated: Vec<Predicate>,
+}
+
+impl JoinQuery {
+    /// Returns `true` if the head holds an aggregate term.
+    pub fn has_aggregates(&self) -> bool {
+        self.head
+            .terms
+            .iter()
+            .any(|t| matches!(t, Term::Aggregate(..)))
+    }
+}
+
+impl std::fmt::Display for JoinQuery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} :- ", self.head)?;
+        let body = self.body.iter().map(|pred| pred.to_string());
+        let comparisons = self.comparisons.iter().map(|cmp| cmp.to_string());
+        let negated = self.negated.iter().map(|pred| format!("not {pred}"));
+        let literals: Vec<String> = body.chain(comparisons).chain(negated).collect();
+        write!(f, "{}.", literals.join(", "))
+    }
+}
+
+/// A rule with a negated predicate over a variable that no positive body
+/// predicate binds, e.g. `Q(X) :- r(X), not s(X, Y).`
+///
+/// Such a rule has no finite answer: `Y` would range over every value not
+/// paired with `X` in `s`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsafeNegation {
+    /// The negated predicate's name.
+    pub predicate: String,
+    /// The variable left unbound.
+    pub variable: String,
+}
+
+impl std::fmt::Display for UnsafeNegation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unsafe rule: variable {} in `not {}(…)` is not bound by a positive body predicate",
+            self.variable, self.predicate
+        )
+    }
+}
+
+impl std::error::Error for UnsafeNegation {}
+
+/// A Datalog program: one or more rules evaluated together to a fixpoint.
+///
+/// Rules sharing a head predicate contribute to the same relation, and head
+/// predicates may appear in rule bodies, including recursively. For example:
+///
+/// ```text
+/// % Transitive closure over two seed edges.
+/// edge(1, 2).
+/// edge(2, 3).
+/// tc(X, Y) :- edge(X, Y).
+/// tc(X, Z) :- tc(X, Y), edge(Y, Z).
+/// .output tc
+/// ```
+///
+/// Head predicates may also be negated in rule bodies, as long as no
+/// predicate depends negatively on itself (the program is *stratified*).
+///
+/// Ground *facts* such as `edge(1, 2).` list tuples of a predicate inline;
+/// they join the union of its rules. `%` and `//` start comments running to
+/// the end of the line, and `/* … */` comments may span lines.
+///
+/// A subset of Soufflé's directives describes the program's schema and I/O:
+/// `.decl edge(x: number, y: number)` declares a predicate's attributes,
+/// `.input edge(filename="edge.csv")` reads a predicate from a file, and
+/// `.output p` marks a predicate as a result. The first `.output` also names
+/// the predicate a single-result consumer reads; see [`output_predicate`].
+///
+/// Implements [`FromStr`](std::str::FromStr) for parsing from a string.
+///
+/// [`output_predicate`]: Program::output_predicate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    /// The rules, in source order.
+    pub rules: Vec<JoinQuery>,
+    /// The ground facts, in source order. Every term is a constant.
+    pub facts: Vec<Predicate>,
+    /// The `.decl` directives, in source order.
+    pub declarations: Vec<Declaration>,
+    /// The `.input` directives, in source order.
+    pub inputs: Vec<IoDirective>,
+    /// The `.output` directives, in source order.
+    pub outputs: Vec<IoDirective>,
+}
+
+/// A `.decl` directive, e.g. `.decl edge(x: number, y: number)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Declaration {
+    /// The declared predicate's name.
+    pub name: String,
+    /// The predicate's attributes, in column order.
+    pub attributes: Vec<Attribute>,
+}
+
+impl Declaration {
+    /// Returns the number of attributes.
+    pub fn arity(&self) -> usize { self.attributes.len() }
+}
+
+/// A typed attribute of a [`Declaration`], e.g. `x: number`.
+///
+/// The type is kept as written; every value is stored as a dictionary ID
+/// regardless.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attribute {
+    /// The attribute's name.
+    pub name: String,
+    /// The attribute's type name, e.g. `"number"` or `"symbol"`.
+    pub ty: String,
+}
+
+/// An `.input` or `.output` directive, e.g. `.input edge(filename="e.csv")`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IoDirective {
+    /// The predicate read or written.
+    pub predicate: String,
+    /// The `key=value` parameters, in source order, with string values
+    /// unquoted.
+    pub parameters: Vec<(String, String)>,
+}
+
+impl IoDirective {
+    /// Returns the value of the parameter named `key`, if given.
+    pub fn parameter(&self, key: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl Program {
+    /// Returns the intensional (derived) predicates — those defined by some
+    /// rule head or fact — in order of first definition by a rule, followed
+    /// by predicates defined only by facts.
+    pub fn idb_predicates(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        let heads = self.rules.iter().map(|rule| &rule.head);
+        for pred in heads.chain(&self.facts) {
+            if !names.contains(&pred.name.as_str()) {
+                names.push(&pred.name);
+            }
+        }
+        names
+    }
+
+    /// Returns the predicate whose tuples are the program's result: the one
+    /// named by the first `.output`, or else the head of the last rule.
+    pub fn output_predicate(&self) -> &str {
+        self.outputs
+            .first()
+            .map(|output| output.predicate.as_str())
+            .or_else(|| self.rules.last().map(|rule| rule.head.name.as_str()))
+            .unwrap_or_default()
+    }
+
+    /// Returns the `.decl` directive for the predicate `name`, if any.
+    pub fn declaration(&self, name: &str) -> Option<&Declaration> {
+        self.declarations.iter().find(|decl| decl.name == name)
+    }
 }
@@ -1,17 +1,33 @@
 //! Datalog query parser for Kermit.
 //!
 //! Parses queries in the form `Head :- Body1, Body2, ... .` into a
-//! [`JoinQuery`] AST. Built on the [winnow](https://docs.rs/winnow) parser
-//! combinator library.
+//! [`JoinQuery`] AST, and sequences of such rules into a [`Program`]. Built
+//! on the [winnow](https://docs.rs/winnow) parser combinator library.
 //!
 //! # Syntax
 //!
 //! - **Variables** start with an uppercase letter: `X`, `Name`
 //! - **Atoms** (constants) start with a lowercase letter: `alice`, `edge`
+//! - **Constants** may also be integers (`42`), quoted strings (`"alice"`,
+//!   `"chat"@fr`) or IRIs (`<http://example.org/alice>`)
 //! - **Placeholders** are the anonymous wildcard `_`
+//! - **Comparisons** `<`, `<=`, `>`, `>=`, `=` and `!=` may appear in a rule
+//!   body between variables and integer constants
+//! - **Negated predicates** `not p(…)` may appear in a rule body; every
+//!   variable they mention must be bound by a positive body predicate
+//! - **Comments** run from `%` or `//` to the end of the line, or between `/*`
+//!   and `*/`
+//! - **Aggregates** `count(V)`, `sum(V)`, `min(V)` and `max(V)` may appear in a
+//!   rule head, over a variable bound in the body
+//! - **Facts** `edge(1, 2).` may appear among a program's rules, as may the
+//!   Soufflé directives `.decl p(x: number)`, `.input p(filename="p.csv")` and
+//!   `.output p`
 //!
 //! ```text
 //! path(X, Z) :- edge(X, Y), edge(Y, Z).
+//! triangle(X, Y, Z) :- edge(X, Y), edge(Y, Z), edge(X, Z), X < Y, Y < Z.
+//! solo(X) :- author(X, P), not coauthor(X, _).
+//! deg(X, count(Y)) :- edge(X, Y).
 //! ```
 //!
 //! # Example
@@ -32,32 +48,89 @@
 //! assert_eq!(query.body[0].name, "edge");
 //! ```
 //!
-//! Parsing returns an error if the input is not a well-formed rule:
+//! Parsing returns a [`ParseError`] locating the problem if the input is not
+//! a well-formed rule:
 //!
 //! ```
 //! use kermit_parser::JoinQuery;
 //!
-//! assert!("not a valid query".parse::<JoinQuery>().is_err());
+//! let err = "P(X) :- Q(X)".parse::<JoinQuery>().unwrap_err();
+//! assert_eq!((err.line, err.column), (1, 13));
+//! assert_eq!(err.expected, vec!["`,`", "`.`"]);
 //! ```
 
 #![deny(missing_docs)]
 
+mod canonical;
+mod error;
 mod join_query;
 
-pub use join_query::{JoinQuery, Predicate, Term};
-use winnow::{
-    ascii::multispace0,
-    combinator::{delimited, separated},
-    error::{ContextError, ErrMode},
-    token::take_while,
-    Parser,
+pub use {
+    error::ParseError,
+    join_query::{
+        AggregateFn, Attribute, Comparison, ComparisonOp, Declaration, IoDirective, JoinQuery,
+        Operand, Predicate, Program, Term, UnsafeNegation,
+    },
+};
+use {
+    error::Rejected,
+    winnow::{
+        ascii::{digit1, multispace0, multispace1},
+        combinator::{
+            alt, cut_err, delimited, eof, fail, opt, preceded, repeat, separated, terminated,
+        },
+        error::{ContextError, ErrMode, FromExternalError, StrContext, StrContextValue},
+        token::{any, take_till, take_until, take_while},
+        Parser,
+    },
 };
 
 type PResult<T> = Result<T, winnow::error::ErrMode<ContextError>>;
 
+// ---------- error labels ----------
+//
+// Once a parser has read enough to know what it is looking at, it commits
+// with `cut_err`, so a later failure is reported where it happened instead of
+// being retried as another reading of the input. The `Expected` contexts
+// attached at those failure points become `ParseError::expected`.
+
+fn expect_char(c: char) -> StrContext { StrContext::Expected(StrContextValue::CharLiteral(c)) }
+
+fn expect_str(s: &'static str) -> StrContext {
+    StrContext::Expected(StrContextValue::StringLiteral(s))
+}
+
+fn expect_a(what: &'static str) -> StrContext {
+    StrContext::Expected(StrContextValue::Description(what))
+}
+
+/// Rejects well-formed input for the given `reason`, pointing the error at
+/// `at`, a suffix of the input.
+fn reject<'i>(
+    input: &mut &'i str, at: &'i str, reason: impl std::error::Error + Send + Sync + 'static,
+) -> ErrMode<ContextError> {
+    *input = at;
+    ErrMode::Cut(ContextError::from_external_error(input, reason))
+}
+
+/// Whitespace, including comments from `%` or `//` to the end of the line
+/// and `/* … */` comments.
 fn ws(input: &mut &str) -> PResult<()> {
-    let _: &str = multispace0.parse_next(input)?;
-    Ok(())
+    loop {
+        let _: &str = multispace0.parse_next(input)?;
+        if input.starts_with("/*") {
+            let _ = (
+                "/*",
+                cut_err(terminated(take_until(0.., "*/"), "*/")).context(expect_str("*/")),
+            )
+                .parse_next(input)?;
+            continue;
+        }
+        if !(input.starts_with('%') || input.starts_with("//")) {
+            return Ok(());
+        }
+        let _: &str = take_till(0.., '\n').parse_next(input)?;
+    }
 }
 
 fn ident(input: &mut &str) -> PResult<String> {
@@ -71,8 +144,6 @@ fn ident(input: &mut &str) -> PResult<String> {
 
 fn comma(input: &mut &str) -> PResult<char> { delimited(ws, ',', ws).parse_next(input) }
 
-fn dot(input: &mut &str) -> PResult<char> { delimited(ws, '.', ws).parse_next(input) }
-
 // ---------- term / predicate ----------
 fn term(input: &mut &str) -> PResult<Term> {
     if input.starts_with('_')
@@ -85,8 +156,23 @@ fn term(input: &mut &str) -> PResult<Term> {
         let _ = '_'.parse_next(input)?;
         return Ok(Term::Placeholder);
     }
+    if input.starts_with(|c: char| c.is_ascii_digit()) {
+        return cut_err(digit1.try_map(str::parse::<usize>))
+            .map(Term::Int)
+            .parse_next(input);
+    }
+    if input.starts_with('"') {
+        return cut_err(quoted)
+            .map(|s: &str| Term::Str(s.to_string()))
+            .parse_next(input);
+    }
+    if input.starts_with('<') {
+        return cut_err(iri)
+            .map(|s: &str| Term::Str(s.to_string()))
+            .parse_next(input);
+    }
 
-    let name = ident.parse_next(input)?;
+    let name = ident.context(expect_a("term")).parse_next(input)?;
 
     let is_var = name
         .chars()
@@ -101,56 +187,457 @@ fn term(input: &mut &str) -> PResult<Term> {
     })
 }
 
-fn term_list(input: &mut &str) -> PResult<Vec<Term>> {
-    delimited(
-        delimited(ws, '(', ws),
-        separated(1.., term, comma),
-        delimited(ws, ')', ws),
+/// An IRI in angle brackets, brackets included.
+fn iri<'i>(input: &mut &'i str) -> PResult<&'i str> {
+    (
+        '<',
+        take_while(0.., |c: char| c != '>' && c != '<' && !c.is_whitespace()),
+        '>'.context(expect_char('>')),
+    )
+        .take()
+        .parse_next(input)
+}
+
+/// A double-quoted string with `\`-escapes, optionally followed by a
+/// language tag (`@en`) or datatype (`^^<iri>`), quotes and suffix included.
+fn quoted<'i>(input: &mut &'i str) -> PResult<&'i str> {
+    let body = repeat::<_, _, (), _, _>(
+        0..,
+        alt((
+            ('\\', any).void(),
+            take_while(1.., |c: char| c != '"' && c != '\\').void(),
+        )),
+    );
+    let suffix = opt(alt((
+        (
+            '@',
+            take_while(1.., |c: char| c.is_ascii_alphanumeric() || c == '-'),
+        )
+            .void(),
+        ("^^", iri).void(),
+    )));
+    ('"', body, '"'.context(expect_char('"')), suffix)
+        .take()
+        .parse_next(input)
+}
+
+/// A parenthesised, comma-separated list of at least one `item`, committed
+/// to once its `(` is read.
+fn parenthesized<'i, O>(
+    item: impl Parser<&'i str, O, ErrMode<ContextError>>,
+) -> impl Parser<&'i str, Vec<O>, ErrMode<ContextError>> {
+    preceded(
+        delimited(ws, '('.context(expect_char('(')), ws),
+        cut_err(terminated(
+            separated(1.., item, comma),
+            delimited(ws, ')', ws)
+                .context(expect_char(','))
+                .context(expect_char(')')),
+        )),
     )
-    .parse_next(input)
 }
 
 fn predicate(input: &mut &str) -> PResult<Predicate> {
     ws.parse_next(input)?;
-    let name = ident.parse_next(input)?;
-    let terms = term_list.parse_next(input)?;
+    let name = ident
+        .context(expect_a("predicate name"))
+        .parse_next(input)?;
+    let terms = cut_err(parenthesized(term)).parse_next(input)?;
+    Ok(Predicate {
+        name,
+        terms,
+    })
+}
+
+// ---------- head ----------
+fn variable(input: &mut &str) -> PResult<String> {
+    ident
+        .verify(|name: &String| name.starts_with(|c: char| c.is_ascii_uppercase()))
+        .parse_next(input)
+}
+
+fn aggregate(input: &mut &str) -> PResult<Term> {
+    let func = alt((
+        "count".value(AggregateFn::Count),
+        "sum".value(AggregateFn::Sum),
+        "min".value(AggregateFn::Min),
+        "max".value(AggregateFn::Max),
+    ))
+    .parse_next(input)?;
+    // Without a `(`, the name is read as an atom instead.
+    let _ = delimited(ws, '(', ws).parse_next(input)?;
+    let var = cut_err(terminated(
+        variable.context(expect_a("variable")),
+        delimited(ws, ')', ws).context(expect_char(')')),
+    ))
+    .parse_next(input)?;
+    Ok(Term::Aggregate(func, var))
+}
+
+fn head(input: &mut &str) -> PResult<Predicate> {
+    ws.parse_next(input)?;
+    let name = ident
+        .context(expect_a("predicate name"))
+        .parse_next(input)?;
+    // Aggregates first, so `count(Y)` is not read as the atom `count`.
+    let terms = cut_err(parenthesized(alt((aggregate, term)))).parse_next(input)?;
     Ok(Predicate {
         name,
         terms,
     })
 }
 
+// ---------- comparison ----------
+fn operand(input: &mut &str) -> PResult<Operand> {
+    ws.parse_next(input)?;
+    if input.starts_with(|c: char| c.is_ascii_digit()) {
+        return digit1
+            .try_map(str::parse::<usize>)
+            .map(Operand::Int)
+            .parse_next(input);
+    }
+    let name = variable.parse_next(input)?;
+    Ok(Operand::Var(name))
+}
+
+fn comparison_op(input: &mut &str) -> PResult<ComparisonOp> {
+    // Two-character operators first, so `<=` is not read as `<`.
+    alt((
+        "<=".value(ComparisonOp::Le),
+        ">=".value(ComparisonOp::Ge),
+        "!=".value(ComparisonOp::Ne),
+        "<".value(ComparisonOp::Lt),
+        ">".value(ComparisonOp::Gt),
+        "=".value(ComparisonOp::Eq),
+    ))
+    .parse_next(input)
+}
+
+fn comparison(input: &mut &str) -> PResult<Comparison> {
+    let left = operand.parse_next(input)?;
+    let op = delimited(ws, comparison_op, ws).parse_next(input)?;
+    let right = cut_err(operand.context(expect_a("variable or integer"))).parse_next(input)?;
+    Ok(Comparison {
+        left,
+        op,
+        right,
+    })
+}
+
+// ---------- negation ----------
+fn negation(input: &mut &str) -> PResult<Predicate> {
+    ws.parse_next(input)?;
+    // The keyword must be followed by whitespace, so `not(X)` and
+    // `notable(X)` still parse as ordinary predicates.
+    let _ = ("not", multispace1).parse_next(input)?;
+    cut_err(predicate).parse_next(input)
+}
+
+/// A body literal: a predicate to join, a predicate that must not match, or
+/// a comparison to enforce.
+enum Literal {
+    Predicate(Predicate),
+    Negated(Predicate),
+    Comparison(Comparison),
+}
+
+fn literal(input: &mut &str) -> PResult<Literal> {
+    alt((
+        negation.map(Literal::Negated),
+        comparison.map(Literal::Comparison),
+        predicate.map(Literal::Predicate),
+    ))
+    .parse_next(input)
+}
+
 fn query(input: &mut &str) -> PResult<JoinQuery> {
-    let head = predicate.parse_next(input)?;
+    let head = head.parse_next(input)?;
     // ":-" separates head from body
-    let _ = delimited(ws, ":-", ws).parse_next(input)?;
-    let body = separated(1.., predicate, comma).parse_next(input)?;
-    let _ = dot.parse_next(input)?;
+    let _ = cut_err(delimited(ws, ":-", ws).context(expect_str(":-"))).parse_next(input)?;
+    rule_body(head, input)
+}
+
+/// The body of a rule with the given `head`, after its `:-` and through its
+/// terminating `.`.
+fn rule_body(head: Predicate, input: &mut &str) -> PResult<JoinQuery> {
+    ws.parse_next(input)?;
+    let start = *input;
+    let mut body = Vec::new();
+    let mut comparisons = Vec::new();
+    let mut negated = Vec::new();
+    loop {
+        ws.parse_next(input)?;
+        // Where each filter starts, to point at it if it is unsafe.
+        let at = *input;
+        match cut_err(literal).parse_next(input)? {
+            | Literal::Predicate(pred) => body.push(pred),
+            | Literal::Negated(pred) => negated.push((at, pred)),
+            | Literal::Comparison(cmp) => comparisons.push((at, cmp)),
+        }
+        let more = cut_err(delimited(
+            ws,
+            alt((','.value(true), '.'.value(false)))
+                .context(expect_char(','))
+                .context(expect_char('.')),
+            ws,
+        ))
+        .parse_next(input)?;
+        if !more {
+            break;
+        }
+    }
+    let end = *input;
+
+    // Comparisons and negations only filter bindings, so the body needs a
+    // predicate to bind every variable they mention.
+    let bound = |var: &str| {
+        body.iter()
+            .flat_map(|pred: &Predicate| &pred.terms)
+            .any(|t| matches!(t, Term::Var(name) if name == var))
+    };
+    if body.is_empty() {
+        return Err(reject(
+            input,
+            start,
+            Rejected("a rule body needs a positive predicate"),
+        ));
+    }
+    for (at, cmp) in &comparisons {
+        if !cmp.vars().all(bound) {
+            return Err(reject(
+                input,
+                at,
+                Rejected(
+                    "unsafe rule: a compared variable is not bound by a positive body predicate",
+                ),
+            ));
+        }
+    }
+    for (at, pred) in &negated {
+        for term in &pred.terms {
+            if let Term::Var(var) = term {
+                if !bound(var) {
+                    let unsafe_negation = UnsafeNegation {
+                        predicate: pred.name.clone(),
+                        variable: var.clone(),
+                    };
+                    return Err(reject(input, at, unsafe_negation));
+                }
+            }
+        }
+    }
+    *input = end;
     Ok(JoinQuery {
         head,
         body,
+        comparisons: comparisons.into_iter().map(|(_, cmp)| cmp).collect(),
+        negated: negated.into_iter().map(|(_, pred)| pred).collect(),
     })
 }
 
+// ---------- directives ----------
+
+/// A double-quoted string returned without its quotes. `\t`, `\n` and `\r`
+/// escape control characters; `\` before any other character escapes it.
+fn string_literal(input: &mut &str) -> PResult<String> {
+    let _ = '"'.parse_next(input)?;
+    cut_err(terminated(
+        repeat(
+            0..,
+            alt((
+                preceded(
+                    '\\',
+                    any.map(|c| match c {
+                        | 't' => '\t',
+                        | 'n' => '\n',
+                        | 'r' => '\r',
+                        | c => c,
+                    }),
+                ),
+                any.verify(|c: &char| *c != '"' && *c != '\\'),
+            )),
+        ),
+        '"'.context(expect_char('"')),
+    ))
+    .parse_next(input)
+}
+
+/// A `key=value` directive parameter, whose value is a quoted string or a
+/// bare word such as `file` or `true`.
+fn parameter(input: &mut &str) -> PResult<(String, String)> {
+    let key = ident
+        .context(expect_a("parameter name"))
+        .parse_next(input)?;
+    let _ = cut_err(delimited(ws, '=', ws).context(expect_char('='))).parse_next(input)?;
+    let bare = take_while(1.., |c: char| {
+        c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '/')
+    });
+    let value = cut_err(alt((string_literal, bare.map(str::to_owned))))
+        .context(expect_a("parameter value"))
+        .parse_next(input)?;
+    Ok((key, value))
+}
+
+/// The predicate name following a directive keyword.
+fn directive_predicate(input: &mut &str) -> PResult<String> {
+    cut_err(preceded(multispace1, ident))
+        .context(expect_a("predicate name"))
+        .parse_next(input)
+}
+
+/// The rest of an `.input` or `.output` directive: a predicate name and
+/// optional `(key=value, …)` parameters directly after it.
+fn io_directive(input: &mut &str) -> PResult<IoDirective> {
+    let predicate = directive_predicate(input)?;
+    let parameters = if input.starts_with('(') {
+        parenthesized(parameter).parse_next(input)?
+    } else {
+        Vec::new()
+    };
+    Ok(IoDirective {
+        predicate,
+        parameters,
+    })
+}
+
+/// An `x: number` attribute of a `.decl` directive.
+fn attribute(input: &mut &str) -> PResult<Attribute> {
+    let name = ident
+        .context(expect_a("attribute name"))
+        .parse_next(input)?;
+    let ty = cut_err(preceded(
+        delimited(ws, ':', ws).context(expect_char(':')),
+        ident.context(expect_a("type name")),
+    ))
+    .parse_next(input)?;
+    Ok(Attribute {
+        name,
+        ty,
+    })
+}
+
+/// The rest of a `.decl` directive: a predicate name and its attributes.
+fn declaration(input: &mut &str) -> PResult<Declaration> {
+    let name = directive_predicate(input)?;
+    let attributes = cut_err(parenthesized(attribute)).parse_next(input)?;
+    Ok(Declaration {
+        name,
+        attributes,
+    })
+}
+
+/// A directive read by [`program`].
+enum Directive {
+    Decl(Declaration),
+    Input(IoDirective),
+    Output(IoDirective),
+}
+
+/// A `.decl`, `.input` or `.output` directive.
+fn directive(input: &mut &str) -> PResult<Directive> {
+    let keyword = opt(preceded('.', alt(("decl", "input", "output")))).parse_next(input)?;
+    match keyword {
+        | Some("decl") => declaration.map(Directive::Decl).parse_next(input),
+        | Some("input") => io_directive.map(Directive::Input).parse_next(input),
+        | Some("output") => io_directive.map(Directive::Output).parse_next(input),
+        | _ => cut_err(
+            fail.context(expect_str(".decl"))
+                .context(expect_str(".input"))
+                .context(expect_str(".output")),
+        )
+        .parse_next(input),
+    }
+}
+
+/// A program: rules, ground facts such as `edge(1, 2).`, and `.decl`,
+/// `.input` and `.output` directives, through the end of the input.
+fn program(input: &mut &str) -> PResult<Program> {
+    let mut program = Program {
+        rules: Vec::new(),
+        facts: Vec::new(),
+        declarations: Vec::new(),
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+    };
+    loop {
+        ws.parse_next(input)?;
+        if input.is_empty() {
+            break;
+        }
+        if input.starts_with('.') {
+            match directive(input)? {
+                | Directive::Decl(decl) => program.declarations.push(decl),
+                | Directive::Input(io) => program.inputs.push(io),
+                | Directive::Output(io) => program.outputs.push(io),
+            }
+            continue;
+        }
+        let head = cut_err(head).parse_next(input)?;
+        ws.parse_next(input)?;
+        if opt(":-").parse_next(input)?.is_some() {
+            program.rules.push(rule_body(head, input)?);
+            continue;
+        }
+        // Without a body, the clause is a fact, so it must be ground.
+        let ground = head
+            .terms
+            .iter()
+            .all(|t| matches!(t, Term::Atom(_) | Term::Int(_) | Term::Str(_)));
+        if !ground {
+            return cut_err(fail.context(expect_str(":-"))).parse_next(input);
+        }
+        let _ =
+            cut_err('.'.context(expect_str(":-")).context(expect_char('.'))).parse_next(input)?;
+        program.facts.push(head);
+    }
+    if program.rules.is_empty() {
+        let end = *input;
+        return Err(reject(
+            input,
+            end,
+            Rejected("a program needs at least one rule"),
+        ));
+    }
+    Ok(program)
+}
+
 impl std::str::FromStr for JoinQuery {
-    type Err = ErrMode<ContextError>;
+    type Err = ParseError;
 
     /// Parses a Datalog rule into a [`JoinQuery`].
     ///
     /// # Errors
     ///
-    /// Returns an error if `s` is not a single well-formed rule of the shape
-    /// `Head(…) :- Body1(…), …, BodyN(…).` — including missing `:-`, empty
-    /// body, missing terminating `.`, trailing content after the `.`, or
-    /// identifiers that don't match the [syntax rules](crate).
+    /// Returns a [`ParseError`] locating the problem if `s` is not a single
+    /// well-formed rule of the shape `Head(…) :- Body1(…), …, BodyN(…).` —
+    /// including missing `:-`, empty body, missing terminating `.`, trailing
+    /// content after the `.`, identifiers that don't match the [syntax
+    /// rules](crate), or a comparison over a variable no body predicate
+    /// binds. A negated predicate over such a variable fails with an
+    /// [`UnsafeNegation`] as the error's source.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut input = s;
-        let result = query.parse_next(&mut input)?;
-        ws.parse_next(&mut input)?;
-        if !input.is_empty() {
-            return Err(ErrMode::Backtrack(ContextError::new()));
-        }
-        Ok(result)
+        terminated(query, (ws, eof.context(expect_a("end of input"))))
+            .parse(s)
+            .map_err(|e| ParseError::new(s, e.offset(), e.inner()))
+    }
+}
+
+impl std::str::FromStr for Program {
+    type Err = ParseError;
+
+    /// Parses one or more Datalog rules, with any facts, comments and
+    /// `.decl`, `.input` and `.output` directives, into a [`Program`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ParseError`] locating the problem if `s` contains no
+    /// rules, if any rule is not well-formed in the sense of [`JoinQuery`]'s
+    /// parser, if a fact holds a variable or placeholder, or if a directive
+    /// is unknown or malformed.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        program
+            .parse(s)
+            .map_err(|e| ParseError::new(s, e.offset(), e.inner()))
     }
 }
 
@@ -358,6 +845,149 @@ mod tests {
     }
 }
 
+#[cfg(test)]
+mod program_tests {
+    use super::*;
+
+    #[test]
+    fn parses_recursive_program() {
+        let program: Program = "tc(X, Y) :- edge(X, Y).\ntc(X, Z) :- tc(X, Y), edge(Y, Z)."
+            .parse()
+            .expect("Failed to parse program");
+        assert_eq!(program.rules.len(), 2);
+        assert_eq!(program.rules[1].body[0].name, "tc");
+        assert_eq!(program.idb_predicates(), vec!["tc"]);
+    }
+
+    #[test]
+    fn single_rule_is_a_program() {
+        let program: Program = "P(X) :- Q(X).".parse().unwrap();
+        assert_eq!(program.rules, vec!["P(X) :- Q(X)."
+            .parse::<JoinQuery>()
+            .unwrap()]);
+    }
+
+    #[test]
+    fn idb_predicates_in_first_definition_order() {
+        let program: Program = "b(X) :- e(X). a(X) :- b(X). b(X) :- a(X).".parse().unwrap();
+        assert_eq!(program.idb_predicates(), vec!["b", "a"]);
+    }
+
+    #[test]
+    fn parses_facts_comments_and_output() {
+        let program: Program = "% Seed edges.\nedge(1, 2).\nedge(2, \"b\"). // trailing\nP(X, Y) \
+                                :- edge(X, Y).\nQ(X) :- P(X, c3).\n.output P\n% done"
+            .parse()
+            .unwrap();
+        assert_eq!(program.rules.len(), 2);
+        assert_eq!(program.facts, vec![
+            Predicate {
+                name: "edge".into(),
+                terms: vec![Term::Int(1), Term::Int(2)],
+            },
+            Predicate {
+                name: "edge".into(),
+                terms: vec![Term::Int(2), Term::Str("\"b\"".into())],
+            },
+        ]);
+        assert_eq!(program.outputs, vec![IoDirective {
+            predicate: "P".into(),
+            parameters: vec![],
+        }]);
+        assert_eq!(program.output_predicate(), "P");
+        assert_eq!(program.idb_predicates(), vec!["P", "Q", "edge"]);
+    }
+
+    #[test]
+    fn output_defaults_to_the_last_rule() {
+        let program: Program = "P(X) :- Q(X). R(X) :- P(X). P(X) :- S(X).".parse().unwrap();
+        assert!(program.outputs.is_empty());
+        assert_eq!(program.output_predicate(), "P");
+    }
+
+    #[test]
+    fn parses_souffle_directives() {
+        let program: Program = [
+            "/* Reachability,",
+            "   Soufflé style. */",
+            ".decl edge(x: number, y: number)",
+            ".decl path(x: number, y: number)",
+            ".input edge(IO=file, filename=\"data/edge \\\"1\\\".csv\")",
+            "path(X, Y) :- edge(X, Y).",
+            "path(X, Z) :- path(X, Y), edge(Y, Z).",
+            ".output path",
+            ".output edge(filename=\"edges.csv\")",
+        ]
+        .join("\n")
+        .parse()
+        .unwrap();
+        assert_eq!(program.declarations.len(), 2);
+        assert_eq!(program.declarations[0], Declaration {
+            name: "edge".into(),
+            attributes: vec![
+                Attribute {
+                    name: "x".into(),
+                    ty: "number".into(),
+                },
+                Attribute {
+                    name: "y".into(),
+                    ty: "number".into(),
+                },
+            ],
+        });
+        assert_eq!(program.declaration("path").map(Declaration::arity), Some(2));
+        assert_eq!(program.inputs, vec![IoDirective {
+            predicate: "edge".into(),
+            parameters: vec![
+                ("IO".into(), "file".into()),
+                ("filename".into(), "data/edge \"1\".csv".into()),
+            ],
+        }]);
+        let outputs: Vec<&str> = program
+            .outputs
+            .iter()
+            .map(|output| output.predicate.as_str())
+            .collect();
+        assert_eq!(outputs, vec!["path", "edge"]);
+        assert_eq!(program.outputs[1].parameter("filename"), Some("edges.csv"));
+        assert_eq!(program.output_predicate(), "path");
+    }
+
+    #[test]
+    fn comments_may_appear_between_tokens() {
+        let program: Program = "P(X) :- % the body\n  Q(X), // second atom\n  R(X)."
+            .parse()
+            .unwrap();
+        assert_eq!(program.rules[0].body.len(), 2);
+    }
+
+    #[test]
+    fn rejects_invalid_programs() {
+        let cases = [
+            ("empty", ""),
+            ("missing dot on last rule", "P(X) :- Q(X). R(X) :- P(X)"),
+            ("trailing garbage", "P(X) :- Q(X). GARBAGE"),
+            ("facts only", "edge(1, 2)."),
+            ("variable in a fact", "edge(1, X). P(X) :- edge(X, Y)."),
+            ("placeholder in a fact", "edge(1, _). P(X) :- edge(X, Y)."),
+            ("output without a name", "P(X) :- Q(X). .output"),
+            ("unknown directive", "P(X) :- Q(X). .printsize P"),
+            ("untyped attribute", ".decl Q(x) P(X) :- Q(X)."),
+            (
+                "parameter without a value",
+                ".input Q(filename=) P(X) :- Q(X).",
+            ),
+            ("unterminated block comment", "P(X) :- Q(X). /* done"),
+        ];
+        for (label, input) in cases {
+            assert!(
+                input.parse::<Program>().is_err(),
+                "{label} should fail to parse"
+            );
+        }
+    }
+}
+
 #[cfg(test)]
 mod edge_case_tests {
     use super::*;
@@ -448,3 +1078,399 @@ mod edge_case_tests {
         assert_eq!(q.body[0].terms.len(), 15);
     }
 }
+
+#[cfg(test)]
+mod comparison_tests {
+    use super::*;
+
+    fn var(name: &str) -> Operand { Operand::Var(name.to_string()) }
+
+    #[test]
+    fn parses_every_operator() {
+        let cases = [
+            ("<", ComparisonOp::Lt),
+            ("<=", ComparisonOp::Le),
+            (">", ComparisonOp::Gt),
+            (">=", ComparisonOp::Ge),
+            ("=", ComparisonOp::Eq),
+            ("!=", ComparisonOp::Ne),
+        ];
+        for (symbol, op) in cases {
+            let q: JoinQuery = format!("Q(X, Y) :- e(X, Y), X {symbol} Y.")
+                .parse()
+                .unwrap_or_else(|e| panic!("{symbol}: {e:?}"));
+            assert_eq!(q.body.len(), 1, "{symbol}");
+            assert_eq!(q.comparisons, vec![Comparison {
+                left: var("X"),
+                op,
+                right: var("Y"),
+            }]);
+        }
+    }
+
+    #[test]
+    fn parses_integer_constants_and_interleaved_literals() {
+        let q: JoinQuery = "Q(X) :- 3<=X, e(X, Y), Y!=10, f(Y).".parse().unwrap();
+        assert_eq!(q.body.len(), 2);
+        assert_eq!(q.body[1].name, "f");
+        assert_eq!(q.comparisons, vec![
+            Comparison {
+                left: Operand::Int(3),
+                op: ComparisonOp::Le,
+                right: var("X"),
+            },
+            Comparison {
+                left: var("Y"),
+                op: ComparisonOp::Ne,
+                right: Operand::Int(10),
+            },
+        ]);
+    }
+
+    #[test]
+    fn query_without_comparisons_has_none() {
+        let q: JoinQuery = "Q(X) :- e(X, Y).".parse().unwrap();
+        assert!(q.comparisons.is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_comparisons() {
+        let cases = [
+            ("unbound variable", "Q(X) :- e(X, Y), X < Z."),
+            ("comparisons only", "Q(X) :- X < 3."),
+            ("atom operand", "Q(X) :- e(X, Y), X < alice."),
+            ("placeholder operand", "Q(X) :- e(X, Y), X < _."),
+            ("missing operand", "Q(X) :- e(X, Y), X <."),
+            ("unknown operator", "Q(X) :- e(X, Y), X <> Y."),
+        ];
+        for (label, input) in cases {
+            assert!(input.parse::<JoinQuery>().is_err(), "{label} should fail");
+        }
+    }
+
+    #[test]
+    fn program_rules_accept_comparisons() {
+        let program: Program = "lt(X, Y) :- e(X, Y), X < Y.\nr(X) :- lt(X, Y), Y >= 2."
+            .parse()
+            .unwrap();
+        assert_eq!(program.rules[0].comparisons.len(), 1);
+        assert_eq!(program.rules[1].comparisons[0].op, ComparisonOp::Ge);
+    }
+
+    #[test]
+    fn op_flip_and_holds_agree() {
+        for op in [
+            ComparisonOp::Lt,
+            ComparisonOp::Le,
+            ComparisonOp::Gt,
+            ComparisonOp::Ge,
+            ComparisonOp::Eq,
+            ComparisonOp::Ne,
+        ] {
+            for (a, b) in [(1, 2), (2, 2), (3, 2)] {
+                assert_eq!(op.holds(a, b), op.flip().holds(b, a), "{op:?} {a} {b}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod negation_tests {
+    use super::*;
+
+    fn pred(name: &str, terms: Vec<Term>) -> Predicate {
+        Predicate {
+            name: name.to_string(),
+            terms,
+        }
+    }
+
+    fn var(name: &str) -> Term { Term::Var(name.to_string()) }
+
+    #[test]
+    fn parses_negated_predicates() {
+        let q: JoinQuery = "Q(X) :- author(X, P), not coauthor(X, _), not banned(X)."
+            .parse()
+            .unwrap();
+        assert_eq!(q.body, vec![pred("author", vec![var("X"), var("P")])]);
+        assert_eq!(q.negated, vec![
+            pred("coauthor", vec![var("X"), Term::Placeholder]),
+            pred("banned", vec![var("X")]),
+        ]);
+    }
+
+    #[test]
+    fn not_without_whitespace_is_a_predicate_name() {
+        let q: JoinQuery = "Q(X) :- e(X), not(X), notable(X).".parse().unwrap();
+        assert_eq!(q.body.len(), 3);
+        assert_eq!(q.body[1].name, "not");
+        assert_eq!(q.body[2].name, "notable");
+        assert!(q.negated.is_empty());
+    }
+
+    #[test]
+    fn unsafe_negation_names_the_unbound_variable() {
+        let err = "Q(X) :- r(X), not s(X, Y)."
+            .parse::<JoinQuery>()
+            .unwrap_err();
+        assert_eq!((err.line, err.column), (1, 15));
+        let cause = std::error::Error::source(&err)
+            .and_then(|c| c.downcast_ref::<UnsafeNegation>())
+            .expect("cause should be an UnsafeNegation");
+        assert_eq!(cause, &UnsafeNegation {
+            predicate: "s".to_string(),
+            variable: "Y".to_string(),
+        });
+        assert!(cause.to_string().contains("variable Y in `not s(…)`"));
+    }
+
+    #[test]
+    fn rejects_malformed_negations() {
+        let cases = [
+            ("negation only", "Q(X) :- not r(X)."),
+            ("negated comparison", "Q(X) :- r(X), not X < 3."),
+            ("missing predicate", "Q(X) :- r(X), not ."),
+        ];
+        for (label, input) in cases {
+            assert!(input.parse::<JoinQuery>().is_err(), "{label} should fail");
+        }
+    }
+
+    #[test]
+    fn program_rules_accept_negation() {
+        let program: Program = "reach(X) :- start(X).\nunreached(X) :- node(X), not reach(X)."
+            .parse()
+            .unwrap();
+        assert!(program.rules[0].negated.is_empty());
+        assert_eq!(program.rules[1].negated, vec![pred("reach", vec![var(
+            "X"
+        )])]);
+    }
+}
+
+#[cfg(test)]
+mod aggregate_tests {
+    use super::*;
+
+    fn var(name: &str) -> Term { Term::Var(name.to_string()) }
+
+    #[test]
+    fn parses_head_aggregates() {
+        let q: JoinQuery = "deg(X, count(Y), sum( Y ), min(Y), max(Y)) :- edge(X, Y)."
+            .parse()
+            .unwrap();
+        assert_eq!(q.head.terms, vec![
+            var("X"),
+            Term::Aggregate(AggregateFn::Count, "Y".to_string()),
+            Term::Aggregate(AggregateFn::Sum, "Y".to_string()),
+            Term::Aggregate(AggregateFn::Min, "Y".to_string()),
+            Term::Aggregate(AggregateFn::Max, "Y".to_string()),
+        ]);
+        assert!(q.has_aggregates());
+        assert!(!"Q(X) :- e(X, Y)."
+            .parse::<JoinQuery>()
+            .unwrap()
+            .has_aggregates());
+    }
+
+    #[test]
+    fn aggregate_names_alone_are_atoms() {
+        let q: JoinQuery = "Q(count, maxX, X) :- e(X).".parse().unwrap();
+        assert_eq!(q.head.terms, vec![
+            Term::Atom("count".to_string()),
+            Term::Atom("maxX".to_string()),
+            var("X"),
+        ]);
+    }
+
+    #[test]
+    fn rejects_malformed_aggregates() {
+        let cases = [
+            ("aggregate in the body", "Q(X) :- e(X, count(Y))."),
+            ("aggregate over an atom", "Q(count(y)) :- e(y)."),
+            ("aggregate over a placeholder", "Q(count(_)) :- e(X)."),
+            ("unknown function", "Q(avg(Y)) :- e(Y)."),
+            ("nested aggregate", "Q(max(count(Y))) :- e(Y)."),
+        ];
+        for (label, input) in cases {
+            assert!(input.parse::<JoinQuery>().is_err(), "{label} should fail");
+        }
+    }
+}
+
+#[cfg(test)]
+mod constant_tests {
+    use super::*;
+
+    fn body_terms(query: &str) -> Vec<Term> {
+        let q: JoinQuery = query.parse().unwrap();
+        q.body.into_iter().flat_map(|p| p.terms).collect()
+    }
+
+    #[test]
+    fn parses_integer_string_and_iri_constants() {
+        let terms = body_terms(r#"Q(X) :- p(X, 42, "alice", <http://example.org/a#b>)."#);
+        assert_eq!(terms, vec![
+            Term::Var("X".to_string()),
+            Term::Int(42),
+            Term::Str("\"alice\"".to_string()),
+            Term::Str("<http://example.org/a#b>".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn strings_keep_escapes_and_suffixes() {
+        let terms = body_terms(
+            r#"Q(X) :- p(X, "say \"hi\", ok"), p(X, "chat"@fr), p(X, "5"^^<http://x/int>)."#,
+        );
+        assert_eq!(terms[1], Term::Str(r#""say \"hi\", ok""#.to_string()));
+        assert_eq!(terms[3], Term::Str(r#""chat"@fr"#.to_string()));
+        assert_eq!(terms[5], Term::Str(r#""5"^^<http://x/int>"#.to_string()));
+    }
+
+    #[test]
+    fn negated_predicates_accept_constants() {
+        let q: JoinQuery = r#"Q(X) :- p(X), not r(X, 7, "bob")."#.parse().unwrap();
+        assert_eq!(q.negated[0].terms[1..], [
+            Term::Int(7),
+            Term::Str("\"bob\"".to_string())
+        ]);
+    }
+
+    #[test]
+    fn rejects_malformed_constants() {
+        let cases = [
+            ("unterminated string", r#"Q(X) :- p(X, "alice)."#),
+            ("unterminated IRI", "Q(X) :- p(X, <http://x)."),
+            ("space in IRI", "Q(X) :- p(X, <http://x y>)."),
+            ("integer overflow", "Q(X) :- p(X, 99999999999999999999999)."),
+            ("digits then letters", "Q(X) :- p(X, 4x)."),
+        ];
+        for (label, input) in cases {
+            assert!(input.parse::<JoinQuery>().is_err(), "{label} should fail");
+        }
+    }
+    #[test]
+    fn display_round_trips() {
+        for rule in [
+            "P(A, C) :- Q(A, B), R(B, C).",
+            r#"Q(X, 42, _) :- p(X, 42, "say \"hi\"", <http://example.org/a#b>), p(X, "chat"@fr)."#,
+            "Q(X, Y) :- e(X, Y), e(Y, c3), X < Y, 3 != X, Y >= 2, not f(X, _), not g(Y).",
+            "deg(X, count(Y), max(Z)) :- edge(X, Y), edge(Y, Z).",
+        ] {
+            let query: JoinQuery = rule.parse().unwrap();
+            assert_eq!(query.to_string(), rule);
+            assert_eq!(query.to_string().parse::<JoinQuery>().unwrap(), query);
+        }
+    }
+}
+
+#[cfg(test)]
+mod parse_error_tests {
+    use super::*;
+
+    fn error(input: &str) -> ParseError {
+        input
+            .parse::<JoinQuery>()
+            .expect_err("input should fail to parse")
+    }
+
+    #[test]
+    fn missing_dot() {
+        let e = error("P(X) :- Q(X)");
+        assert_eq!((e.offset, e.line, e.column), (12, 1, 13));
+        assert_eq!(e.expected, vec!["`,`", "`.`"]);
+        assert_eq!(
+            e.to_string(),
+            "expected `,` or `.` at line 1, column 13\n    P(X) :- Q(X)\n                ^"
+        );
+    }
+
+    #[test]
+    fn lowercase_variable() {
+        let cases = [
+            (
+                "comparison",
+                "P(X) :- Q(X, Y), X < y.",
+                22,
+                "variable or integer",
+            ),
+            ("aggregate", "P(X, count(y)) :- Q(X, Y).", 12, "variable"),
+        ];
+        for (label, input, column, expected) in cases {
+            let e = error(input);
+            assert_eq!((e.line, e.column), (1, column), "{label}");
+            assert_eq!(e.expected, vec![expected], "{label}");
+        }
+    }
+
+    #[test]
+    fn unbalanced_parentheses() {
+        let cases = [
+            ("unclosed head", "P(X :- Q(X).", 5, vec!["`,`", "`)`"]),
+            ("unclosed body", "P(X) :- Q(X, Y.", 15, vec!["`,`", "`)`"]),
+            ("extra close", "P(X) :- Q(X)).", 13, vec!["`,`", "`.`"]),
+            ("nested open", "P(X) :- Q((X)).", 11, vec!["term"]),
+            ("missing open", "P(X) :- Q X).", 11, vec!["`(`"]),
+        ];
+        for (label, input, column, expected) in cases {
+            let e = error(input);
+            assert_eq!((e.line, e.column), (1, column), "{label}");
+            assert_eq!(e.expected, expected, "{label}");
+        }
+    }
+
+    #[test]
+    fn locates_errors_on_later_lines() {
+        let e = error("P(X, Y) :-\n\tQ(X),\n\tR(X Y).");
+        assert_eq!((e.offset, e.line, e.column), (23, 3, 6));
+        assert_eq!(e.snippet, "\tR(X Y).");
+        assert!(e.to_string().ends_with("    \tR(X Y).\n    \t    ^"), "{e}");
+    }
+
+    #[test]
+    fn trailing_input_and_missing_separator() {
+        let e = error("P(X) :- Q(X). R(X) :- Q(X).");
+        assert_eq!(e.column, 15);
+        assert_eq!(e.expected, vec!["end of input"]);
+        let e = error("P(X) Q(X).");
+        assert_eq!(e.column, 6);
+        assert_eq!(e.expected, vec!["`:-`"]);
+    }
+
+    #[test]
+    fn rejections_explain_their_cause() {
+        let e = error("P(X) :- Q(X), Z < 3.");
+        assert_eq!(e.column, 15);
+        assert!(e.expected.is_empty());
+        assert!(
+            e.to_string()
+                .starts_with("unsafe rule: a compared variable"),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn directives_report_what_they_expect() {
+        let e = "P(X) :- Q(X).\n.printsize P"
+            .parse::<Program>()
+            .unwrap_err();
+        assert_eq!((e.line, e.column), (2, 1));
+        assert_eq!(e.expected, vec!["`.decl`", "`.input`", "`.output`"]);
+
+        let e = ".decl Q(x number)\nP(X) :- Q(X)."
+            .parse::<Program>()
+            .unwrap_err();
+        assert_eq!((e.line, e.column), (1, 11));
+        assert_eq!(e.expected, vec!["`:`"]);
+    }
+
+    #[test]
+    fn facts_with_variables_need_a_body() {
+        let e = "edge(1, X).\nP(X) :- edge(X, Y)."
+            .parse::<Program>()
+            .unwrap_err();
+        assert_eq!((e.line, e.column), (1, 11));
+        assert_eq!(e.expected, vec!["`:-`"]);
+    }
+}
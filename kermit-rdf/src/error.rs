@@ -77,6 +77,11 @@ pub enum RdfError {
         message: String,
     },
 
+    /// A dictionary Parquet file does not hold `id` / `value` columns with
+    /// IDs numbered from zero.
+    #[error("invalid dictionary file: {0}")]
+    InvalidDict(String),
+
     /// Underlying I/O error.
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
@@ -1,13 +1,17 @@
-//! Parquet writers for the dictionary and per-predicate relation tables.
+//! Parquet writers for the dictionary and per-predicate relation tables,
+//! and a reader for the dictionary.
 
 use {
-    crate::{dict::Dictionary, error::RdfError, partition::PartitionedRelation},
+    crate::{dict::Dictionary, error::RdfError, partition::PartitionedRelation, value::RdfValue},
     arrow::{
         array::{ArrayRef, Int64Array, StringArray},
         datatypes::{DataType, Field, Schema},
         record_batch::RecordBatch,
     },
-    parquet::{arrow::ArrowWriter, file::properties::WriterProperties},
+    parquet::{
+        arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter},
+        file::properties::WriterProperties,
+    },
     std::{path::Path, sync::Arc},
 };
 
@@ -30,6 +34,48 @@ pub fn write_dict(dict: &Dictionary, out_path: &Path) -> Result<(), RdfError> {
     Ok(())
 }
 
+/// Reads a dictionary written by [`write_dict`].
+///
+/// # Errors
+///
+/// Returns [`RdfError::InvalidDict`] if the file lacks the `id` / `value`
+/// columns or its IDs are not numbered `0, 1, …` in order, and an I/O,
+/// Arrow or Parquet error if it cannot be read.
+pub fn read_dict(path: &Path) -> Result<Dictionary, RdfError> {
+    let file = std::fs::File::open(path)?;
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)?.build()?;
+    let mut dict = Dictionary::new();
+    for batch in reader {
+        let batch = batch?;
+        let column = |name: &str| {
+            batch
+                .column_by_name(name)
+                .ok_or_else(|| RdfError::InvalidDict(format!("missing `{name}` column")))
+        };
+        let ids = column("id")?
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .ok_or_else(|| RdfError::InvalidDict("`id` is not an i64 column".to_string()))?;
+        let values = column("value")?
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .ok_or_else(|| RdfError::InvalidDict("`value` is not a string column".to_string()))?;
+        for (id, value) in ids.iter().zip(values.iter()) {
+            let (Some(id), Some(value)) = (id, value) else {
+                return Err(RdfError::InvalidDict("null entry".to_string()));
+            };
+            if id != dict.len() as i64 {
+                return Err(RdfError::InvalidDict(format!(
+                    "expected id {}, found {id}",
+                    dict.len()
+                )));
+            }
+            dict.intern(RdfValue::from_canonical(value));
+        }
+    }
+    Ok(dict)
+}
+
 /// Writes one predicate's tuples as a 2-column Parquet file: `s: i64`, `o:
 /// i64`.
 pub fn write_relation(rel: &PartitionedRelation, out_path: &Path) -> Result<(), RdfError> {
@@ -53,7 +99,6 @@ pub fn write_relation(rel: &PartitionedRelation, out_path: &Path) -> Result<(),
 mod tests {
     use {
         super::*,
-        crate::value::RdfValue,
         parquet::file::reader::{FileReader, SerializedFileReader},
     };
 
@@ -71,6 +116,24 @@ mod tests {
         let reader = SerializedFileReader::new(f).unwrap();
         let meta = reader.metadata();
         assert_eq!(meta.file_metadata().num_rows(), 2);
+
+        let back = read_dict(&path).unwrap();
+        assert_eq!(
+            back.iter().collect::<Vec<_>>(),
+            d.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn read_dict_rejects_a_relation_file() {
+        let rel = PartitionedRelation {
+            name: "follows".into(),
+            tuples: vec![(0, 1)],
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("follows.parquet");
+        write_relation(&rel, &path).unwrap();
+        assert!(matches!(read_dict(&path), Err(RdfError::InvalidDict(_))));
     }
 
     #[test]
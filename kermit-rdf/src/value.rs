@@ -30,6 +30,17 @@ impl RdfValue {
             | RdfValue::Literal(s) => s.clone(),
         }
     }
+
+    /// Parses the canonical string form produced by
+    /// [`to_canonical`](Self::to_canonical): `<...>` is an IRI, a leading
+    /// `_:` a blank node, and anything else a literal.
+    pub fn from_canonical(s: &str) -> RdfValue {
+        match s.strip_prefix('<').and_then(|rest| rest.strip_suffix('>')) {
+            | Some(iri) => RdfValue::Iri(iri.to_string()),
+            | None if s.starts_with("_:") => RdfValue::BlankNode(s.to_string()),
+            | None => RdfValue::Literal(s.to_string()),
+        }
+    }
 }
 
 impl fmt::Display for RdfValue {
@@ -58,6 +69,18 @@ mod tests {
         assert_eq!(v.to_canonical(), "\"hello\"@en");
     }
 
+    #[test]
+    fn canonical_form_round_trips() {
+        for v in [
+            RdfValue::Iri("http://example/x".to_string()),
+            RdfValue::BlankNode("_:b1".to_string()),
+            RdfValue::Literal("\"hello\"@en".to_string()),
+            RdfValue::Literal("\"5\"^^<http://x/int>".to_string()),
+        ] {
+            assert_eq!(RdfValue::from_canonical(&v.to_canonical()), v);
+        }
+    }
+
     #[test]
     fn equality_uses_underlying_string() {
         let a = RdfValue::Iri("http://x".to_string());
@@ -65,6 +65,7 @@ pub fn write_benchmark_yaml(
             name: qname.clone(),
             description: format!("query {qname}"),
             query: dl.clone(),
+            expected_count: None,
         })
         .collect();
     let def = BenchmarkDefinition {
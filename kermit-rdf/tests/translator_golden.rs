@@ -1,6 +1,7 @@
 //! Port of `scripts/watdiv-preprocess/tests/test_translator.py`.
 
 use {
+    kermit_parser::JoinQuery,
     kermit_rdf::{
         dict::Dictionary, error::RdfError, sparql::translator::translate_query, value::RdfValue,
     },
@@ -15,6 +16,21 @@ fn build_dict(uris: &[&str]) -> Dictionary {
     d
 }
 
+/// Asserts that two rules are the same query up to variable names and body
+/// atom order, so the goldens do not pin the translator's naming choices.
+fn assert_same_query(actual: &str, expected: &str) {
+    let canonical = |rule: &str| {
+        rule.parse::<JoinQuery>()
+            .unwrap_or_else(|e| panic!("{rule:?} does not parse: {e}"))
+            .canonical()
+    };
+    assert_eq!(
+        canonical(actual),
+        canonical(expected),
+        "got {actual:?}, expected {expected:?}"
+    );
+}
+
 #[test]
 fn simple_bgp_one_triple() {
     let mut dict = build_dict(&["http://example/p", "http://example/c"]);
@@ -27,7 +43,7 @@ fn simple_bgp_one_triple() {
         "Q0",
     )
     .unwrap();
-    assert_eq!(out, "Q0(X) :- p(X, c1).");
+    assert_same_query(&out, "Q0(X) :- p(X, c1).");
 }
 
 #[test]
@@ -43,7 +59,7 @@ fn select_star_projects_all_bound_in_source_order() {
         "Q1",
     )
     .unwrap();
-    assert_eq!(out, "Q1(X, Y, Z) :- p(X, Y), q(Y, Z).");
+    assert_same_query(&out, "Q1(X, Y, Z) :- p(X, Y), q(Y, Z).");
 }
 
 #[test]
@@ -68,9 +84,9 @@ fn watdiv_style_select_star_with_constant_object() {
         "Q_test1_q0000",
     )
     .unwrap();
-    assert_eq!(
-        out,
-        "Q_test1_q0000(V0, V2) :- homepage(V0, c1), title(V0, V2)."
+    assert_same_query(
+        &out,
+        "Q_test1_q0000(V0, V2) :- homepage(V0, c1), title(V0, V2).",
     );
 }
 
@@ -93,8 +109,7 @@ fn predicate_map_disambiguates_sanitize_collisions() {
          ?x <http://purl.org/stuff/rev#title> <http://example/o2> . \
          }";
     let out = translate_query(sparql, &mut dict, &pm, "Q_collision").unwrap();
-    assert!(out.contains("title(X, c2)"), "got: {out}");
-    assert!(out.contains("title_1(X, c3)"), "got: {out}");
+    assert_same_query(&out, "Q_collision(S) :- title_1(S, c3), title(S, c2).");
 }
 
 #[test]
@@ -159,7 +174,7 @@ fn unknown_uri_added_to_dict() {
         .lookup(&RdfValue::Iri("http://example/unseen".into()))
         .unwrap();
     assert_eq!(assigned, 1);
-    assert!(rule.contains(&format!("c{assigned}")), "rule: {rule}");
+    assert_same_query(&rule, "Q4(X) :- p(X, c1).");
 }
 
 #[test]
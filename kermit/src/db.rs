@@ -9,12 +9,25 @@
 
 use {
     kermit_algos::{
-        rewrite_atoms, JoinAlgo, JoinAlgorithm, JoinQuery, LeapfrogTriejoin, SingletonTrieIter,
-        TrieIterKind,
+        dependency_order, evaluate_program, ground_tuple, resolve_constants,
+        resolve_program_constants, rewrite_atoms, validate_query, Aggregation, GenericJoin,
+        HashJoin, JoinAlgo, JoinAlgorithm, JoinQuery, LeapfrogTriejoin, Program, ProgramError,
+        QueryError, RewriteError, SingletonTrieIter, TrieIterKind, VariableOrderStrategy, ViewKey,
+    },
+    kermit_ds::{
+        BitmapTrie, ColumnTrie, CompressedColumnTrie, HashTrie, IndexStructure, Relation,
+        RelationFileExt, RelationHeader, SortedTupleArray, TreeTrie, TrieStats,
     },
-    kermit_ds::{ColumnTrie, IndexStructure, Relation, RelationFileExt, TreeTrie},
     kermit_iters::TrieIterable,
-    std::{collections::HashMap, path::Path},
+    kermit_parser::{Predicate, Term},
+    kermit_rdf::{dict::Dictionary, value::RdfValue},
+    std::{
+        cell::RefCell,
+        collections::{BTreeSet, HashMap},
+        ops::ControlFlow,
+        path::Path,
+        rc::Rc,
+    },
 };
 
 /// Object-safe interface for a relational database that can store relations
@@ -38,17 +51,125 @@ pub trait DB {
     /// Inserts multiple tuples into the named relation.
     fn add_keys_batch(&mut self, relation_name: &str, keys: Vec<Vec<usize>>);
 
+    /// Selects how subsequent joins choose their global variable order.
+    /// Defaults to [`VariableOrderStrategy::HeadFirst`].
+    fn set_variable_order_strategy(&mut self, strategy: VariableOrderStrategy);
+
+    /// Attaches a dictionary (such as the `dict.parquet` written by
+    /// `kermit_rdf::parquet::write_dict`) through which quoted-string and IRI
+    /// constants in queries resolve to IDs. Without one, or for a constant
+    /// it lacks, a body predicate matching such a constant matches nothing.
+    fn set_dictionary(&mut self, dictionary: Dictionary);
+
+    /// Checks `query` against the registered relations without running it;
+    /// see [`validate_query`]. Every method that runs a query does this
+    /// first.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`QueryError`] if a body or negated predicate is unknown or
+    /// has the wrong arity, a head variable is unbound, or a constant is
+    /// malformed.
+    fn validate(&self, query: &kermit_algos::JoinQuery) -> Result<(), QueryError>;
+
     /// Executes `query` against the registered relations and materialises
     /// the result tuples.
-    fn join(&self, query: kermit_algos::JoinQuery) -> Vec<Vec<usize>>;
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`QueryError`] if `query` fails [`DB::validate`]; nothing is
+    /// joined then.
+    fn join(&self, query: kermit_algos::JoinQuery) -> Result<Vec<Vec<usize>>, QueryError> {
+        let mut tuples = vec![];
+        self.join_each(query, &mut |tuple| {
+            tuples.push(tuple.to_vec());
+            ControlFlow::Continue(())
+        })?;
+        Ok(tuples)
+    }
+
+    /// Executes `query` and hands each result tuple to `sink` as the join
+    /// algorithm produces it, without materialising the result.
+    ///
+    /// The join stops as soon as `sink` returns [`ControlFlow::Break`], so
+    /// callers that only need a prefix of the result (e.g. `kermit join
+    /// --limit`) pay only for the tuples they consume.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`QueryError`] as [`DB::join`] does, before `sink` sees any
+    /// tuple.
+    fn join_each(
+        &self, query: kermit_algos::JoinQuery, sink: &mut dyn FnMut(&[usize]) -> ControlFlow<()>,
+    ) -> Result<(), QueryError>;
+
+    /// Executes `query` and returns the number of result tuples without
+    /// materialising them.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`QueryError`] as [`DB::join`] does.
+    fn count(&self, query: kermit_algos::JoinQuery) -> Result<usize, QueryError>;
+
+    /// Executes `query` and returns the size of each intermediate result
+    /// the join algorithm produced, or `None` if it produces none (see
+    /// [`JoinAlgo::intermediate_sizes`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`QueryError`] as [`DB::join`] does.
+    fn intermediate_sizes(
+        &self, query: kermit_algos::JoinQuery,
+    ) -> Result<Option<Vec<usize>>, QueryError>;
 
-    /// Loads a relation from a file (CSV or Parquet) and registers it.
+    /// Evaluates a possibly recursive `program` to its fixpoint over the
+    /// registered relations and materialises every derived predicate.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ProgramError`] if the program references unknown
+    /// predicates, disagrees with relation arities, has unsafe heads, or
+    /// negates a predicate that depends on itself.
+    fn evaluate(&self, program: &Program)
+        -> Result<HashMap<String, Vec<Vec<usize>>>, ProgramError>;
+
+    /// Prepares a non-recursive `program` for querying: materialises every
+    /// intensional predicate its output predicate (see
+    /// [`Program::output_predicate`]) depends on, in dependency order, as a
+    /// registered relation, and returns the query producing the output.
+    ///
+    /// Each predicate's relation holds its facts and the union of its rules'
+    /// results. An output defined by a single rule and no facts is returned
+    /// as that rule, so it streams like any other query; otherwise it is
+    /// materialised too and the returned query scans it. A program of one
+    /// rule and no facts is returned as that rule once its head is checked
+    /// to be the output; the query itself is checked when it runs.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ProgramError`] if the program is malformed in the sense of
+    /// [`DB::evaluate`], names an unknown output, or is recursive.
+    fn prepare_program(&mut self, program: &Program) -> Result<JoinQuery, ProgramError>;
+
+    /// Loads a relation from a file (CSV, Parquet or a `.ktrie` index
+    /// written by
+    /// [`ColumnTrie::write_ktrie`](kermit_ds::ColumnTrie::write_ktrie)) and
+    /// registers it.
     ///
     /// # Errors
     ///
     /// Returns `std::io::Error` if the extension is unsupported, the file
     /// cannot be read, or the relation cannot be parsed.
     fn add_file(&mut self, filepath: &Path) -> Result<(), std::io::Error>;
+
+    /// Loads a relation from a file like [`DB::add_file`], but registers it
+    /// as `name` rather than under the file's stem, as a Soufflé-style
+    /// `.input name(filename="…")` directive asks.
+    ///
+    /// # Errors
+    ///
+    /// Returns `std::io::Error` as [`DB::add_file`] does.
+    fn add_file_as(&mut self, name: &str, filepath: &Path) -> Result<(), std::io::Error>;
 }
 
 /// A typed relational database parameterized by its data structure `R` and
@@ -56,19 +177,29 @@ pub trait DB {
 ///
 /// Implements the object-safe [`DB`] trait so it can be used behind `Box<dyn
 /// DB>`.
+///
+/// Leapfrog-style joins require each body atom's trie to be keyed in the
+/// query's global variable order. When an atom disagrees (e.g. `r(Y, X)`
+/// with `X` ordered before `Y`), [`DB::join`] transparently joins against a
+/// reordered copy of the relation instead. Those copies are cached per
+/// relation and column order, and dropped whenever the source relation is
+/// modified.
 pub struct DatabaseEngine<R, JA>
 where
     R: Relation,
 {
     name: String,
     relations: HashMap<String, R>,
+    views: RefCell<HashMap<ViewKey, Rc<R>>>,
+    order_strategy: VariableOrderStrategy,
+    dictionary: Option<Dictionary>,
     phantom_rb: std::marker::PhantomData<R>,
     phantom_ja: std::marker::PhantomData<JA>,
 }
 
 impl<R, JA> DB for DatabaseEngine<R, JA>
 where
-    R: Relation + TrieIterable,
+    R: Relation + TrieIterable + TrieStats,
     JA: for<'a> JoinAlgo<TrieIterKind<'a, R>>,
 {
     fn new(name: String) -> Self
@@ -78,6 +209,9 @@ where
         DatabaseEngine {
             name,
             relations: HashMap::new(),
+            views: RefCell::new(HashMap::new()),
+            order_strategy: VariableOrderStrategy::default(),
+            dictionary: None,
             phantom_rb: std::marker::PhantomData,
             phantom_ja: std::marker::PhantomData,
         }
@@ -87,46 +221,310 @@ where
 
     fn add_relation(&mut self, name: &str, arity: usize) {
         let relation = R::new(arity.into());
+        self.invalidate_views(name);
         self.relations.insert(name.to_owned(), relation);
     }
 
     fn add_keys(&mut self, relation_name: &str, keys: Vec<usize>) {
+        self.invalidate_views(relation_name);
         self.relations.get_mut(relation_name).unwrap().insert(keys);
     }
 
     fn add_keys_batch(&mut self, relation_name: &str, keys: Vec<Vec<usize>>) {
+        self.invalidate_views(relation_name);
         self.relations
             .get_mut(relation_name)
             .unwrap()
             .insert_all(keys);
     }
 
-    fn join(&self, query: JoinQuery) -> Vec<Vec<usize>> {
-        let (rewritten, const_specs) =
-            rewrite_atoms(query).expect("malformed constant atom in query");
+    fn set_variable_order_strategy(&mut self, strategy: VariableOrderStrategy) {
+        self.order_strategy = strategy;
+    }
 
-        let mut wrappers: HashMap<String, TrieIterKind<'_, R>> = HashMap::new();
-        for pred in &rewritten.body {
-            if wrappers.contains_key(&pred.name) {
-                continue;
+    fn set_dictionary(&mut self, dictionary: Dictionary) { self.dictionary = Some(dictionary); }
+
+    fn validate(&self, query: &JoinQuery) -> Result<(), QueryError> {
+        validate_query(query, |name| {
+            self.relations
+                .get(name)
+                .map(|relation| relation.header().arity())
+        })
+    }
+
+    fn join_each(
+        &self, query: JoinQuery, sink: &mut dyn FnMut(&[usize]) -> ControlFlow<()>,
+    ) -> Result<(), QueryError> {
+        self.validate(&query)?;
+        let Some((aggregation, bindings)) = Aggregation::plan(&query) else {
+            self.with_join_inputs(query, |query, order, ds_map| {
+                for tuple in JA::join_iter_with_order(query, order, ds_map) {
+                    if sink(&tuple).is_break() {
+                        break;
+                    }
+                }
+            });
+            return Ok(());
+        };
+        self.with_join_inputs(bindings, |query, order, ds_map| {
+            let contiguous =
+                <JA as JoinAlgo<TrieIterKind<'_, R>>>::ORDERED && aggregation.groups_lead(&order);
+            let tuples = JA::join_iter_with_order(query, order, ds_map);
+            for row in aggregation.apply(tuples, contiguous) {
+                if sink(&row).is_break() {
+                    break;
+                }
             }
+        });
+        Ok(())
+    }
+
+    fn count(&self, query: JoinQuery) -> Result<usize, QueryError> {
+        if query.has_aggregates() {
+            // One row per group; the groups are only known once folded.
+            let mut groups = 0;
+            self.join_each(query, &mut |_| {
+                groups += 1;
+                ControlFlow::Continue(())
+            })?;
+            return Ok(groups);
+        }
+        self.validate(&query)?;
+        Ok(self
+            .with_join_inputs(query, |query, order, ds_map| {
+                JA::count_with_order(query, order, ds_map)
+            })
+            .unwrap_or(0))
+    }
+
+    fn intermediate_sizes(&self, query: JoinQuery) -> Result<Option<Vec<usize>>, QueryError> {
+        self.validate(&query)?;
+        let query = Aggregation::plan(&query).map_or(query, |(_, bindings)| bindings);
+        Ok(self
+            .with_join_inputs(query, |query, _, ds_map| {
+                JA::intermediate_sizes(query, ds_map)
+            })
+            .flatten())
+    }
+
+    fn evaluate(
+        &self, program: &Program,
+    ) -> Result<HashMap<String, Vec<Vec<usize>>>, ProgramError> {
+        let mut program = program.clone();
+        self.resolve_program_constants(&mut program);
+        let derived = evaluate_program::<R, JA>(&program, &self.relations)?;
+        Ok(derived
+            .into_iter()
+            .map(|(name, relation)| (name, relation.trie_iter().into_iter().collect()))
+            .collect())
+    }
+
+    fn prepare_program(&mut self, program: &Program) -> Result<JoinQuery, ProgramError> {
+        let output = program.output_predicate();
+        if let ([rule], []) = (program.rules.as_slice(), program.facts.as_slice()) {
+            if rule.head.name != output {
+                return Err(ProgramError::UnknownOutput(output.to_owned()));
+            }
+            return Ok(rule.clone());
+        }
+        let order: Vec<String> = dependency_order(program, &self.relations, output)?
+            .into_iter()
+            .map(str::to_owned)
+            .collect();
+        let mut program = program.clone();
+        self.resolve_program_constants(&mut program);
+
+        for name in &order {
+            let rules: Vec<&JoinQuery> = program
+                .rules
+                .iter()
+                .filter(|rule| &rule.head.name == name)
+                .collect();
+            let facts: Vec<&Predicate> = program
+                .facts
+                .iter()
+                .filter(|fact| &fact.name == name)
+                .collect();
+            if name == output && facts.is_empty() {
+                if let [rule] = rules.as_slice() {
+                    return Ok((*rule).clone());
+                }
+            }
+
+            let mut tuples: BTreeSet<Vec<usize>> = BTreeSet::new();
+            for fact in &facts {
+                tuples.extend(ground_tuple(fact)?);
+            }
+            for rule in &rules {
+                tuples.extend(self.join((*rule).clone())?);
+            }
+            let arity = match (rules.first(), facts.first()) {
+                | (Some(rule), _) => rule.head.terms.len(),
+                | (None, Some(fact)) => fact.terms.len(),
+                | (None, None) => unreachable!("dependency_order only lists defined predicates"),
+            };
+            self.add_relation(name, arity);
+            self.add_keys_batch(name, tuples.into_iter().collect());
+        }
+
+        // The output is materialised; scan it under its first rule's head
+        // variables where those are distinct, so result columns keep their
+        // names.
+        let first = program.rules.iter().find(|rule| rule.head.name == output);
+        let arity = self.relations[output].header().arity();
+        let mut vars: Vec<String> = Vec::with_capacity(arity);
+        for (i, term) in (0..arity).zip(first.into_iter().flat_map(|rule| &rule.head.terms)) {
+            match term {
+                | Term::Var(var) if !vars.contains(var) => vars.push(var.clone()),
+                | _ => vars.push(format!("V{i}")),
+            }
+        }
+        vars.extend((vars.len()..arity).map(|i| format!("V{i}")));
+        let scan = Predicate {
+            name: output.to_owned(),
+            terms: vars.into_iter().map(Term::Var).collect(),
+        };
+        Ok(JoinQuery {
+            head: scan.clone(),
+            body: vec![scan],
+            comparisons: vec![],
+            negated: vec![],
+        })
+    }
+
+    /// Loads a relation from a file (CSV, Parquet or `.ktrie`) and adds it
+    /// to the database.
+    ///
+    /// The file type is determined by the extension (.csv, .parquet or
+    /// .ktrie). The relation name is extracted from the filename, or for a
+    /// `.ktrie` index taken from the file itself.
+    fn add_file(&mut self, filepath: &Path) -> Result<(), std::io::Error> {
+        let relation = Self::read_file(filepath)?;
+        let relation_name = relation.header().name().to_string();
+        self.invalidate_views(&relation_name);
+        self.relations.insert(relation_name, relation);
+
+        Ok(())
+    }
+
+    fn add_file_as(&mut self, name: &str, filepath: &Path) -> Result<(), std::io::Error> {
+        let mut relation = Self::read_file(filepath)?;
+        if relation.header().name() != name {
+            // Headers are fixed at construction, so rebuild under the new name.
+            let header = RelationHeader::new(name, relation.header().attrs().to_vec());
+            relation = R::from_sorted_tuples(header, relation.trie_iter().into_iter().collect());
+        }
+        self.invalidate_views(name);
+        self.relations.insert(name.to_owned(), relation);
+
+        Ok(())
+    }
+}
+
+impl<R, JA> DatabaseEngine<R, JA>
+where
+    R: Relation + TrieIterable + TrieStats,
+{
+    /// Reads a relation from a CSV, Parquet or `.ktrie` file, chosen by
+    /// extension. CSV and Parquet relations are named after the file stem;
+    /// `.ktrie` relations keep the name stored in the file.
+    fn read_file(path: &Path) -> Result<R, std::io::Error> {
+        let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+        match extension.to_lowercase().as_str() {
+            | "csv" => R::from_csv(path)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())),
+            | "parquet" => R::from_parquet(path)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())),
+            | "ktrie" => R::from_ktrie(path)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())),
+            | _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Unsupported file extension: {}", extension),
+            )),
+        }
+    }
+
+    /// Looks up the string constants of `query` in the attached dictionary.
+    fn resolve_constants(&self, query: &mut JoinQuery) {
+        if let Some(dictionary) = &self.dictionary {
+            resolve_constants(query, |s| dictionary.lookup(&RdfValue::from_canonical(s)));
+        }
+    }
+
+    /// Looks up the string constants of every rule and fact of `program` in
+    /// the attached dictionary.
+    fn resolve_program_constants(&self, program: &mut Program) {
+        if let Some(dictionary) = &self.dictionary {
+            resolve_program_constants(program, |s| dictionary.lookup(&RdfValue::from_canonical(s)));
+        }
+    }
+
+    /// Rewrites constants in `query`, redirects atoms whose terms disagree
+    /// with the global variable order to reordered views, and hands the
+    /// rewritten query, that order and the matching data structures to `f`.
+    /// Returns `None` without calling `f` if a body predicate matches a
+    /// constant missing from the dictionary, since the query then has no
+    /// results.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `query` has not passed [`DB::validate`].
+    fn with_join_inputs<T>(
+        &self, mut query: JoinQuery,
+        f: impl FnOnce(JoinQuery, Vec<String>, HashMap<String, &TrieIterKind<'_, R>>) -> T,
+    ) -> Option<T> {
+        self.resolve_constants(&mut query);
+        let (mut rewritten, const_specs) = match rewrite_atoms(query) {
+            | Ok(rewritten) => rewritten,
+            | Err(RewriteError::UnknownConstant(_)) => return None,
+            | Err(e) => panic!("query was not validated: {e}"),
+        };
+
+        // Atoms whose terms disagree with the global variable order are
+        // redirected to a reordered view of their relation.
+        let order = self.order_strategy.order(&rewritten, |name| {
+            if name.starts_with("Const_") {
+                Some(vec![1])
+            } else {
+                self.relations.get(name).map(TrieStats::level_sizes)
+            }
+        });
+        let mut views: HashMap<String, Rc<R>> = HashMap::new();
+        for pred in &mut rewritten.body {
             // Const_* predicates are synthetic — created by rewrite_atoms
             // above and materialised from const_specs below. They aren't
             // expected to live in self.relations.
             if pred.name.starts_with("Const_") {
                 continue;
             }
-            match self.relations.get(&pred.name) {
-                | Some(r) => {
-                    wrappers.insert(pred.name.clone(), TrieIterKind::Relation(r));
-                },
-                | None => panic!(
-                    "DatabaseEngine::join: query body references unknown relation {:?}; known \
-                     relations: {:?}",
-                    pred.name,
-                    self.relations.keys().collect::<Vec<_>>(),
-                ),
+            let relation = &self.relations[&pred.name];
+            if let Some((key, terms)) = ViewKey::plan(&pred.name, &pred.terms, &order) {
+                let name = key.predicate_name();
+                if !views.contains_key(&name) {
+                    views.insert(name.clone(), self.view(relation, key));
+                }
+                pred.name = name;
+                pred.terms = terms;
+            }
+        }
+
+        let mut wrappers: HashMap<String, TrieIterKind<'_, R>> = HashMap::new();
+        for pred in &rewritten.body {
+            if wrappers.contains_key(&pred.name) || pred.name.starts_with("Const_") {
+                continue;
             }
+            let relation = match views.get(&pred.name) {
+                | Some(view) => view.as_ref(),
+                | None => &self.relations[&pred.name],
+            };
+            wrappers.insert(pred.name.clone(), TrieIterKind::Relation(relation));
+        }
+        // Negated atoms are probed in their own column order, so they always
+        // use the stored relation.
+        for pred in &rewritten.negated {
+            wrappers
+                .entry(pred.name.clone())
+                .or_insert_with(|| TrieIterKind::Relation(&self.relations[&pred.name]));
         }
         for (name, id) in const_specs {
             wrappers
@@ -137,35 +535,18 @@ where
         let ds_map: HashMap<String, &TrieIterKind<'_, R>> =
             wrappers.iter().map(|(k, v)| (k.clone(), v)).collect();
 
-        JA::join_iter(rewritten, ds_map).collect()
+        Some(f(rewritten, order, ds_map))
     }
 
-    /// Loads a relation from a file (CSV or Parquet) and adds it to the
-    /// database.
-    ///
-    /// The file type is determined by the extension (.csv or .parquet).
-    /// The relation name is extracted from the filename.
-    fn add_file(&mut self, filepath: &Path) -> Result<(), std::io::Error> {
-        let path = filepath;
-        let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("");
-
-        let relation = match extension.to_lowercase().as_str() {
-            | "csv" => R::from_csv(path)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?,
-            | "parquet" => R::from_parquet(path)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?,
-            | _ => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::InvalidInput,
-                    format!("Unsupported file extension: {}", extension),
-                ))
-            },
-        };
-
-        let relation_name = relation.header().name().to_string();
-        self.relations.insert(relation_name, relation);
-
-        Ok(())
+    /// Returns the cached view for `key`, building it from `relation` on
+    /// first use.
+    fn view(&self, relation: &R, key: ViewKey) -> Rc<R> {
+        Rc::clone(
+            self.views
+                .borrow_mut()
+                .entry(key)
+                .or_insert_with_key(|key| Rc::new(key.build(relation))),
+        )
     }
 }
 
@@ -173,12 +554,23 @@ impl<R, JA> DatabaseEngine<R, JA>
 where
     R: Relation,
 {
+    /// Drops every cached view derived from `relation_name`; called whenever
+    /// that relation is replaced or modified.
+    fn invalidate_views(&mut self, relation_name: &str) {
+        self.views
+            .get_mut()
+            .retain(|key, _| key.relation() != relation_name);
+    }
+
     /// Inherent constructor so tests can build the engine without needing
     /// the full [`DB`] trait bound in scope.
     pub fn new(name: String) -> Self {
         DatabaseEngine {
             name,
             relations: HashMap::new(),
+            views: RefCell::new(HashMap::new()),
+            order_strategy: VariableOrderStrategy::default(),
+            dictionary: None,
             phantom_rb: std::marker::PhantomData,
             phantom_ja: std::marker::PhantomData,
         }
@@ -188,13 +580,32 @@ where
 /// Creates a [`DatabaseEngine`] as a `Box<dyn DB>` based on the CLI-selected
 /// index structure and join algorithm.
 pub fn instantiate_database(ds: IndexStructure, ja: JoinAlgorithm) -> Box<dyn DB> {
-    match (ds, ja) {
-        | (IndexStructure::TreeTrie, JoinAlgorithm::LeapfrogTriejoin) => Box::new(
-            DatabaseEngine::<TreeTrie, LeapfrogTriejoin>::new("test".to_string()),
-        ),
-        | (IndexStructure::ColumnTrie, JoinAlgorithm::LeapfrogTriejoin) => Box::new(
-            DatabaseEngine::<ColumnTrie, LeapfrogTriejoin>::new("test".to_string()),
-        ),
+    fn engine<R, JA>() -> Box<dyn DB>
+    where
+        R: Relation + TrieIterable + TrieStats + 'static,
+        JA: for<'a> JoinAlgo<TrieIterKind<'a, R>> + 'static,
+    {
+        Box::new(DatabaseEngine::<R, JA>::new("test".to_string()))
+    }
+
+    fn with_algorithm<R>(ja: JoinAlgorithm) -> Box<dyn DB>
+    where
+        R: Relation + TrieIterable + TrieStats + 'static,
+    {
+        match ja {
+            | JoinAlgorithm::LeapfrogTriejoin => engine::<R, LeapfrogTriejoin>(),
+            | JoinAlgorithm::GenericJoin => engine::<R, GenericJoin>(),
+            | JoinAlgorithm::HashJoin => engine::<R, HashJoin>(),
+        }
+    }
+
+    match ds {
+        | IndexStructure::TreeTrie => with_algorithm::<TreeTrie>(ja),
+        | IndexStructure::ColumnTrie => with_algorithm::<ColumnTrie>(ja),
+        | IndexStructure::SortedTupleArray => with_algorithm::<SortedTupleArray>(ja),
+        | IndexStructure::CompressedColumnTrie => with_algorithm::<CompressedColumnTrie>(ja),
+        | IndexStructure::HashTrie => with_algorithm::<HashTrie>(ja),
+        | IndexStructure::BitmapTrie => with_algorithm::<BitmapTrie>(ja),
     }
 }
 
@@ -228,7 +639,7 @@ mod tests {
         db.add_keys_batch("second", vec![vec![1_usize], vec![2], vec![3]]);
 
         let query: JoinQuery = "Q(X) :- first(X), second(X).".parse().unwrap();
-        db.join(query);
+        db.join(query).unwrap();
     }
 
     #[test]
@@ -240,7 +651,7 @@ mod tests {
         db.add_keys_batch("p", vec![vec![1, 10], vec![2, 20], vec![3, 30]]);
 
         let query: JoinQuery = "Q(X) :- p(X, c10).".parse().unwrap();
-        let result = db.join(query);
+        let result = db.join(query).unwrap();
         let mut got: Vec<_> = result.iter().map(|r| r[0]).collect();
         got.sort();
         assert_eq!(
@@ -250,15 +661,628 @@ mod tests {
         );
     }
 
+    fn sorted(mut tuples: Vec<Vec<usize>>) -> Vec<Vec<usize>> {
+        tuples.sort();
+        tuples
+    }
+
+    #[test]
+    fn test_join_permutes_out_of_order_atoms() {
+        let mut db: DatabaseEngine<TreeTrie, LeapfrogTriejoin> =
+            DatabaseEngine::new("test".to_string());
+        db.add_relation("r", 2);
+        db.add_keys_batch("r", vec![vec![2, 1], vec![3, 1], vec![5, 4]]);
+        db.add_relation("s", 2);
+        db.add_keys_batch("s", vec![vec![1, 2], vec![1, 3], vec![4, 6]]);
+
+        // r is stored as (Y, X) but X precedes Y in the global order.
+        let query: JoinQuery = "Q(X, Y) :- r(Y, X), s(X, Y).".parse().unwrap();
+        assert_eq!(sorted(db.join(query).unwrap()), vec![vec![1, 2], vec![
+            1, 3
+        ]]);
+    }
+
     #[test]
-    #[should_panic(expected = "unknown relation")]
-    fn test_join_panics_on_missing_relation() {
+    fn test_join_permutes_self_join_with_reordered_head() {
+        let mut db: DatabaseEngine<ColumnTrie, LeapfrogTriejoin> =
+            DatabaseEngine::new("test".to_string());
+        db.add_relation("edge", 2);
+        db.add_keys_batch("edge", vec![vec![1, 2], vec![2, 3], vec![3, 4], vec![1, 3]]);
+
+        // Global order Z, X, Y: both edge atoms need a reordered view.
+        let query: JoinQuery = "Q(Z, X) :- edge(X, Y), edge(Y, Z).".parse().unwrap();
+        assert_eq!(sorted(db.join(query).unwrap()), vec![
+            vec![3, 1],
+            vec![4, 1],
+            vec![4, 2]
+        ]);
+    }
+
+    #[test]
+    fn test_join_drops_placeholder_columns() {
+        let mut db: DatabaseEngine<TreeTrie, LeapfrogTriejoin> =
+            DatabaseEngine::new("test".to_string());
+        db.add_relation("r", 2);
+        db.add_keys_batch("r", vec![vec![1, 10], vec![2, 10], vec![3, 20]]);
+
+        let query: JoinQuery = "Q(X) :- r(_, X).".parse().unwrap();
+        assert_eq!(sorted(db.join(query).unwrap()), vec![vec![10], vec![20]]);
+    }
+
+    #[test]
+    fn test_join_filters_repeated_variables() {
+        let mut db: DatabaseEngine<TreeTrie, LeapfrogTriejoin> =
+            DatabaseEngine::new("test".to_string());
+        db.add_relation("r", 2);
+        db.add_keys_batch("r", vec![vec![1, 1], vec![1, 2], vec![3, 3]]);
+
+        let query: JoinQuery = "Q(X) :- r(X, X).".parse().unwrap();
+        assert_eq!(sorted(db.join(query).unwrap()), vec![vec![1], vec![3]]);
+    }
+
+    #[test]
+    fn test_join_caches_views_until_relation_changes() {
+        let mut db: DatabaseEngine<TreeTrie, LeapfrogTriejoin> =
+            DatabaseEngine::new("test".to_string());
+        db.add_relation("r", 2);
+        db.add_keys_batch("r", vec![vec![2, 1]]);
+
+        let query = "Q(X, Y) :- r(Y, X).";
+        assert_eq!(db.join(query.parse().unwrap()).unwrap(), vec![vec![1, 2]]);
+        assert_eq!(db.views.borrow().len(), 1);
+        db.join(query.parse().unwrap()).unwrap();
+        assert_eq!(db.views.borrow().len(), 1, "view should be reused");
+
+        db.add_keys("r", vec![4, 3]);
+        assert!(
+            db.views.borrow().is_empty(),
+            "insert should drop stale views"
+        );
+        assert_eq!(sorted(db.join(query.parse().unwrap()).unwrap()), vec![
+            vec![1, 2],
+            vec![3, 4]
+        ]);
+    }
+
+    #[test]
+    fn test_join_in_order_atoms_skip_views() {
+        let mut db: DatabaseEngine<TreeTrie, LeapfrogTriejoin> =
+            DatabaseEngine::new("test".to_string());
+        db.add_relation("edge", 2);
+        db.add_keys_batch("edge", vec![vec![1, 2], vec![2, 3]]);
+
+        let query: JoinQuery = "Q(X, Y, Z) :- edge(X, Y), edge(Y, Z).".parse().unwrap();
+        assert_eq!(db.join(query).unwrap(), vec![vec![1, 2, 3]]);
+        assert!(db.views.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_join_greedy_order_matches_head_first() {
+        let mut db: DatabaseEngine<ColumnTrie, LeapfrogTriejoin> =
+            DatabaseEngine::new("test".to_string());
+        db.add_relation("r", 2);
+        db.add_keys_batch("r", (0..50).map(|i| vec![i, i % 3]).collect());
+        db.add_relation("s", 2);
+        db.add_keys_batch("s", vec![vec![0, 7], vec![1, 8], vec![2, 9]]);
+
+        let query = "Q(X, Y, Z) :- r(X, Y), s(Y, Z).";
+        let head_first = sorted(db.join(query.parse().unwrap()).unwrap());
+        db.set_variable_order_strategy(VariableOrderStrategy::Greedy);
+        let greedy = sorted(db.join(query.parse().unwrap()).unwrap());
+        assert_eq!(head_first.len(), 50);
+        assert_eq!(greedy, head_first);
+    }
+
+    #[test]
+    fn test_join_each_streams_same_tuples_as_join() {
+        let mut db: DatabaseEngine<ColumnTrie, HashJoin> = DatabaseEngine::new("test".to_string());
+        db.add_relation("e", 2);
+        db.add_keys_batch("e", vec![vec![1, 2], vec![2, 3], vec![3, 4], vec![2, 4]]);
+
+        let query = "Q(X, Z) :- e(X, Y), e(Y, Z).";
+        let mut streamed = vec![];
+        db.join_each(query.parse().unwrap(), &mut |tuple| {
+            streamed.push(tuple.to_vec());
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        assert_eq!(
+            sorted(streamed),
+            sorted(db.join(query.parse().unwrap()).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_join_each_stops_on_break() {
+        let mut db: DatabaseEngine<TreeTrie, LeapfrogTriejoin> =
+            DatabaseEngine::new("test".to_string());
+        db.add_relation("r", 1);
+        db.add_keys_batch("r", (0..1000).map(|i| vec![i]).collect());
+
+        let mut seen = 0;
+        db.join_each("Q(X) :- r(X).".parse().unwrap(), &mut |_| {
+            seen += 1;
+            if seen == 3 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+        .unwrap();
+        assert_eq!(seen, 3);
+    }
+
+    #[test]
+    fn test_count_matches_join_for_every_algorithm() {
+        let edges = vec![vec![1, 2], vec![2, 3], vec![3, 1], vec![3, 4], vec![2, 4]];
+        let queries = [
+            "Q(X, Z) :- e(X, Y), e(Y, Z).",
+            "Q(X) :- e(X, Y), e(Y, Z).",
+            "Q(X, Y, Z) :- e(X, Y), e(Y, Z), e(Z, X).",
+            "Q(X) :- e(X, c4).",
+        ];
+        for ja in [
+            JoinAlgorithm::LeapfrogTriejoin,
+            JoinAlgorithm::GenericJoin,
+            JoinAlgorithm::HashJoin,
+        ] {
+            let mut db = instantiate_database(IndexStructure::ColumnTrie, ja);
+            db.add_relation("e", 2);
+            db.add_keys_batch("e", edges.clone());
+            for query in queries {
+                assert_eq!(
+                    db.count(query.parse().unwrap()).unwrap(),
+                    db.join(query.parse().unwrap()).unwrap().len(),
+                    "{ja:?}: {query}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_comparisons_agree_across_algorithms_and_structures() {
+        let edges = vec![
+            vec![1, 1],
+            vec![1, 2],
+            vec![1, 3],
+            vec![2, 1],
+            vec![2, 3],
+            vec![3, 1],
+            vec![3, 2],
+            vec![3, 4],
+        ];
+        let cases: [(&str, Vec<Vec<usize>>); 4] = [
+            (
+                "Q(X, Y, Z) :- e(X, Y), e(Y, Z), e(X, Z), X < Y, Y < Z.",
+                vec![vec![1, 2, 3]],
+            ),
+            ("Q(X, Y) :- e(X, Y), X != Y, Y <= 2.", vec![
+                vec![1, 2],
+                vec![2, 1],
+                vec![3, 1],
+                vec![3, 2],
+            ]),
+            ("Q(Y, X) :- e(X, Y), Y > X, X >= 2.", vec![
+                vec![3, 2],
+                vec![4, 3],
+            ]),
+            ("Q(X) :- e(X, Y), Y = 4.", vec![vec![3]]),
+        ];
+        for ds in [
+            IndexStructure::TreeTrie,
+            IndexStructure::ColumnTrie,
+            IndexStructure::SortedTupleArray,
+            IndexStructure::CompressedColumnTrie,
+            IndexStructure::HashTrie,
+            IndexStructure::BitmapTrie,
+        ] {
+            for ja in [
+                JoinAlgorithm::LeapfrogTriejoin,
+                JoinAlgorithm::GenericJoin,
+                JoinAlgorithm::HashJoin,
+            ] {
+                let mut db = instantiate_database(ds, ja);
+                db.add_relation("e", 2);
+                db.add_keys_batch("e", edges.clone());
+                for (query, expected) in &cases {
+                    let mut got = db.join(query.parse().unwrap()).unwrap();
+                    got.sort();
+                    got.dedup();
+                    assert_eq!(&got, expected, "{ds:?}/{ja:?}: {query}");
+                    assert_eq!(
+                        db.count(query.parse().unwrap()).unwrap(),
+                        db.join(query.parse().unwrap()).unwrap().len(),
+                        "{ds:?}/{ja:?}: {query}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_negation_agrees_across_algorithms_and_structures() {
+        let wrote = vec![vec![1, 10], vec![2, 10], vec![2, 11], vec![3, 12], vec![
+            4, 13,
+        ]];
+        let coauthor = vec![vec![1, 2], vec![2, 1]];
+        let cases: [(&str, Vec<Vec<usize>>); 4] = [
+            ("Q(A) :- wrote(A, P), not coauthor(A, _).", vec![
+                vec![3],
+                vec![4],
+            ]),
+            (
+                "Q(A, B) :- wrote(A, P), wrote(B, P), A != B, not coauthor(B, A).",
+                vec![],
+            ),
+            (
+                "Q(P, A) :- wrote(A, P), not wrote(c2, P), not coauthor(c1, A).",
+                vec![vec![12, 3], vec![13, 4]],
+            ),
+            ("Q(A) :- wrote(A, P), not coauthor(c9, c9).", vec![
+                vec![1],
+                vec![2],
+                vec![3],
+                vec![4],
+            ]),
+        ];
+        for ds in [
+            IndexStructure::TreeTrie,
+            IndexStructure::ColumnTrie,
+            IndexStructure::SortedTupleArray,
+            IndexStructure::CompressedColumnTrie,
+            IndexStructure::HashTrie,
+            IndexStructure::BitmapTrie,
+        ] {
+            for ja in [
+                JoinAlgorithm::LeapfrogTriejoin,
+                JoinAlgorithm::GenericJoin,
+                JoinAlgorithm::HashJoin,
+            ] {
+                let mut db = instantiate_database(ds, ja);
+                db.add_relation("wrote", 2);
+                db.add_keys_batch("wrote", wrote.clone());
+                db.add_relation("coauthor", 2);
+                db.add_keys_batch("coauthor", coauthor.clone());
+                for (query, expected) in &cases {
+                    let mut got = db.join(query.parse().unwrap()).unwrap();
+                    got.sort();
+                    assert_eq!(&got, expected, "{ds:?}/{ja:?}: {query}");
+                    assert_eq!(
+                        db.count(query.parse().unwrap()).unwrap(),
+                        expected.len(),
+                        "{ds:?}/{ja:?}: {query}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_aggregates_agree_across_algorithms_and_structures() {
+        let edge = vec![
+            vec![1, 2],
+            vec![1, 3],
+            vec![1, 4],
+            vec![2, 3],
+            vec![2, 4],
+            vec![3, 4],
+        ];
+        let cases: [(&str, Vec<Vec<usize>>); 6] = [
+            ("deg(X, count(Y)) :- edge(X, Y).", vec![
+                vec![1, 3],
+                vec![2, 2],
+                vec![3, 1],
+            ]),
+            // Grouped on a variable the default order binds second.
+            ("indeg(count(X), Y) :- edge(X, Y).", vec![
+                vec![1, 2],
+                vec![2, 3],
+                vec![3, 4],
+            ]),
+            (
+                "tri(X, count(Y)) :- edge(X, Y), edge(Y, Z), edge(X, Z).",
+                vec![vec![1, 3], vec![2, 1]],
+            ),
+            ("Q(X, sum(Y), min(Y), max(Y)) :- edge(X, Y), Y > 2.", vec![
+                vec![1, 7, 3, 4],
+                vec![2, 7, 3, 4],
+                vec![3, 4, 4, 4],
+            ]),
+            ("Q(count(X)) :- edge(X, Y).", vec![vec![6]]),
+            ("Q(count(X)) :- edge(X, Y), Y > 9.", vec![]),
+        ];
+        for ds in [
+            IndexStructure::TreeTrie,
+            IndexStructure::ColumnTrie,
+            IndexStructure::SortedTupleArray,
+            IndexStructure::CompressedColumnTrie,
+            IndexStructure::HashTrie,
+            IndexStructure::BitmapTrie,
+        ] {
+            for ja in [
+                JoinAlgorithm::LeapfrogTriejoin,
+                JoinAlgorithm::GenericJoin,
+                JoinAlgorithm::HashJoin,
+            ] {
+                let mut db = instantiate_database(ds, ja);
+                db.add_relation("edge", 2);
+                db.add_keys_batch("edge", edge.clone());
+                for strategy in [
+                    VariableOrderStrategy::HeadFirst,
+                    VariableOrderStrategy::Greedy,
+                ] {
+                    db.set_variable_order_strategy(strategy);
+                    for (query, expected) in &cases {
+                        let mut got = db.join(query.parse().unwrap()).unwrap();
+                        got.sort();
+                        assert_eq!(&got, expected, "{ds:?}/{ja:?}/{strategy:?}: {query}");
+                        assert_eq!(
+                            db.count(query.parse().unwrap()).unwrap(),
+                            expected.len(),
+                            "{ds:?}/{ja:?}/{strategy:?}: {query}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_string_constants_resolve_through_dictionary() {
+        let mut dictionary = Dictionary::new();
+        for value in ["<http://x/alice>", "\"bob\"", "<http://x/paper>"] {
+            dictionary.intern(RdfValue::from_canonical(value));
+        }
+        let wrote = vec![vec![0, 2], vec![1, 2], vec![1, 3]];
+        let cases: [(&str, Vec<Vec<usize>>); 5] = [
+            (r#"Q(P) :- wrote("bob", P)."#, vec![vec![2], vec![3]]),
+            ("Q(A) :- wrote(A, <http://x/paper>).", vec![vec![0], vec![
+                1,
+            ]]),
+            ("Q(A) :- wrote(A, 3).", vec![vec![1]]),
+            (r#"Q(P) :- wrote("carol", P)."#, vec![]),
+            (r#"Q(A) :- wrote(A, P), not wrote("carol", P)."#, vec![
+                vec![0],
+                vec![1],
+            ]),
+        ];
+        for ja in [
+            JoinAlgorithm::LeapfrogTriejoin,
+            JoinAlgorithm::GenericJoin,
+            JoinAlgorithm::HashJoin,
+        ] {
+            let mut db = instantiate_database(IndexStructure::ColumnTrie, ja);
+            db.add_relation("wrote", 2);
+            db.add_keys_batch("wrote", wrote.clone());
+            // Without a dictionary no string constant names a stored value.
+            assert!(db
+                .join(r#"Q(P) :- wrote("bob", P)."#.parse().unwrap())
+                .unwrap()
+                .is_empty());
+
+            db.set_dictionary(dictionary.clone());
+            for (query, expected) in &cases {
+                let mut got = db.join(query.parse().unwrap()).unwrap();
+                got.sort();
+                assert_eq!(&got, expected, "{ja:?}: {query}");
+                assert_eq!(
+                    db.count(query.parse().unwrap()).unwrap(),
+                    expected.len(),
+                    "{ja:?}: {query}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_negation_rejects_missing_relation() {
+        let mut db: DatabaseEngine<TreeTrie, LeapfrogTriejoin> =
+            DatabaseEngine::new("test".to_string());
+        db.add_relation("r", 1);
+        assert_eq!(
+            db.join("Q(X) :- r(X), not missing(X).".parse().unwrap()),
+            Err(QueryError::UnknownPredicate("missing".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_join_rejects_missing_relation() {
         let db: DatabaseEngine<TreeTrie, LeapfrogTriejoin> =
             DatabaseEngine::new("test".to_string());
 
         // `missing` was never added; previously the body predicate was
         // silently dropped, which could mask typos or load failures.
         let query: JoinQuery = "Q(X) :- missing(X).".parse().unwrap();
-        db.join(query);
+        assert_eq!(
+            db.join(query),
+            Err(QueryError::UnknownPredicate("missing".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_join_rejects_arity_mismatch() {
+        let mut db: DatabaseEngine<ColumnTrie, LeapfrogTriejoin> =
+            DatabaseEngine::new("test".to_string());
+        db.add_relation("edge", 2);
+        db.add_keys_batch("edge", vec![vec![1, 2], vec![2, 3]]);
+
+        // A third column used to be joined as if it were absent.
+        let query: JoinQuery = "Q(X, Z) :- edge(X, Y, Z).".parse().unwrap();
+        assert_eq!(
+            db.join(query.clone()),
+            Err(QueryError::ArityMismatch {
+                predicate: "edge".to_string(),
+                expected: 2,
+                found: 3,
+            })
+        );
+        assert!(db.count(query.clone()).is_err());
+        assert!(db.intermediate_sizes(query).is_err());
+    }
+
+    #[test]
+    fn test_join_rejects_unsafe_head_and_malformed_constant() {
+        let mut db: DatabaseEngine<ColumnTrie, LeapfrogTriejoin> =
+            DatabaseEngine::new("test".to_string());
+        db.add_relation("edge", 2);
+
+        assert_eq!(
+            db.join("Q(X, W) :- edge(X, Y).".parse().unwrap()),
+            Err(QueryError::UnsafeHeadVariable("W".to_string()))
+        );
+        assert_eq!(
+            db.join("Q(X) :- edge(X, bob).".parse().unwrap()),
+            Err(QueryError::MalformedConstant(RewriteError::BadAtom(
+                "bob".to_string()
+            )))
+        );
+    }
+
+    #[test]
+    fn test_add_file_as_registers_under_the_given_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("edges.csv");
+        std::fs::write(&path, "src,dst\n2,3\n1,2\n").unwrap();
+
+        let mut db: DatabaseEngine<ColumnTrie, LeapfrogTriejoin> =
+            DatabaseEngine::new("test".to_string());
+        db.add_file_as("edge", &path).unwrap();
+        assert_eq!(
+            db.join("Q(X, Y) :- edge(X, Y).".parse().unwrap()).unwrap(),
+            vec![vec![1, 2], vec![2, 3]]
+        );
+        assert_eq!(
+            db.validate(&"Q(X) :- edges(X, Y).".parse().unwrap()),
+            Err(QueryError::UnknownPredicate("edges".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_add_file_loads_ktrie_indexes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index.ktrie");
+        let header = RelationHeader::new("edge", vec!["src".into(), "dst".into()]);
+        ColumnTrie::from_tuples(header, vec![vec![2, 3], vec![1, 2]])
+            .write_ktrie(&path)
+            .unwrap();
+
+        let mut db: DatabaseEngine<ColumnTrie, LeapfrogTriejoin> =
+            DatabaseEngine::new("test".to_string());
+        db.add_file(&path).unwrap();
+        let mut tree_db: DatabaseEngine<TreeTrie, LeapfrogTriejoin> =
+            DatabaseEngine::new("test".to_string());
+        tree_db.add_file(&path).unwrap();
+        for result in [
+            db.join("Q(X, Z) :- edge(X, Y), edge(Y, Z).".parse().unwrap()),
+            tree_db.join("Q(X, Z) :- edge(X, Y), edge(Y, Z).".parse().unwrap()),
+        ] {
+            assert_eq!(result.unwrap(), vec![vec![1, 3]]);
+        }
+    }
+
+    #[test]
+    fn test_evaluate_recursive_program() {
+        let mut db: DatabaseEngine<ColumnTrie, LeapfrogTriejoin> =
+            DatabaseEngine::new("test".to_string());
+        db.add_relation("edge", 2);
+        db.add_keys_batch("edge", vec![vec![1, 2], vec![2, 3], vec![3, 1], vec![4, 5]]);
+
+        let program: Program = "tc(X, Y) :- edge(X, Y).\ntc(X, Z) :- tc(X, Y), edge(Y, Z)."
+            .parse()
+            .unwrap();
+        let derived = db.evaluate(&program).unwrap();
+        let mut expected: Vec<Vec<usize>> = (1..=3)
+            .flat_map(|x| (1..=3).map(move |y| vec![x, y]))
+            .collect();
+        expected.push(vec![4, 5]);
+        assert_eq!(derived["tc"], expected);
+    }
+
+    #[test]
+    fn test_evaluate_reports_unknown_predicate() {
+        let db: DatabaseEngine<TreeTrie, LeapfrogTriejoin> =
+            DatabaseEngine::new("test".to_string());
+        let program: Program = "p(X) :- missing(X).".parse().unwrap();
+        assert_eq!(
+            db.evaluate(&program),
+            Err(ProgramError::UnknownPredicate("missing".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_prepare_program_materialises_dependencies_in_order() {
+        let program: Program = [
+            "% Two hops, minus direct edges, plus a seeded pair.",
+            "hop2(X, Z) :- edge(X, Y), edge(Y, Z).",
+            "pair(X, Y) :- hop2(X, Y), not edge(X, Y).",
+            "pair(X, Y) :- seed(X, Y).",
+            "seed(9, 9).",
+            "unused(X) :- edge(X, X).",
+            ".output pair",
+        ]
+        .join("\n")
+        .parse()
+        .unwrap();
+        fn check<R, JA>(program: &Program)
+        where
+            R: Relation + TrieIterable + TrieStats,
+            JA: for<'a> JoinAlgo<TrieIterKind<'a, R>>,
+        {
+            let mut db: DatabaseEngine<R, JA> = DatabaseEngine::new("test".to_string());
+            db.add_relation("edge", 2);
+            db.add_keys_batch("edge", vec![vec![1, 2], vec![2, 3], vec![1, 3], vec![3, 4]]);
+            let query = db.prepare_program(program).unwrap();
+            assert_eq!(query, "pair(X, Y) :- pair(X, Y).".parse().unwrap());
+            assert_eq!(sorted(db.join(query).unwrap()), vec![
+                vec![1, 4],
+                vec![2, 4],
+                vec![9, 9]
+            ]);
+            assert!(db.relations.contains_key("hop2"));
+            assert!(!db.relations.contains_key("unused"));
+        }
+        check::<TreeTrie, LeapfrogTriejoin>(&program);
+        check::<ColumnTrie, GenericJoin>(&program);
+        check::<SortedTupleArray, HashJoin>(&program);
+    }
+
+    #[test]
+    fn test_prepare_program_streams_a_single_output_rule() {
+        let mut db: DatabaseEngine<TreeTrie, LeapfrogTriejoin> =
+            DatabaseEngine::new("test".to_string());
+        db.add_relation("edge", 2);
+        db.add_keys_batch("edge", vec![vec![1, 2], vec![2, 3]]);
+        let program: Program = "mid(Y) :- edge(X, Y).\nout(Y, count(X)) :- edge(X, Y), mid(Y)."
+            .parse()
+            .unwrap();
+        let query = db.prepare_program(&program).unwrap();
+        assert_eq!(query, program.rules[1]);
+        assert_eq!(db.join(query).unwrap(), vec![vec![2, 1], vec![3, 1]]);
+    }
+
+    #[test]
+    fn test_prepare_program_rejects_recursion() {
+        let mut db: DatabaseEngine<TreeTrie, LeapfrogTriejoin> =
+            DatabaseEngine::new("test".to_string());
+        db.add_relation("edge", 2);
+        let program: Program = "tc(X, Y) :- edge(X, Y).\ntc(X, Z) :- tc(X, Y), edge(Y, Z)."
+            .parse()
+            .unwrap();
+        assert_eq!(
+            db.prepare_program(&program),
+            Err(ProgramError::Recursive("tc".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_prepare_program_rejects_an_unknown_output_of_a_single_rule() {
+        let mut db: DatabaseEngine<TreeTrie, LeapfrogTriejoin> =
+            DatabaseEngine::new("test".to_string());
+        db.add_relation("r", 2);
+        let program: Program = "out(X, Y) :- r(X, Y).\n.output nope".parse().unwrap();
+        assert_eq!(
+            db.prepare_program(&program),
+            Err(ProgramError::UnknownOutput("nope".to_string()))
+        );
     }
 }
@@ -9,7 +9,7 @@
 
 /// Re-exports of join algorithms from [`kermit_algos`].
 pub mod algos {
-    pub use kermit_algos::LeapfrogTriejoin;
+    pub use kermit_algos::{GenericJoin, LeapfrogTriejoin};
 }
 
 /// Re-exports of relation data structures from [`kermit_ds`].
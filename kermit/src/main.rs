@@ -1,7 +1,8 @@
 //! Kermit command-line interface.
 //!
-//! Two top-level subcommands: `join` (execute a Datalog query against
-//! relation files) and `bench` (Criterion-based benchmarks, including the
+//! Three top-level subcommands: `join` (execute a Datalog query against
+//! relation files), `run` (evaluate a program file that declares its own
+//! inputs and outputs) and `bench` (Criterion-based benchmarks, including the
 //! YAML-defined benchmarks under `benchmarks/`).
 //!
 //! Run `kermit --help` for the full help text; each `clap` `#[arg(help =
@@ -13,15 +14,16 @@
 use {
     clap::{Args, Parser, Subcommand},
     kermit::db::instantiate_database,
-    kermit_algos::{JoinAlgorithm, JoinQuery},
+    kermit_algos::{JoinAlgorithm, JoinQuery, Program, QueryHypergraph, VariableOrderStrategy},
     kermit_bench::BenchmarkDefinition,
-    kermit_ds::{HeapSize, IndexStructure, Relation, RelationFileExt},
+    kermit_ds::{ColumnTrie, HeapSize, IndexStructure, Relation, RelationFileExt},
     kermit_iters::TrieIterable,
-    kermit_parser::Term,
+    kermit_parser::{IoDirective, Predicate, Term},
     std::{
-        collections::BTreeMap,
+        collections::{BTreeMap, HashMap},
         fs,
         io::{self, BufWriter, Write},
+        ops::ControlFlow,
         path::{Path, PathBuf},
         time::Duration,
     },
@@ -50,7 +52,8 @@ struct QueryArgs {
     #[arg(short, long, value_name = "PATH", num_args = 1.., required = true)]
     relations: Vec<PathBuf>,
 
-    /// Query file path
+    /// Query file path: a single rule, or a non-recursive program whose
+    /// output predicate is joined
     #[arg(short, long, value_name = "PATH", required = true)]
     query: PathBuf,
 
@@ -67,6 +70,15 @@ struct QueryArgs {
         value_enum
     )]
     indexstructure: IndexStructure,
+
+    /// Variable ordering strategy for the join
+    #[arg(long, value_name = "STRATEGY", value_enum, default_value_t)]
+    variable_order: VariableOrderStrategy,
+
+    /// Dictionary Parquet file (e.g. a kermit-rdf `dict.parquet`) resolving
+    /// quoted-string and IRI constants in the query
+    #[arg(long, value_name = "PATH")]
+    dictionary: Option<PathBuf>,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, clap::ValueEnum)]
@@ -84,6 +96,10 @@ enum Metric {
 enum IndexStructureSelector {
     TreeTrie,
     ColumnTrie,
+    SortedTupleArray,
+    CompressedColumnTrie,
+    HashTrie,
+    BitmapTrie,
     All,
 }
 
@@ -93,21 +109,24 @@ impl IndexStructureSelector {
         match self {
             | Self::TreeTrie => vec![IndexStructure::TreeTrie],
             | Self::ColumnTrie => vec![IndexStructure::ColumnTrie],
+            | Self::SortedTupleArray => vec![IndexStructure::SortedTupleArray],
+            | Self::CompressedColumnTrie => vec![IndexStructure::CompressedColumnTrie],
+            | Self::HashTrie => vec![IndexStructure::HashTrie],
+            | Self::BitmapTrie => vec![IndexStructure::BitmapTrie],
             | Self::All => IndexStructure::value_variants().to_vec(),
         }
     }
 }
 
 /// CLI-side selector for `--algorithm`. Wraps [`JoinAlgorithm`] with an
-/// `All` variant for sweeps. `LeapfrogTriejoin` is currently the only
-/// concrete algorithm, so `All` and `LeapfrogTriejoin` produce the same
-/// sweep today; the selector exists so adding new algorithms is purely
-/// additive — `All` resolves through `clap::ValueEnum::value_variants()`,
-/// so a new variant on `JoinAlgorithm` automatically joins the sweep
-/// without touching this match.
+/// `All` variant for sweeps. `All` resolves through
+/// `clap::ValueEnum::value_variants()`, so a new variant on `JoinAlgorithm`
+/// automatically joins the sweep without touching this match.
 #[derive(Copy, Clone, Debug, PartialEq, clap::ValueEnum)]
 enum JoinAlgorithmSelector {
     LeapfrogTriejoin,
+    GenericJoin,
+    HashJoin,
     All,
 }
 
@@ -116,6 +135,8 @@ impl JoinAlgorithmSelector {
         use clap::ValueEnum;
         match self {
             | Self::LeapfrogTriejoin => vec![JoinAlgorithm::LeapfrogTriejoin],
+            | Self::GenericJoin => vec![JoinAlgorithm::GenericJoin],
+            | Self::HashJoin => vec![JoinAlgorithm::HashJoin],
             | Self::All => JoinAlgorithm::value_variants().to_vec(),
         }
     }
@@ -218,6 +239,10 @@ enum BenchSubcommand {
         #[arg(short, long, value_name = "ALGORITHM", required = true, value_enum)]
         algorithm: JoinAlgorithmSelector,
 
+        /// Variable ordering strategy for every query's join
+        #[arg(long, value_name = "STRATEGY", value_enum, default_value_t)]
+        variable_order: VariableOrderStrategy,
+
         /// Metrics to benchmark
         #[arg(
             short,
@@ -360,6 +385,59 @@ enum Commands {
         /// Output file (optional, defaults to stdout)
         #[arg(short, long, value_name = "PATH")]
         output: Option<PathBuf>,
+
+        /// Stop the join after writing this many result rows
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+
+        /// Print only the number of result rows instead of the rows
+        #[arg(long, conflicts_with = "limit")]
+        count: bool,
+    },
+
+    /// Run a program file, reading its `.input` relations and writing each
+    /// `.output` relation as CSV
+    Run {
+        /// Program file path
+        #[arg(value_name = "PROGRAM")]
+        program: PathBuf,
+
+        /// Join algorithm
+        #[arg(
+            short,
+            long,
+            value_name = "ALGORITHM",
+            value_enum,
+            default_value = "leapfrog-triejoin"
+        )]
+        algorithm: JoinAlgorithm,
+
+        /// Data structure
+        #[arg(
+            short,
+            long,
+            value_name = "INDEXSTRUCTURE",
+            value_enum,
+            default_value = "column-trie"
+        )]
+        indexstructure: IndexStructure,
+
+        /// Variable ordering strategy for every rule's join
+        #[arg(long, value_name = "STRATEGY", value_enum, default_value_t)]
+        variable_order: VariableOrderStrategy,
+
+        /// Dictionary Parquet file resolving quoted-string and IRI constants
+        #[arg(long, value_name = "PATH")]
+        dictionary: Option<PathBuf>,
+
+        /// Directory `.input` filenames are resolved against (default: the
+        /// program's directory)
+        #[arg(short = 'F', long, value_name = "DIR")]
+        fact_dir: Option<PathBuf>,
+
+        /// Directory `.output` files are written to
+        #[arg(short = 'D', long, value_name = "DIR", default_value = ".")]
+        output_dir: PathBuf,
     },
 
     /// Run a Criterion benchmark
@@ -376,54 +454,239 @@ use materialize::{vendored_lubm_jar, vendored_watdiv_root, workspace_root};
 
 /// Column names derived from a query's head predicate. `Var(X)` becomes
 /// `"X"`, `Atom(c)` becomes `"c"` (constants are pre-rewritten by
-/// `rewrite_atoms` so they appear as `c<id>` in the head when present), and
-/// `Placeholder` becomes `"_"`.
+/// `rewrite_atoms` so they appear as `c<id>` in the head when present),
+/// `Placeholder` becomes `"_"`, and integer, string and aggregate terms keep
+/// their source spelling, e.g. `"42"` or `"count(Y)"`.
 fn head_column_names(query: &JoinQuery) -> Vec<String> {
     query
         .head
         .terms
         .iter()
         .map(|t| match t {
-            | Term::Var(name) | Term::Atom(name) => name.clone(),
+            | Term::Var(name) | Term::Atom(name) | Term::Str(name) => name.clone(),
+            | Term::Int(id) => id.to_string(),
             | Term::Placeholder => "_".to_string(),
+            | Term::Aggregate(func, var) => format!("{func}({var})"),
         })
         .collect()
 }
 
-fn write_tuples(
-    mut writer: impl Write, header: &[String], tuples: &[Vec<usize>],
-) -> io::Result<()> {
+fn write_header(writer: &mut impl Write, header: &[String]) -> io::Result<()> {
     if !header.is_empty() {
         writeln!(writer, "{}", header.join(","))?;
     }
+    Ok(())
+}
+
+fn write_row(writer: &mut impl Write, tuple: &[usize]) -> io::Result<()> {
+    let line: String = tuple
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    writeln!(writer, "{}", line)
+}
+
+fn write_tuples(
+    mut writer: impl Write, header: &[String], tuples: &[Vec<usize>],
+) -> io::Result<()> {
+    write_header(&mut writer, header)?;
     for tuple in tuples {
-        let line: String = tuple
-            .iter()
-            .map(|v| v.to_string())
-            .collect::<Vec<_>>()
-            .join(",");
-        writeln!(writer, "{}", line)?;
+        write_row(&mut writer, tuple)?;
     }
     writer.flush()
 }
 
+/// Streams the result of `query` to `writer` as the join produces it,
+/// stopping after `limit` rows when one is given. The first write error
+/// aborts the join and is returned, as is a query the database rejects.
+fn stream_join(
+    db: &dyn kermit::db::DB, query: JoinQuery, mut writer: impl Write, limit: Option<usize>,
+) -> anyhow::Result<()> {
+    write_header(&mut writer, &head_column_names(&query))?;
+    if limit == Some(0) {
+        return Ok(writer.flush()?);
+    }
+    let mut written = 0;
+    let mut result = Ok(());
+    db.join_each(query, &mut |tuple| {
+        if let Err(e) = write_row(&mut writer, tuple) {
+            result = Err(e);
+            return ControlFlow::Break(());
+        }
+        written += 1;
+        if limit.is_some_and(|limit| written >= limit) {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    })?;
+    result?;
+    Ok(writer.flush()?)
+}
+
 fn load_query(args: &QueryArgs) -> anyhow::Result<(Box<dyn kermit::db::DB>, JoinQuery)> {
     let query_str = fs::read_to_string(&args.query)
         .map_err(|e| anyhow::anyhow!("Failed to read query file {:?}: {}", args.query, e))?;
-    let join_query: JoinQuery = query_str
-        .trim()
+    let program: Program = query_str
         .parse()
         .map_err(|e| anyhow::anyhow!("Failed to parse query from {:?}: {}", args.query, e))?;
 
     let mut db = instantiate_database(args.indexstructure, args.algorithm);
+    db.set_variable_order_strategy(args.variable_order);
     for path in &args.relations {
         db.add_file(path)
             .map_err(|e| anyhow::anyhow!("Failed to load relation {:?}: {}", path, e))?;
     }
+    if let Some(path) = &args.dictionary {
+        let dictionary = kermit_rdf::parquet::read_dict(path)
+            .map_err(|e| anyhow::anyhow!("Failed to load dictionary {:?}: {}", path, e))?;
+        db.set_dictionary(dictionary);
+    }
+    // Intermediate predicates are materialised into the chosen index
+    // structure; the output's query is returned for streaming.
+    if program.outputs.len() > 1 {
+        anyhow::bail!(
+            "{:?} declares {} outputs; use `kermit run` to write them all",
+            args.query,
+            program.outputs.len()
+        );
+    }
+    let join_query = db
+        .prepare_program(&program)
+        .map_err(|e| anyhow::anyhow!("Invalid program in {:?}: {}", args.query, e))?;
+    db.validate(&join_query)
+        .map_err(|e| anyhow::anyhow!("Invalid query in {:?}: {}", args.query, e))?;
 
     Ok((db, join_query))
 }
 
+/// Rejects `.input` and `.output` parameters `kermit run` cannot honour.
+/// Files are read and written with a header row and `,` delimiters, so
+/// besides `filename` only `IO=file`, `delimiter=","` and `headers=true` are
+/// accepted.
+fn check_io_parameters(kind: &str, directive: &IoDirective) -> anyhow::Result<()> {
+    for (key, value) in &directive.parameters {
+        let supported = match key.as_str() {
+            | "filename" => true,
+            | "IO" => value == "file",
+            | "delimiter" => value == ",",
+            | "headers" => value == "true",
+            | _ => false,
+        };
+        if !supported {
+            anyhow::bail!(
+                "Unsupported parameter {key}={value:?} on `{kind} {}`",
+                directive.predicate
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The file a directive reads or writes: its `filename` parameter, or
+/// `<predicate>.csv`, resolved against `dir`.
+fn directive_path(dir: &Path, directive: &IoDirective) -> PathBuf {
+    match directive.parameter("filename") {
+        | Some(filename) => dir.join(filename),
+        | None => dir.join(format!("{}.csv", directive.predicate)),
+    }
+}
+
+/// Evaluates the program at `path` to its fixpoint and writes every
+/// `.output` predicate to a CSV file under `output_dir`. Relations come from
+/// the program's `.input` directives, resolved against `fact_dir`; declared
+/// predicates that are neither read nor derived start empty.
+fn run_program(
+    db: &mut dyn kermit::db::DB, path: &Path, fact_dir: &Path, output_dir: &Path,
+) -> anyhow::Result<()> {
+    let source = fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read program {:?}: {}", path, e))?;
+    let program: Program = source
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Failed to parse program {:?}: {}", path, e))?;
+
+    // Reject unsupported directives before loading or evaluating anything.
+    for output in &program.outputs {
+        check_io_parameters(".output", output)?;
+    }
+    for input in &program.inputs {
+        check_io_parameters(".input", input)?;
+        let file = directive_path(fact_dir, input);
+        db.add_file_as(&input.predicate, &file).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to load input {:?} from {:?}: {}",
+                input.predicate,
+                file,
+                e
+            )
+        })?;
+    }
+    let idb = program.idb_predicates();
+    for decl in &program.declarations {
+        let read = program.inputs.iter().any(|i| i.predicate == decl.name);
+        if !read && !idb.contains(&decl.name.as_str()) {
+            db.add_relation(&decl.name, decl.arity());
+        }
+    }
+
+    let derived = db
+        .evaluate(&program)
+        .map_err(|e| anyhow::anyhow!("Invalid program in {:?}: {}", path, e))?;
+
+    fs::create_dir_all(output_dir)?;
+    for output in &program.outputs {
+        let name = &output.predicate;
+        let declaration = program.declaration(name);
+        let columns: Vec<String> = match declaration {
+            | Some(decl) => decl.attributes.iter().map(|a| a.name.clone()).collect(),
+            | None => match program.rules.iter().find(|rule| &rule.head.name == name) {
+                | Some(rule) => head_column_names(rule),
+                | None => {
+                    let fact = program.facts.iter().find(|fact| &fact.name == name);
+                    let arity = fact.map_or(0, |fact| fact.terms.len());
+                    (0..arity).map(|i| format!("V{i}")).collect()
+                },
+            },
+        };
+        let tuples = match derived.get(name) {
+            | Some(tuples) => tuples.clone(),
+            | None if declaration.is_some() => {
+                // A stored relation: scan it.
+                let scan = Predicate {
+                    name: name.clone(),
+                    terms: (0..columns.len())
+                        .map(|i| Term::Var(format!("V{i}")))
+                        .collect(),
+                };
+                let mut tuples = db.join(JoinQuery {
+                    head: scan.clone(),
+                    body: vec![scan],
+                    comparisons: vec![],
+                    negated: vec![],
+                })?;
+                tuples.sort();
+                tuples
+            },
+            | None => anyhow::bail!(
+                "Output predicate {name:?} in {:?} is neither derived nor declared",
+                path
+            ),
+        };
+
+        let file = directive_path(output_dir, output);
+        if file.extension().and_then(|e| e.to_str()) != Some("csv") {
+            anyhow::bail!("Output {:?} for {name:?} must be a .csv file", file);
+        }
+        let writer = BufWriter::new(
+            fs::File::create(&file)
+                .map_err(|e| anyhow::anyhow!("Failed to create output {:?}: {}", file, e))?,
+        );
+        write_tuples(writer, &columns, &tuples)?;
+    }
+    Ok(())
+}
+
 fn build_time_criterion(args: &BenchArgs) -> criterion::Criterion {
     criterion::Criterion::default()
         .sample_size(args.sample_size)
@@ -486,6 +749,8 @@ where
             .map_err(|e| anyhow::anyhow!("Failed to load relation: {e}"))?,
         | "parquet" => R::from_parquet(relation_path)
             .map_err(|e| anyhow::anyhow!("Failed to load relation: {e}"))?,
+        | "ktrie" => R::from_ktrie(relation_path)
+            .map_err(|e| anyhow::anyhow!("Failed to load relation: {e}"))?,
         | _ => anyhow::bail!("Unsupported file extension: {extension}"),
     };
 
@@ -600,7 +865,8 @@ where
 
 fn run_benchmark<R>(
     benchmark: &BenchmarkDefinition, indexstructure: IndexStructure, algorithm: JoinAlgorithm,
-    metrics: &[Metric], query_filter: Option<&str>, bench_args: &BenchArgs,
+    variable_order: VariableOrderStrategy, metrics: &[Metric], query_filter: Option<&str>,
+    bench_args: &BenchArgs,
 ) -> anyhow::Result<Vec<BenchReport>>
 where
     R: Relation + TrieIterable + HeapSize + 'static,
@@ -629,10 +895,32 @@ where
         | None => benchmark.queries.iter().collect(),
     };
 
-    let cached_paths = kermit_bench::cache::ensure_cached(benchmark)
-        .map_err(|e| anyhow::anyhow!("Failed to fetch benchmark data: {e}"))?;
+    // Generated workloads often repeat a query under other variable names;
+    // those still run, but are flagged so they are not counted twice.
+    let duplicate_of: HashMap<&str, &str> = benchmark.duplicate_queries().into_iter().collect();
+    for query_def in &queries {
+        if let Some(original) = duplicate_of.get(query_def.name.as_str()) {
+            eprintln!(
+                "warning: query '{}' duplicates '{}' in benchmark '{}'",
+                query_def.name, original, benchmark.name
+            );
+        }
+    }
+
+    // Column tries are mapped straight from indexes prebuilt next to the
+    // cached parquets, rather than rebuilt from them on every run.
+    let cached_paths = match indexstructure {
+        | IndexStructure::ColumnTrie => {
+            kermit_bench::cache::ensure_indexed(benchmark, |src, dst| {
+                ColumnTrie::from_parquet(src)?.write_ktrie(dst)
+            })
+        },
+        | _ => kermit_bench::cache::ensure_cached(benchmark),
+    }
+    .map_err(|e| anyhow::anyhow!("Failed to fetch benchmark data: {e}"))?;
 
     let mut db = instantiate_database(indexstructure, algorithm);
+    db.set_variable_order_strategy(variable_order);
     for path in &cached_paths {
         db.add_file(path)
             .map_err(|e| anyhow::anyhow!("Failed to load relation {:?}: {}", path, e))?;
@@ -640,11 +928,18 @@ where
 
     let relations: Vec<R> = cached_paths
         .iter()
-        .map(|p| R::from_parquet(p).map_err(|e| anyhow::anyhow!("Failed to load {p:?}: {e}")))
+        .map(|p| {
+            match indexstructure {
+                | IndexStructure::ColumnTrie => R::from_ktrie(p),
+                | _ => R::from_parquet(p),
+            }
+            .map_err(|e| anyhow::anyhow!("Failed to load {p:?}: {e}"))
+        })
         .collect::<Result<_, _>>()?;
 
     let ds_name = format!("{:?}", indexstructure);
     let algo_name = format!("{:?}", algorithm);
+    let order_name = format!("{:?}", variable_order);
 
     let has_time_metrics = metrics
         .iter()
@@ -652,25 +947,66 @@ where
 
     // Sum across relations: scaling plots key off this as the workload's
     // total input size. One trie walk per relation is cheap vs the bench itself.
-    let total_tuples: usize = relations
+    let cardinalities: HashMap<&str, usize> = relations
         .iter()
-        .map(|r| r.trie_iter().into_iter().count())
-        .sum();
+        .map(|r| (r.header().name(), r.trie_iter().into_iter().count()))
+        .collect();
+    let total_tuples: usize = cardinalities.values().sum();
 
     let mut reports: Vec<BenchReport> = Vec::with_capacity(queries.len());
 
     for query_def in &queries {
-        let join_query: JoinQuery =
-            query_def.query.trim().parse().map_err(|e| {
-                anyhow::anyhow!("Failed to parse query '{}': {:?}", query_def.query, e)
-            })?;
+        let join_query: JoinQuery = query_def
+            .query
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Failed to parse query '{}': {}", query_def.name, e))?;
+        db.validate(&join_query)
+            .map_err(|e| anyhow::anyhow!("Invalid query '{}': {}", query_def.name, e))?;
+        let fingerprint = format!("{:016x}", join_query.fingerprint());
+        let duplicate = duplicate_of.get(query_def.name.as_str()).copied();
+        let shape = QueryShape::new(&join_query, &cardinalities);
+
+        // One untimed join per query; only worth it when iteration is timed.
+        let intermediate_sizes = if metrics.contains(&Metric::Iteration) {
+            db.intermediate_sizes(join_query.clone())?
+        } else {
+            None
+        };
+        // One untimed count per query with a known cardinality.
+        let count_check = query_def
+            .expected_count
+            .map(|expected| db.count(join_query.clone()).map(|count| (count, expected)))
+            .transpose()?;
 
         let mut lines = vec![
             MetadataLine::new("benchmark", &benchmark.name),
             MetadataLine::new("query", &query_def.name),
             MetadataLine::new("data structure", &ds_name),
             MetadataLine::new("algorithm", &algo_name),
+            MetadataLine::new("variable order", &order_name),
+            MetadataLine::new("fingerprint", &fingerprint),
         ];
+        if let Some(original) = duplicate {
+            lines.push(MetadataLine::new("duplicate of", original));
+        }
+        lines.extend(shape.metadata_lines());
+        if let Some(sizes) = &intermediate_sizes {
+            lines.push(MetadataLine::new(
+                "intermediate sizes",
+                format!("{sizes:?}"),
+            ));
+        }
+        if let Some((count, expected)) = count_check {
+            let verdict = if count == expected {
+                "ok"
+            } else {
+                "MISMATCH"
+            };
+            lines.push(MetadataLine::new(
+                "result count",
+                format!("{count} (expected {expected}, {verdict})"),
+            ));
+        }
         for rel in &relations {
             let h = rel.header();
             lines.push(MetadataLine::new(
@@ -681,10 +1017,15 @@ where
         write_metadata_block(&mut io::stderr(), "bench run metadata", &lines)?;
 
         let prefix = bench_args.name.as_deref().unwrap_or("run");
-        let group_name = format!(
+        let mut group_name = format!(
             "{}/{}/{}/{}/{}",
             prefix, benchmark.name, query_def.name, ds_name, algo_name
         );
+        // Only non-default orders get their own segment, so Criterion
+        // baselines recorded before ordering was selectable stay comparable.
+        if variable_order != VariableOrderStrategy::default() {
+            group_name = format!("{group_name}/{order_name}");
+        }
 
         let mut criterion_groups: Vec<CriterionGroupRef> = Vec::new();
 
@@ -772,13 +1113,33 @@ where
             criterion.final_summary();
         }
 
-        let axes = BTreeMap::from([
+        let mut axes = BTreeMap::from([
             ("benchmark".to_string(), serde_json::json!(benchmark.name)),
             ("query".to_string(), serde_json::json!(query_def.name)),
             ("data_structure".to_string(), serde_json::json!(ds_name)),
             ("algorithm".to_string(), serde_json::json!(algo_name)),
+            ("variable_order".to_string(), serde_json::json!(order_name)),
             ("tuples".to_string(), serde_json::json!(total_tuples)),
+            (
+                "query_fingerprint".to_string(),
+                serde_json::json!(fingerprint),
+            ),
         ]);
+        if let Some(original) = duplicate {
+            axes.insert("duplicate_of".to_string(), serde_json::json!(original));
+        }
+        shape.insert_axes(&mut axes);
+        if let Some(sizes) = &intermediate_sizes {
+            insert_intermediate_size_axes(&mut axes, sizes);
+        }
+        if let Some((count, expected)) = count_check {
+            axes.insert("result_count".to_string(), serde_json::json!(count));
+            axes.insert("expected_count".to_string(), serde_json::json!(expected));
+            axes.insert(
+                "count_matches".to_string(),
+                serde_json::json!(count == expected),
+            );
+        }
         reports.push(BenchReport::new(
             BenchKind::Run,
             &lines,
@@ -790,6 +1151,115 @@ where
     Ok(reports)
 }
 
+/// The structure of a query's hypergraph (see
+/// [`QueryHypergraph`](kermit_algos::QueryHypergraph)), reported by `bench run`
+/// alongside its timings.
+struct QueryShape {
+    variables: usize,
+    atoms: usize,
+    alpha_acyclic: bool,
+    fractional_hypertree_width: f64,
+    agm_bound: f64,
+}
+
+impl QueryShape {
+    /// Analyses `query`, bounding its output with the `cardinalities` of the
+    /// relations it reads. The query must have been validated.
+    fn new(query: &JoinQuery, cardinalities: &HashMap<&str, usize>) -> Self {
+        let hypergraph = QueryHypergraph::new(query);
+        let sizes: Vec<usize> = query
+            .body
+            .iter()
+            .map(|pred| cardinalities[pred.name.as_str()])
+            .collect();
+        QueryShape {
+            variables: hypergraph.variables().len(),
+            atoms: hypergraph.edges().len(),
+            alpha_acyclic: hypergraph.is_alpha_acyclic(),
+            fractional_hypertree_width: hypergraph.decomposition().width,
+            agm_bound: hypergraph.agm_bound(&sizes),
+        }
+    }
+
+    fn metadata_lines(&self) -> [MetadataLine; 3] {
+        let acyclicity = if self.alpha_acyclic {
+            "alpha-acyclic"
+        } else {
+            "cyclic"
+        };
+        [
+            MetadataLine::new(
+                "hypergraph",
+                format!(
+                    "{} variables, {} atoms, {acyclicity}",
+                    self.variables, self.atoms
+                ),
+            ),
+            MetadataLine::new(
+                "fractional hypertree width",
+                format!("{:.3}", self.fractional_hypertree_width),
+            ),
+            MetadataLine::new("AGM bound", format!("{:.0}", self.agm_bound)),
+        ]
+    }
+
+    /// Records the shape as the `alpha_acyclic`, `fractional_hypertree_width`
+    /// and `agm_bound` report axes.
+    fn insert_axes(&self, axes: &mut BTreeMap<String, serde_json::Value>) {
+        axes.insert(
+            "alpha_acyclic".to_string(),
+            serde_json::json!(self.alpha_acyclic),
+        );
+        axes.insert(
+            "fractional_hypertree_width".to_string(),
+            serde_json::json!(self.fractional_hypertree_width),
+        );
+        axes.insert("agm_bound".to_string(), serde_json::json!(self.agm_bound));
+    }
+}
+
+/// Records a join's intermediate result sizes (see
+/// [`DB::intermediate_sizes`](kermit::db::DB::intermediate_sizes)) as the
+/// `intermediate_sizes` and `max_intermediate_size` report axes.
+fn insert_intermediate_size_axes(axes: &mut BTreeMap<String, serde_json::Value>, sizes: &[usize]) {
+    axes.insert("intermediate_sizes".to_string(), serde_json::json!(sizes));
+    axes.insert(
+        "max_intermediate_size".to_string(),
+        serde_json::json!(sizes.iter().copied().max().unwrap_or(0)),
+    );
+}
+
+/// Fails if any report's `count_matches` axis is `false`, naming every
+/// query whose result count disagreed with its `expected_count`. Runs after
+/// the report is written so the mismatching counts are kept on disk.
+fn check_expected_counts(reports: &[BenchReport]) -> anyhow::Result<()> {
+    let mismatches: Vec<String> = reports
+        .iter()
+        .filter(|r| r.axes.get("count_matches") == Some(&serde_json::json!(false)))
+        .map(|r| {
+            let axis = |key: &str| match r.axes.get(key) {
+                | Some(serde_json::Value::String(s)) => s.clone(),
+                | Some(value) => value.to_string(),
+                | None => "?".to_string(),
+            };
+            format!(
+                "{}/{} on {}/{}: got {}, expected {}",
+                axis("benchmark"),
+                axis("query"),
+                axis("data_structure"),
+                axis("algorithm"),
+                axis("result_count"),
+                axis("expected_count"),
+            )
+        })
+        .collect();
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        anyhow::bail!("result count mismatch:\n  {}", mismatches.join("\n  "))
+    }
+}
+
 /// Returns the `bench list` status string for a benchmark.
 ///
 /// For static benchmarks the values are "cached" / "not cached" (matching
@@ -876,15 +1346,41 @@ fn main() -> anyhow::Result<()> {
         | Commands::Join {
             query_args,
             output,
+            limit,
+            count,
         } => {
             let (db, join_query) = load_query(&query_args)?;
-            let header = head_column_names(&join_query);
-            let tuples = db.join(join_query);
-            let writer: Box<dyn Write> = match &output {
+            let mut writer: Box<dyn Write> = match &output {
                 | Some(path) => Box::new(BufWriter::new(fs::File::create(path)?)),
                 | None => Box::new(BufWriter::new(io::stdout().lock())),
             };
-            write_tuples(writer, &header, &tuples)?;
+            if count {
+                writeln!(writer, "{}", db.count(join_query)?)?;
+                writer.flush()?;
+            } else {
+                stream_join(db.as_ref(), join_query, writer, limit)?;
+            }
+        },
+
+        | Commands::Run {
+            program,
+            algorithm,
+            indexstructure,
+            variable_order,
+            dictionary,
+            fact_dir,
+            output_dir,
+        } => {
+            let mut db = instantiate_database(indexstructure, algorithm);
+            db.set_variable_order_strategy(variable_order);
+            if let Some(path) = &dictionary {
+                let dictionary = kermit_rdf::parquet::read_dict(path)
+                    .map_err(|e| anyhow::anyhow!("Failed to load dictionary {:?}: {}", path, e))?;
+                db.set_dictionary(dictionary);
+            }
+            let fact_dir = fact_dir
+                .unwrap_or_else(|| program.parent().map(Path::to_path_buf).unwrap_or_default());
+            run_program(db.as_mut(), &program, &fact_dir, &output_dir)?;
         },
 
         | Commands::Bench {
@@ -959,7 +1455,7 @@ fn main() -> anyhow::Result<()> {
 
                 if let Some(path) = &output {
                     let header = head_column_names(&join_query);
-                    let tuples = db.join(join_query.clone());
+                    let tuples = db.join(join_query.clone())?;
                     let writer = BufWriter::new(fs::File::create(path)?);
                     write_tuples(writer, &header, &tuples)?;
                 }
@@ -968,11 +1464,20 @@ fn main() -> anyhow::Result<()> {
                 let bench_id =
                     format!("{:?}/{:?}", query_args.indexstructure, query_args.algorithm);
 
-                let metadata = vec![
+                let intermediate_sizes = db.intermediate_sizes(join_query.clone())?;
+
+                let mut metadata = vec![
                     MetadataLine::new("data structure", format!("{:?}", query_args.indexstructure)),
                     MetadataLine::new("algorithm", format!("{:?}", query_args.algorithm)),
+                    MetadataLine::new("variable order", format!("{:?}", query_args.variable_order)),
                     MetadataLine::new("relations", query_args.relations.len()),
                 ];
+                if let Some(sizes) = &intermediate_sizes {
+                    metadata.push(MetadataLine::new(
+                        "intermediate sizes",
+                        format!("{sizes:?}"),
+                    ));
+                }
                 write_metadata_block(&mut io::stderr(), "bench metadata", &metadata)?;
 
                 let mut criterion = build_time_criterion(&bench_args);
@@ -987,7 +1492,7 @@ fn main() -> anyhow::Result<()> {
                 group.finish();
                 criterion.final_summary();
 
-                let axes = BTreeMap::from([
+                let mut axes = BTreeMap::from([
                     (
                         "data_structure".to_string(),
                         serde_json::json!(format!("{:?}", query_args.indexstructure)),
@@ -996,11 +1501,18 @@ fn main() -> anyhow::Result<()> {
                         "algorithm".to_string(),
                         serde_json::json!(format!("{:?}", query_args.algorithm)),
                     ),
+                    (
+                        "variable_order".to_string(),
+                        serde_json::json!(format!("{:?}", query_args.variable_order)),
+                    ),
                     (
                         "relations".to_string(),
                         serde_json::json!(query_args.relations.len()),
                     ),
                 ]);
+                if let Some(sizes) = &intermediate_sizes {
+                    insert_intermediate_size_axes(&mut axes, sizes);
+                }
                 let report =
                     BenchReport::new(BenchKind::Join, &metadata, axes, vec![CriterionGroupRef {
                         group: group_name,
@@ -1037,6 +1549,38 @@ fn main() -> anyhow::Result<()> {
                             group_name,
                             &bench_args,
                         )?,
+                        | IndexStructure::SortedTupleArray => {
+                            run_ds_bench::<kermit_ds::SortedTupleArray>(
+                                &relation,
+                                ds,
+                                &metrics,
+                                group_name,
+                                &bench_args,
+                            )?
+                        },
+                        | IndexStructure::CompressedColumnTrie => {
+                            run_ds_bench::<kermit_ds::CompressedColumnTrie>(
+                                &relation,
+                                ds,
+                                &metrics,
+                                group_name,
+                                &bench_args,
+                            )?
+                        },
+                        | IndexStructure::HashTrie => run_ds_bench::<kermit_ds::HashTrie>(
+                            &relation,
+                            ds,
+                            &metrics,
+                            group_name,
+                            &bench_args,
+                        )?,
+                        | IndexStructure::BitmapTrie => run_ds_bench::<kermit_ds::BitmapTrie>(
+                            &relation,
+                            ds,
+                            &metrics,
+                            group_name,
+                            &bench_args,
+                        )?,
                     };
                     reports.push(report);
                 }
@@ -1049,6 +1593,7 @@ fn main() -> anyhow::Result<()> {
                 query,
                 indexstructure,
                 algorithm,
+                variable_order,
                 metrics,
                 force,
             } => {
@@ -1073,6 +1618,7 @@ fn main() -> anyhow::Result<()> {
                                     benchmark,
                                     ds,
                                     algo,
+                                    variable_order,
                                     &metrics,
                                     query.as_deref(),
                                     &bench_args,
@@ -1082,6 +1628,49 @@ fn main() -> anyhow::Result<()> {
                                         benchmark,
                                         ds,
                                         algo,
+                                        variable_order,
+                                        &metrics,
+                                        query.as_deref(),
+                                        &bench_args,
+                                    )?
+                                },
+                                | IndexStructure::SortedTupleArray => {
+                                    run_benchmark::<kermit_ds::SortedTupleArray>(
+                                        benchmark,
+                                        ds,
+                                        algo,
+                                        variable_order,
+                                        &metrics,
+                                        query.as_deref(),
+                                        &bench_args,
+                                    )?
+                                },
+                                | IndexStructure::CompressedColumnTrie => {
+                                    run_benchmark::<kermit_ds::CompressedColumnTrie>(
+                                        benchmark,
+                                        ds,
+                                        algo,
+                                        variable_order,
+                                        &metrics,
+                                        query.as_deref(),
+                                        &bench_args,
+                                    )?
+                                },
+                                | IndexStructure::HashTrie => run_benchmark::<kermit_ds::HashTrie>(
+                                    benchmark,
+                                    ds,
+                                    algo,
+                                    variable_order,
+                                    &metrics,
+                                    query.as_deref(),
+                                    &bench_args,
+                                )?,
+                                | IndexStructure::BitmapTrie => {
+                                    run_benchmark::<kermit_ds::BitmapTrie>(
+                                        benchmark,
+                                        ds,
+                                        algo,
+                                        variable_order,
                                         &metrics,
                                         query.as_deref(),
                                         &bench_args,
@@ -1093,6 +1682,7 @@ fn main() -> anyhow::Result<()> {
                     }
                 }
                 write_bench_report(bench_args.report_json.as_deref(), BenchKind::Run, &reports)?;
+                check_expected_counts(&reports)?;
             },
 
             | BenchSubcommand::Gen {
@@ -1307,12 +1897,87 @@ mod tests {
         assert_eq!(String::from_utf8(buf).unwrap(), "");
     }
 
+    fn count_report(query: &str, count: usize, expected: usize) -> BenchReport {
+        let axes = BTreeMap::from([
+            ("benchmark".to_string(), serde_json::json!("b")),
+            ("query".to_string(), serde_json::json!(query)),
+            ("data_structure".to_string(), serde_json::json!("TreeTrie")),
+            (
+                "algorithm".to_string(),
+                serde_json::json!("LeapfrogTriejoin"),
+            ),
+            ("result_count".to_string(), serde_json::json!(count)),
+            ("expected_count".to_string(), serde_json::json!(expected)),
+            (
+                "count_matches".to_string(),
+                serde_json::json!(count == expected),
+            ),
+        ]);
+        BenchReport::new(BenchKind::Run, &[], axes, vec![])
+    }
+
+    #[test]
+    fn check_expected_counts_accepts_matching_counts() {
+        let reports = vec![count_report("q1", 3, 3), count_report("q2", 0, 0)];
+        assert!(check_expected_counts(&reports).is_ok());
+    }
+
+    #[test]
+    fn check_expected_counts_names_every_mismatch() {
+        let reports = vec![
+            count_report("q1", 3, 4),
+            count_report("q2", 1, 1),
+            count_report("q3", 9, 2),
+        ];
+        let err = check_expected_counts(&reports).unwrap_err().to_string();
+        assert!(
+            err.contains("b/q1 on TreeTrie/LeapfrogTriejoin: got 3, expected 4"),
+            "{err}"
+        );
+        assert!(err.contains("b/q3"), "{err}");
+        assert!(!err.contains("b/q2"), "{err}");
+    }
+
+    fn stream_test_db() -> Box<dyn kermit::db::DB> {
+        let mut db =
+            instantiate_database(IndexStructure::TreeTrie, JoinAlgorithm::LeapfrogTriejoin);
+        db.add_relation("r", 2);
+        db.add_keys_batch("r", vec![vec![1, 2], vec![3, 4], vec![5, 6]]);
+        db
+    }
+
+    #[test]
+    fn stream_join_writes_header_then_every_row() {
+        let query: JoinQuery = "Q(X, Y) :- r(X, Y).".parse().unwrap();
+        let mut buf = Vec::new();
+        stream_join(stream_test_db().as_ref(), query, &mut buf, None).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "X,Y\n1,2\n3,4\n5,6\n");
+    }
+
+    #[test]
+    fn stream_join_stops_at_limit() {
+        let query: JoinQuery = "Q(X, Y) :- r(X, Y).".parse().unwrap();
+        let mut buf = Vec::new();
+        stream_join(stream_test_db().as_ref(), query.clone(), &mut buf, Some(2)).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "X,Y\n1,2\n3,4\n");
+
+        let mut buf = Vec::new();
+        stream_join(stream_test_db().as_ref(), query, &mut buf, Some(0)).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "X,Y\n");
+    }
+
     #[test]
     fn head_column_names_extracts_variables_atoms_and_placeholders() {
         let q: JoinQuery = "Q(X, Y, _) :- R(X, Y, Z).".parse().unwrap();
         assert_eq!(head_column_names(&q), vec!["X", "Y", "_"]);
     }
 
+    #[test]
+    fn head_column_names_spells_out_aggregates() {
+        let q: JoinQuery = "deg(X, count(Y)) :- edge(X, Y).".parse().unwrap();
+        assert_eq!(head_column_names(&q), vec!["X", "count(Y)"]);
+    }
+
     fn make_generator_def(name: &str, spec: kermit_bench::GeneratorSpec) -> BenchmarkDefinition {
         BenchmarkDefinition {
             name: name.to_string(),
@@ -1335,6 +2000,7 @@ mod tests {
                 name: "q".to_string(),
                 description: "q".to_string(),
                 query: "Q(X) :- edge(X, Y).".to_string(),
+                expected_count: None,
             }],
             generator: None,
         }
@@ -1449,6 +2115,18 @@ mod tests {
         assert_eq!(IndexStructureSelector::ColumnTrie.expand(), vec![
             IndexStructure::ColumnTrie
         ]);
+        assert_eq!(IndexStructureSelector::SortedTupleArray.expand(), vec![
+            IndexStructure::SortedTupleArray
+        ]);
+        assert_eq!(IndexStructureSelector::CompressedColumnTrie.expand(), vec![
+            IndexStructure::CompressedColumnTrie
+        ]);
+        assert_eq!(IndexStructureSelector::HashTrie.expand(), vec![
+            IndexStructure::HashTrie
+        ]);
+        assert_eq!(IndexStructureSelector::BitmapTrie.expand(), vec![
+            IndexStructure::BitmapTrie
+        ]);
     }
 
     #[test]
@@ -1465,6 +2143,12 @@ mod tests {
         assert_eq!(JoinAlgorithmSelector::LeapfrogTriejoin.expand(), vec![
             JoinAlgorithm::LeapfrogTriejoin
         ]);
+        assert_eq!(JoinAlgorithmSelector::GenericJoin.expand(), vec![
+            JoinAlgorithm::GenericJoin
+        ]);
+        assert_eq!(JoinAlgorithmSelector::HashJoin.expand(), vec![
+            JoinAlgorithm::HashJoin
+        ]);
     }
 
     /// Regression test: when discovery merges a workspace generator YAML
@@ -258,6 +258,7 @@ mod tests {
                 name: "q".to_string(),
                 description: "q".to_string(),
                 query: "Q(X) :- edge(X, Y).".to_string(),
+                expected_count: None,
             }],
             generator: None,
         }
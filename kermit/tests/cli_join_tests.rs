@@ -96,6 +96,74 @@ fn cli_join_intersection_column_trie() {
     assert_eq!(tuples, vec![vec![2], vec![3]]);
 }
 
+#[test]
+fn cli_join_intersection_sorted_tuple_array() {
+    let output = run_join(
+        &["first.csv", "second.csv"],
+        "intersect_query.dl",
+        "leapfrog-triejoin",
+        "sorted-tuple-array",
+    );
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let tuples = parse_output(&output);
+    assert_eq!(tuples, vec![vec![2], vec![3]]);
+}
+
+#[test]
+fn cli_join_intersection_compressed_column_trie() {
+    let output = run_join(
+        &["first.csv", "second.csv"],
+        "intersect_query.dl",
+        "leapfrog-triejoin",
+        "compressed-column-trie",
+    );
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let tuples = parse_output(&output);
+    assert_eq!(tuples, vec![vec![2], vec![3]]);
+}
+
+#[test]
+fn cli_join_intersection_hash_trie() {
+    let output = run_join(
+        &["first.csv", "second.csv"],
+        "intersect_query.dl",
+        "leapfrog-triejoin",
+        "hash-trie",
+    );
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let tuples = parse_output(&output);
+    assert_eq!(tuples, vec![vec![2], vec![3]]);
+}
+
+#[test]
+fn cli_join_intersection_bitmap_trie() {
+    let output = run_join(
+        &["first.csv", "second.csv"],
+        "intersect_query.dl",
+        "leapfrog-triejoin",
+        "bitmap-trie",
+    );
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let tuples = parse_output(&output);
+    assert_eq!(tuples, vec![vec![2], vec![3]]);
+}
+
 #[test]
 fn cli_join_path_query() {
     // edge: (1,2), (2,3), (3,4), (1,3)
@@ -120,6 +188,114 @@ fn cli_join_path_query() {
     assert_eq!(tuples, vec![vec![1, 2, 3], vec![1, 3, 4], vec![2, 3, 4]]);
 }
 
+#[test]
+fn cli_join_reverse_path_query_column_trie() {
+    // rpath(Z, X) :- edge(X, Y), edge(Y, Z).
+    // The head orders Z before X and Y, so neither edge atom matches the
+    // stored column order; the engine must join against reordered views.
+    let output = run_join(
+        &["edge.csv"],
+        "reverse_path_query.dl",
+        "leapfrog-triejoin",
+        "column-trie",
+    );
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let tuples = parse_output(&output);
+    assert_eq!(tuples, vec![vec![3, 1], vec![4, 1], vec![4, 2]]);
+}
+
+#[test]
+fn cli_join_path_query_generic_join() {
+    let output = run_join(
+        &["edge.csv"],
+        "path_query.dl",
+        "generic-join",
+        "column-trie",
+    );
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let tuples = parse_output(&output);
+    assert_eq!(tuples, vec![vec![1, 2, 3], vec![1, 3, 4], vec![2, 3, 4]]);
+}
+
+#[test]
+fn cli_join_path_query_hash_join() {
+    let output = run_join(&["edge.csv"], "path_query.dl", "hash-join", "tree-trie");
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let tuples = parse_output(&output);
+    assert_eq!(tuples, vec![vec![1, 2, 3], vec![1, 3, 4], vec![2, 3, 4]]);
+}
+
+#[test]
+fn cli_join_greedy_variable_order() {
+    let output = run_subcommand(
+        "join",
+        &["edge.csv"],
+        "path_query.dl",
+        "leapfrog-triejoin",
+        "tree-trie",
+        &["--variable-order", "greedy"],
+    );
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let tuples = parse_output(&output);
+    assert_eq!(tuples, vec![vec![1, 2, 3], vec![1, 3, 4], vec![2, 3, 4]]);
+}
+
+#[test]
+fn cli_join_limit_truncates_result() {
+    let output = run_subcommand(
+        "join",
+        &["edge.csv"],
+        "path_query.dl",
+        "leapfrog-triejoin",
+        "tree-trie",
+        &["--limit", "2"],
+    );
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let tuples = parse_output(&output);
+    assert_eq!(tuples.len(), 2);
+    for tuple in &tuples {
+        assert!([vec![1, 2, 3], vec![1, 3, 4], vec![2, 3, 4]].contains(tuple));
+    }
+}
+
+#[test]
+fn cli_join_count_prints_cardinality() {
+    let output = run_subcommand(
+        "join",
+        &["edge.csv"],
+        "path_query.dl",
+        "leapfrog-triejoin",
+        "tree-trie",
+        &["--count"],
+    );
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "3");
+}
+
 #[test]
 fn cli_join_missing_query_file() {
     let output = run_join(
@@ -280,6 +456,27 @@ fn cli_bench_default_name() {
     );
 }
 
+#[test]
+fn cli_bench_hash_join_reports_intermediate_sizes() {
+    let output = run_bench_join(&["edge.csv"], "path_query.dl", "hash-join", "tree-trie", &[
+        "--sample-size",
+        "10",
+        "--measurement-time",
+        "1",
+    ]);
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("intermediate sizes:"),
+        "stderr missing intermediate sizes: {stderr}"
+    );
+}
+
 fn run_bench_ds(
     relation: &str, indexstructure: &str, bench_args: &[&str], ds_args: &[&str],
 ) -> std::process::Output {
@@ -500,3 +697,285 @@ fn cli_bench_ds_space_only() {
         "space-only should not have iteration benchmark: {stdout}"
     );
 }
+
+#[test]
+fn cli_bench_ds_all_index_structures() {
+    let output = run_bench_ds("first.csv", "all", &["--sample-size", "10"], &[
+        "-m", "space",
+    ]);
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for ds in [
+        "TreeTrie",
+        "ColumnTrie",
+        "SortedTupleArray",
+        "CompressedColumnTrie",
+        "HashTrie",
+        "BitmapTrie",
+    ] {
+        assert!(
+            stdout.contains(&format!("{ds}/space")),
+            "stdout should contain {ds} space benchmark: {stdout}"
+        );
+    }
+}
+
+#[test]
+fn cli_join_applies_comparisons_for_each_algorithm() {
+    for algorithm in ["leapfrog-triejoin", "generic-join", "hash-join"] {
+        let output = run_join(
+            &["edge.csv"],
+            "bounded_path_query.dl",
+            algorithm,
+            "column-trie",
+        );
+        assert!(
+            output.status.success(),
+            "{algorithm} stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            parse_output(&output),
+            vec![vec![1, 3, 4], vec![2, 3, 4]],
+            "{algorithm}"
+        );
+    }
+}
+
+#[test]
+fn cli_join_applies_negation_for_each_algorithm() {
+    for algorithm in ["leapfrog-triejoin", "generic-join", "hash-join"] {
+        let output = run_join(&["edge.csv"], "open_path_query.dl", algorithm, "tree-trie");
+        assert!(
+            output.status.success(),
+            "{algorithm} stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            parse_output(&output),
+            vec![vec![1, 3, 4], vec![2, 3, 4]],
+            "{algorithm}"
+        );
+    }
+}
+
+#[test]
+fn cli_join_rejects_unsafe_negation() {
+    let output = run_join(
+        &["edge.csv"],
+        "unsafe_negation_query.dl",
+        "leapfrog-triejoin",
+        "tree-trie",
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("variable Z in `not edge(…)` is not bound"),
+        "stderr: {stderr}"
+    );
+}
+
+#[test]
+fn cli_join_aggregates_for_each_algorithm() {
+    for algorithm in ["leapfrog-triejoin", "generic-join", "hash-join"] {
+        let output = run_join(&["edge.csv"], "degree_query.dl", algorithm, "column-trie");
+        assert!(
+            output.status.success(),
+            "{algorithm} stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.starts_with("X,count(Y)\n"), "stdout: {stdout}");
+        assert_eq!(
+            parse_output(&output),
+            vec![vec![1, 2], vec![2, 1], vec![3, 1]],
+            "{algorithm}"
+        );
+    }
+}
+
+#[test]
+fn cli_join_resolves_constants_through_dictionary() {
+    use kermit_rdf::{dict::Dictionary, parquet::write_dict, value::RdfValue};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut dict = Dictionary::new();
+    for value in ["<http://x/zero>", "\"one\"", "<http://x/two>"] {
+        dict.intern(RdfValue::from_canonical(value));
+    }
+    let dict_path = dir.path().join("dict.parquet");
+    write_dict(&dict, &dict_path).unwrap();
+
+    let cases: [(&str, Vec<Vec<usize>>); 4] = [
+        (r#"Q(Y) :- edge("one", Y)."#, vec![vec![2], vec![3]]),
+        ("Q(X) :- edge(X, <http://x/two>).", vec![vec![1]]),
+        ("Q(X) :- edge(X, 4).", vec![vec![3]]),
+        (r#"Q(Y) :- edge("nine", Y)."#, vec![]),
+    ];
+    for (query, expected) in cases {
+        let query_path = dir.path().join("query.dl");
+        std::fs::write(&query_path, query).unwrap();
+        let output = run_subcommand(
+            "join",
+            &["edge.csv"],
+            query_path.to_str().unwrap(),
+            "leapfrog-triejoin",
+            "tree-trie",
+            &["--dictionary", dict_path.to_str().unwrap()],
+        );
+        assert!(
+            output.status.success(),
+            "{query} stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(parse_output(&output), expected, "{query}");
+    }
+}
+
+#[test]
+fn cli_join_evaluates_multi_rule_programs() {
+    for (algorithm, ds) in [
+        ("leapfrog-triejoin", "tree-trie"),
+        ("generic-join", "column-trie"),
+        ("hash-join", "sorted-tuple-array"),
+    ] {
+        let output = run_join(&["edge.csv"], "two_hop_program.dl", algorithm, ds);
+        assert!(
+            output.status.success(),
+            "{algorithm}/{ds} stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.starts_with("X,Y\n"), "stdout: {stdout}");
+        assert_eq!(
+            parse_output(&output),
+            vec![vec![1, 4], vec![2, 4], vec![7, 8]],
+            "{algorithm}/{ds}"
+        );
+    }
+
+    let output = run_subcommand(
+        "join",
+        &["edge.csv"],
+        "two_hop_program.dl",
+        "leapfrog-triejoin",
+        "tree-trie",
+        &["--count"],
+    );
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "3");
+}
+
+#[test]
+fn cli_join_rejects_recursive_programs() {
+    let output = run_join(
+        &["edge.csv"],
+        "recursive_program.dl",
+        "leapfrog-triejoin",
+        "tree-trie",
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("predicate \"tc\" depends on itself"),
+        "stderr: {stderr}"
+    );
+}
+
+#[test]
+fn cli_join_reports_parse_error_location() {
+    let dir = tempfile::tempdir().unwrap();
+    let query_path = dir.path().join("query.dl");
+    std::fs::write(&query_path, "Q(X, Z) :-\n    edge(X, Y),\n    edge(Y Z).").unwrap();
+    let output = run_join(
+        &["edge.csv"],
+        query_path.to_str().unwrap(),
+        "leapfrog-triejoin",
+        "tree-trie",
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("expected `,` or `)` at line 3, column 12"),
+        "stderr: {stderr}"
+    );
+    assert!(stderr.contains("    edge(Y Z).\n"), "stderr: {stderr}");
+}
+
+#[test]
+fn cli_join_reports_arity_mismatch() {
+    let dir = tempfile::tempdir().unwrap();
+    let query_path = dir.path().join("query.dl");
+    std::fs::write(&query_path, "Q(X, Z) :- edge(X, Y, Z).").unwrap();
+    let output = run_join(
+        &["edge.csv"],
+        query_path.to_str().unwrap(),
+        "leapfrog-triejoin",
+        "tree-trie",
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("predicate \"edge\" used with arity 3, but its relation has arity 2"),
+        "stderr: {stderr}"
+    );
+}
+
+#[test]
+fn cli_join_rejects_constant_head_terms() {
+    let dir = tempfile::tempdir().unwrap();
+    let query_path = dir.path().join("query.dl");
+    std::fs::write(&query_path, "Q(3, X) :- edge(X, Y).").unwrap();
+    let output = run_join(
+        &["edge.csv"],
+        query_path.to_str().unwrap(),
+        "leapfrog-triejoin",
+        "tree-trie",
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("head term 3 is not a variable or an aggregate over one"),
+        "stderr: {stderr}"
+    );
+}
+
+#[test]
+fn cli_join_rejects_placeholder_head_terms() {
+    let dir = tempfile::tempdir().unwrap();
+    let query_path = dir.path().join("query.dl");
+    std::fs::write(&query_path, "Q(_) :- edge(X, Y).").unwrap();
+    let output = run_join(
+        &["edge.csv"],
+        query_path.to_str().unwrap(),
+        "generic-join",
+        "column-trie",
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("head term _ is not a variable or an aggregate over one"),
+        "stderr: {stderr}"
+    );
+}
+
+#[test]
+fn cli_join_points_multi_output_programs_to_run() {
+    let output = run_join(
+        &["edge.csv"],
+        "souffle_program.dl",
+        "leapfrog-triejoin",
+        "tree-trie",
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("declares 3 outputs; use `kermit run`"),
+        "stderr: {stderr}"
+    );
+}
@@ -0,0 +1,139 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+fn fixtures_dir() -> PathBuf { Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures") }
+
+fn kermit_bin() -> PathBuf { Path::new(env!("CARGO_BIN_EXE_kermit")).to_path_buf() }
+
+fn run_program(program: &Path, output_dir: &Path, extra_args: &[&str]) -> Output {
+    Command::new(kermit_bin())
+        .arg("run")
+        .arg(program)
+        .arg("--output-dir")
+        .arg(output_dir)
+        .args(extra_args)
+        .output()
+        .expect("failed to execute kermit binary")
+}
+
+#[test]
+fn cli_run_reads_inputs_and_writes_every_output() {
+    let out = tempfile::tempdir().unwrap();
+    for (algorithm, indexstructure) in [
+        ("leapfrog-triejoin", "column-trie"),
+        ("generic-join", "tree-trie"),
+        ("hash-join", "sorted-tuple-array"),
+    ] {
+        let output = run_program(&fixtures_dir().join("souffle_program.dl"), out.path(), &[
+            "--algorithm",
+            algorithm,
+            "--indexstructure",
+            indexstructure,
+        ]);
+        assert!(
+            output.status.success(),
+            "{algorithm}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            fs::read_to_string(out.path().join("reach.csv")).unwrap(),
+            "from,to\n1,2\n1,3\n1,4\n2,3\n2,4\n3,4\n"
+        );
+        assert_eq!(
+            fs::read_to_string(out.path().join("sinks.csv")).unwrap(),
+            "node\n4\n"
+        );
+        assert_eq!(
+            fs::read_to_string(out.path().join("edge_copy.csv")).unwrap(),
+            "src,dst\n1,2\n1,3\n2,3\n3,4\n"
+        );
+    }
+}
+
+#[test]
+fn cli_run_resolves_inputs_against_the_fact_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let program = dir.path().join("program.dl");
+    fs::write(
+        &program,
+        ".decl edge(x: number, y: number)\n.input edge\nout(X) :- edge(X, 3).\n.output out",
+    )
+    .unwrap();
+    let fact_dir = fixtures_dir();
+    let output = run_program(&program, dir.path(), &[
+        "--fact-dir",
+        fact_dir.to_str().unwrap(),
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("out.csv")).unwrap(),
+        "X\n1\n2\n"
+    );
+}
+
+#[test]
+fn cli_run_rejects_declarations_that_disagree_with_inputs() {
+    let dir = tempfile::tempdir().unwrap();
+    let program = dir.path().join("program.dl");
+    let edge = fixtures_dir().join("edge.csv");
+    fs::write(
+        &program,
+        format!(
+            ".decl edge(x: number)\n.input edge(filename={:?})\nout(X) :- edge(X).\n.output out",
+            edge.to_str().unwrap()
+        ),
+    )
+    .unwrap();
+    let output = run_program(&program, dir.path(), &[]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("predicate \"edge\" used with arity 2, expected 1"),
+        "stderr: {stderr}"
+    );
+}
+
+#[test]
+fn cli_run_rejects_unsupported_io_parameters() {
+    let dir = tempfile::tempdir().unwrap();
+    let program = dir.path().join("program.dl");
+    fs::write(
+        &program,
+        ".input edge(delimiter=\"\\t\")\nout(X) :- edge(X, Y).\n.output out",
+    )
+    .unwrap();
+    let output = run_program(&program, dir.path(), &[]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Unsupported parameter delimiter=\"\\t\" on `.input edge`"),
+        "stderr: {stderr}"
+    );
+}
+
+#[test]
+fn cli_run_rejects_unsupported_outputs_before_evaluating() {
+    let dir = tempfile::tempdir().unwrap();
+    let program = dir.path().join("program.dl");
+    fs::write(
+        &program,
+        ".decl edge(src: number, dst: number)\nout(X) :- edge(X, Y).\n.output out(IO=\"stdout\")",
+    )
+    .unwrap();
+    let output_dir = dir.path().join("out");
+    let output = run_program(&program, &output_dir, &[]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Unsupported parameter IO=\"stdout\" on `.output out`"),
+        "stderr: {stderr}"
+    );
+    assert!(!output_dir.exists());
+}
@@ -1,10 +1,44 @@
 mod common;
 
 use {
-    kermit_algos::LeapfrogTriejoin,
-    kermit_ds::{ColumnTrie, TreeTrie},
+    kermit_algos::{GenericJoin, HashJoin, LeapfrogTriejoin},
+    kermit_ds::{
+        BitmapTrie, ColumnTrie, CompressedColumnTrie, HashTrie, SortedTupleArray, TreeTrie,
+    },
 };
 
 define_multiway_join_test_suite!(TreeTrie, LeapfrogTriejoin);
 
 define_multiway_join_test_suite!(ColumnTrie, LeapfrogTriejoin);
+
+define_multiway_join_test_suite!(SortedTupleArray, LeapfrogTriejoin);
+
+define_multiway_join_test_suite!(CompressedColumnTrie, LeapfrogTriejoin);
+
+define_multiway_join_test_suite!(HashTrie, LeapfrogTriejoin);
+
+define_multiway_join_test_suite!(BitmapTrie, LeapfrogTriejoin);
+
+define_multiway_join_test_suite!(TreeTrie, GenericJoin);
+
+define_multiway_join_test_suite!(ColumnTrie, GenericJoin);
+
+define_multiway_join_test_suite!(SortedTupleArray, GenericJoin);
+
+define_multiway_join_test_suite!(CompressedColumnTrie, GenericJoin);
+
+define_multiway_join_test_suite!(HashTrie, GenericJoin);
+
+define_multiway_join_test_suite!(BitmapTrie, GenericJoin);
+
+define_multiway_join_test_suite!(TreeTrie, HashJoin);
+
+define_multiway_join_test_suite!(ColumnTrie, HashJoin);
+
+define_multiway_join_test_suite!(SortedTupleArray, HashJoin);
+
+define_multiway_join_test_suite!(CompressedColumnTrie, HashJoin);
+
+define_multiway_join_test_suite!(HashTrie, HashJoin);
+
+define_multiway_join_test_suite!(BitmapTrie, HashJoin);
@@ -21,21 +21,29 @@ WONG_PALETTE: list[str] = [
     "#CC79A7",  # reddish purple
 ]
 
-# data_structure → colour. Reserved for kermit's two committed data
+# data_structure → colour. Reserved for kermit's committed data
 # structures; extend when adding a new IndexStructure.
 DATA_STRUCTURE_COLOURS: dict[str, str] = {
     "TreeTrie": WONG_PALETTE[5],   # blue
     "ColumnTrie": WONG_PALETTE[6],  # vermilion
+    "SortedTupleArray": WONG_PALETTE[3],  # bluish green
+    "CompressedColumnTrie": WONG_PALETTE[7],  # reddish purple
+    "HashTrie": WONG_PALETTE[1],  # orange
+    "BitmapTrie": WONG_PALETTE[2],  # sky blue
 }
 
 # algorithm → linestyle for line plots (scaling, dist).
 ALGORITHM_LINESTYLES: dict[str, str] = {
     "LeapfrogTriejoin": "-",
+    "GenericJoin": ":",
+    "HashJoin": "-.",
 }
 
 # algorithm → marker for scatter / bar / tradeoff plots.
 ALGORITHM_MARKERS: dict[str, str] = {
     "LeapfrogTriejoin": "o",
+    "GenericJoin": "^",
+    "HashJoin": "D",
 }
 
 # Fallback colour for unknown DS values; rotates through the palette.
@@ -29,12 +29,25 @@ _AXIS_STR_KEYS: tuple[str, ...] = (
     "query",
     "benchmark",
     "relation_path",
+    "query_fingerprint",
+    "duplicate_of",
 )
 _AXIS_INT_KEYS: tuple[str, ...] = (
     "tuples",
     "arity",
     "relations",
     "relation_bytes",
+    "max_intermediate_size",
+    "result_count",
+    "expected_count",
+)
+_AXIS_BOOL_KEYS: tuple[str, ...] = (
+    "alpha_acyclic",
+    "count_matches",
+)
+_AXIS_FLOAT_KEYS: tuple[str, ...] = (
+    "fractional_hypertree_width",
+    "agm_bound",
 )
 
 # Fixed column order for the summary frame. Used by ``pd.DataFrame(rows,
@@ -43,6 +56,8 @@ _SUMMARY_COLUMNS: tuple[str, ...] = (
     "kind", "metric", "phase",
     *_AXIS_STR_KEYS,
     *_AXIS_INT_KEYS,
+    *_AXIS_BOOL_KEYS,
+    *_AXIS_FLOAT_KEYS,
     "mean_ns", "mean_lo", "mean_hi", "mean_se",
     "median_ns", "median_lo", "median_hi",
     "source_path", "criterion_group", "criterion_function",
@@ -66,6 +81,12 @@ def _summary_row(
     for key in _AXIS_INT_KEYS:
         v = report.axis(key)
         row[key] = v if isinstance(v, int) and not isinstance(v, bool) else pd.NA
+    for key in _AXIS_BOOL_KEYS:
+        v = report.axis(key)
+        row[key] = v if isinstance(v, bool) else pd.NA
+    for key in _AXIS_FLOAT_KEYS:
+        v = report.axis(key)
+        row[key] = float(v) if isinstance(v, (int, float)) and not isinstance(v, bool) else pd.NA
     row["mean_ns"] = data.mean.point
     row["mean_lo"] = data.mean.lower
     row["mean_hi"] = data.mean.upper
@@ -95,6 +116,10 @@ def _summary_from_reports(
     df = pd.DataFrame(rows, columns=list(_SUMMARY_COLUMNS))
     for key in _AXIS_INT_KEYS:
         df[key] = df[key].astype("Int64")
+    for key in _AXIS_BOOL_KEYS:
+        df[key] = df[key].astype("boolean")
+    for key in _AXIS_FLOAT_KEYS:
+        df[key] = df[key].astype("Float64")
     return df
 
 
@@ -178,6 +178,9 @@ def fixture_tree(tmp_path: Path) -> dict:
                         "data_structure": ds,
                         "algorithm": "LeapfrogTriejoin",
                         "tuples": n,
+                        "alpha_acyclic": False,
+                        "fractional_hypertree_width": 1.5,
+                        "agm_bound": n**1.5,
                     },
                     metadata=[
                         {"label": "data structure", "value": ds},
@@ -215,6 +218,11 @@ def fixture_tree(tmp_path: Path) -> dict:
                     "data_structure": "TreeTrie",
                     "algorithm": "LeapfrogTriejoin",
                     "tuples": 100,
+                    "query_fingerprint": f"{len(query):016x}",
+                    **({"duplicate_of": "chain"} if query == "star" else {}),
+                    "result_count": 7,
+                    "expected_count": 7 if query == "chain" else 8,
+                    "count_matches": query == "chain",
                 },
                 metadata=[
                     {"label": "query", "value": query},
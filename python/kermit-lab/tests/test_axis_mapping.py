@@ -21,6 +21,10 @@ def test_palette_is_eight_distinct_hex_colours() -> None:
 def test_committed_data_structures_have_distinct_palette_colours() -> None:
     assert "TreeTrie" in DATA_STRUCTURE_COLOURS
     assert "ColumnTrie" in DATA_STRUCTURE_COLOURS
+    assert "SortedTupleArray" in DATA_STRUCTURE_COLOURS
+    assert "CompressedColumnTrie" in DATA_STRUCTURE_COLOURS
+    assert "HashTrie" in DATA_STRUCTURE_COLOURS
+    assert "BitmapTrie" in DATA_STRUCTURE_COLOURS
     colours = list(DATA_STRUCTURE_COLOURS.values())
     assert len(set(colours)) == len(colours)
     for c in colours:
@@ -37,6 +41,10 @@ def test_unknown_ds_falls_back_stably() -> None:
 def test_known_algo_returns_committed_linestyle_and_marker() -> None:
     assert linestyle_for_algo("LeapfrogTriejoin") == "-"
     assert marker_for_algo("LeapfrogTriejoin") == "o"
+    assert linestyle_for_algo("GenericJoin") == ":"
+    assert marker_for_algo("GenericJoin") == "^"
+    assert linestyle_for_algo("HashJoin") == "-."
+    assert marker_for_algo("HashJoin") == "D"
 
 
 def test_unknown_algo_returns_visible_fallback() -> None:
@@ -34,7 +34,9 @@ def test_summary_columns_present(df):
     expected = {
         "kind", "metric", "phase",
         "data_structure", "algorithm", "query", "benchmark", "relation_path",
-        "tuples", "arity", "relations", "relation_bytes",
+        "query_fingerprint", "duplicate_of",
+        "tuples", "arity", "relations", "relation_bytes", "max_intermediate_size",
+        "result_count", "expected_count", "count_matches", "alpha_acyclic", "fractional_hypertree_width", "agm_bound",
         "mean_ns", "mean_lo", "mean_hi", "mean_se",
         "median_ns", "median_lo", "median_hi",
         "source_path", "criterion_group", "criterion_function",
@@ -61,6 +63,40 @@ def test_tuples_is_nullable_int64(df):
     assert set(df.tuples.dropna().unique()) == {10, 100, 1000}
 
 
+def test_fingerprint_axes_are_strings(df):
+    # Only the chain/star reports carry fingerprints; star duplicates chain.
+    extra = df[df["query"].isin(["chain", "star"])].set_index("query")
+    assert extra.query_fingerprint.to_dict() == {
+        "chain": "0000000000000005",
+        "star": "0000000000000004",
+    }
+    assert extra.duplicate_of["star"] == "chain"
+    assert pd.isna(extra.duplicate_of["chain"])
+    assert df[df["query"] == "triangle"].query_fingerprint.isna().all()
+
+
+def test_count_check_axes_are_typed(df):
+    # Only the chain/star reports check their counts; star's is off by one.
+    assert str(df.expected_count.dtype) == "Int64"
+    assert str(df.count_matches.dtype) == "boolean"
+    extra = df[df["query"].isin(["chain", "star"])].set_index("query")
+    assert extra.result_count.to_dict() == {"chain": 7, "star": 7}
+    assert extra.expected_count.to_dict() == {"chain": 7, "star": 8}
+    assert extra.count_matches.to_dict() == {"chain": True, "star": False}
+    assert df[df["query"] == "triangle"].count_matches.isna().all()
+
+
+def test_query_shape_axes_are_typed(df):
+    # Only the triangle reports carry the query shape.
+    assert str(df.alpha_acyclic.dtype) == "boolean"
+    assert str(df.agm_bound.dtype) == "Float64"
+    triangle = df[df["query"] == "triangle"]
+    assert not triangle.alpha_acyclic.any()
+    assert set(triangle.fractional_hypertree_width.unique()) == {1.5}
+    assert set(triangle.agm_bound.unique()) == {10**1.5, 100**1.5, 1000**1.5}
+    assert df[df["query"] != "triangle"].agm_bound.isna().all()
+
+
 def test_unknown_axes_become_pdNA(df):
     # The fixture never sets `arity` or `relations`, so those columns are
     # entirely NA but still present (and Int64-typed).